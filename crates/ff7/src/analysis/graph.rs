@@ -0,0 +1,127 @@
+//! Builds a graph of which fields lead to which, from the gateways in every field of `flevel.lgp`.
+
+use std::collections::BTreeSet;
+
+use super::report::json_string;
+use crate::extract::{decompress_lzss, LGPFile};
+use crate::field::{FieldFile, MapList, TriggerSection};
+
+
+/// A single gateway from one field to another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldLink {
+    /// The ID of the field that the gateway is in.
+    pub from: u16,
+    /// The ID of the field that the gateway leads to.
+    pub to: u16,
+    /// Where the player ends up in the destination field. See [`Gateway::destination`][crate::field::Gateway].
+    pub destination: [i16; 2],
+    pub destination_triangle: u16,
+}
+
+
+/// Every field-to-field connection in an archive.
+#[derive(Debug, Clone)]
+pub struct FieldGraph<'a> {
+    /// The name of every field, indexed by field ID, as given by the [map list][MapList].
+    pub names: Vec<&'a str>,
+
+    /// Every gateway in every field, sorted by the ID of the field they are in. Gateways within the same field are in
+    /// the order that the field lists them.
+    pub links: Vec<FieldLink>,
+
+    /// Fields that could not be decompressed or parsed, and so have no links, along with the error's message.
+    pub failures: Vec<(&'a str, String)>,
+}
+
+
+impl<'a> FieldGraph<'a> {
+    /// Reads the gateways out of every field in the map list. Fields that the archive doesn't contain (like the
+    /// placeholder names in the map list) are skipped.
+    pub fn from_archive(maplist: &MapList<'a>, archive: &LGPFile<'a>) -> Self {
+        let mut links = Vec::new();
+        let mut failures = Vec::new();
+
+        for (id, &name) in maplist.names.iter().enumerate() {
+            let Some(data) = archive.get(name) else {
+                continue;
+            };
+
+            // The field file has to be decompressed before it can be parsed, so errors can't borrow from it.
+            let result = decompress_lzss(data).map_err(|err| err.to_string()).and_then(|field| {
+                let field = FieldFile::from_bytes(&field).map_err(|err| err.to_string())?;
+                let triggers = TriggerSection::from_bytes(field.triggers()).map_err(|err| err.to_string())?;
+                Ok(triggers.gateways)
+            });
+
+            match result {
+                Ok(gateways) => links.extend(gateways.iter().map(|gateway| FieldLink {
+                    from: id as u16,
+                    to: gateway.field_id,
+                    destination: gateway.destination,
+                    destination_triangle: gateway.destination_triangle,
+                })),
+                Err(err) => failures.push((name, err)),
+            }
+        }
+
+        Self { names: maplist.names.clone(), links, failures }
+    }
+
+    /// Gets the name of the field with the given ID.
+    pub fn name(&self, id: u16) -> Option<&'a str> {
+        self.names.get(id as usize).copied()
+    }
+
+    /// Every gateway out of the given field.
+    pub fn links_from(&self, id: u16) -> impl Iterator<Item = &FieldLink> {
+        self.links.iter().filter(move |link| link.from == id)
+    }
+
+    /// Every gateway into the given field.
+    pub fn links_to(&self, id: u16) -> impl Iterator<Item = &FieldLink> {
+        self.links.iter().filter(move |link| link.to == id)
+    }
+
+    /// Formats the graph in Graphviz's DOT language. Fields are labelled by name, and several gateways between the
+    /// same pair of fields are drawn as a single edge.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph fields {\n");
+        for (from, to) in self.edges() {
+            out.push_str(&format!("    {} -> {};\n", self.node_name(from), self.node_name(to)));
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Formats the graph as a JSON object, with a `fields` array of names (indexed by ID) and a `links` array with one
+    /// object per gateway.
+    pub fn to_json(&self) -> String {
+        let fields = self.names.iter().map(|name| json_string(name)).collect::<Vec<_>>();
+        let links = self
+            .links
+            .iter()
+            .map(|link| {
+                format!(
+                    r#"{{"from":{},"to":{},"destination":[{},{}],"triangle":{}}}"#,
+                    link.from, link.to, link.destination[0], link.destination[1], link.destination_triangle,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        format!(r#"{{"fields":[{}],"links":[{}]}}"#, fields.join(","), links.join(","))
+    }
+
+    /// Every distinct pair of connected fields, sorted.
+    fn edges(&self) -> BTreeSet<(u16, u16)> {
+        self.links.iter().map(|link| (link.from, link.to)).collect()
+    }
+
+    /// The quoted name of a field for DOT output, falling back to its ID if it isn't in the map list.
+    fn node_name(&self, id: u16) -> String {
+        match self.name(id) {
+            Some(name) => json_string(name),
+            None => format!("\"#{id}\""),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Checks that every file a model depends on actually exists.
+
+use super::{has_extension, ReferenceIndex};
+
+
+/// A reference to a file that doesn't exist in the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingReference<'a> {
+    /// The file that contains the reference.
+    pub from: &'a str,
+    /// The name of the file that could not be found.
+    pub name: String,
+}
+
+
+/// The result of checking a single model (an HRC file) for broken references.
+#[derive(Debug, Clone)]
+pub struct ModelLint<'a> {
+    /// The HRC file that was checked.
+    pub model: &'a str,
+
+    /// Every file referenced by the model, directly or indirectly, that doesn't exist.
+    pub missing: Vec<MissingReference<'a>>,
+
+    /// Files belonging to the model that exist, but couldn't be parsed (so their references couldn't be checked).
+    pub unparsable: Vec<&'a str>,
+}
+
+
+impl<'a> ModelLint<'a> {
+    /// Whether or not every reference in the model could be resolved.
+    pub fn passed(&self) -> bool {
+        self.missing.is_empty() && self.unparsable.is_empty()
+    }
+}
+
+
+impl<'a> ReferenceIndex<'a> {
+    /// Checks every model (HRC file) in the archive for references to RSD, P, or TEX files that don't exist. Results
+    /// are sorted by model name.
+    pub fn lint(&self) -> Vec<ModelLint<'a>> {
+        let failed = |name: &str| self.failures.iter().any(|&(failed, _)| failed == name);
+
+        let mut models = self
+            .references
+            .keys()
+            .copied()
+            .chain(self.failures.iter().map(|&(name, _)| name))
+            .filter(|name| has_extension(name, "hrc"))
+            .collect::<Vec<_>>();
+        models.sort_unstable();
+
+        models
+            .into_iter()
+            .map(|model| {
+                let mut lint = ModelLint { model, missing: Vec::new(), unparsable: Vec::new() };
+
+                if failed(model) {
+                    lint.unparsable.push(model);
+                    return lint;
+                }
+
+                for rsd_name in &self.references[model] {
+                    let Some(rsd) = self.resolve(rsd_name) else {
+                        lint.missing.push(MissingReference { from: model, name: rsd_name.clone() });
+                        continue;
+                    };
+
+                    if failed(rsd) {
+                        lint.unparsable.push(rsd);
+                        continue;
+                    }
+
+                    for name in &self.references[rsd] {
+                        if self.resolve(name).is_none() {
+                            lint.missing.push(MissingReference { from: rsd, name: name.clone() });
+                        }
+                    }
+                }
+
+                lint
+            })
+            .collect()
+    }
+}
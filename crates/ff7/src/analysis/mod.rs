@@ -0,0 +1,22 @@
+//! Reports and checks that run over the contents of whole archives, rather than individual files.
+
+use std::path::Path;
+
+
+mod graph;
+mod lint;
+mod references;
+mod report;
+mod textures;
+
+pub use graph::*;
+pub use lint::*;
+pub use references::*;
+pub use report::*;
+pub use textures::*;
+
+
+/// Checks if a file name has the given extension, ignoring case.
+pub(crate) fn has_extension(name: &str, extension: &str) -> bool {
+    Path::new(name).extension().is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+}
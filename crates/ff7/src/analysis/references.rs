@@ -0,0 +1,100 @@
+//! Cross-references between model files in `char.lgp`-style archives.
+//!
+//! Model files refer to each other by name: HRC files list the RSD resources attached to each bone, and RSD files name
+//! the `.P` polygon file and `.TEX` textures they use. Names are compared case-insensitively, since the references
+//! inside the files don't always use the same case as the archive's table of contents.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::has_extension;
+use crate::char::{HierarchyFile, ResourceFile};
+use crate::extract::{LGPFile, ParseError};
+
+
+/// An index of which files in an archive reference which others.
+#[derive(Debug)]
+pub struct ReferenceIndex<'a> {
+    /// Every HRC and RSD file in the archive, mapped to the names of the files that it references. Referenced names
+    /// are exactly as they were derived from the file (e.g., `"AAAC.P"`), and may not actually exist in the archive;
+    /// use [`resolve`][Self::resolve] to look them up.
+    pub references: BTreeMap<&'a str, Vec<String>>,
+
+    /// HRC and RSD files that could not be parsed, and so could not have their references checked.
+    pub failures: Vec<(&'a str, ParseError<'a>)>,
+
+    /// Maps the lowercase version of every file name in the archive to its real name.
+    names: HashMap<String, &'a str>,
+}
+
+
+impl<'a> ReferenceIndex<'a> {
+    pub fn from_archive(archive: &LGPFile<'a>) -> Self {
+        let mut references = BTreeMap::new();
+        let mut failures = Vec::new();
+        let mut names = HashMap::with_capacity(archive.files.len());
+
+        for (&name, &data) in archive.files.iter() {
+            // If several names differ only by case, keep the same one no matter what order the archive's map is in.
+            names
+                .entry(name.to_lowercase())
+                .and_modify(|kept: &mut &str| *kept = name.min(kept))
+                .or_insert(name);
+
+            let refs = if has_extension(name, "hrc") {
+                HierarchyFile::from_bytes(data).map(|hrc| {
+                    hrc.bones
+                        .iter()
+                        .flat_map(|bone| bone.resources.iter())
+                        .map(|resource| format!("{resource}.RSD"))
+                        .collect::<Vec<_>>()
+                })
+            } else if has_extension(name, "rsd") {
+                ResourceFile::from_bytes(data).map(|rsd| {
+                    let mut refs = vec![rsd.polygon_file()];
+                    refs.extend(rsd.texture_files());
+                    refs
+                })
+            } else {
+                continue;
+            };
+
+            match refs {
+                Ok(refs) => {
+                    references.insert(name, refs);
+                },
+                Err(err) => failures.push((name, err)),
+            }
+        }
+
+        failures.sort_by_key(|&(name, _)| name);
+        Self { references, failures, names }
+    }
+
+    /// Finds the real name of a file in the archive, ignoring case.
+    pub fn resolve(&self, name: &str) -> Option<&'a str> {
+        self.names.get(&name.to_lowercase()).copied()
+    }
+
+    /// Finds every RSD, P, and TEX file in the archive that is not referenced by any HRC or RSD file, sorted by name.
+    ///
+    /// HRC and A files are never reported, since they are only referenced by field scripts, which aren't indexed.
+    pub fn unused(&self) -> Vec<&'a str> {
+        let referenced = self
+            .references
+            .values()
+            .flatten()
+            .filter_map(|name| self.resolve(name))
+            .collect::<BTreeSet<_>>();
+
+        let mut unused = self
+            .names
+            .values()
+            .copied()
+            .filter(|name| ["rsd", "p", "tex"].iter().any(|ext| has_extension(name, ext)))
+            .filter(|name| !referenced.contains(name))
+            .collect::<Vec<_>>();
+
+        unused.sort_unstable();
+        unused
+    }
+}
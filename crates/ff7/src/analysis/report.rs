@@ -0,0 +1,121 @@
+//! An opt-in, local log of files that failed to parse, for attaching to bug reports.
+//!
+//! Only archive names, entry names, and error messages are recorded, never any file contents, so a report can be shared
+//! without sharing any of the game's data. Nothing is written anywhere unless a [`FailureLog`] is created.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use super::ReferenceIndex;
+
+
+/// A single file that failed to parse.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailureRecord {
+    /// The archive that the file came from (e.g., `"char.lgp"`).
+    pub archive: String,
+    /// The file's name within the archive.
+    pub entry: String,
+    /// The error that parsing failed with.
+    pub error: String,
+}
+
+
+impl FailureRecord {
+    /// Formats this record as a single-line JSON object.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"archive":{},"entry":{},"error":{}}}"#,
+            json_string(&self.archive),
+            json_string(&self.entry),
+            json_string(&self.error),
+        )
+    }
+}
+
+
+/// A log file that parse failures are added to over any number of sessions.
+#[derive(Debug, Clone)]
+pub struct FailureLog {
+    path: PathBuf,
+}
+
+
+impl FailureLog {
+    /// Uses the log at the given path. The file is only created once something is recorded.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Adds records to the end of the log, one JSON object per line, so that each session adds to the log instead of
+    /// replacing it.
+    pub fn record(&self, records: &[FailureRecord]) -> io::Result<()> {
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        for record in records {
+            writeln!(file, "{}", record.to_json())?;
+        }
+
+        Ok(())
+    }
+
+    /// Exports every record in the log as a single JSON array. An empty array is returned if nothing has been recorded
+    /// yet.
+    pub fn export_json(&self) -> io::Result<String> {
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        let lines = contents.lines().filter(|line| !line.trim().is_empty()).collect::<Vec<_>>();
+        Ok(format!("[{}]", lines.join(",")))
+    }
+
+    /// Deletes the log.
+    pub fn clear(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+
+impl<'a> ReferenceIndex<'a> {
+    /// Turns this index's [`failures`][Self::failures] into records for a [`FailureLog`].
+    pub fn failure_records(&self, archive: &str) -> Vec<FailureRecord> {
+        self.failures
+            .iter()
+            .map(|(name, err)| FailureRecord {
+                archive: archive.to_owned(),
+                entry: (*name).to_owned(),
+                error: err.to_string(),
+            })
+            .collect()
+    }
+}
+
+
+/// Quotes and escapes a string for JSON.
+pub(super) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,71 @@
+//! Finds textures that are stored more than once in the same archive.
+
+use std::collections::HashMap;
+
+use super::has_extension;
+use crate::extract::LGPFile;
+
+
+/// A set of files with byte-for-byte identical contents.
+#[derive(Debug, Clone)]
+pub struct DuplicateGroup<'a> {
+    /// The names of every file in the group, sorted.
+    pub names: Vec<&'a str>,
+
+    /// The size of each of the files, in bytes.
+    pub size: usize,
+}
+
+
+impl<'a> DuplicateGroup<'a> {
+    /// How many bytes could be saved by only storing one copy of this file.
+    pub fn wasted_bytes(&self) -> usize {
+        self.size * (self.names.len() - 1)
+    }
+}
+
+
+/// A report of all the duplicated TEX files in an archive.
+#[derive(Debug, Clone)]
+pub struct TextureReport<'a> {
+    /// The total number of TEX files in the archive.
+    pub texture_count: usize,
+
+    /// Every group of two or more identical TEX files, sorted from most to least wasted space.
+    pub duplicates: Vec<DuplicateGroup<'a>>,
+}
+
+
+impl<'a> TextureReport<'a> {
+    pub fn from_archive(archive: &LGPFile<'a>) -> Self {
+        let mut by_contents: HashMap<&[u8], Vec<&str>> = HashMap::new();
+        let mut texture_count = 0;
+
+        for (&name, &data) in archive.files.iter() {
+            if has_extension(name, "tex") {
+                by_contents.entry(data).or_default().push(name);
+                texture_count += 1;
+            }
+        }
+
+        let mut duplicates = by_contents
+            .into_iter()
+            .filter(|(_, names)| names.len() > 1)
+            .map(|(data, mut names)| {
+                names.sort_unstable();
+                DuplicateGroup { names, size: data.len() }
+            })
+            .collect::<Vec<_>>();
+
+        // Sort by name as a tie-breaker so that the report's order is stable.
+        duplicates.sort_by(|a, b| b.wasted_bytes().cmp(&a.wasted_bytes()).then_with(|| a.names.cmp(&b.names)));
+
+        Self { texture_count, duplicates }
+    }
+
+    /// The total number of bytes that could be saved by de-duplicating every texture.
+    pub fn wasted_bytes(&self) -> usize {
+        self.duplicates.iter().map(DuplicateGroup::wasted_bytes).sum()
+    }
+}
+
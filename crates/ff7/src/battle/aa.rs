@@ -0,0 +1,100 @@
+//! Parses [**AA files](https://wiki.ffrtt.ru/index.php/FF7/Battle/Battle_Model_Format_(PC)), which hold battle model
+//! skeletons.
+//!
+//! An AA file is a 52-byte header, made up of 13 `u32` fields, followed by one 12-byte entry for each bone. Unlike
+//! field skeletons, bones don't have names; they refer to their parents by index, and their parts are found by file
+//! name instead.
+
+use crate::char::read_array;
+use crate::extract::{read_f32, read_i32, read_u32, ParseError};
+
+
+/// The size of the header at the start of every AA file.
+const HEADER_LEN: usize = 0x34;
+
+
+/// What a battle skeleton belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BattleSkeletonKind {
+    /// A battle location (the scenery behind a battle).
+    Location,
+    /// A playable character or an enemy.
+    Model,
+    /// Any other value, which shouldn't appear in the game's files.
+    Unknown(u32),
+}
+
+
+/// A single bone from an [AA file][BattleSkeleton].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BattleBone {
+    /// The index of this bone's parent, or `None` for bones attached directly to the root.
+    pub parent: Option<usize>,
+    /// The length of the bone.
+    pub length: f32,
+    /// Whether or not this bone has a part (a P file) attached to it.
+    pub has_part: bool,
+}
+
+
+/// The parsed contents of an AA file.
+#[derive(Debug, Clone)]
+pub struct BattleSkeleton {
+    pub kind: BattleSkeletonKind,
+    /// The number of textures that the model's parts use.
+    pub texture_count: u32,
+    /// The number of the model's own animations in its DA file.
+    pub body_animation_count: u32,
+    /// The number of weapon animations in the DA file, which come after the body animations.
+    pub weapon_animation_count: u32,
+    /// The bones, in the order they appear in the file. Parents always appear before their children.
+    pub bones: Vec<BattleBone>,
+}
+
+
+impl BattleSkeleton {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = 0;
+        let header = read_array(data, &mut ptr, HEADER_LEN / 4, read_u32)?;
+        let field = |offset: usize| header[offset / 4];
+
+        let kind = match field(0x00) {
+            0 => BattleSkeletonKind::Location,
+            1 => BattleSkeletonKind::Model,
+            other => BattleSkeletonKind::Unknown(other),
+        };
+        let bone_count = field(0x0C) as usize;
+        let texture_count = field(0x18);
+        let body_animation_count = field(0x1C);
+        let weapon_animation_count = field(0x28);
+
+        let bones = read_array(data, &mut ptr, bone_count, |data, ptr| {
+            let parent = read_i32(data, ptr)?;
+            let length = read_f32(data, ptr)?;
+            let has_part = read_u32(data, ptr)? != 0;
+            Ok(BattleBone { parent: usize::try_from(parent).ok(), length, has_part })
+        })?;
+
+        Ok(Self { kind, texture_count, body_animation_count, weapon_animation_count, bones })
+    }
+
+    /// The names of the P files for each bone with a part, in bone order, given the model's two-letter prefix (e.g.,
+    /// `"rt"`). Parts are named from `**AM` onwards, skipping bones without one.
+    pub fn part_names(&self, prefix: &str) -> Vec<String> {
+        let count = self.bones.iter().filter(|bone| bone.has_part).count();
+        (0..count).map(|i| format!("{prefix}{}", suffix(i + 12))).collect()
+    }
+
+    /// The names of the model's texture files, given its two-letter prefix. Textures are named from `**AC` onwards.
+    pub fn texture_names(&self, prefix: &str) -> Vec<String> {
+        (0..self.texture_count as usize).map(|i| format!("{prefix}{}", suffix(i + 2))).collect()
+    }
+}
+
+
+/// Turns a file's index into its two-letter suffix: 0 is `aa`, 1 is `ab`, 26 is `ba`, and so on.
+fn suffix(index: usize) -> String {
+    let first = (b'a' + (index / 26 % 26) as u8) as char;
+    let second = (b'a' + (index % 26) as u8) as char;
+    format!("{first}{second}")
+}
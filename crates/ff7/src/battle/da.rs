@@ -0,0 +1,192 @@
+//! Parses [**DA files](https://wiki.ffrtt.ru/index.php/FF7/Battle/Battle_Animation_(PC)), which hold every animation
+//! for a battle model.
+//!
+//! A DA file starts with the number of animations, and each animation starts with a 12-byte header: its bone count,
+//! frame count, and the length of its data. The data is a bit stream (most significant bit first) that starts with a
+//! 5-byte header of its own. The first frame is stored in full, and every frame after that is stored as differences
+//! from the one before it, each packed into as few bits as possible.
+//!
+//! Rotations are stored as fractions of a full turn out of 4096, with the lowest `key` bits dropped to save space.
+
+use crate::extract::{read, read_u32, u32_from_le_bytes, ParseError};
+
+
+/// The number of steps in a full turn, for rotations.
+const FULL_TURN: u32 = 4096;
+
+
+/// A single frame of a [battle animation][BattleAnimation].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BattleFrame {
+    /// The translation of the whole model.
+    pub root_translation: [i16; 3],
+    /// The rotation of each bone, relative to its parent, as Euler angles in degrees. The first rotation is the whole
+    /// model's; the rest are in the same order as the bones in the skeleton's [AA file][super::BattleSkeleton].
+    pub rotations: Vec<[f32; 3]>,
+}
+
+
+/// One animation from a DA file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BattleAnimation {
+    /// The number of rotations in each frame.
+    pub bone_count: usize,
+    pub frames: Vec<BattleFrame>,
+}
+
+
+/// The parsed contents of a DA file.
+#[derive(Debug, Clone)]
+pub struct BattleAnimationFile {
+    /// Every animation in the file: first the model's own, then its weapons'. See
+    /// [`BattleSkeleton::body_animation_count`][super::BattleSkeleton::body_animation_count].
+    pub animations: Vec<BattleAnimation>,
+}
+
+
+impl BattleAnimationFile {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = 0;
+        let count = read_u32(data, &mut ptr)? as usize;
+
+        let mut animations = Vec::with_capacity(count.min(data.len() / 12));
+        for _ in 0..count {
+            let bone_count_pos = ptr;
+            let bone_count_data = read(data, &mut ptr, 4)?;
+            let bone_count = u32_from_le_bytes(bone_count_data)? as usize;
+            let _frame_count = read_u32(data, &mut ptr)?; // the frame count in the data itself is the one that's used
+            let len = read_u32(data, &mut ptr)? as usize;
+            let block = read(data, &mut ptr, len)?;
+
+            // Some animations are just placeholders with no data; keep them so that the indices still line up.
+            let frames = if len == 0 {
+                Vec::new()
+            } else {
+                let max_bones = max_bone_count(block)?;
+                if bone_count > max_bones {
+                    return Err(ParseError::InvalidValueError(bone_count_data, bone_count_pos));
+                }
+                read_frames(block, bone_count)?
+            };
+            animations.push(BattleAnimation { bone_count, frames });
+        }
+
+        Ok(Self { animations })
+    }
+}
+
+
+/// The most bones that an animation's block of data has room for in its first frame, which stores every rotation in
+/// full. Anything more would run out of bits anyway, so this stops a bad bone count from allocating before then.
+fn max_bone_count(block: &[u8]) -> Result<usize, ParseError<'_>> {
+    let header = read(block, &mut 0, 5)?;
+    let key = header[4] as usize;
+    let bits_left = (block.len() - 5) * 8;
+    Ok(bits_left.saturating_sub(3 * 16) / (3 * 12usize.saturating_sub(key).max(1)))
+}
+
+
+/// Decodes the frames from an animation's block of data.
+fn read_frames(block: &[u8], bone_count: usize) -> Result<Vec<BattleFrame>, ParseError<'_>> {
+    let mut ptr = 0;
+    let header = read(block, &mut ptr, 5)?;
+    let frame_count = u16::from_le_bytes([header[0], header[1]]) as usize;
+    let key = header[4] as u32;
+    if key >= 12 {
+        return Err(ParseError::InvalidValueError(&header[4..5], 4));
+    }
+
+    let mut bits = BitReader { data: &block[ptr..], bit: 0 };
+    let rotation_bits = 12 - key;
+
+    // The first frame is stored in full.
+    let mut translation = [0i32; 3];
+    for value in translation.iter_mut() {
+        *value = bits.read_signed(16)?;
+    }
+    let mut rotations = vec![[0u32; 3]; bone_count];
+    for value in rotations.iter_mut().flatten() {
+        *value = bits.read(rotation_bits)? << key;
+    }
+
+    let mut frames = Vec::with_capacity(frame_count.min(block.len()));
+    frames.push(to_frame(translation, &rotations));
+
+    // Every other frame is stored as differences from the one before it.
+    for _ in 1..frame_count {
+        for value in translation.iter_mut() {
+            // The game only keeps the lower 16 bits, so overflowing doesn't matter.
+            *value = value.wrapping_add(bits.read_delta(16)?);
+        }
+        for value in rotations.iter_mut().flatten() {
+            let delta = bits.read_delta(rotation_bits)? << key;
+            *value = (*value as i32 + delta).rem_euclid(FULL_TURN as i32) as u32;
+        }
+        frames.push(to_frame(translation, &rotations));
+    }
+
+    Ok(frames)
+}
+
+
+fn to_frame(translation: [i32; 3], rotations: &[[u32; 3]]) -> BattleFrame {
+    let degrees = |value: u32| value as f32 * 360.0 / FULL_TURN as f32;
+    BattleFrame {
+        root_translation: translation.map(|value| value as i16),
+        rotations: rotations.iter().map(|rotation| rotation.map(degrees)).collect(),
+    }
+}
+
+
+/// Reads values of any number of bits from a byte slice, most significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    bit: usize,
+}
+
+
+impl<'a> BitReader<'a> {
+    /// Reads an unsigned value.
+    fn read(&mut self, count: u32) -> Result<u32, ParseError<'a>> {
+        let mut value = 0;
+        for _ in 0..count {
+            let byte = *self.data.get(self.bit / 8).ok_or(ParseError::EndOfBufferError)?;
+            value = (value << 1) | ((byte >> (7 - self.bit % 8)) & 1) as u32;
+            self.bit += 1;
+        }
+        Ok(value)
+    }
+
+    /// Reads a two's complement signed value.
+    fn read_signed(&mut self, count: u32) -> Result<i32, ParseError<'a>> {
+        let value = self.read(count)? as i32;
+        let sign = 1 << (count - 1);
+        Ok(if value & sign != 0 { value - (sign << 1) } else { value })
+    }
+
+    /// Reads the difference between a value and its value in the last frame.
+    ///
+    /// A single bit says whether or not the value changed at all. If it did, the next three bits say how it is stored:
+    /// `0` means the difference is -1, `7` means it is stored in full (in `full_bits` bits), and anything else is the
+    /// number of bits that it is packed into. Packed differences skip over the values that smaller sizes can already
+    /// store, so `n` bits cover magnitudes from `2^(n-1)` up to `2^n - 1` (or `2^n` for negative differences).
+    fn read_delta(&mut self, full_bits: u32) -> Result<i32, ParseError<'a>> {
+        if self.read(1)? == 0 {
+            return Ok(0);
+        }
+
+        Ok(match self.read(3)? {
+            0 => -1,
+            7 => self.read_signed(full_bits)?,
+            len => {
+                let value = self.read_signed(len)?;
+                let offset = 1 << (len - 1);
+                if value < 0 {
+                    value - offset
+                } else {
+                    value + offset
+                }
+            },
+        })
+    }
+}
@@ -0,0 +1,16 @@
+//! Parsing of `battle.lgp` related files: the skeletons and animations of battle models.
+//!
+//! Battle models are named by a two-letter prefix (e.g., `rt` for Cloud), followed by two more letters saying what
+//! each file is. `**AA` is the skeleton, `**DA` holds every animation, and the model's parts and textures follow from
+//! `**AM` and `**AC` onwards. The parts themselves are ordinary [P files][crate::char::PolygonFile].
+//!
+//! The battle camera scripts in `camdat0.bin` to `camdat2.bin` aren't parsed. They're bytecode with no length prefix
+//! on each instruction, so reading them needs the argument length of every camera opcode. The field script table in
+//! [`field::opcode_info`][crate::field::opcode_info] comes from a reference that sizes every opcode. There is no
+//! equally complete table for the camera opcodes to work from.
+
+mod aa;
+mod da;
+
+pub use aa::*;
+pub use da::*;
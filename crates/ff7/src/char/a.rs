@@ -0,0 +1,146 @@
+//! Parses [A files](https://wiki.ffrtt.ru/index.php/FF7/Field_Module/Field_Models/A_file), which hold field model
+//! animations.
+//!
+//! An A file is a 36-byte header followed by a list of frames. Each frame holds the root's rotation and translation,
+//! then one set of Euler angles (in degrees) for every bone in the skeleton.
+
+use super::p::{read_array, read_vec3};
+use crate::extract::{read, read_u32, ParseError};
+
+
+/// A single frame of an [animation][AnimationFile].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameView<'a> {
+    /// The rotation of the whole model, as Euler angles in degrees.
+    pub root_rotation: [f32; 3],
+    /// The translation of the whole model.
+    pub root_translation: [f32; 3],
+    /// The rotation of each bone, relative to its parent, as Euler angles in degrees. Indexed in the same order as the
+    /// bones in the skeleton's HRC file.
+    pub bone_rotations: &'a [[f32; 3]],
+}
+
+
+/// The parsed contents of an A file.
+#[derive(Debug, Clone)]
+pub struct AnimationFile {
+    /// The number of frames in the animation.
+    pub frame_count: usize,
+
+    /// The number of bones each frame has rotations for.
+    pub bone_count: usize,
+
+    /// The order in which each frame's Euler angles should be applied, as axis indices (0 = X, 1 = Y, 2 = Z).
+    pub rotation_order: [u8; 3],
+
+    /// The root rotation and translation of each frame.
+    roots: Vec<([f32; 3], [f32; 3])>,
+
+    /// Every frame's bone rotations, one frame after the other.
+    rotations: Vec<[f32; 3]>,
+}
+
+
+impl AnimationFile {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = 0;
+
+        let _version = read_u32(data, &mut ptr)?; // always 1
+        let frame_count = read_u32(data, &mut ptr)? as usize;
+        let bone_count = read_u32(data, &mut ptr)? as usize;
+
+        let order = read(data, &mut ptr, 4)?; // three axes, then one byte of padding
+        let rotation_order = [order[0], order[1], order[2]];
+        if rotation_order.iter().any(|&axis| axis > 2) {
+            return Err(ParseError::InvalidValueError(&order[0..3], 12));
+        }
+
+        read(data, &mut ptr, 20)?; // runtime data
+
+        let mut roots = Vec::with_capacity(frame_count.min(data.len()));
+        let mut rotations = Vec::with_capacity(frame_count.saturating_mul(bone_count).min(data.len()));
+
+        for _ in 0..frame_count {
+            let root_rotation = read_vec3(data, &mut ptr)?;
+            let root_translation = read_vec3(data, &mut ptr)?;
+            roots.push((root_rotation, root_translation));
+            rotations.extend(read_array(data, &mut ptr, bone_count, read_vec3)?);
+        }
+
+        Ok(Self { frame_count, bone_count, rotation_order, roots, rotations })
+    }
+
+    /// Reads just the number of bones that an A file animates, from its header, without parsing any of its frames.
+    pub fn read_bone_count(data: &[u8]) -> Result<usize, ParseError<'_>> {
+        let mut ptr = 8;
+        Ok(read_u32(data, &mut ptr)? as usize)
+    }
+
+    /// Gets a single frame of the animation, or `None` if `n` is out of range.
+    pub fn frame(&self, n: usize) -> Option<FrameView<'_>> {
+        let &(root_rotation, root_translation) = self.roots.get(n)?;
+        let start = n * self.bone_count;
+        let bone_rotations = &self.rotations[start..start + self.bone_count];
+        Some(FrameView { root_rotation, root_translation, bone_rotations })
+    }
+
+    /// Iterates over every frame of the animation.
+    pub fn frames(&self) -> impl Iterator<Item = FrameView<'_>> {
+        (0..self.frame_count).filter_map(|n| self.frame(n))
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds an A file with two frames of two bones, applied in Y, X, Z order. Every value is distinct, so that
+    /// anything read from the wrong place shows up.
+    fn two_frames() -> Vec<u8> {
+        let mut data = Vec::new();
+        for value in [1, 2, 2] {
+            data.extend_from_slice(&u32::to_le_bytes(value)); // version, frames, bones
+        }
+        data.extend_from_slice(&[1, 0, 2, 0]); // rotation order, then padding
+        data.extend_from_slice(&[0; 20]); // runtime data
+
+        // Each frame: root rotation, root translation, then two bone rotations
+        for frame in 0..2 {
+            for value in 0..12 {
+                data.extend_from_slice(&((frame * 100 + value) as f32).to_le_bytes());
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn frames_are_read() {
+        let animation = AnimationFile::from_bytes(&two_frames()).unwrap();
+        assert_eq!((animation.frame_count, animation.bone_count), (2, 2));
+        assert_eq!(animation.rotation_order, [1, 0, 2]);
+
+        let frame = animation.frame(1).unwrap();
+        assert_eq!(frame.root_rotation, [100.0, 101.0, 102.0]);
+        assert_eq!(frame.root_translation, [103.0, 104.0, 105.0]);
+        assert_eq!(frame.bone_rotations, [[106.0, 107.0, 108.0], [109.0, 110.0, 111.0]]);
+
+        assert!(animation.frame(2).is_none());
+        assert_eq!(animation.frames().count(), 2);
+        assert_eq!(AnimationFile::read_bone_count(&two_frames()).unwrap(), 2);
+    }
+
+    #[test]
+    fn truncated_frames_are_rejected() {
+        let mut data = two_frames();
+        data.truncate(data.len() - 4);
+        assert!(matches!(AnimationFile::from_bytes(&data), Err(ParseError::EndOfBufferError)));
+    }
+
+    #[test]
+    fn unknown_axes_are_rejected() {
+        let mut data = two_frames();
+        data[13] = 3;
+        assert!(matches!(AnimationFile::from_bytes(&data), Err(ParseError::InvalidValueError(_, 12))));
+    }
+}
@@ -0,0 +1,92 @@
+//! Binds an [animation][AnimationFile] to an [assembled model][Model], checking that it actually fits the model's
+//! skeleton and rearranging its frames into one track per bone.
+//!
+//! A files don't say which skeleton they were made for, only how many bones they animate, so the bone count is the
+//! only real check. An animation with the same number of bones as another skeleton will still bind to it, and just
+//! look wrong.
+
+use thiserror::Error;
+
+use super::{AnimationFile, Model};
+use crate::extract::{FileKind, LGPFile};
+
+
+/// An error from binding an animation to a model.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum BindError {
+    #[error("the animation has rotations for {0} bones, but the skeleton has {1}")]
+    BoneCountError(usize, usize),
+
+    #[error("the animation has no frames")]
+    NoFramesError,
+
+    #[error("frame {0} has an invalid rotation for bone {1} (\"{2}\")")]
+    InvalidRotationError(usize, usize, String),
+}
+
+
+/// An animation that has been checked against a skeleton, split into tracks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundAnimation {
+    /// The order in which each rotation's Euler angles should be applied. See [`AnimationFile::rotation_order`].
+    pub rotation_order: [u8; 3],
+    /// The whole model's rotation in each frame, as Euler angles in degrees.
+    pub root_rotations: Vec<[f32; 3]>,
+    /// The whole model's translation in each frame.
+    pub root_translations: Vec<[f32; 3]>,
+    /// One track per bone, in the same order as [`Model::bones`], each holding the bone's rotation (relative to its
+    /// parent, as Euler angles in degrees) in every frame.
+    pub tracks: Vec<Vec<[f32; 3]>>,
+}
+
+
+impl BoundAnimation {
+    pub fn frame_count(&self) -> usize {
+        self.root_rotations.len()
+    }
+}
+
+
+impl<'a> Model<'a> {
+    /// The names of every A file in an archive that animates as many bones as this model has, which are the ones that
+    /// [`bind`][Self::bind] will accept. Only each file's header is read.
+    pub fn compatible_animations<'b>(&self, archive: &LGPFile<'b>) -> Vec<&'b str> {
+        archive
+            .names_of_kind(FileKind::Animation)
+            .filter(|&name| {
+                let bone_count = archive.get(name).map(AnimationFile::read_bone_count);
+                matches!(bone_count, Some(Ok(count)) if count == self.bones.len())
+            })
+            .collect()
+    }
+
+    /// Checks that an animation fits this model's skeleton, and splits it into one track per bone.
+    pub fn bind(&self, animation: &AnimationFile) -> Result<BoundAnimation, BindError> {
+        if animation.bone_count != self.bones.len() {
+            return Err(BindError::BoneCountError(animation.bone_count, self.bones.len()));
+        }
+        if animation.frame_count == 0 {
+            return Err(BindError::NoFramesError);
+        }
+
+        let mut root_rotations = Vec::with_capacity(animation.frame_count);
+        let mut root_translations = Vec::with_capacity(animation.frame_count);
+        let mut tracks = vec![Vec::with_capacity(animation.frame_count); self.bones.len()];
+
+        for (n, frame) in animation.frames().enumerate() {
+            root_rotations.push(frame.root_rotation);
+            root_translations.push(frame.root_translation);
+
+            for (bone, (track, &rotation)) in tracks.iter_mut().zip(frame.bone_rotations).enumerate() {
+                // Broken files tend to show up as NaNs or huge values, which would otherwise only show up as a mangled
+                // model once drawn.
+                if !rotation.iter().all(|angle| angle.is_finite()) {
+                    return Err(BindError::InvalidRotationError(n, bone, self.bones[bone].name.to_owned()));
+                }
+                track.push(rotation);
+            }
+        }
+
+        Ok(BoundAnimation { rotation_order: animation.rotation_order, root_rotations, root_translations, tracks })
+    }
+}
@@ -0,0 +1,179 @@
+//! Parses [HRC files](https://wiki.ffrtt.ru/index.php/FF7/Field_Module/Field_Models/HRC_file), which describe a
+//! model's skeleton.
+//!
+//! HRC files are plain ASCII. They start with a short header:
+//!
+//! ```text
+//! :HEADER_BLOCK 2
+//! :SKELETON aaaa
+//! :BONES 21
+//! ```
+//!
+//! ...followed by one block per bone, each of which contains four lines: the bone's name, the name of its parent, its
+//! length, and a list of RSD resources attached to it (prefixed by how many there are).
+
+use crate::extract::{invalid_substr, sz_to_str, ParseError};
+
+
+/// The name that bones attached directly to the root of the skeleton use as their parent.
+pub const ROOT_BONE_NAME: &str = "root";
+
+
+/// The parsed contents of an HRC file.
+#[derive(Debug, Clone)]
+pub struct HierarchyFile<'a> {
+    /// The name of the skeleton.
+    pub name: &'a str,
+
+    /// All of the bones in the skeleton, in the order they appear in the file. Parents always appear before their
+    /// children.
+    pub bones: Vec<Bone<'a>>,
+}
+
+
+/// A single bone from an [HRC file][HierarchyFile].
+#[derive(Debug, Clone)]
+pub struct Bone<'a> {
+    /// The bone's name.
+    pub name: &'a str,
+
+    /// The name of this bone's parent. Bones attached to the root of the skeleton have [`ROOT_BONE_NAME`] as their
+    /// parent.
+    pub parent: &'a str,
+
+    /// The length of the bone.
+    pub length: f32,
+
+    /// The names of the RSD resources (without file extensions) attached to this bone.
+    pub resources: Vec<&'a str>,
+}
+
+
+impl<'a> HierarchyFile<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        let text = sz_to_str(data)?;
+
+        // Strip comments and blank lines; what's left are the meaningful lines.
+        let mut lines = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty());
+
+        let mut name = None;
+        let mut bone_count = None;
+
+        // Read header lines until we've got all the ones we need.
+        while name.is_none() || bone_count.is_none() {
+            let line = lines.next().ok_or(ParseError::EndOfBufferError)?;
+            let (key, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+
+            match key {
+                ":HEADER_BLOCK" => {}, // always 2; nothing to do with it
+                ":SKELETON" => name = Some(value),
+                ":BONES" => bone_count = Some(value.parse::<usize>().map_err(|_| invalid_substr(data, value))?),
+                _ => return Err(invalid_substr(data, line)),
+            }
+        }
+
+        let name = name.unwrap();
+        let bone_count = bone_count.unwrap();
+
+        // Don't trust the count for the allocation: every bone takes four lines of at least one character each.
+        let mut bones = Vec::with_capacity(bone_count.min(text.len() / 8));
+        for _ in 0..bone_count {
+            let mut next = || lines.next().ok_or(ParseError::EndOfBufferError);
+
+            let bone_name = next()?;
+            let parent = next()?;
+
+            let length = next()?;
+            let length = length.parse::<f32>().map_err(|_| invalid_substr(data, length))?;
+
+            let resource_line = next()?;
+            let mut words = resource_line.split_whitespace();
+            let count = words.next().unwrap(); // lines are non-empty, so there's always at least one word
+            let count = count.parse::<usize>().map_err(|_| invalid_substr(data, count))?;
+            let resources = words.collect::<Vec<_>>();
+
+            if resources.len() != count {
+                return Err(invalid_substr(data, resource_line));
+            }
+
+            bones.push(Bone { name: bone_name, parent, length, resources });
+        }
+
+        Ok(Self { name, bones })
+    }
+
+    /// Finds a bone by name.
+    pub fn bone(&self, name: &str) -> Option<&Bone<'a>> {
+        self.bones.iter().find(|bone| bone.name == name)
+    }
+
+    /// Finds the index of a bone's parent in [`bones`][Self::bones], or `None` if it is attached to the root.
+    pub fn parent_index(&self, bone: &Bone) -> Option<usize> {
+        self.bones.iter().position(|other| other.name == bone.parent)
+    }
+}
+
+
+
+/// An owned copy of a [`HierarchyFile`], which can be kept after the file's data is gone. Use
+/// [`as_borrowed`][Self::as_borrowed] to get a [`HierarchyFile`] back to work with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HierarchyFileOwned {
+    pub name: String,
+    pub bones: Vec<BoneOwned>,
+}
+
+
+/// An owned copy of a [`Bone`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoneOwned {
+    pub name: String,
+    pub parent: String,
+    pub length: f32,
+    pub resources: Vec<String>,
+}
+
+
+impl<'a> HierarchyFile<'a> {
+    /// Copies everything that this file borrows, so that it no longer depends on the data it was parsed from.
+    pub fn into_owned(self) -> HierarchyFileOwned {
+        let bones = self.bones.into_iter().map(Bone::into_owned).collect();
+        HierarchyFileOwned { name: self.name.to_owned(), bones }
+    }
+}
+
+
+impl<'a> Bone<'a> {
+    pub fn into_owned(self) -> BoneOwned {
+        BoneOwned {
+            name: self.name.to_owned(),
+            parent: self.parent.to_owned(),
+            length: self.length,
+            resources: self.resources.into_iter().map(str::to_owned).collect(),
+        }
+    }
+}
+
+
+impl HierarchyFileOwned {
+    pub fn as_borrowed(&self) -> HierarchyFile<'_> {
+        let bones = self.bones.iter().map(BoneOwned::as_borrowed).collect();
+        HierarchyFile { name: &self.name, bones }
+    }
+}
+
+
+impl BoneOwned {
+    pub fn as_borrowed(&self) -> Bone<'_> {
+        Bone {
+            name: &self.name,
+            parent: &self.parent,
+            length: self.length,
+            resources: self.resources.iter().map(String::as_str).collect(),
+        }
+    }
+}
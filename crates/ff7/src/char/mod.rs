@@ -1 +1,29 @@
-//! Parsing of `char.lgp` related files, like `HRC`, `RSD`, `P`, `A`, and so on.
+//! Parsing of `char.lgp` related files, like `HRC`, `RSD`, `P`, `A`, and so on, as well as the PlayStation's `TMD`
+//! models, which are converted to the same form as `P` files.
+//!
+//! The PlayStation's `BSX` and `BCX` field model containers aren't parsed. Their parts aren't TMD objects but the
+//! game's own packed meshes, with a separate layout for each kind of primitive, and their animations are compressed.
+//! Both need to be written against a byte-exact reference for those layouts, which nothing here has been checked
+//! against yet.
+
+mod a;
+mod bind;
+mod hrc;
+mod model;
+mod normals;
+mod p;
+mod pose;
+mod rsd;
+mod tex;
+mod tmd;
+
+pub use a::*;
+pub use bind::*;
+pub use hrc::*;
+pub use model::*;
+pub use normals::*;
+pub use p::*;
+pub use pose::*;
+pub use rsd::*;
+pub use tex::*;
+pub use tmd::*;
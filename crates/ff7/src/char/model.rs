@@ -0,0 +1,146 @@
+//! Puts a whole model together from its separate files: the skeleton from its HRC file, and then, for each bone, the
+//! RSD resources attached to it, their P meshes, and their TEX textures.
+//!
+//! Parts of a model can be missing or broken without the rest of it being unusable (some of the game's own models
+//! refer to files that don't exist), so only a problem with the HRC file itself stops a model from being assembled.
+//! Everything else is collected into [`missing`][Model::missing] and [`failures`][Model::failures], and the affected
+//! meshes or textures are left out.
+
+use std::collections::HashMap;
+
+use super::{Bone, PolygonFile, ResourceFile, TextureFile, DEFAULT_SMOOTHING_ANGLE};
+use crate::extract::{LGPFile, ParseError};
+
+
+/// One of a model's meshes, attached to a bone.
+#[derive(Debug, Clone)]
+pub struct ModelMesh<'a> {
+    /// The index of the bone in [`Model::bones`] that the mesh is attached to.
+    pub bone: usize,
+    /// The name of the RSD resource that the mesh came from, as the HRC file gives it.
+    pub resource: &'a str,
+    /// The mesh's geometry. If the P file didn't have usable normals, they have been
+    /// [generated][PolygonFile::generate_normals].
+    pub polygon: PolygonFile,
+    /// The index in [`Model::textures`] of each of the resource's textures, in the resource's order (which is what the
+    /// mesh's groups refer to them by). Textures that are missing or couldn't be parsed are `None`.
+    pub textures: Vec<Option<usize>>,
+}
+
+
+/// One of a model's textures.
+#[derive(Debug, Clone)]
+pub struct ModelTexture<'a> {
+    /// The name of the TEX file, as the RSD file gives it.
+    pub name: String,
+    pub texture: TextureFile<'a>,
+}
+
+
+/// A model, assembled from all of its files.
+#[derive(Debug)]
+pub struct Model<'a> {
+    /// The name of the skeleton.
+    pub name: &'a str,
+    /// The skeleton's bones, in the HRC file's order.
+    pub bones: Vec<Bone<'a>>,
+    pub meshes: Vec<ModelMesh<'a>>,
+    /// Every texture that the model uses. Textures shared by several meshes only appear once.
+    pub textures: Vec<ModelTexture<'a>>,
+    /// Files that the model refers to but that aren't in the archive.
+    pub missing: Vec<String>,
+    /// Files that the model refers to but that couldn't be parsed.
+    pub failures: Vec<(String, ParseError<'a>)>,
+}
+
+
+impl<'a> Model<'a> {
+    /// Assembles a model from the files in an archive, starting from its HRC file. Returns `None` if there is no HRC
+    /// file with that name, and an error if it can't be parsed. See the [module-level documentation](self) for what
+    /// happens when any of the other files are missing or broken.
+    pub fn assemble(archive: &LGPFile<'a>, hrc_name: &str) -> Option<Result<Self, ParseError<'a>>> {
+        let hierarchy = match archive.hierarchy(hrc_name)? {
+            Ok(hierarchy) => hierarchy,
+            Err(err) => return Some(Err(err)),
+        };
+
+        let mut model = Self {
+            name: hierarchy.name,
+            bones: hierarchy.bones,
+            meshes: Vec::new(),
+            textures: Vec::new(),
+            missing: Vec::new(),
+            failures: Vec::new(),
+        };
+
+        // Maps the lowercase name of every texture that has been looked up to its index, if it loaded.
+        let mut texture_indices = HashMap::new();
+
+        for bone in 0..model.bones.len() {
+            for &resource in &model.bones[bone].resources.clone() {
+                let Some(rsd) = model.load(archive, format!("{resource}.RSD"), ResourceFile::from_bytes) else {
+                    continue;
+                };
+                let Some(mut polygon) = model.load(archive, rsd.polygon_file(), PolygonFile::from_bytes) else {
+                    continue;
+                };
+                if !polygon.has_usable_normals() {
+                    polygon.generate_normals(DEFAULT_SMOOTHING_ANGLE);
+                }
+
+                let mut textures = Vec::with_capacity(rsd.textures.len());
+                for name in rsd.texture_files() {
+                    let key = name.to_lowercase();
+                    let index = match texture_indices.get(&key) {
+                        Some(&index) => index,
+                        None => {
+                            let index = model.load(archive, name.clone(), TextureFile::from_bytes).map(|texture| {
+                                model.textures.push(ModelTexture { name, texture });
+                                model.textures.len() - 1
+                            });
+                            texture_indices.insert(key, index);
+                            index
+                        },
+                    };
+                    textures.push(index);
+                }
+
+                model.meshes.push(ModelMesh { bone, resource, polygon, textures });
+            }
+        }
+
+        Some(Ok(model))
+    }
+
+    /// Finds the index of a bone's parent in [`bones`][Self::bones], or `None` if it is attached to the root.
+    pub fn parent_index(&self, bone: usize) -> Option<usize> {
+        let parent = self.bones.get(bone)?.parent;
+        self.bones.iter().position(|other| other.name == parent)
+    }
+
+    /// The meshes attached to a bone.
+    pub fn meshes_of(&self, bone: usize) -> impl Iterator<Item = &ModelMesh<'a>> {
+        self.meshes.iter().filter(move |mesh| mesh.bone == bone)
+    }
+
+    /// Loads and parses one of the model's files, noting it as missing or failed if that doesn't work.
+    fn load<T>(
+        &mut self,
+        archive: &LGPFile<'a>,
+        name: String,
+        parse: impl FnOnce(&'a [u8]) -> Result<T, ParseError<'a>>,
+    ) -> Option<T> {
+        let Some(data) = archive.get(&name) else {
+            self.missing.push(name);
+            return None;
+        };
+
+        match parse(data) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                self.failures.push((name, err));
+                None
+            },
+        }
+    }
+}
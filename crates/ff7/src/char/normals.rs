@@ -0,0 +1,164 @@
+//! Works out normals for [P files][PolygonFile] that don't come with usable ones, so that every model can be lit.
+//!
+//! Every polygon gets a face normal from the order of its corners. Each corner's normal is then the average of the
+//! face normals of every polygon that touches the same point, out of those that are within a threshold angle of the
+//! corner's own polygon; sharper edges than that keep separate normals on either side. Larger polygons count for more
+//! in the average. Points are matched by position rather than by index, since P files give each group its own copies of
+//! any vertices that it shares with other groups.
+//!
+//! The results are stored as per-corner normals in [`Polygon::normals`], replacing any
+//! [`normal_indices`][PolygonFile::normal_indices], since a single point can end up with different normals on either
+//! side of a sharp edge.
+
+use std::collections::HashMap;
+
+use super::PolygonFile;
+
+
+/// The angle, in degrees, that [`PolygonFile::generate_normals`] is usually given: enough to smooth over curved
+/// surfaces, while leaving the corners of boxy shapes sharp.
+pub const DEFAULT_SMOOTHING_ANGLE: f32 = 60.0;
+
+
+impl PolygonFile {
+    /// Whether or not the file has normals that can be used for lighting: every polygon (or vertex, for files with
+    /// [`normal_indices`][Self::normal_indices]) points at a normal that exists, and they aren't all zero.
+    pub fn has_usable_normals(&self) -> bool {
+        let in_range = |&index: &usize| index < self.normals.len();
+        let all_in_range = if self.normal_indices.is_empty() {
+            self.polygons.iter().flat_map(|polygon| polygon.normals).map(usize::from).all(|i| in_range(&i))
+        } else {
+            self.normal_indices.len() >= self.vertices.len()
+                && self.normal_indices.iter().map(|&i| i as usize).all(|i| in_range(&i))
+        };
+
+        all_in_range && self.normals.iter().any(|&normal| length(normal) > 0.0)
+    }
+
+    /// Replaces the file's normals with ones worked out from its polygons. Edges sharper than `smoothing_angle` (in
+    /// degrees) are left sharp; an angle of 0 gives every polygon its own flat normal.
+    ///
+    /// Returns `false`, and leaves the file as it was, if there would be too many distinct normals for a polygon's
+    /// 16-bit normal indices to refer to.
+    pub fn generate_normals(&mut self, smoothing_angle: f32) -> bool {
+        // The corner positions of every polygon, found through its group.
+        let mut corners = vec![None; self.polygons.len()];
+        for group in &self.groups {
+            let polygons = self.polygons.iter().zip(&mut corners);
+            for (polygon, corners) in polygons.skip(group.polygon_start as usize).take(group.polygon_count as usize) {
+                let positions = polygon.vertices.map(|v| {
+                    self.vertices.get(group.vertex_start as usize + v as usize).copied()
+                });
+                if let [Some(a), Some(b), Some(c)] = positions {
+                    *corners = Some([a, b, c]);
+                }
+            }
+        }
+
+        // Face normals, scaled by the polygons' areas.
+        let mut faces = corners
+            .iter()
+            .map(|corners| corners.map_or([0.0; 3], |[a, b, c]| cross(sub(b, a), sub(c, a))))
+            .collect::<Vec<_>>();
+
+        // Nothing says which way round corners go, so point the normals outwards from the middle of the model.
+        let known = corners.iter().flatten().flatten().collect::<Vec<_>>();
+        let center = known.iter().fold([0.0; 3], |sum, &&p| add(sum, p)).map(|c| c / known.len().max(1) as f32);
+        let outwards: f32 = corners
+            .iter()
+            .zip(&faces)
+            .filter_map(|(corners, &face)| corners.map(|[a, b, c]| (add(add(a, b), c).map(|x| x / 3.0), face)))
+            .map(|(middle, face)| dot(face, sub(middle, center)))
+            .sum();
+        if outwards < 0.0 {
+            faces.iter_mut().for_each(|face| *face = face.map(|c| -c));
+        }
+
+        // Every polygon that touches each point.
+        let mut touching = HashMap::<[u32; 3], Vec<usize>>::new();
+        for (i, corners) in corners.iter().enumerate() {
+            for point in corners.iter().flatten() {
+                touching.entry(point.map(f32::to_bits)).or_default().push(i);
+            }
+        }
+
+        let threshold = smoothing_angle.clamp(0.0, 180.0).to_radians().cos();
+        let units = faces.iter().map(|&face| normalize(face)).collect::<Vec<_>>();
+
+        let mut normals = Vec::new();
+        let mut normal_indices = HashMap::<[u32; 3], u16>::new();
+        let mut polygon_normals = Vec::with_capacity(self.polygons.len());
+
+        for (i, corners) in corners.iter().enumerate() {
+            let Some(corners) = corners else {
+                polygon_normals.push(self.polygons[i].normals);
+                continue;
+            };
+
+            let mut indices = [0; 3];
+            for (index, point) in indices.iter_mut().zip(corners) {
+                let neighbours = touching.get(&point.map(f32::to_bits)).map_or(&[][..], Vec::as_slice);
+                let normal = neighbours
+                    .iter()
+                    .filter(|&&j| j == i || dot(units[i], units[j]) >= threshold)
+                    .fold([0.0; 3], |sum, &j| add(sum, faces[j]));
+                let normal = normalize(normal);
+
+                *index = match normal_indices.get(&normal.map(f32::to_bits)) {
+                    Some(&index) => index,
+                    None => {
+                        let Ok(index) = u16::try_from(normals.len()) else {
+                            return false;
+                        };
+                        normals.push(normal);
+                        normal_indices.insert(normal.map(f32::to_bits), index);
+                        index
+                    },
+                };
+            }
+            polygon_normals.push(indices);
+        }
+
+        for (polygon, normals) in self.polygons.iter_mut().zip(polygon_normals) {
+            polygon.normals = normals;
+        }
+        self.normals = normals;
+        self.normal_indices.clear();
+        true
+    }
+}
+
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+
+fn length(v: [f32; 3]) -> f32 {
+    dot(v, v).sqrt()
+}
+
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = length(v);
+    if len > 0.0 {
+        v.map(|c| c / len)
+    } else {
+        v
+    }
+}
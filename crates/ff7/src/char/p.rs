@@ -0,0 +1,447 @@
+//! Parses [P files](https://wiki.ffrtt.ru/index.php/FF7/P), the PC version's polygon format.
+//!
+//! A P file is made of a fixed-size header followed by a series of tightly-packed arrays. The header gives the length
+//! of each array. Polygons are grouped together into [groups][PolygonGroup], each of which is drawn with a single
+//! texture and [render state][RenderState]. Indices stored in polygons are relative to the start of their group.
+
+use crate::extract::{read, read_f32, read_u16, read_u32, ParseError};
+
+
+/// An RGBA color.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+
+impl Color {
+    /// Reads a color stored in BGRA order, which is how colors are stored in P and TEX files.
+    pub(crate) fn read_bgra<'a>(data: &'a [u8], ptr: &mut usize) -> Result<Self, ParseError<'a>> {
+        let &[b, g, r, a] = read(data, ptr, 4)? else {
+            unreachable!(); // success of `read` with length 4 guarantees slice length
+        };
+        Ok(Self { r, g, b, a })
+    }
+
+    pub fn to_array(self) -> [u8; 4] {
+        [self.r, self.g, self.b, self.a]
+    }
+}
+
+
+/// A single triangle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Polygon {
+    /// Indices of this triangle's vertices, relative to its group's [`vertex_start`][PolygonGroup::vertex_start].
+    pub vertices: [u16; 3],
+    /// Indices of this triangle's normals.
+    pub normals: [u16; 3],
+    /// Indices of this triangle's edges, relative to its group's [`edge_start`][PolygonGroup::edge_start].
+    pub edges: [u16; 3],
+}
+
+
+/// Bits for [`RenderState::feature_mask`] and [`RenderState::feature_values`].
+pub mod render_features {
+    pub const WIREFRAME: u32 = 0x1;
+    pub const TEXTURE: u32 = 0x2;
+    pub const LINEAR_FILTER: u32 = 0x4;
+    pub const PERSPECTIVE: u32 = 0x8;
+    pub const TEXTURE_BLEND: u32 = 0x10;
+    pub const WRAP_U: u32 = 0x20;
+    pub const WRAP_V: u32 = 0x40;
+    pub const COLOR_KEY: u32 = 0x100;
+    pub const DITHER: u32 = 0x200;
+    pub const ALPHA_BLEND: u32 = 0x400;
+    pub const ALPHA_TEST: u32 = 0x800;
+    pub const ANTIALIAS: u32 = 0x1000;
+    pub const CULL_FACE: u32 = 0x2000;
+    pub const NO_CULL: u32 = 0x4000;
+    pub const DEPTH_TEST: u32 = 0x8000;
+    pub const DEPTH_MASK: u32 = 0x10000;
+    pub const SHADE_MODE: u32 = 0x20000;
+    pub const SPECULAR: u32 = 0x40000;
+    pub const LIGHT_STATE: u32 = 0x80000;
+    pub const FOG: u32 = 0x100000;
+    pub const TEXTURE_ADDRESS: u32 = 0x200000;
+}
+
+
+/// A render state block, which the community calls a "hundred" since each one is 100 bytes long.
+///
+/// Only the fields whose purpose is known are broken out; everything is still available through [`raw`][Self::raw].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderState {
+    /// Which of the [render features][render_features] this state changes.
+    pub feature_mask: u32,
+    /// The values of the [render features][render_features] selected by [`feature_mask`][Self::feature_mask].
+    pub feature_values: u32,
+    /// The index of the texture to use, within the model's list of textures. The field before it (`raw[4]`) is a
+    /// pointer to the texture set that the game fills in once the file is loaded, so it means nothing on disk and is
+    /// only kept in [`raw`][Self::raw].
+    pub texture_id: u32,
+    /// The blending mode: 0 is average, 1 is additive, 2 is subtractive, 3 is 25% additive, and 4 is no blending.
+    pub blend_mode: u32,
+    /// The raw 25 fields of the block.
+    pub raw: [u32; 25],
+}
+
+
+impl RenderState {
+    /// Checks whether a [render feature][render_features] is set by this state. Returns `None` if this state doesn't
+    /// change that feature at all.
+    pub fn feature(&self, feature: u32) -> Option<bool> {
+        (self.feature_mask & feature != 0).then_some(self.feature_values & feature != 0)
+    }
+
+    /// How polygons drawn with this state are blended with what's behind them, or `None` if they are opaque: either
+    /// because the state doesn't turn on [alpha blending][render_features::ALPHA_BLEND], or because its
+    /// [`blend_mode`][Self::blend_mode] is "no blending" (or not one that's known).
+    pub fn blending(&self) -> Option<BlendMode> {
+        if self.feature(render_features::ALPHA_BLEND) != Some(true) {
+            return None;
+        }
+
+        match self.blend_mode {
+            0 => Some(BlendMode::Average),
+            1 => Some(BlendMode::Additive),
+            2 => Some(BlendMode::Subtractive),
+            3 => Some(BlendMode::QuarterAdditive),
+            _ => None,
+        }
+    }
+}
+
+
+/// The ways that semi-transparent polygons can be blended with what's behind them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Half of the polygon's color plus half of what's behind it.
+    Average,
+    /// The polygon's color added to what's behind it.
+    Additive,
+    /// The polygon's color subtracted from what's behind it.
+    Subtractive,
+    /// A quarter of the polygon's color added to what's behind it.
+    QuarterAdditive,
+}
+
+
+/// A group of polygons that are drawn together with the same texture and render state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PolygonGroup {
+    /// The primitive type of this group.
+    pub primitive_type: u32,
+    /// The index of this group's first polygon.
+    pub polygon_start: u32,
+    pub polygon_count: u32,
+    /// The index of the first vertex used by this group. Polygon vertex indices are relative to this.
+    pub vertex_start: u32,
+    pub vertex_count: u32,
+    /// The index of the first edge used by this group. Polygon edge indices are relative to this.
+    pub edge_start: u32,
+    pub edge_count: u32,
+    /// The index of this group's first texture coordinate. The group's vertices use texture coordinates in order,
+    /// starting from this one.
+    pub tex_coord_start: u32,
+    /// Whether or not this group is textured.
+    pub textured: bool,
+    /// Which of the model's textures this group uses, if [`textured`][Self::textured].
+    pub texture_index: u32,
+}
+
+
+/// An axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub max: [f32; 3],
+    pub min: [f32; 3],
+}
+
+
+/// The parsed contents of a P file.
+#[derive(Debug, Clone)]
+pub struct PolygonFile {
+    /// Whether or not the model uses vertex colors.
+    pub vertex_color: bool,
+    pub vertices: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub tex_coords: Vec<[f32; 2]>,
+    pub vertex_colors: Vec<Color>,
+    /// One flat color per polygon.
+    pub polygon_colors: Vec<Color>,
+    /// Pairs of vertex indices.
+    pub edges: Vec<[u16; 2]>,
+    pub polygons: Vec<Polygon>,
+    /// The render state of each group, in the same order as [`groups`][Self::groups].
+    pub render_states: Vec<RenderState>,
+    pub groups: Vec<PolygonGroup>,
+    pub bounding_boxes: Vec<BoundingBox>,
+    /// For each vertex, the index of its normal. Not every file has one.
+    pub normal_indices: Vec<u32>,
+}
+
+
+impl PolygonFile {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = 0;
+
+        // Header
+        // --------------------
+
+        let _version = read_u32(data, &mut ptr)?; // always 1
+        let _unknown = read_u32(data, &mut ptr)?; // always 1
+        let vertex_color = read_u32(data, &mut ptr)? != 0;
+        let num_vertices = read_u32(data, &mut ptr)? as usize;
+        let num_normals = read_u32(data, &mut ptr)? as usize;
+        let num_unknown1 = read_u32(data, &mut ptr)? as usize;
+        let num_tex_coords = read_u32(data, &mut ptr)? as usize;
+        let num_vertex_colors = read_u32(data, &mut ptr)? as usize;
+        let num_edges = read_u32(data, &mut ptr)? as usize;
+        let num_polygons = read_u32(data, &mut ptr)? as usize;
+        let num_unknown2 = read_u32(data, &mut ptr)? as usize;
+        let num_unknown3 = read_u32(data, &mut ptr)? as usize;
+        let num_render_states = read_u32(data, &mut ptr)? as usize;
+        let num_groups = read_u32(data, &mut ptr)? as usize;
+        let num_bounding_boxes = read_u32(data, &mut ptr)? as usize;
+        let has_normal_indices = read_u32(data, &mut ptr)? != 0;
+        read(data, &mut ptr, 64)?; // runtime data, only used by the game's engine
+
+        // Arrays
+        // --------------------
+
+        let vertices = read_array(data, &mut ptr, num_vertices, read_vec3)?;
+        let normals = read_array(data, &mut ptr, num_normals, read_vec3)?;
+        read(data, &mut ptr, num_unknown1.saturating_mul(12))?;
+        let tex_coords = read_array(data, &mut ptr, num_tex_coords, |data, ptr| {
+            Ok([read_f32(data, ptr)?, read_f32(data, ptr)?])
+        })?;
+        let vertex_colors = read_array(data, &mut ptr, num_vertex_colors, Color::read_bgra)?;
+        let polygon_colors = read_array(data, &mut ptr, num_polygons, Color::read_bgra)?;
+        let edges = read_array(data, &mut ptr, num_edges, |data, ptr| {
+            Ok([read_u16(data, ptr)?, read_u16(data, ptr)?])
+        })?;
+        let polygons = read_array(data, &mut ptr, num_polygons, read_polygon)?;
+        read(data, &mut ptr, num_unknown2.saturating_mul(24))?;
+        read(data, &mut ptr, num_unknown3.saturating_mul(3))?;
+        let render_states = read_array(data, &mut ptr, num_render_states, read_render_state)?;
+        let groups = read_array(data, &mut ptr, num_groups, read_group)?;
+        let bounding_boxes = read_array(data, &mut ptr, num_bounding_boxes, |data, ptr| {
+            let _unknown = read_u32(data, ptr)?;
+            Ok(BoundingBox { max: read_vec3(data, ptr)?, min: read_vec3(data, ptr)? })
+        })?;
+
+        // Some files set the flag for the normal index table, but are cut off before it. Since it isn't needed to draw
+        // the model, only read it if it's all there.
+        let normal_indices = if has_normal_indices && data.len() - ptr >= num_vertices.saturating_mul(4) {
+            read_array(data, &mut ptr, num_vertices, read_u32)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            vertex_color,
+            vertices,
+            normals,
+            tex_coords,
+            vertex_colors,
+            polygon_colors,
+            edges,
+            polygons,
+            render_states,
+            groups,
+            bounding_boxes,
+            normal_indices,
+        })
+    }
+}
+
+
+/// Reads `count` items using the given function.
+pub(crate) fn read_array<'a, T>(
+    data: &'a [u8],
+    ptr: &mut usize,
+    count: usize,
+    read_item: impl Fn(&'a [u8], &mut usize) -> Result<T, ParseError<'a>>,
+) -> Result<Vec<T>, ParseError<'a>> {
+    // Don't trust `count` for the allocation until we know it's not garbage: every item takes at least one byte.
+    if count > data.len().saturating_sub(*ptr) {
+        return Err(ParseError::EndOfBufferError);
+    }
+
+    (0..count).map(|_| read_item(data, ptr)).collect()
+}
+
+
+pub(crate) fn read_vec3<'a>(data: &'a [u8], ptr: &mut usize) -> Result<[f32; 3], ParseError<'a>> {
+    Ok([read_f32(data, ptr)?, read_f32(data, ptr)?, read_f32(data, ptr)?])
+}
+
+
+fn read_polygon<'a>(data: &'a [u8], ptr: &mut usize) -> Result<Polygon, ParseError<'a>> {
+    let _tag = read_u16(data, ptr)?;
+    let mut read_3 = || Ok::<_, ParseError>([read_u16(data, ptr)?, read_u16(data, ptr)?, read_u16(data, ptr)?]);
+    let vertices = read_3()?;
+    let normals = read_3()?;
+    let edges = read_3()?;
+    let _tag = read_u32(data, ptr)?;
+    Ok(Polygon { vertices, normals, edges })
+}
+
+
+fn read_render_state<'a>(data: &'a [u8], ptr: &mut usize) -> Result<RenderState, ParseError<'a>> {
+    let mut raw = [0u32; 25];
+    for field in raw.iter_mut() {
+        *field = read_u32(data, ptr)?;
+    }
+
+    Ok(RenderState {
+        feature_mask: raw[2],
+        feature_values: raw[3],
+        texture_id: raw[5],
+        blend_mode: raw[17],
+        raw,
+    })
+}
+
+
+fn read_group<'a>(data: &'a [u8], ptr: &mut usize) -> Result<PolygonGroup, ParseError<'a>> {
+    let primitive_type = read_u32(data, ptr)?;
+    let polygon_start = read_u32(data, ptr)?;
+    let polygon_count = read_u32(data, ptr)?;
+    let vertex_start = read_u32(data, ptr)?;
+    let vertex_count = read_u32(data, ptr)?;
+    let edge_start = read_u32(data, ptr)?;
+    let edge_count = read_u32(data, ptr)?;
+    read(data, ptr, 16)?; // four unknown fields
+    let tex_coord_start = read_u32(data, ptr)?;
+    let textured = read_u32(data, ptr)? != 0;
+    let texture_index = read_u32(data, ptr)?;
+
+    Ok(PolygonGroup {
+        primitive_type,
+        polygon_start,
+        polygon_count,
+        vertex_start,
+        vertex_count,
+        edge_start,
+        edge_count,
+        tex_coord_start,
+        textured,
+        texture_index,
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a P file with one triangle in one textured group, and two render states: one additive and textured, and
+    /// one that turns on alpha blending but asks for no blending.
+    fn triangle_file() -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut push = |values: &[u32]| values.iter().for_each(|value| data.extend_from_slice(&value.to_le_bytes()));
+
+        // version, unknown, vertex color, vertices, normals, unknown, tex coords, vertex colors, edges, polygons,
+        // unknown, unknown, render states, groups, bounding boxes, normal index table
+        push(&[1, 1, 1, 3, 1, 0, 3, 3, 3, 1, 0, 0, 2, 1, 1, 1]);
+        push(&[0; 16]); // runtime data
+
+        let floats = |values: &[f32]| values.iter().map(|value| value.to_bits()).collect::<Vec<_>>();
+        push(&floats(&[0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 0.0])); // vertices
+        push(&floats(&[0.0, 0.0, 1.0])); // normals
+        push(&floats(&[0.0, 0.0, 1.0, 0.0, 0.0, 1.0])); // tex coords
+        push(&[0xFFFF0000, 0xFF00FF00, 0xFF0000FF]); // vertex colors: red, green, and blue, stored as BGRA
+        push(&[0x80402010]); // polygon colors
+        push(&[0x0001_0000, 0x0002_0001, 0x0000_0002]); // edges, as pairs of u16
+
+        // One polygon: tag, vertices, normals, edges, tag
+        data.extend([0u16, 0, 1, 2, 0, 0, 0, 0, 1, 2].iter().flat_map(|value| value.to_le_bytes()));
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        let mut push = |values: &[u32]| values.iter().for_each(|value| data.extend_from_slice(&value.to_le_bytes()));
+        let mut additive = [0; 25];
+        additive[2] = render_features::ALPHA_BLEND | render_features::TEXTURE;
+        additive[3] = render_features::ALPHA_BLEND | render_features::TEXTURE;
+        additive[4] = 0xDEADBEEF; // the runtime texture set pointer, which has to be ignored
+        additive[5] = 7;
+        additive[17] = 1;
+        push(&additive);
+
+        let mut opaque = [0; 25];
+        opaque[2] = render_features::ALPHA_BLEND;
+        opaque[3] = render_features::ALPHA_BLEND;
+        opaque[17] = 4;
+        push(&opaque);
+
+        // One group: primitive type, polygons, vertices, edges, four unknowns, tex coord start, textured, texture
+        push(&[1, 0, 1, 0, 3, 0, 3, 0, 0, 0, 0, 0, 1, 2]);
+        push(&[0]); // bounding box
+        push(&floats(&[1.0, 1.0, 0.0, 0.0, 0.0, 0.0]));
+        push(&[0, 0, 0]); // normal indices
+        data
+    }
+
+    #[test]
+    fn header_and_arrays_are_read() {
+        let file = PolygonFile::from_bytes(&triangle_file()).unwrap();
+        assert!(file.vertex_color);
+        assert_eq!(file.vertices, [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]);
+        assert_eq!(file.normals, [[0.0, 0.0, 1.0]]);
+        assert_eq!(file.tex_coords, [[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]]);
+        assert_eq!(file.vertex_colors[0], Color { r: 0xFF, g: 0, b: 0, a: 0xFF });
+        assert_eq!(file.vertex_colors[2], Color { r: 0, g: 0, b: 0xFF, a: 0xFF });
+        assert_eq!(file.polygon_colors, [Color { r: 0x40, g: 0x20, b: 0x10, a: 0x80 }]);
+        assert_eq!(file.edges, [[0, 1], [1, 2], [2, 0]]);
+        assert_eq!(file.polygons, [Polygon { vertices: [0, 1, 2], normals: [0; 3], edges: [0, 1, 2] }]);
+        assert_eq!(file.bounding_boxes, [BoundingBox { max: [1.0, 1.0, 0.0], min: [0.0; 3] }]);
+        assert_eq!(file.normal_indices, [0, 0, 0]);
+    }
+
+    #[test]
+    fn groups_are_read() {
+        let file = PolygonFile::from_bytes(&triangle_file()).unwrap();
+        let group = PolygonGroup {
+            primitive_type: 1,
+            polygon_start: 0,
+            polygon_count: 1,
+            vertex_start: 0,
+            vertex_count: 3,
+            edge_start: 0,
+            edge_count: 3,
+            tex_coord_start: 0,
+            textured: true,
+            texture_index: 2,
+        };
+        assert_eq!(file.groups, [group]);
+    }
+
+    #[test]
+    fn render_states_are_read() {
+        let file = PolygonFile::from_bytes(&triangle_file()).unwrap();
+        let [additive, opaque] = file.render_states[..] else {
+            panic!("expected two render states");
+        };
+
+        assert_eq!(additive.texture_id, 7);
+        assert_eq!(additive.raw[4], 0xDEADBEEF);
+        assert_eq!(additive.feature(render_features::TEXTURE), Some(true));
+        assert_eq!(additive.feature(render_features::CULL_FACE), None);
+        assert_eq!(additive.blending(), Some(BlendMode::Additive));
+
+        assert_eq!(opaque.blend_mode, 4);
+        assert_eq!(opaque.blending(), None);
+    }
+
+    #[test]
+    fn missing_normal_index_table_is_tolerated() {
+        let mut data = triangle_file();
+        data.truncate(data.len() - 12);
+        let file = PolygonFile::from_bytes(&data).unwrap();
+        assert!(file.normal_indices.is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+//! Works out where each bone of an [assembled model][Model] is, so that its meshes can be drawn in place, either
+//! in a single frame of an animation or blended between two of them for smooth playback.
+//!
+//! Each bone starts at the far end of its parent and is rotated relative to it. A bone's meshes are drawn with the
+//! bone's rotation, from its start, and its children start `length` units further along the bone's -Z axis. Bones
+//! attached to the root start from the model's root transform.
+
+use super::{BoundAnimation, Model};
+
+
+/// A column-major 4×4 matrix.
+pub type Matrix = [f32; 16];
+
+
+/// The identity matrix.
+pub const IDENTITY: Matrix = [1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+
+
+/// A model's skeleton in one particular pose.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pose {
+    /// The transform for each bone's meshes, in the same order as [`Model::bones`].
+    pub bones: Vec<Matrix>,
+}
+
+
+impl Pose {
+    /// Transforms a point from one of a bone's meshes into the model's space. Returns `None` if there's no such bone.
+    pub fn transform_point(&self, bone: usize, point: [f32; 3]) -> Option<[f32; 3]> {
+        Some(transform_point(self.bones.get(bone)?, point))
+    }
+}
+
+
+/// The shape of a model's skeleton, without any names or meshes: just enough to pose it. Unlike a [`Model`], it can be
+/// kept after the archive that the model came from is gone.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Skeleton {
+    /// The index of each bone's parent, or `None` for bones attached to the root. In the same order as
+    /// [`Model::bones`].
+    pub parents: Vec<Option<usize>>,
+    /// The length of each bone.
+    pub lengths: Vec<f32>,
+}
+
+
+impl<'a> Model<'a> {
+    /// The parts of the model's skeleton that are needed to pose it.
+    pub fn skeleton(&self) -> Skeleton {
+        Skeleton {
+            parents: (0..self.bones.len()).map(|i| self.parent_index(i)).collect(),
+            lengths: self.bones.iter().map(|bone| bone.length).collect(),
+        }
+    }
+
+    /// See [`Skeleton::rest_pose`].
+    pub fn rest_pose(&self) -> Pose {
+        self.skeleton().rest_pose()
+    }
+
+    /// See [`Skeleton::pose`].
+    pub fn pose(&self, animation: &BoundAnimation, frame: usize) -> Option<Pose> {
+        self.skeleton().pose(animation, frame)
+    }
+
+    /// See [`Skeleton::pose_at`].
+    pub fn pose_at(&self, animation: &BoundAnimation, position: f32, looping: bool) -> Option<Pose> {
+        self.skeleton().pose_at(animation, position, looping)
+    }
+}
+
+
+impl Skeleton {
+    /// The skeleton's rest pose, with none of the bones rotated. Every bone points straight along its parent, so this
+    /// mostly shows whether the meshes are attached to the right bones; most models only look right in an
+    /// [animation's pose][Self::pose].
+    pub fn rest_pose(&self) -> Pose {
+        self.pose_with(IDENTITY, [0, 1, 2], |_| [0.0; 3])
+    }
+
+    /// The skeleton's pose in one frame of an animation. Returns `None` if the frame is out of range.
+    pub fn pose(&self, animation: &BoundAnimation, frame: usize) -> Option<Pose> {
+        let root_rotation = rotation(*animation.root_rotations.get(frame)?, animation.rotation_order);
+        let root = multiply(&translation(*animation.root_translations.get(frame)?), &root_rotation);
+        let rotation_of = |bone: usize| animation.tracks.get(bone).and_then(|track| track.get(frame)).copied();
+        Some(self.pose_with(root, animation.rotation_order, |bone| rotation_of(bone).unwrap_or_default()))
+    }
+
+    /// The skeleton's pose part of the way through an animation, `position` frames in (so 2.5 is halfway between
+    /// frames 2 and 3). Every rotation, and the root's translation, is blended between the frames on either side.
+    ///
+    /// When `looping`, the last frame blends back into the first and positions wrap around; otherwise, positions past
+    /// either end hold that end's frame. Returns `None` if the animation has no frames.
+    pub fn pose_at(&self, animation: &BoundAnimation, position: f32, looping: bool) -> Option<Pose> {
+        let count = animation.frame_count();
+        let last = count.checked_sub(1)?;
+        let position = if looping { position.rem_euclid(count as f32) } else { position.clamp(0.0, last as f32) };
+
+        let from = (position.floor() as usize).min(last);
+        let to = if from < last { from + 1 } else if looping { 0 } else { from };
+        let t = position - from as f32;
+
+        // Neighbouring frames are close together, so blending each Euler angle separately is close enough.
+        let blend = |track: &[[f32; 3]]| -> Option<[f32; 3]> {
+            let (a, b) = (track.get(from)?, track.get(to)?);
+            Some([0, 1, 2].map(|i| a[i] + angle_between(a[i], b[i]) * t))
+        };
+
+        let root_rotation = rotation(blend(&animation.root_rotations)?, animation.rotation_order);
+        let (a, b) = (animation.root_translations.get(from)?, animation.root_translations.get(to)?);
+        let root_translation = [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * t);
+        let root = multiply(&translation(root_translation), &root_rotation);
+
+        let rotation_of = |bone: usize| animation.tracks.get(bone).and_then(|track| blend(track));
+        Some(self.pose_with(root, animation.rotation_order, |bone| rotation_of(bone).unwrap_or_default()))
+    }
+
+    fn pose_with(&self, root: Matrix, order: [u8; 3], rotation_of: impl Fn(usize) -> [f32; 3]) -> Pose {
+        let mut bones = Vec::with_capacity(self.lengths.len());
+        // Where each bone ends, for its children to start from.
+        let mut ends = Vec::<Matrix>::with_capacity(self.lengths.len());
+
+        for (i, &length) in self.lengths.iter().enumerate() {
+            // Parents always come before their children, so their ends are already known.
+            let parent = self.parents.get(i).copied().flatten();
+            let start = parent.and_then(|parent| ends.get(parent)).unwrap_or(&root);
+            let transform = multiply(start, &rotation(rotation_of(i), order));
+            ends.push(multiply(&transform, &translation([0.0, 0.0, -length])));
+            bones.push(transform);
+        }
+
+        Pose { bones }
+    }
+}
+
+
+/// The change from one angle to another, in degrees, going the short way around.
+fn angle_between(from: f32, to: f32) -> f32 {
+    (to - from + 180.0).rem_euclid(360.0) - 180.0
+}
+
+
+/// Multiplies two matrices, `a * b`.
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+
+/// Transforms a point by a matrix.
+pub fn transform_point(m: &Matrix, [x, y, z]: [f32; 3]) -> [f32; 3] {
+    [0, 1, 2].map(|row| m[row] * x + m[4 + row] * y + m[8 + row] * z + m[12 + row])
+}
+
+
+fn translation([x, y, z]: [f32; 3]) -> Matrix {
+    let mut m = IDENTITY;
+    m[12..15].copy_from_slice(&[x, y, z]);
+    m
+}
+
+
+/// A rotation from Euler angles in degrees. The rotations about each axis are multiplied together in the given order
+/// (as axis indices), so the last one listed is applied to a point first.
+fn rotation(angles: [f32; 3], order: [u8; 3]) -> Matrix {
+    order.iter().fold(IDENTITY, |m, &axis| {
+        let Some(&degrees) = angles.get(axis as usize) else {
+            return m; // log warning?
+        };
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let mut r = IDENTITY;
+        // The two axes that the rotation mixes, in the order that keeps it right-handed.
+        let (a, b) = match axis {
+            0 => (1, 2),
+            1 => (2, 0),
+            _ => (0, 1),
+        };
+        r[a * 4 + a] = cos;
+        r[a * 4 + b] = sin;
+        r[b * 4 + a] = -sin;
+        r[b * 4 + b] = cos;
+        multiply(&m, &r)
+    })
+}
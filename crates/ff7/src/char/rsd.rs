@@ -0,0 +1,162 @@
+//! Parses [RSD files](https://wiki.ffrtt.ru/index.php/FF7/Field_Module/Field_Models/RSD_file), which tie a model
+//! part's polygon data to its textures.
+//!
+//! RSD files are plain ASCII, with one `KEY=VALUE` pair per line:
+//!
+//! ```text
+//! @RSD940102
+//! PLY=AAAC.PLY
+//! MAT=AAAC.MAT
+//! GRP=AAAC.GRP
+//! NTEX=1
+//! TEX[0]=AAAD.TIM
+//! ```
+//!
+//! The file names in RSD files refer to the original PlayStation formats. On PC, the `.PLY`, `.MAT`, and `.GRP` files
+//! are all combined into a single `.P` file, and `.TIM` textures are replaced with `.TEX` files.
+
+use std::path::Path;
+
+use crate::extract::{invalid_substr, sz_to_str, ParseError};
+
+
+/// The parsed contents of an RSD file.
+#[derive(Debug, Clone)]
+pub struct ResourceFile<'a> {
+    /// The version from the `@RSD` header line; e.g., `"940102"`.
+    pub version: &'a str,
+
+    /// The name of the polygon data file, as written in the file (`PLY=`).
+    pub polygon: &'a str,
+
+    /// The name of the material file, as written in the file (`MAT=`), if there is one.
+    pub material: Option<&'a str>,
+
+    /// The name of the group file, as written in the file (`GRP=`), if there is one.
+    pub group: Option<&'a str>,
+
+    /// The names of the textures used by this resource, as written in the file, in order. The order matters, since
+    /// polygon groups refer to textures by index.
+    pub textures: Vec<&'a str>,
+}
+
+
+impl<'a> ResourceFile<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        let text = sz_to_str(data)?;
+
+        let mut lines = text
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+
+        let header = lines.next().ok_or(ParseError::EndOfBufferError)?;
+        let version = header.strip_prefix("@RSD").ok_or_else(|| invalid_substr(data, header))?;
+
+        let mut polygon = None;
+        let mut material = None;
+        let mut group = None;
+        let mut tex_count = None;
+        let mut textures = Vec::new();
+
+        for line in lines {
+            let (key, value) = line.split_once('=').ok_or_else(|| invalid_substr(data, line))?;
+            let (key, value) = (key.trim(), value.trim());
+
+            match key {
+                "PLY" => polygon = Some(value),
+                "MAT" => material = Some(value),
+                "GRP" => group = Some(value),
+                "NTEX" => {
+                    let count = value.parse::<usize>().map_err(|_| invalid_substr(data, value))?;
+                    // Every texture needs a `TEX[n]=x` line, which is at least 8 bytes long, so a larger count can't
+                    // be filled in anyway. Checking it here keeps it from being used for a huge allocation.
+                    if count > text.len() / 8 {
+                        return Err(invalid_substr(data, value));
+                    }
+                    textures.resize(count, None);
+                    tex_count = Some(count);
+                },
+                _ => {
+                    // The only other keys should be `TEX[n]`.
+                    let index = key
+                        .strip_prefix("TEX[")
+                        .and_then(|key| key.strip_suffix(']'))
+                        .and_then(|index| index.parse::<usize>().ok())
+                        .ok_or_else(|| invalid_substr(data, key))?;
+
+                    // NTEX always comes before the textures themselves, so the list should already be big enough.
+                    let slot = textures.get_mut(index).ok_or_else(|| invalid_substr(data, line))?;
+                    *slot = Some(value);
+                },
+            }
+        }
+
+        let polygon = polygon.ok_or(ParseError::EndOfBufferError)?;
+
+        // Every texture from 0 to NTEX should have been given.
+        let textures = match tex_count {
+            Some(_) => textures.into_iter().collect::<Option<Vec<_>>>().ok_or(ParseError::EndOfBufferError)?,
+            None => Vec::new(),
+        };
+
+        Ok(Self { version, polygon, material, group, textures })
+    }
+
+    /// The name of the `.P` file in `char.lgp` that holds this resource's polygon data.
+    pub fn polygon_file(&self) -> String {
+        pc_file_name(self.polygon, "P")
+    }
+
+    /// The names of the `.TEX` files in `char.lgp` that hold this resource's textures, in order.
+    pub fn texture_files(&self) -> Vec<String> {
+        self.textures.iter().map(|name| pc_file_name(name, "TEX")).collect()
+    }
+}
+
+
+
+/// An owned copy of a [`ResourceFile`], which can be kept after the file's data is gone. Use
+/// [`as_borrowed`][Self::as_borrowed] to get a [`ResourceFile`] back to work with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResourceFileOwned {
+    pub version: String,
+    pub polygon: String,
+    pub material: Option<String>,
+    pub group: Option<String>,
+    pub textures: Vec<String>,
+}
+
+
+impl<'a> ResourceFile<'a> {
+    /// Copies everything that this file borrows, so that it no longer depends on the data it was parsed from.
+    pub fn into_owned(self) -> ResourceFileOwned {
+        ResourceFileOwned {
+            version: self.version.to_owned(),
+            polygon: self.polygon.to_owned(),
+            material: self.material.map(str::to_owned),
+            group: self.group.map(str::to_owned),
+            textures: self.textures.into_iter().map(str::to_owned).collect(),
+        }
+    }
+}
+
+
+impl ResourceFileOwned {
+    pub fn as_borrowed(&self) -> ResourceFile<'_> {
+        ResourceFile {
+            version: &self.version,
+            polygon: &self.polygon,
+            material: self.material.as_deref(),
+            group: self.group.as_deref(),
+            textures: self.textures.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+
+/// Swaps the extension of an original PlayStation file name for the one used by the PC version.
+fn pc_file_name(name: &str, extension: &str) -> String {
+    let stem = Path::new(name).file_stem().and_then(|stem| stem.to_str()).unwrap_or(name);
+    format!("{stem}.{extension}")
+}
@@ -0,0 +1,389 @@
+//! Parses [TEX files](https://wiki.ffrtt.ru/index.php/FF7/TEX_format), the PC version's texture format.
+//!
+//! TEX files start with a 236-byte header that describes the image's size and pixel format, followed by an optional
+//! set of palettes, the pixel data, and an optional per-palette color key table. Pixels are either indices into a
+//! palette, or direct colors described by a set of channel masks and shifts.
+
+use super::p::{read_array, Color};
+use crate::extract::{read, read_u32, ParseError};
+
+
+/// The size of the header at the start of every TEX file.
+const HEADER_LEN: usize = 0xEC;
+
+
+/// How each channel of a direct-color pixel is laid out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PixelFormat {
+    /// How many bits each channel has, in RGBA order.
+    pub bits: [u32; 4],
+    /// The mask used to extract each channel from a pixel, in RGBA order.
+    pub masks: [u32; 4],
+    /// How far each channel is shifted within a pixel, in RGBA order.
+    pub shifts: [u32; 4],
+    /// The maximum value of each channel, in RGBA order.
+    pub maxes: [u32; 4],
+}
+
+
+/// A decoded RGBA image with 8 bits per channel, stored top-to-bottom, left-to-right.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    /// `width * height * 4` bytes of RGBA data.
+    pub pixels: Vec<u8>,
+}
+
+
+/// The parsed contents of a TEX file.
+#[derive(Debug, Clone)]
+pub struct TextureFile<'a> {
+    pub width: u32,
+    pub height: u32,
+
+    /// Whether or not black pixels should be treated as transparent.
+    pub color_key: bool,
+
+    /// How many bits are used for each pixel.
+    pub bits_per_pixel: u32,
+    pub bytes_per_pixel: u32,
+
+    /// The layout of direct-color pixels. Unused for paletted textures.
+    pub pixel_format: PixelFormat,
+
+    /// The number of palettes in the file.
+    pub palette_count: u32,
+    pub colors_per_palette: u32,
+    /// Every palette's colors, one after the other. Empty for textures that aren't paletted.
+    pub palette: Vec<Color>,
+
+    /// For each palette, whether or not the color key applies to it. Empty if the file has no color key table, in which
+    /// case [`color_key`][Self::color_key] applies to every palette.
+    pub color_key_table: Vec<bool>,
+
+    /// The raw pixel data, `width * height * bytes_per_pixel` bytes long.
+    pub pixels: &'a [u8],
+}
+
+
+impl<'a> TextureFile<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        // The header is a long list of 32-bit fields; read it all at once and pick out the ones we need.
+        let mut ptr = 0;
+        let header = read_array(data, &mut ptr, HEADER_LEN / 4, read_u32)?;
+        let field = |offset: usize| header[offset / 4];
+
+        let color_key = field(0x08) != 0;
+        let palette_count = field(0x30);
+        let colors_per_palette = field(0x34);
+        let width = field(0x3C);
+        let height = field(0x40);
+        let has_palette = field(0x4C) != 0;
+        let palette_size = field(0x58);
+        let bits_per_pixel = field(0x64);
+        let bytes_per_pixel = field(0x68);
+
+        let pixel_format = PixelFormat {
+            bits: [field(0x6C), field(0x70), field(0x74), field(0x78)],
+            masks: [field(0x7C), field(0x80), field(0x84), field(0x88)],
+            shifts: [field(0x8C), field(0x90), field(0x94), field(0x98)],
+            maxes: [field(0xAC), field(0xB0), field(0xB4), field(0xB8)],
+        };
+
+        let has_color_key_table = field(0xBC) != 0;
+
+        let palette = if has_palette {
+            read_array(data, &mut ptr, palette_size as usize, Color::read_bgra)?
+        } else {
+            Vec::new()
+        };
+
+        let pixel_len = (width as usize)
+            .saturating_mul(height as usize)
+            .saturating_mul(bytes_per_pixel as usize);
+        let pixels = read(data, &mut ptr, pixel_len)?;
+
+        let color_key_table = if has_color_key_table {
+            read(data, &mut ptr, palette_count as usize)?.iter().map(|&b| b != 0).collect()
+        } else {
+            Vec::new()
+        };
+
+        if has_palette && bytes_per_pixel != 1 {
+            // Paletted pixels are always a single byte on PC.
+            return Err(ParseError::InvalidValueError(&data[0x68..0x6C], 0x68));
+        }
+
+        if !has_palette && !(1..=4).contains(&bytes_per_pixel) {
+            return Err(ParseError::InvalidValueError(&data[0x68..0x6C], 0x68));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            color_key,
+            bits_per_pixel,
+            bytes_per_pixel,
+            pixel_format,
+            palette_count,
+            colors_per_palette,
+            palette,
+            color_key_table,
+            pixels,
+        })
+    }
+
+    /// Whether or not the pixels in this texture are palette indices.
+    pub fn is_paletted(&self) -> bool {
+        !self.palette.is_empty()
+    }
+
+    /// Gets the colors of a single palette, or `None` if the index is out of range.
+    pub fn palette(&self, index: u32) -> Option<&[Color]> {
+        let start = (index as usize).checked_mul(self.colors_per_palette as usize)?;
+        self.palette.get(start..start.checked_add(self.colors_per_palette as usize)?)
+    }
+
+    /// Whether or not the color key applies when the texture is decoded with the given palette, so that its black
+    /// pixels come out transparent. `palette_index` is ignored for textures that aren't paletted.
+    pub fn is_keyed(&self, palette_index: u32) -> bool {
+        let table = self.is_paletted().then(|| self.color_key_table.get(palette_index as usize)).flatten();
+        self.color_key && table.copied().unwrap_or(true)
+    }
+
+    /// Decodes the texture into RGBA pixels, resolving paletted pixels using the given palette. `palette_index` is
+    /// ignored for textures that aren't paletted.
+    ///
+    /// Returns `None` if the texture is paletted and `palette_index` is out of range.
+    pub fn decode(&self, palette_index: u32) -> Option<RgbaImage> {
+        let mut pixels = Vec::with_capacity(self.pixels.len() * 4 / self.bytes_per_pixel.max(1) as usize);
+
+        if self.is_paletted() {
+            let palette = self.palette(palette_index)?;
+            let keyed = self.is_keyed(palette_index);
+
+            for &index in self.pixels {
+                // Out-of-range indices shouldn't happen, but show them as transparent rather than failing outright.
+                let color = palette.get(index as usize).copied().unwrap_or_default();
+                pixels.extend_from_slice(&self.key(color, keyed).to_array());
+            }
+        } else {
+            for bytes in self.pixels.chunks_exact(self.bytes_per_pixel as usize) {
+                let mut value = [0u8; 4];
+                value[..bytes.len()].copy_from_slice(bytes);
+                let color = self.direct_color(u32::from_le_bytes(value));
+                pixels.extend_from_slice(&self.key(color, self.is_keyed(0)).to_array());
+            }
+        }
+
+        Some(RgbaImage { width: self.width, height: self.height, pixels })
+    }
+
+    /// Decodes the texture once with each of its palettes, in order. Textures that aren't paletted are decoded once.
+    pub fn decode_palettes(&self) -> Vec<RgbaImage> {
+        let count = if self.is_paletted() { self.palette_count.max(1) } else { 1 };
+        (0..count).map_while(|i| self.decode(i)).collect()
+    }
+
+    /// Unpacks a direct-color pixel into 8-bit channels.
+    fn direct_color(&self, value: u32) -> Color {
+        let fmt = &self.pixel_format;
+        let channel = |i: usize| {
+            if fmt.bits[i] == 0 || fmt.maxes[i] == 0 {
+                // Missing channels (usually alpha) are fully saturated.
+                return 255;
+            }
+
+            let raw = (value & fmt.masks[i]).checked_shr(fmt.shifts[i]).unwrap_or(0);
+            (raw.min(fmt.maxes[i]) as u64 * 255 / fmt.maxes[i] as u64) as u8
+        };
+
+        Color { r: channel(0), g: channel(1), b: channel(2), a: channel(3) }
+    }
+
+    /// Applies the color key to a color: when keyed, pure black becomes fully transparent.
+    fn key(&self, color: Color, keyed: bool) -> Color {
+        if keyed && color.r == 0 && color.g == 0 && color.b == 0 {
+            Color { a: 0, ..color }
+        } else {
+            color
+        }
+    }
+}
+
+
+/// An owned copy of a [`TextureFile`], which can be kept after the file's data is gone. Use
+/// [`as_borrowed`][Self::as_borrowed] to get a [`TextureFile`] back to decode.
+#[derive(Debug, Clone)]
+pub struct TextureFileOwned {
+    pub width: u32,
+    pub height: u32,
+    pub color_key: bool,
+    pub bits_per_pixel: u32,
+    pub bytes_per_pixel: u32,
+    pub pixel_format: PixelFormat,
+    pub palette_count: u32,
+    pub colors_per_palette: u32,
+    pub palette: Vec<Color>,
+    pub color_key_table: Vec<bool>,
+    pub pixels: Vec<u8>,
+}
+
+
+impl<'a> TextureFile<'a> {
+    /// Copies the pixel data, so that the texture no longer depends on the data it was parsed from.
+    pub fn into_owned(self) -> TextureFileOwned {
+        TextureFileOwned {
+            width: self.width,
+            height: self.height,
+            color_key: self.color_key,
+            bits_per_pixel: self.bits_per_pixel,
+            bytes_per_pixel: self.bytes_per_pixel,
+            pixel_format: self.pixel_format,
+            palette_count: self.palette_count,
+            colors_per_palette: self.colors_per_palette,
+            palette: self.palette,
+            color_key_table: self.color_key_table,
+            pixels: self.pixels.to_vec(),
+        }
+    }
+}
+
+
+impl TextureFileOwned {
+    /// Borrows the texture as a [`TextureFile`]. The palette and color key table are copied; the pixels are not.
+    pub fn as_borrowed(&self) -> TextureFile<'_> {
+        TextureFile {
+            width: self.width,
+            height: self.height,
+            color_key: self.color_key,
+            bits_per_pixel: self.bits_per_pixel,
+            bytes_per_pixel: self.bytes_per_pixel,
+            pixel_format: self.pixel_format,
+            palette_count: self.palette_count,
+            colors_per_palette: self.colors_per_palette,
+            palette: self.palette.clone(),
+            color_key_table: self.color_key_table.clone(),
+            pixels: &self.pixels,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a TEX file from its header fields (as offsets and values) and everything after the header.
+    fn tex_file(fields: &[(usize, u32)], body: &[u8]) -> Vec<u8> {
+        let mut data = vec![0; HEADER_LEN];
+        for &(offset, value) in fields {
+            data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(body);
+        data
+    }
+
+    /// A 2×1 paletted texture with two palettes of two colors each. The color key is on, but the color key table only
+    /// applies it to the first palette.
+    fn paletted() -> Vec<u8> {
+        let fields = [
+            (0x08, 1), // color key
+            (0x30, 2), // palette count
+            (0x34, 2), // colors per palette
+            (0x3C, 2), // width
+            (0x40, 1), // height
+            (0x4C, 1), // has palette
+            (0x58, 4), // palette size
+            (0x64, 8), // bits per pixel
+            (0x68, 1), // bytes per pixel
+            (0xBC, 1), // has color key table
+        ];
+
+        let mut body = Vec::new();
+        // Palettes, in BGRA: black and red, then black and blue
+        for color in [0xFF000000u32, 0xFFFF0000, 0xFF000000, 0xFF0000FF] {
+            body.extend_from_slice(&color.to_le_bytes());
+        }
+        body.extend_from_slice(&[0, 1]); // pixels
+        body.extend_from_slice(&[1, 0]); // color key table
+        tex_file(&fields, &body)
+    }
+
+    #[test]
+    fn paletted_textures_are_decoded_with_each_palette() {
+        let data = paletted();
+        let texture = TextureFile::from_bytes(&data).unwrap();
+        assert!(texture.is_paletted());
+        assert_eq!((texture.width, texture.height), (2, 1));
+        let black = Color { r: 0, g: 0, b: 0, a: 0xFF };
+        let blue = Color { r: 0, g: 0, b: 0xFF, a: 0xFF };
+        assert_eq!(texture.palette(1), Some(&[black, blue][..]));
+        assert_eq!(texture.palette(2), None);
+        assert_eq!(texture.color_key_table, [true, false]);
+
+        let images = texture.decode_palettes();
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].pixels, [0, 0, 0, 0, 0xFF, 0, 0, 0xFF]);
+        assert_eq!(images[1].pixels, [0, 0, 0, 0xFF, 0, 0, 0xFF, 0xFF]);
+        assert_eq!(texture.decode(2), None);
+    }
+
+    #[test]
+    fn color_key_follows_the_table() {
+        let data = paletted();
+        let texture = TextureFile::from_bytes(&data).unwrap();
+        assert!(texture.is_keyed(0));
+        assert!(!texture.is_keyed(1));
+        // Palettes past the end of the table fall back to the header's flag.
+        assert!(texture.is_keyed(5));
+
+        let mut unkeyed = data.clone();
+        unkeyed[0x08] = 0;
+        let texture = TextureFile::from_bytes(&unkeyed).unwrap();
+        assert!(!texture.is_keyed(0));
+        assert_eq!(texture.decode(0).unwrap().pixels[3], 0xFF);
+    }
+
+    #[test]
+    fn direct_color_pixels_are_unpacked() {
+        // One ARGB1555 pixel: opaque, full red, half green, no blue
+        let fields = [
+            (0x3C, 1),
+            (0x40, 1),
+            (0x64, 16),
+            (0x68, 2),
+            (0x6C, 5),
+            (0x70, 5),
+            (0x74, 5),
+            (0x78, 1),
+            (0x7C, 0x7C00),
+            (0x80, 0x03E0),
+            (0x84, 0x001F),
+            (0x88, 0x8000),
+            (0x8C, 10),
+            (0x90, 5),
+            (0x94, 0),
+            (0x98, 15),
+            (0xAC, 31),
+            (0xB0, 31),
+            (0xB4, 31),
+            (0xB8, 1),
+        ];
+        let pixel: u16 = 0x8000 | (31 << 10) | (15 << 5);
+        let data = tex_file(&fields, &pixel.to_le_bytes());
+
+        let texture = TextureFile::from_bytes(&data).unwrap();
+        assert!(!texture.is_paletted());
+        assert_eq!(texture.decode(0).unwrap().pixels, [0xFF, 123, 0, 0xFF]); // 15/31 of 255, rounded down
+    }
+
+    #[test]
+    fn paletted_pixels_must_be_one_byte() {
+        let mut data = paletted();
+        data[0x68] = 2;
+        data.extend_from_slice(&[0, 0]); // make room for the larger pixels
+        assert!(matches!(TextureFile::from_bytes(&data), Err(ParseError::InvalidValueError(_, 0x68))));
+    }
+}
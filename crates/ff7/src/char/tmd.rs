@@ -0,0 +1,393 @@
+//! Parses TMD files, the PlayStation SDK's standard model format, into the same [`PolygonFile`]s that P files produce.
+//!
+//! A TMD file has a 12-byte header (an ID of `0x41`, some flags, and an object count), followed by a table with one
+//! 28-byte entry per object. Each entry points to the object's vertices, normals, and primitives, which are stored one
+//! after another in the rest of the file. Offsets are relative to the start of the object table, unless the `FIXP` flag
+//! is set.
+//!
+//! Vertices and normals are both three `i16`s padded out to 8 bytes; normals are fixed-point, with 4096 being 1.0.
+//! Each primitive starts with a 4-byte header: the length of the GPU packet it becomes and of its own data (both in
+//! words), then some flags, and a mode byte. The flags and mode together decide the layout of the rest of the data.
+//!
+//! Unlike P files, TMD primitives each have their own colors and texture coordinates, so every corner of every
+//! primitive becomes a separate vertex once converted. Quads are split into two triangles. Lines and sprites are
+//! skipped, since P files have nothing to hold them.
+
+use super::render_features::{ALPHA_BLEND, NO_CULL, TEXTURE};
+use super::{read_array, BoundingBox, Color, Polygon, PolygonFile, PolygonGroup, RenderState};
+use crate::extract::{read, read_i16, read_u16, read_u32, ParseError};
+
+
+/// The ID at the start of every TMD file.
+const TMD_ID: u32 = 0x41;
+
+/// The length of the file's header, which the object table comes directly after.
+const HEADER_LEN: usize = 12;
+
+/// The brightness that colors are given when a primitive doesn't have any. The PlayStation draws textures at their
+/// normal brightness when they are multiplied by 128, not 255.
+const NEUTRAL_COLOR: Color = Color { r: 128, g: 128, b: 128, a: 255 };
+
+/// The value of [`RenderState::blend_mode`] that means "no blending".
+const NO_BLEND: u32 = 4;
+
+/// The most primitives that go in one [`PolygonGroup`]. Each has at most four corners, so a group's vertices can always
+/// be indexed with a `u16`.
+const MAX_GROUP_PRIMITIVES: usize = u16::MAX as usize / 4;
+
+
+/// Where a textured primitive's texture is in the PlayStation's video memory, given by the primitive's texture page
+/// (`TSB`) and palette (`CBA`) fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TmdTexture {
+    pub tsb: u16,
+    pub cba: u16,
+}
+
+
+impl TmdTexture {
+    /// The position of the texture page in video memory, in 16-bit units.
+    pub fn page_position(&self) -> [u16; 2] {
+        [(self.tsb & 0x0F) * 64, ((self.tsb >> 4) & 0x01) * 256]
+    }
+
+    /// How many bits each of the texture's pixels take up: 4 or 8 for paletted textures, or 16 for direct colors.
+    pub fn bit_depth(&self) -> u8 {
+        match (self.tsb >> 7) & 0x03 {
+            0 => 4,
+            1 => 8,
+            _ => 16,
+        }
+    }
+
+    /// The position of the texture's palette in video memory, in 16-bit units.
+    pub fn palette_position(&self) -> [u16; 2] {
+        [(self.cba & 0x3F) * 16, (self.cba >> 6) & 0x01FF]
+    }
+
+    /// The texture page's blending mode, in the same numbering as [`RenderState::blend_mode`].
+    fn blend_mode(&self) -> u32 {
+        ((self.tsb >> 5) & 0x03) as u32
+    }
+}
+
+
+/// The parsed contents of a TMD file.
+#[derive(Debug, Clone)]
+pub struct TmdFile {
+    /// Every object in the file, converted to the same form as a P file. Each group's
+    /// [`texture_index`][PolygonGroup::texture_index] is an index into [`textures`][Self::textures].
+    pub objects: Vec<PolygonFile>,
+    /// Every distinct texture used by any of the objects, in the order that they first appear.
+    pub textures: Vec<TmdTexture>,
+}
+
+
+impl TmdFile {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = 0;
+
+        let id_data = read(data, &mut ptr, 4)?;
+        if id_data != TMD_ID.to_le_bytes() {
+            return Err(ParseError::InvalidValueError(id_data, 0));
+        }
+
+        let fixp = read_u32(data, &mut ptr)? & 0x01 != 0;
+        let object_count = read_u32(data, &mut ptr)? as usize;
+        let base = if fixp { 0 } else { HEADER_LEN };
+
+        let mut objects = Vec::with_capacity(object_count.min(data.len() / 28));
+        let mut textures = Vec::new();
+        for _ in 0..object_count {
+            let mut field = || read_u32(data, &mut ptr).map(|value| value as usize);
+            let (vertex_ptr, vertex_count) = (field()?, field()?);
+            let (normal_ptr, normal_count) = (field()?, field()?);
+            let (primitive_ptr, primitive_count) = (field()?, field()?);
+            let _scale = field()?; // unused by the game
+
+            let vertices = read_array(data, &mut (base + vertex_ptr), vertex_count, read_svector)?;
+            let normals = read_array(data, &mut (base + normal_ptr), normal_count, read_svector)?;
+
+            let mut primitive_ptr = base + primitive_ptr;
+            let mut primitives = Vec::with_capacity(primitive_count.min(data.len() / 4));
+            for _ in 0..primitive_count {
+                let &[_packet_len, len, flag, mode] = read(data, &mut primitive_ptr, 4)? else {
+                    unreachable!(); // success of `read` with length 4 guarantees slice length
+                };
+
+                let start = primitive_ptr;
+                let packet = read(data, &mut primitive_ptr, len as usize * 4)?;
+                // Offsets in errors are relative to the primitive's data; make them relative to the whole file instead.
+                let primitive = read_primitive(packet, flag, mode, vertices.len()).map_err(|err| match err {
+                    ParseError::InvalidValueError(bytes, at) => ParseError::InvalidValueError(bytes, start + at),
+                    err => err,
+                })?;
+                primitives.extend(primitive);
+            }
+
+            objects.push(to_polygon_file(&vertices, &normals, &primitives, &mut textures));
+        }
+
+        Ok(Self { objects, textures })
+    }
+}
+
+
+/// One corner of a [`Primitive`].
+#[derive(Debug, Clone, Copy, Default)]
+struct Corner {
+    vertex: u16,
+    /// The index of the corner's normal, or `None` for primitives that aren't lit.
+    normal: Option<u16>,
+    uv: [u8; 2],
+    color: Option<Color>,
+}
+
+
+/// Everything about a primitive that decides which group it goes in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Material {
+    texture: Option<TmdTexture>,
+    blended: bool,
+    double_sided: bool,
+}
+
+
+/// A triangle or quad, with its layout already untangled.
+#[derive(Debug, Clone, Copy)]
+struct Primitive {
+    corners: [Corner; 4],
+    quad: bool,
+    material: Material,
+}
+
+
+/// Reads a primitive's data, given its flags and mode. Returns `None` for primitives that aren't polygons.
+///
+/// The data is made up of up to three parts, in order: one texture coordinate word per corner, colors, and then the
+/// vertex and normal indices. Which of those are there, and how many of each, depends on whether the primitive is
+/// lit, textured, and smooth-shaded.
+fn read_primitive(
+    packet: &[u8],
+    flag: u8,
+    mode: u8,
+    vertex_count: usize,
+) -> Result<Option<Primitive>, ParseError<'_>> {
+    if mode >> 5 != 0x01 {
+        return Ok(None); // log warning?
+    }
+
+    let smooth = mode & 0x10 != 0;
+    let quad = mode & 0x08 != 0;
+    let textured = mode & 0x04 != 0;
+    let blended = mode & 0x02 != 0;
+    let lit = flag & 0x01 == 0;
+    let double_sided = flag & 0x02 != 0;
+    let gradient = flag & 0x04 != 0;
+
+    let n = if quad { 4 } else { 3 };
+    let mut corners = [Corner::default(); 4];
+    let mut ptr = 0;
+
+    // The first two corners' texture coordinates share their words with the palette and texture page.
+    let mut texture = None;
+    if textured {
+        let mut extra = [0u16; 2];
+        for (i, corner) in corners[..n].iter_mut().enumerate() {
+            let &[u, v, lo, hi] = read(packet, &mut ptr, 4)? else {
+                unreachable!(); // success of `read` with length 4 guarantees slice length
+            };
+            corner.uv = [u, v];
+            if let Some(value) = extra.get_mut(i) {
+                *value = u16::from_le_bytes([lo, hi]);
+            }
+        }
+        texture = Some(TmdTexture { cba: extra[0], tsb: extra[1] });
+    }
+
+    // Lit, textured primitives get all of their color from the texture. Otherwise, there's either one color for the
+    // whole primitive or one per corner.
+    let color_count = match (lit, textured) {
+        (true, true) => 0,
+        (true, false) if gradient => n,
+        (false, _) if smooth => n,
+        _ => 1,
+    };
+    let colors = read_array(packet, &mut ptr, color_count, |data, ptr| {
+        let &[r, g, b, _] = read(data, ptr, 4)? else {
+            unreachable!(); // success of `read` with length 4 guarantees slice length
+        };
+        Ok(Color { r, g, b, a: 255 })
+    })?;
+    for (i, corner) in corners[..n].iter_mut().enumerate() {
+        corner.color = colors.get(i).or(colors.first()).copied();
+    }
+
+    // Smooth-shaded, lit primitives have a normal for each corner; flat ones have a single normal up front.
+    let flat_normal = if lit && !smooth { Some(read_u16(packet, &mut ptr)?) } else { None };
+    for corner in corners[..n].iter_mut() {
+        corner.normal = if lit && smooth { Some(read_u16(packet, &mut ptr)?) } else { flat_normal };
+
+        let index_ptr = ptr;
+        corner.vertex = read_u16(packet, &mut ptr)?;
+        if corner.vertex as usize >= vertex_count {
+            return Err(ParseError::InvalidValueError(&packet[index_ptr..ptr], index_ptr));
+        }
+    }
+
+    // Anything left over is padding.
+    Ok(Some(Primitive { corners, quad, material: Material { texture, blended, double_sided } }))
+}
+
+
+/// Converts one object's primitives into a [`PolygonFile`], grouping them by material. New textures are added to
+/// `textures`.
+fn to_polygon_file(
+    positions: &[[i16; 3]],
+    tmd_normals: &[[i16; 3]],
+    primitives: &[Primitive],
+    textures: &mut Vec<TmdTexture>,
+) -> PolygonFile {
+    let mut materials: Vec<(Material, Vec<&Primitive>)> = Vec::new();
+    for primitive in primitives {
+        match materials.iter_mut().find(|(material, _)| *material == primitive.material) {
+            Some((_, group)) => group.push(primitive),
+            None => materials.push((primitive.material, vec![primitive])),
+        }
+    }
+
+    // Unlit primitives have no normals, so they all share a zero normal at the end.
+    // Corners can only refer to the first 65535 normals anyway, which leaves room for the zero normal's index.
+    let normals = tmd_normals.iter().take(u16::MAX as usize);
+    let mut normals = normals.map(|n| n.map(|v| v as f32 / 4096.0)).collect::<Vec<_>>();
+    let zero_normal = normals.len() as u16;
+    normals.push([0.0; 3]);
+
+    let mut vertices = Vec::new();
+    let mut tex_coords = Vec::new();
+    let mut vertex_colors = Vec::new();
+    let mut polygon_colors = Vec::new();
+    let mut polygons = Vec::new();
+    let mut render_states = Vec::new();
+    let mut groups = Vec::new();
+
+    // Polygons index their vertices with a `u16` relative to their group, so materials with too many primitives are
+    // split across several groups.
+    let chunks = materials.iter().flat_map(|(material, primitives)| {
+        primitives.chunks(MAX_GROUP_PRIMITIVES).map(move |chunk| (*material, chunk))
+    });
+    for (material, primitives) in chunks {
+        let vertex_start = vertices.len();
+        let polygon_start = polygons.len();
+
+        for primitive in primitives {
+            let first = (vertices.len() - vertex_start) as u16;
+            let n = if primitive.quad { 4 } else { 3 };
+            for corner in &primitive.corners[..n] {
+                vertices.push(positions[corner.vertex as usize].map(|v| v as f32));
+                tex_coords.push(corner.uv.map(|v| v as f32 / 256.0));
+                vertex_colors.push(corner.color.unwrap_or(NEUTRAL_COLOR));
+            }
+
+            // The PlayStation draws quads as two triangles, (0, 1, 2) and (1, 3, 2).
+            let triangles: &[[usize; 3]] = if primitive.quad { &[[0, 1, 2], [1, 3, 2]] } else { &[[0, 1, 2]] };
+            for triangle in triangles {
+                polygons.push(Polygon {
+                    vertices: triangle.map(|i| first + i as u16),
+                    normals: triangle.map(|i| primitive.corners[i].normal.unwrap_or(zero_normal)),
+                    edges: [0; 3],
+                });
+                polygon_colors.push(primitive.corners[0].color.unwrap_or(NEUTRAL_COLOR));
+            }
+        }
+
+        let texture_index = material.texture.map(|texture| match textures.iter().position(|&t| t == texture) {
+            Some(index) => index,
+            None => {
+                textures.push(texture);
+                textures.len() - 1
+            },
+        });
+
+        let blend_mode = match (material.blended, material.texture) {
+            (false, _) => NO_BLEND,
+            (true, Some(texture)) => texture.blend_mode(),
+            (true, None) => 0, // untextured primitives have no texture page to say otherwise; average is the default
+        };
+
+        let mut feature_values = 0;
+        for (enabled, feature) in [
+            (material.texture.is_some(), TEXTURE),
+            (material.blended, ALPHA_BLEND),
+            (material.double_sided, NO_CULL),
+        ] {
+            if enabled {
+                feature_values |= feature;
+            }
+        }
+
+        // Only the fields that P files' render states are read from are filled in. See `read_render_state`.
+        let mut raw = [0; 25];
+        raw[2] = TEXTURE | ALPHA_BLEND | NO_CULL;
+        raw[3] = feature_values;
+        raw[5] = texture_index.unwrap_or(0) as u32;
+        raw[17] = blend_mode;
+        render_states.push(RenderState {
+            feature_mask: raw[2],
+            feature_values: raw[3],
+            texture_id: raw[5],
+            blend_mode: raw[17],
+            raw,
+        });
+
+        groups.push(PolygonGroup {
+            primitive_type: 0, // TMD primitives have no equivalent
+            polygon_start: polygon_start as u32,
+            polygon_count: (polygons.len() - polygon_start) as u32,
+            vertex_start: vertex_start as u32,
+            vertex_count: (vertices.len() - vertex_start) as u32,
+            edge_start: 0,
+            edge_count: 0,
+            tex_coord_start: vertex_start as u32,
+            textured: material.texture.is_some(),
+            texture_index: texture_index.unwrap_or(0) as u32,
+        });
+    }
+
+    let bounding_boxes = bounding_box(&vertices).into_iter().collect();
+
+    PolygonFile {
+        vertex_color: true,
+        vertices,
+        normals,
+        tex_coords,
+        vertex_colors,
+        polygon_colors,
+        edges: Vec::new(),
+        polygons,
+        render_states,
+        groups,
+        bounding_boxes,
+        normal_indices: Vec::new(),
+    }
+}
+
+
+fn bounding_box(vertices: &[[f32; 3]]) -> Option<BoundingBox> {
+    let first = *vertices.first()?;
+    let mut bounds = BoundingBox { max: first, min: first };
+    for vertex in vertices {
+        bounds.max = [0, 1, 2].map(|axis| bounds.max[axis].max(vertex[axis]));
+        bounds.min = [0, 1, 2].map(|axis| bounds.min[axis].min(vertex[axis]));
+    }
+    Some(bounds)
+}
+
+
+/// Reads an `SVECTOR`: three `i16`s, padded to 8 bytes.
+fn read_svector<'a>(data: &'a [u8], ptr: &mut usize) -> Result<[i16; 3], ParseError<'a>> {
+    let vector = [read_i16(data, ptr)?, read_i16(data, ptr)?, read_i16(data, ptr)?];
+    read(data, ptr, 2)?; // padding
+    Ok(vector)
+}
+
@@ -0,0 +1,283 @@
+//! Reads files straight out of a PlayStation disc image, through its [ISO 9660](https://wiki.osdev.org/ISO_9660) file
+//! system.
+//!
+//! Disc images come in two layouts. Plain `.iso` files hold only the 2048 bytes of data from each sector, while raw
+//! `.bin` files keep all 2352 bytes of every sector as it is on the disc. PlayStation discs use Mode 2 Form 1 sectors
+//! for their files, where the data starts after a 12-byte sync pattern, a 4-byte header, and an 8-byte subheader.
+//! Both layouts are detected automatically.
+//!
+//! The file system starts with the primary volume descriptor in sector 16, which holds the root directory's record.
+//! Each directory is a list of records, one per file or subdirectory, giving the sector that its data starts at and
+//! its length. Records never cross a sector boundary; a record length of zero means that the rest of the sector is
+//! padding.
+//!
+//! Only Form 1 files can be read. Streamed audio and video (`.XA` and `.STR` files) use Form 2 sectors, which hold
+//! 2324 bytes of data each, and will come out garbled. The files in `FIELD/` are LZSS compressed like those in the
+//! PC version's archives (see [`decompress_lzss`][super::decompress_lzss]).
+
+use std::collections::BTreeSet;
+use std::io::{Read, Seek, SeekFrom};
+
+use super::lgp_reader::read_more;
+use super::{read, read_u32, read_u8, Limits, ReadError};
+
+
+/// The amount of data in each sector.
+const SECTOR_LEN: u64 = 2048;
+
+/// The full size of each sector in a raw image.
+const RAW_SECTOR_LEN: u64 = 2352;
+
+/// Where the data starts in each of a raw image's sectors: after the sync pattern, header, and subheader.
+const RAW_DATA_OFFSET: u64 = 12 + 4 + 8;
+
+/// The sector that the primary volume descriptor is in.
+const VOLUME_DESCRIPTOR_SECTOR: u32 = 16;
+
+/// The type and identifier that the primary volume descriptor starts with.
+const VOLUME_DESCRIPTOR_ID: &[u8] = b"\x01CD001";
+
+/// Where the root directory's record is in the primary volume descriptor.
+const ROOT_RECORD_OFFSET: usize = 156;
+
+/// ISO 9660 doesn't allow directories to be nested more than eight deep.
+const MAX_DEPTH: usize = 8;
+
+
+/// How a disc image's sectors are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectorLayout {
+    /// Only the 2048 bytes of data from each sector (`.iso`).
+    Cooked,
+    /// Every sector in full, 2352 bytes each (`.bin`).
+    Raw,
+}
+
+
+impl SectorLayout {
+    /// Where a sector's data starts in the image.
+    fn data_position(&self, sector: u32) -> u64 {
+        match self {
+            SectorLayout::Cooked => sector as u64 * SECTOR_LEN,
+            SectorLayout::Raw => sector as u64 * RAW_SECTOR_LEN + RAW_DATA_OFFSET,
+        }
+    }
+
+    fn sector_len(&self) -> u64 {
+        match self {
+            SectorLayout::Cooked => SECTOR_LEN,
+            SectorLayout::Raw => RAW_SECTOR_LEN,
+        }
+    }
+}
+
+
+/// A single file on a disc.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscEntry {
+    /// The file's full path, with directories separated by `/` and without the version suffix (e.g.,
+    /// `"FIELD/MD1STIN.DAT"`).
+    pub path: String,
+    /// The sector that the file's data starts at.
+    pub sector: u32,
+    /// The file's length in bytes.
+    pub len: u32,
+}
+
+
+/// A disc image that reads its files on demand from an underlying stream.
+pub struct DiscImage<R: Read + Seek> {
+    source: R,
+    /// The length of the whole stream, used to check extents before reading them.
+    image_len: u64,
+    layout: SectorLayout,
+    /// Every file on the disc, sorted by path.
+    entries: Vec<DiscEntry>,
+    limits: Limits,
+}
+
+
+impl<R: Read + Seek> DiscImage<R> {
+    /// Reads a disc image's file system, starting from the beginning of `source`. Every directory is read up front,
+    /// but no files are.
+    pub fn new(source: R) -> Result<Self, ReadError> {
+        Self::with_limits(source, Limits::default())
+    }
+
+    /// Reads a disc image's file system, like [`new`][Self::new]. Directories and files larger than the limits'
+    /// [`max_entry_size`][Limits::max_entry_size] can't be read.
+    pub fn with_limits(mut source: R, limits: Limits) -> Result<Self, ReadError> {
+        let image_len = source.seek(SeekFrom::End(0))?;
+
+        // Try the raw layout first: a cooked image could only be mistaken for a raw one if its sector 16 happened to
+        // have the descriptor's ID 24 bytes in, which would make it an invalid cooked image anyway.
+        let mut image = Self { source, image_len, layout: SectorLayout::Raw, entries: Vec::new(), limits };
+        let mut descriptor = None;
+        for layout in [SectorLayout::Raw, SectorLayout::Cooked] {
+            image.layout = layout;
+            match image.read_extent(VOLUME_DESCRIPTOR_SECTOR, SECTOR_LEN as usize) {
+                Ok(sector) if sector.starts_with(VOLUME_DESCRIPTOR_ID) => {
+                    descriptor = Some(sector);
+                    break;
+                },
+                Ok(_) | Err(ReadError::EntryOutOfBoundsError(..)) => continue, // too small to be this layout
+                Err(err) => return Err(err),
+            }
+        }
+
+        let Some(descriptor) = descriptor else {
+            return Err(ReadError::ParseError("not an ISO 9660 disc image".to_owned()));
+        };
+
+        let root = read_record(&descriptor, ROOT_RECORD_OFFSET)?;
+        image.entries = image.read_tree(root.sector, root.len)?;
+        Ok(image)
+    }
+
+    /// How the image's sectors are stored.
+    pub fn layout(&self) -> SectorLayout {
+        self.layout
+    }
+
+    /// Every file on the disc, sorted by path.
+    pub fn entries(&self) -> &[DiscEntry] {
+        &self.entries
+    }
+
+    /// Finds a file by its path, ignoring case. Either `/` or `\` can separate directories, and the version suffix
+    /// (`;1`) is optional. Returns `None` if there is no such file.
+    pub fn entry(&self, path: &str) -> Option<&DiscEntry> {
+        let path = path.trim_start_matches(['/', '\\']).replace('\\', "/");
+        let path = strip_version(&path);
+        self.entries.iter().find(|entry| entry.path.eq_ignore_ascii_case(path))
+    }
+
+    /// Reads a file by its path, as in [`entry`][Self::entry]. Returns `Ok(None)` if there is no such file.
+    pub fn get(&mut self, path: &str) -> Result<Option<Vec<u8>>, ReadError> {
+        match self.entry(path) {
+            Some(entry) => {
+                let (sector, len) = (entry.sector, entry.len);
+                self.read_extent(sector, len as usize).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Reads `len` bytes of data, starting at the beginning of the given sector.
+    pub fn read_extent(&mut self, sector: u32, len: usize) -> Result<Vec<u8>, ReadError> {
+        self.limits.check_entry_size(len)?;
+
+        // Check the extent before reading anything, so that a bad one gives a useful error instead of a short read.
+        let sector_count = (len as u64).div_ceil(SECTOR_LEN);
+        let start = sector as u64 * self.layout.sector_len();
+        if start + sector_count * self.layout.sector_len() > self.image_len {
+            return Err(ReadError::EntryOutOfBoundsError(format!("sector {sector}"), start, len as u64, self.image_len));
+        }
+
+        let mut data = Vec::new();
+        match self.layout {
+            SectorLayout::Cooked => {
+                self.source.seek(SeekFrom::Start(self.layout.data_position(sector)))?;
+                read_more(&mut self.source, &mut data, len)?;
+            },
+            SectorLayout::Raw => {
+                // Every sector's data is surrounded by its headers and error correction, so it has to be read one
+                // sector at a time.
+                for i in 0..sector_count as u32 {
+                    self.source.seek(SeekFrom::Start(self.layout.data_position(sector + i)))?;
+                    let remaining = len - data.len();
+                    read_more(&mut self.source, &mut data, remaining.min(SECTOR_LEN as usize))?;
+                }
+            },
+        }
+
+        Ok(data)
+    }
+
+    /// Gives back the underlying stream.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    /// Reads every directory, starting from the root, and lists every file in them.
+    fn read_tree(&mut self, root_sector: u32, root_len: u32) -> Result<Vec<DiscEntry>, ReadError> {
+        let mut entries = Vec::new();
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![(String::new(), root_sector, root_len, 0)];
+
+        while let Some((prefix, sector, len, depth)) = pending.pop() {
+            // A broken image could have a directory that contains itself.
+            if depth > MAX_DEPTH || !visited.insert(sector) {
+                continue; // log warning?
+            }
+
+            let data = self.read_extent(sector, len as usize)?;
+            let mut ptr = 0;
+            while ptr < data.len() {
+                if data[ptr] == 0 {
+                    // The rest of this sector is padding.
+                    ptr = (ptr / SECTOR_LEN as usize + 1) * SECTOR_LEN as usize;
+                    continue;
+                }
+
+                let record = read_record(&data, ptr)?;
+                ptr += record.record_len;
+
+                // The first two records in every directory are the directory itself and its parent.
+                if record.name == "\0" || record.name == "\x01" {
+                    continue;
+                }
+
+                let path = format!("{prefix}{}", strip_version(&record.name));
+                if record.is_dir {
+                    pending.push((format!("{path}/"), record.sector, record.len, depth + 1));
+                } else {
+                    entries.push(DiscEntry { path, sector: record.sector, len: record.len });
+                }
+            }
+        }
+
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+}
+
+
+/// The parts of a directory record that are needed to find its data.
+struct Record {
+    record_len: usize,
+    sector: u32,
+    len: u32,
+    is_dir: bool,
+    name: String,
+}
+
+
+/// Reads the directory record that starts at `ptr`.
+fn read_record(data: &[u8], ptr: usize) -> Result<Record, ReadError> {
+    let mut field_ptr = ptr;
+    let record_len = read_u8(data, &mut field_ptr)? as usize;
+    field_ptr = ptr + 2;
+    let sector = read_u32(data, &mut field_ptr)?;
+    field_ptr = ptr + 10;
+    let len = read_u32(data, &mut field_ptr)?;
+    field_ptr = ptr + 25;
+    let is_dir = read_u8(data, &mut field_ptr)? & 0x02 != 0;
+    field_ptr = ptr + 32;
+    let name_len = read_u8(data, &mut field_ptr)? as usize;
+    let name = String::from_utf8_lossy(read(data, &mut field_ptr, name_len)?).into_owned();
+
+    if record_len < 33 + name_len {
+        return Err(ReadError::ParseError(format!("directory record for \"{name}\" is too short")));
+    }
+
+    Ok(Record { record_len, sector, len, is_dir, name })
+}
+
+
+/// Removes the version suffix (e.g., `;1`) from a file name, along with the `.` that files without an extension are
+/// sometimes given.
+fn strip_version(name: &str) -> &str {
+    let name = name.split_once(';').map_or(name, |(name, _)| name);
+    name.strip_suffix('.').unwrap_or(name)
+}
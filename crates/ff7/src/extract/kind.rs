@@ -0,0 +1,232 @@
+//! Works out what kind of file each entry in an archive is, and parses entries as a given kind.
+//!
+//! Archives like `char.lgp` hold model files, but others (`flevel.lgp`, `menu.lgp`, `world_us.lgp`) hold all sorts of
+//! things that there aren't parsers for yet. Every entry is kept as raw bytes, so those archives can still be opened and
+//! listed; the kind is only a hint, inferred from the file's extension.
+//!
+//! Extensions aren't always enough: field files have none, and some archives use an extension for something other than
+//! what it usually means. A [`KindMap`] overrides the inferred kind for particular extensions or file names, either in
+//! every archive or only in one. It is written as plain text, one rule per line, with the archive (or `*` for any
+//! archive), the file name or `*.` and an extension, and the kind, separated by whitespace:
+//!
+//! ```text
+//! # archive    pattern    kind
+//! flevel.lgp   *.dat      field
+//! *            maplist    other
+//! ```
+//!
+//! Names and extensions are matched ignoring case. Rules for a particular archive win over rules for any archive, and
+//! after that, rules for a file name win over rules for an extension.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::{LGPFile, ParseError};
+use crate::char::{AnimationFile, HierarchyFile, PolygonFile, ResourceFile, TextureFile};
+
+
+/// The kind of a file from an archive, based on its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileKind {
+    /// An `.HRC` skeleton.
+    Hierarchy,
+    /// An `.RSD` resource list.
+    Resource,
+    /// A `.P` polygon mesh.
+    Polygon,
+    /// A `.TEX` texture.
+    Texture,
+    /// An `.A` animation.
+    Animation,
+    /// A field file, like those in `flevel.lgp`. These have no extension, so they are only known through a
+    /// [`KindMap`].
+    Field,
+    /// Anything else, including files with no extension at all (like the field files in `flevel.lgp`).
+    Other,
+}
+
+
+impl FileKind {
+    /// Infers a file's kind from its name, ignoring case.
+    pub fn from_name(name: &str) -> Self {
+        let Some(ext) = Path::new(name).extension().and_then(|ext| ext.to_str()) else {
+            return FileKind::Other;
+        };
+
+        match ext.to_ascii_lowercase().as_str() {
+            "hrc" => FileKind::Hierarchy,
+            "rsd" => FileKind::Resource,
+            "p" => FileKind::Polygon,
+            "tex" => FileKind::Texture,
+            "a" => FileKind::Animation,
+            _ => FileKind::Other,
+        }
+    }
+}
+
+
+impl FromStr for FileKind {
+    type Err = KindMapError;
+
+    /// Reads a kind from its name in a [`KindMap`]: `hierarchy`, `resource`, `polygon`, `texture`, `animation`,
+    /// `field`, or `other`. The usual extensions (`hrc`, `rsd`, `p`, `tex`, and `a`) are accepted too.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "hierarchy" | "hrc" => Ok(FileKind::Hierarchy),
+            "resource" | "rsd" => Ok(FileKind::Resource),
+            "polygon" | "p" => Ok(FileKind::Polygon),
+            "texture" | "tex" => Ok(FileKind::Texture),
+            "animation" | "a" => Ok(FileKind::Animation),
+            "field" => Ok(FileKind::Field),
+            "other" => Ok(FileKind::Other),
+            _ => Err(KindMapError::UnknownKindError(s.to_owned())),
+        }
+    }
+}
+
+
+/// An error from reading a [`KindMap`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum KindMapError {
+    #[error("unknown file kind \"{0}\"")]
+    UnknownKindError(String),
+
+    #[error("line {0} of the kind map is not a valid rule")]
+    InvalidLineError(usize),
+}
+
+
+/// What a [`KindMap`] rule matches within an archive.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Pattern {
+    /// Files with this extension, lowercase and without the dot.
+    Extension(String),
+    /// The file with this name, lowercase.
+    Name(String),
+}
+
+
+/// Overrides for the kinds inferred by [`FileKind::from_name`]. See the [module-level documentation](self) for how
+/// rules are written and which ones win.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KindMap {
+    /// Rules keyed by the lowercase archive name they apply to, or `None` for any archive.
+    rules: HashMap<(Option<String>, Pattern), FileKind>,
+}
+
+
+impl KindMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treats every file with the given extension as `kind`, either in one archive or (with `None`) in any archive.
+    pub fn set_extension(&mut self, archive: Option<&str>, extension: &str, kind: FileKind) {
+        let extension = extension.trim_start_matches('.').to_ascii_lowercase();
+        self.rules.insert((archive.map(str::to_ascii_lowercase), Pattern::Extension(extension)), kind);
+    }
+
+    /// Treats the file with the given name as `kind`, either in one archive or (with `None`) in any archive.
+    pub fn set_name(&mut self, archive: Option<&str>, name: &str, kind: FileKind) {
+        self.rules.insert((archive.map(str::to_ascii_lowercase), Pattern::Name(name.to_ascii_lowercase())), kind);
+    }
+
+    /// Works out the kind of a file in the given archive (by its file name, like `flevel.lgp`), falling back on
+    /// [`FileKind::from_name`] when no rule matches.
+    pub fn kind_of(&self, archive: &str, name: &str) -> FileKind {
+        let archive = Some(archive.to_ascii_lowercase());
+        let name = name.to_ascii_lowercase();
+        let extension = Path::new(&name).extension().and_then(|ext| ext.to_str()).map(str::to_owned);
+
+        let mut patterns = vec![Pattern::Name(name.clone())];
+        patterns.extend(extension.map(Pattern::Extension));
+
+        [archive, None]
+            .into_iter()
+            .flat_map(|archive| patterns.iter().map(move |pattern| (archive.clone(), pattern.clone())))
+            .find_map(|key| self.rules.get(&key).copied())
+            .unwrap_or_else(|| FileKind::from_name(&name))
+    }
+}
+
+
+impl FromStr for KindMap {
+    type Err = KindMapError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut map = Self::new();
+
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default();
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let &[archive, pattern, kind] = fields.as_slice() else {
+                if fields.is_empty() {
+                    continue;
+                }
+                return Err(KindMapError::InvalidLineError(i + 1));
+            };
+
+            let archive = (archive != "*").then_some(archive);
+            let kind = kind.parse()?;
+            match pattern.strip_prefix("*.") {
+                Some(extension) if !extension.is_empty() => map.set_extension(archive, extension, kind),
+                None if !pattern.contains('*') => map.set_name(archive, pattern, kind),
+                _ => return Err(KindMapError::InvalidLineError(i + 1)),
+            }
+        }
+
+        Ok(map)
+    }
+}
+
+
+impl<'a> LGPFile<'a> {
+    /// The kind of each file in the archive, in no particular order. Names that are shared by several files appear
+    /// once for each of them.
+    pub fn entry_kinds(&self) -> impl Iterator<Item = (&'a str, FileKind)> + '_ {
+        self.entry_names().map(|name| (name, FileKind::from_name(name)))
+    }
+
+    /// The kind of each file in the archive like [`entry_kinds`][Self::entry_kinds], with a [`KindMap`]'s overrides
+    /// applied. `archive` is the archive's file name, like `flevel.lgp`, for rules that only apply to one archive.
+    pub fn entry_kinds_with<'m>(
+        &'m self,
+        kinds: &'m KindMap,
+        archive: &'m str,
+    ) -> impl Iterator<Item = (&'a str, FileKind)> + 'm {
+        self.entry_names().map(move |name| (name, kinds.kind_of(archive, name)))
+    }
+
+    /// The names of every file of the given kind.
+    pub fn names_of_kind(&self, kind: FileKind) -> impl Iterator<Item = &'a str> + '_ {
+        self.entry_kinds().filter(move |&(_, k)| k == kind).map(|(name, _)| name)
+    }
+
+    /// Parses a file as an [HRC file][HierarchyFile]. Returns `None` if there is no file with that name.
+    pub fn hierarchy(&self, name: &str) -> Option<Result<HierarchyFile<'a>, ParseError<'a>>> {
+        self.get(name).map(HierarchyFile::from_bytes)
+    }
+
+    /// Parses a file as an [RSD file][ResourceFile]. Returns `None` if there is no file with that name.
+    pub fn resource(&self, name: &str) -> Option<Result<ResourceFile<'a>, ParseError<'a>>> {
+        self.get(name).map(ResourceFile::from_bytes)
+    }
+
+    /// Parses a file as a [P file][PolygonFile]. Returns `None` if there is no file with that name.
+    pub fn polygon(&self, name: &str) -> Option<Result<PolygonFile, ParseError<'a>>> {
+        self.get(name).map(PolygonFile::from_bytes)
+    }
+
+    /// Parses a file as a [TEX file][TextureFile]. Returns `None` if there is no file with that name.
+    pub fn texture(&self, name: &str) -> Option<Result<TextureFile<'a>, ParseError<'a>>> {
+        self.get(name).map(TextureFile::from_bytes)
+    }
+
+    /// Parses a file as an [A file][AnimationFile]. Returns `None` if there is no file with that name.
+    pub fn animation(&self, name: &str) -> Option<Result<AnimationFile, ParseError<'a>>> {
+        self.get(name).map(AnimationFile::from_bytes)
+    }
+}
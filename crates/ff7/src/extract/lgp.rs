@@ -1,11 +1,126 @@
 //! Extracts [LGP files](https://wiki.ffrtt.ru/index.php/FF7/LGP_format).
 
 use std::collections::HashMap;
+use std::path::Path;
 
-use super::{read, sz_to_str, u16_from_le_bytes, u32_from_le_bytes, ParseError};
+use super::{read, save_atomic, sz_to_str, u16_from_le_bytes, u32_from_le_bytes, ArchiveManifest, Limits, ParseError};
+use super::WriteError;
 
 
-/// The parsed contents of one LGP file.
+/// The length of the creator string at the start of the file.
+pub(super) const CREATOR_LEN: usize = 12;
+
+/// The length of the file names in the table of contents and in each file's header.
+pub(super) const NAME_LEN: usize = 20;
+
+/// The length of one entry in the table of contents: name, offset, check byte, and duplicate flag.
+pub(super) const TOC_ENTRY_LEN: usize = NAME_LEN + 4 + 1 + 2;
+
+/// The check byte that almost every entry in the table of contents has. A few official archives use `0x0B` instead.
+pub(super) const DEFAULT_CHECK: u8 = 0x0E;
+
+/// The length of the directory paths in the conflict table.
+pub(super) const CONFLICT_PATH_LEN: usize = 128;
+
+/// How many different values each of the first two characters of a name can map to in the lookup table.
+const LOOKUP_VALUE_MAX: usize = 30;
+
+/// The number of entries in the lookup table.
+const LOOKUP_TABLE_ENTRIES: usize = LOOKUP_VALUE_MAX * LOOKUP_VALUE_MAX;
+
+/// The length of the lookup table that sits between the table of contents and the conflict table. It is a 30×30 grid
+/// of (`u16`, `u16`) pairs.
+pub(super) const LOOKUP_TABLE_LEN: usize = LOOKUP_TABLE_ENTRIES * 4;
+
+
+/// Options for [`LGPFile::from_bytes_with_options`] and [`LGPReader::with_options`][super::LGPReader::with_options].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReadOptions {
+    /// Cross-check the lookup table against the table of contents, recording any entries that don't match in
+    /// [`LGPFile::lookup_mismatches`]. The lookup table is otherwise skipped entirely.
+    pub validate_lookup_table: bool,
+
+    /// Caps on the size of the archive's files. Only [`max_entry_size`][Limits::max_entry_size] applies to archives.
+    pub limits: Limits,
+}
+
+
+/// Options for [`LGPFile::to_bytes`] and [`LGPFile::save`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// Store files with identical contents only once, pointing all of their table of contents entries at the same data
+    /// block. The game only uses the offset and size of a block, so this is safe, but the name in the shared block's
+    /// header will only match one of the entries.
+    pub deduplicate: bool,
+
+    /// When saving over an existing archive, keep a copy of the original with `.bak` added to its name. Only used by
+    /// [`LGPFile::save`].
+    pub backup: bool,
+}
+
+
+/// One entry in an LGP file's lookup table. The game uses the first two characters of a file's name to find its entry,
+/// and then searches a run of `count` entries in the table of contents starting at `toc_index`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LookupEntry {
+    /// The 1-based index of the first file in the table of contents with this entry's prefix, or 0 if there are none.
+    pub toc_index: u16,
+    /// How many files in a row share this entry's prefix.
+    pub count: u16,
+}
+
+
+/// An entry in an LGP file's lookup table that does not agree with its table of contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LookupMismatch {
+    /// The entry's position in the lookup table.
+    pub index: usize,
+    /// The entry that the table of contents says should be there.
+    pub expected: LookupEntry,
+    /// The entry that was actually in the file.
+    pub found: LookupEntry,
+}
+
+
+/// One file's entry in an archive's table of contents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LGPEntry<'a> {
+    /// The file's name.
+    pub name: &'a str,
+    /// The file's directory path from the conflict table, if its name is shared with other files in the archive.
+    pub path: Option<&'a str>,
+    /// Where the file's data block starts.
+    pub offset: u32,
+    /// The entry's check byte. What it is for is unknown; it is nearly always [`0x0E`][DEFAULT_CHECK].
+    pub check: u8,
+    /// The (1-based) number of the file's group in the conflict table, or 0 if its name isn't shared.
+    pub dupe: u16,
+}
+
+
+/// An owned copy of an [`LGPEntry`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LGPEntryOwned {
+    pub name: String,
+    pub path: Option<String>,
+    pub offset: u32,
+    pub check: u8,
+    pub dupe: u16,
+}
+
+
+/// Everything in an archive that comes before the files' data: the creator, table of contents, lookup table, and
+/// conflict table.
+pub(super) struct Header<'a> {
+    pub creator: &'a str,
+    pub entries: Vec<LGPEntry<'a>>,
+    pub lookup_table: &'a [u8],
+    /// How many bytes the header takes up.
+    pub len: usize,
+}
+
+
+/// The parsed contents of one LGP file. See [`LGPFileOwned`] for a version that doesn't borrow the archive's data.
 pub struct LGPFile<'a> {
     /// The "creator" marker string from the file.
     ///
@@ -19,18 +134,220 @@ pub struct LGPFile<'a> {
     /// are not incorrect, just uncommon.
     pub terminator: &'a str,
 
+    /// Anything found after the terminator, like padding or junk left behind by other tools. Usually empty. Not
+    /// written back out by [`to_bytes`][Self::to_bytes].
+    pub trailing_data: &'a [u8],
+
     /// All of the files that were found in this LGP archive. Keys are the filenames given to files in the archive and
     /// the values are the raw bytes, ready to be parsed further.
+    ///
+    /// Files whose names appear more than once in the archive are not in this map; see
+    /// [`conflicts`][Self::conflicts].
     pub files: HashMap<&'a str, &'a [u8]>,
+
+    /// Files whose names appear more than once in the archive. These are told apart by a directory path from the
+    /// archive's conflict table, so their keys are `(name, path)` pairs.
+    pub conflicts: HashMap<(&'a str, &'a str), &'a [u8]>,
+
+    /// Entries in the lookup table that don't match the table of contents. Only filled in when reading with
+    /// [`ReadOptions::validate_lookup_table`].
+    pub lookup_mismatches: Vec<LookupMismatch>,
+
+    /// The archive's table of contents, in its original order. Only used to build a [`manifest`][Self::manifest]; it
+    /// is not kept up to date with changes to [`files`][Self::files] or [`conflicts`][Self::conflicts].
+    pub toc: Vec<LGPEntry<'a>>,
 }
 
 
 impl<'a> LGPFile<'a> {
     pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError> {
+        Self::from_bytes_with_options(data, &ReadOptions::default())
+    }
+
+    pub fn from_bytes_with_options(data: &'a [u8], options: &ReadOptions) -> Result<Self, ParseError<'a>> {
+        let header = Header::from_bytes(data)?;
+
+        let lookup_mismatches = if options.validate_lookup_table {
+            header.lookup_mismatches()
+        } else {
+            Vec::new()
+        };
+
+        let mut files = HashMap::with_capacity(header.entries.len());
+        let mut conflicts = HashMap::new();
+        let mut end_of_data = header.len; // updated as we look through the files pointed to by the TOC
+
+        for (i, &LGPEntry { name: file_name, path, offset, .. }) in header.entries.iter().enumerate() {
+            // A file's data can't start inside the header. Point the error at the offending TOC offset.
+            if (offset as usize) < header.len {
+                let offset_pos = CREATOR_LEN + 4 + i * TOC_ENTRY_LEN + NAME_LEN;
+                return Err(ParseError::InvalidValueError(&data[offset_pos..offset_pos + 4], offset_pos));
+            }
+
+            // Go read the file's data
+            // -----------------------
+
+            let mut file_ptr = offset as usize;
+            let out_of_bounds = |start, len| ParseError::EntryOutOfBoundsError(file_name, start, len, data.len());
+
+            // verify that the TOC's name matches the actual file's name
+            let block_header = read(data, &mut file_ptr, NAME_LEN + 4)
+                .map_err(|_| out_of_bounds(offset as usize, NAME_LEN + 4))?;
+            if sz_to_str(&block_header[..NAME_LEN])? != file_name {
+                // log warning?
+            }
+
+            let file_size = u32_from_le_bytes(&block_header[NAME_LEN..])? as usize;
+            options.limits.check_entry_size(file_size)?;
+            let file_data = read(data, &mut file_ptr, file_size).map_err(|_| out_of_bounds(file_ptr, file_size))?;
+
+            // Files flagged as duplicates are told apart by their path; everything else is just known by its name.
+            let existing = match path {
+                None => files.insert(file_name, file_data),
+                Some(path) => conflicts.insert((file_name, path), file_data),
+            };
+
+            if existing.is_some() {
+                return Err(ParseError::DuplicateNameError);
+            }
+
+            // Keep track of the furthest point we find in the file so that we can jump to the end later
+            end_of_data = end_of_data.max(file_ptr);
+        }
+
+        // Finally there is a string, terminated by end of file. Some archives have padding or junk after it, so stop at
+        // the first byte that can't be part of it instead of failing.
+        let (terminator, trailing_data) = split_terminator(&data[end_of_data..]);
+        let (creator, toc) = (header.creator, header.entries);
+        Ok(Self { creator, terminator, trailing_data, files, conflicts, lookup_mismatches, toc })
+    }
+
+    /// Gets a file by its name. Like the game, names are matched case-insensitively, though an exact match is
+    /// preferred. Files whose names are shared with others can only be found with
+    /// [`get_conflicted`][Self::get_conflicted].
+    ///
+    /// The returned data is a slice of the original archive; nothing is copied.
+    pub fn get(&self, name: &str) -> Option<&'a [u8]> {
+        // If several names differ only by case, always pick the same one, rather than whichever the map yields first.
+        self.files.get(name).copied().or_else(|| {
+            self.files
+                .iter()
+                .filter(|(file_name, _)| file_name.eq_ignore_ascii_case(name))
+                .min_by_key(|(&file_name, _)| file_name)
+                .map(|(_, &data)| data)
+        })
+    }
+
+    /// Gets a file whose name is shared with other files in the archive by its name and conflict path.
+    pub fn get_conflicted(&self, name: &str, path: &str) -> Option<&'a [u8]> {
+        self.conflicts.get(&(name, path)).copied()
+    }
+
+    /// The names of every file in the archive, in no particular order. Names that are shared by several files appear
+    /// once for each of them.
+    pub fn entry_names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        let conflicted = self.conflicts.keys().map(|&(name, _)| name);
+        self.files.keys().copied().chain(conflicted)
+    }
+
+    /// The number of files in the archive.
+    pub fn len(&self) -> usize {
+        self.files.len() + self.conflicts.len()
+    }
+
+    /// Whether or not the archive has no files in it.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Records the metadata from this archive's header that [`to_bytes`][Self::to_bytes] would otherwise discard, so
+    /// that it can be [written back][Self::to_bytes_with_manifest] after the files have been extracted and repacked.
+    pub fn manifest(&self) -> ArchiveManifest {
+        ArchiveManifest::from_entries(self.creator, self.toc.iter().copied())
+    }
+
+    /// Copies every file out of the archive, so that it no longer depends on the data it was parsed from.
+    pub fn into_owned(self) -> LGPFileOwned {
+        let files = self.files.into_iter().map(|(name, data)| (name.to_owned(), data.to_vec())).collect();
+        let conflicts = self
+            .conflicts
+            .into_iter()
+            .map(|((name, path), data)| ((name.to_owned(), path.to_owned()), data.to_vec()))
+            .collect();
+
+        LGPFileOwned {
+            creator: self.creator.to_owned(),
+            terminator: self.terminator.to_owned(),
+            trailing_data: self.trailing_data.to_vec(),
+            files,
+            conflicts,
+            lookup_mismatches: self.lookup_mismatches,
+            toc: self.toc.into_iter().map(LGPEntry::into_owned).collect(),
+        }
+    }
+}
+
+
+/// An owned copy of an [`LGPFile`], which can be kept after the archive's data is gone or sent to another thread. Use
+/// [`as_borrowed`][Self::as_borrowed] to get an [`LGPFile`] back, for its typed accessors and for writing.
+#[derive(Debug, Clone, Default)]
+pub struct LGPFileOwned {
+    pub creator: String,
+    pub terminator: String,
+    pub trailing_data: Vec<u8>,
+    pub files: HashMap<String, Vec<u8>>,
+    pub conflicts: HashMap<(String, String), Vec<u8>>,
+    pub lookup_mismatches: Vec<LookupMismatch>,
+    pub toc: Vec<LGPEntryOwned>,
+}
+
+
+impl LGPFileOwned {
+    /// Borrows the archive as an [`LGPFile`]. None of the files' data is copied, but the maps are rebuilt, so keep the
+    /// result around rather than calling this for every lookup.
+    pub fn as_borrowed(&self) -> LGPFile<'_> {
+        let files = self.files.iter().map(|(name, data)| (name.as_str(), data.as_slice())).collect();
+        let conflicts = self
+            .conflicts
+            .iter()
+            .map(|((name, path), data)| ((name.as_str(), path.as_str()), data.as_slice()))
+            .collect();
+
+        LGPFile {
+            creator: &self.creator,
+            terminator: &self.terminator,
+            trailing_data: &self.trailing_data,
+            files,
+            conflicts,
+            lookup_mismatches: self.lookup_mismatches.clone(),
+            toc: self.toc.iter().map(LGPEntryOwned::as_borrowed).collect(),
+        }
+    }
+}
+
+
+impl<'a> LGPEntry<'a> {
+    pub fn into_owned(self) -> LGPEntryOwned {
+        let LGPEntry { name, path, offset, check, dupe } = self;
+        LGPEntryOwned { name: name.to_owned(), path: path.map(str::to_owned), offset, check, dupe }
+    }
+}
+
+
+impl LGPEntryOwned {
+    pub fn as_borrowed(&self) -> LGPEntry<'_> {
+        let LGPEntryOwned { ref name, ref path, offset, check, dupe } = *self;
+        LGPEntry { name, path: path.as_deref(), offset, check, dupe }
+    }
+}
+
+
+impl<'a> Header<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
         let mut main_ptr = 0;
 
         // Check the first 12 bytes for the file's creator
-        let creator = sz_to_str(read(data, &mut main_ptr, 12)?)?;
+        let creator = sz_to_str(read(data, &mut main_ptr, CREATOR_LEN)?)?;
         if creator != "SQUARESOFT" && creator != "FICEDULA-LGP" {
             // log warning?
         }
@@ -40,49 +357,569 @@ impl<'a> LGPFile<'a> {
         let file_count = u32_from_le_bytes(read(data, &mut main_ptr, 4)?).unwrap();
 
         // Next is the table of contents
-        let mut files = HashMap::with_capacity(file_count as usize);
-        let mut end_of_data = main_ptr; // updated as we look through the files pointed to by the TOC
+        let mut entries = Vec::with_capacity((file_count as usize).min(data.len() / TOC_ENTRY_LEN));
 
         for _ in 0..file_count {
-            let file_name_data = read(data, &mut main_ptr, 20)?;
-            let file_name = sz_to_str(file_name_data)?;
+            let file_name_data = read(data, &mut main_ptr, NAME_LEN)?;
+            let name = sz_to_str(file_name_data)?;
 
             let offset = u32_from_le_bytes(read(data, &mut main_ptr, 4)?).unwrap();
             let check = read(data, &mut main_ptr, 1)?[0];
-            let dupe = u16_from_le_bytes(read(data, &mut main_ptr, 2)?).unwrap();
+            let dupe = u16_from_le_bytes(read(data, &mut main_ptr, 2)?).unwrap(); // the conflict table is what matters
 
-            if check != 0x0E && check != 0x0B {
+            if check != DEFAULT_CHECK && check != 0x0B {
                 // log warning?
             }
 
-            if dupe != 0 {
-                // handle duplicate
-                return Err(ParseError::DuplicateNameError);
+            entries.push(LGPEntry { name, path: None, offset, check, dupe });
+        }
+
+        // After the TOC is the lookup table, which we don't need since we have a hashmap (though it can be checked),
+        // followed by the conflict table. The conflict table gives a directory path to each file that shares its name
+        // with another.
+        let lookup_table = read(data, &mut main_ptr, LOOKUP_TABLE_LEN)?;
+
+        let conflict_count = u16_from_le_bytes(read(data, &mut main_ptr, 2)?).unwrap();
+        for _ in 0..conflict_count {
+            let entry_count = u16_from_le_bytes(read(data, &mut main_ptr, 2)?).unwrap();
+            for _ in 0..entry_count {
+                let path = sz_to_str(read(data, &mut main_ptr, CONFLICT_PATH_LEN)?)?;
+                let toc_index_data = read(data, &mut main_ptr, 2)?;
+                let toc_index = u16_from_le_bytes(toc_index_data).unwrap() as usize;
+
+                let entry = entries
+                    .get_mut(toc_index)
+                    .ok_or(ParseError::InvalidValueError(toc_index_data, main_ptr - 2))?;
+                entry.path = Some(path);
             }
+        }
 
-            // Go read the file's data
-            // -----------------------
+        Ok(Self { creator, entries, lookup_table, len: main_ptr })
+    }
 
-            let mut file_ptr = offset as usize;
+    /// Cross-checks the lookup table against the table of contents.
+    pub fn lookup_mismatches(&self) -> Vec<LookupMismatch> {
+        let mut mismatches = Vec::new();
+        let expected = build_lookup_table(self.entries.iter().map(|entry| entry.name));
 
-            // verify that the TOC's name matches the actual file's name
-            if sz_to_str(read(data, &mut file_ptr, 20)?)? != file_name {
-                // log warning?
+        for (index, (chunk, &expected)) in self.lookup_table.chunks_exact(4).zip(&expected).enumerate() {
+            // Can unwrap because the chunks are exactly 4 bytes long.
+            let toc_index = u16_from_le_bytes(&chunk[0..2]).unwrap();
+            let count = u16_from_le_bytes(&chunk[2..4]).unwrap();
+            let found = LookupEntry { toc_index, count };
+            if found != expected {
+                mismatches.push(LookupMismatch { index, expected, found });
             }
+        }
 
-            let file_size = u32_from_le_bytes(read(data, &mut file_ptr, 4)?)? as usize;
-            let file_data = read(data, &mut file_ptr, file_size)?;
+        mismatches
+    }
+}
 
-            if let Some(_) = files.insert(file_name, file_data) {
-                return Err(ParseError::DuplicateNameError);
+
+impl<'a> LGPFile<'a> {
+    /// Writes this archive out in the LGP format.
+    ///
+    /// The output is always compact: files are sorted by name and written back-to-back directly after the header, with
+    /// no slack space between them, regardless of how the original archive was laid out. Nothing depends on the order
+    /// of the maps or on the time, so the same archive always produces byte-identical output.
+    pub fn to_bytes(&self, options: &WriteOptions) -> Result<Vec<u8>, WriteError> {
+        let plain = self.files.iter().map(|(&name, &data)| TocEntry::new(name, None, data));
+        let conflicted = self.conflicts.iter().map(|(&(name, path), &data)| TocEntry::new(name, Some(path), data));
+        let mut entries = plain.chain(conflicted).collect::<Vec<_>>();
+        sort_entries(&mut entries);
+        write_entries(self.creator, entries, self.terminator, options)
+    }
+
+    /// Writes this archive out in the LGP format, following a manifest taken from the original archive: its creator,
+    /// the order of its table of contents, and each entry's check byte and conflict group are all kept. Repacking an
+    /// archive's files with its manifest gives back the original archive, as long as it was laid out compactly (as
+    /// the game's own archives are).
+    ///
+    /// Files that aren't in the manifest are written after the ones that are, in the same order as
+    /// [`to_bytes`][Self::to_bytes] would use. Entries in the manifest whose files are gone are left out.
+    pub fn to_bytes_with_manifest(
+        &self,
+        manifest: &ArchiveManifest,
+        options: &WriteOptions,
+    ) -> Result<Vec<u8>, WriteError> {
+        let mut entries = Vec::with_capacity(self.len());
+        let mut listed_files = HashMap::new();
+        let mut listed_conflicts = HashMap::new();
+
+        for entry in &manifest.entries {
+            let (name, path) = (entry.name.as_str(), entry.path.as_deref());
+            let found = match path {
+                None => self.files.get_key_value(name).map(|(&name, &data)| (name, None, data)),
+                Some(path) => {
+                    let found = self.conflicts.get_key_value(&(name, path));
+                    found.map(|(&(name, path), &data)| (name, Some(path), data))
+                },
+            };
+
+            // Also skip an entry that has somehow been listed twice, since it can only be written once.
+            let Some((name, path, data)) = found else {
+                continue;
+            };
+            let first = match path {
+                None => listed_files.insert(name, ()).is_none(),
+                Some(path) => listed_conflicts.insert((name, path), ()).is_none(),
+            };
+            if first {
+                entries.push(TocEntry { name, path, data, check: entry.check, dupe: entry.dupe });
             }
+        }
 
-            // Keep track of the furthest point we find in the file so that we can jump to the end later
-            end_of_data = end_of_data.max(file_ptr);
+        let plain = self.files.iter().filter(|(name, _)| !listed_files.contains_key(*name));
+        let conflicted = self.conflicts.iter().filter(|(key, _)| !listed_conflicts.contains_key(*key));
+        let mut unlisted = plain
+            .map(|(&name, &data)| TocEntry::new(name, None, data))
+            .chain(conflicted.map(|(&(name, path), &data)| TocEntry::new(name, Some(path), data)))
+            .collect::<Vec<_>>();
+        sort_entries(&mut unlisted);
+        entries.extend(unlisted);
+
+        write_entries(&manifest.creator, entries, self.terminator, options)
+    }
+
+    /// Writes this archive to a file, replacing it atomically if it already exists (see [`save_atomic`]). An
+    /// interrupted save never leaves a partially-written archive behind.
+    pub fn save(&self, path: impl AsRef<Path>, options: &WriteOptions) -> Result<(), WriteError> {
+        let data = self.to_bytes(options)?;
+        save_atomic(path, &data, options.backup)?;
+        Ok(())
+    }
+
+    /// Writes this archive to a file like [`save`][Self::save], following a manifest like
+    /// [`to_bytes_with_manifest`][Self::to_bytes_with_manifest].
+    pub fn save_with_manifest(
+        &self,
+        path: impl AsRef<Path>,
+        manifest: &ArchiveManifest,
+        options: &WriteOptions,
+    ) -> Result<(), WriteError> {
+        let data = self.to_bytes_with_manifest(manifest, options)?;
+        save_atomic(path, &data, options.backup)?;
+        Ok(())
+    }
+}
+
+
+/// One entry to be written to an archive's table of contents.
+struct TocEntry<'e> {
+    name: &'e str,
+    path: Option<&'e str>,
+    data: &'e [u8],
+    check: u8,
+    /// The entry's conflict group, or 0 to have one picked for it if it has a path.
+    dupe: u16,
+}
+
+
+impl<'e> TocEntry<'e> {
+    fn new(name: &'e str, path: Option<&'e str>, data: &'e [u8]) -> Self {
+        Self { name, path, data, check: DEFAULT_CHECK, dupe: 0 }
+    }
+}
+
+
+/// Sorts entries so that files that share a lookup table entry are next to each other in the table of contents, which
+/// the game needs to find them. Names that can't be looked up at all go at the end.
+fn sort_entries(entries: &mut [TocEntry]) {
+    entries.sort_unstable_by_key(|entry| {
+        let bucket = lookup_index(entry.name).unwrap_or(usize::MAX);
+        (bucket, entry.name.to_lowercase(), entry.name, entry.path)
+    });
+}
+
+
+/// Lays out and writes a whole archive, with its table of contents in the given order.
+fn write_entries(
+    creator: &str,
+    mut entries: Vec<TocEntry>,
+    terminator: &str,
+    options: &WriteOptions,
+) -> Result<Vec<u8>, WriteError> {
+    for entry in &entries {
+        if entry.name.len() > NAME_LEN {
+            return Err(WriteError::NameTooLongError(entry.name.to_owned(), NAME_LEN));
+        }
+        if let Some(path) = entry.path.filter(|path| path.len() > CONFLICT_PATH_LEN) {
+            return Err(WriteError::NameTooLongError(path.to_owned(), CONFLICT_PATH_LEN));
         }
+    }
+
+    // Conflicted entries without a group yet are grouped by name, with runs of the same name becoming new groups after
+    // any that are already in use. Each group becomes one conflict in the conflict table, and each entry's TOC `dupe`
+    // field holds its group's (1-based) number.
+    let mut next_group = entries.iter().map(|entry| entry.dupe as usize).max().unwrap_or(0);
+    let mut prev_name = None;
+    for entry in entries.iter_mut().filter(|entry| entry.path.is_some() && entry.dupe == 0) {
+        if prev_name != Some(entry.name) {
+            next_group += 1;
+            prev_name = Some(entry.name);
+        }
+
+        // Both the group numbers and the TOC indices in the conflict table are only 16 bits wide.
+        entry.dupe = u16::try_from(next_group).map_err(|_| WriteError::ArchiveTooLargeError)?;
+    }
+
+    let mut groups = vec![Vec::new(); next_group];
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.path.is_some() {
+            groups[entry.dupe as usize - 1].push(i);
+        }
+    }
+
+    if groups.iter().flatten().any(|&i| i > u16::MAX as usize) {
+        return Err(WriteError::ArchiveTooLargeError);
+    }
+
+    let conflict_table_len = 2 + groups
+        .iter()
+        .map(|indices| 2 + indices.len() * (CONFLICT_PATH_LEN + 2))
+        .sum::<usize>();
+
+    let toc_start = CREATOR_LEN + 4;
+    let data_start = toc_start + entries.len() * TOC_ENTRY_LEN + LOOKUP_TABLE_LEN + conflict_table_len;
+
+    // Lay out the data blocks first, so we know the offsets to put in the table of contents.
+    let mut offsets = Vec::with_capacity(entries.len());
+    let mut written: HashMap<&[u8], usize> = HashMap::new();
+    let mut end = data_start;
+
+    for entry in &entries {
+        let existing = options.deduplicate.then(|| written.get(entry.data).copied()).flatten();
+        let offset = existing.unwrap_or_else(|| {
+            let offset = end;
+            end += NAME_LEN + 4 + entry.data.len();
+            written.insert(entry.data, offset);
+            offset
+        });
+        offsets.push(offset);
+    }
+
+    if end > u32::MAX as usize {
+        return Err(WriteError::ArchiveTooLargeError);
+    }
+
+    let mut out = Vec::with_capacity(end + terminator.len());
+
+    // Creator, which is right-aligned in its field (e.g., "\0\0SQUARESOFT")
+    out.resize(CREATOR_LEN - creator.len().min(CREATOR_LEN), 0);
+    out.extend_from_slice(&creator.as_bytes()[..creator.len().min(CREATOR_LEN)]);
+    out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+
+    // Table of contents
+    for (entry, &offset) in entries.iter().zip(&offsets) {
+        push_name(&mut out, entry.name);
+        out.extend_from_slice(&(offset as u32).to_le_bytes());
+        out.push(entry.check);
+        out.extend_from_slice(&entry.dupe.to_le_bytes());
+    }
+
+    // Lookup table, followed by the conflict table
+    for lookup in build_lookup_table(entries.iter().map(|entry| entry.name)) {
+        out.extend_from_slice(&lookup.toc_index.to_le_bytes());
+        out.extend_from_slice(&lookup.count.to_le_bytes());
+    }
+
+    out.extend_from_slice(&(groups.len() as u16).to_le_bytes());
+    for indices in &groups {
+        out.extend_from_slice(&(indices.len() as u16).to_le_bytes());
+        for &i in indices {
+            let path = entries[i].path.unwrap_or_default();
+            let start = out.len();
+            out.extend_from_slice(path.as_bytes());
+            out.resize(start + CONFLICT_PATH_LEN, 0);
+            out.extend_from_slice(&(i as u16).to_le_bytes());
+        }
+    }
+
+    // Data blocks, in the same order that their offsets were assigned.
+    for (entry, &offset) in entries.iter().zip(&offsets) {
+        if offset == out.len() {
+            push_name(&mut out, entry.name);
+            out.extend_from_slice(&(entry.data.len() as u32).to_le_bytes());
+            out.extend_from_slice(entry.data);
+        }
+    }
+
+    out.extend_from_slice(terminator.as_bytes());
+    Ok(out)
+}
+
+
+/// Splits the end of an archive into its terminator string and whatever comes after it. Leading null bytes are skipped,
+/// and the terminator ends at the first byte that isn't printable ASCII.
+fn split_terminator(data: &[u8]) -> (&str, &[u8]) {
+    let start = data.iter().position(|&b| b != 0).unwrap_or(data.len());
+    let len = data[start..].iter().position(|&b| !(b == b' ' || b.is_ascii_graphic())).unwrap_or(data.len() - start);
+
+    // Can unwrap because printable ASCII is always valid UTF-8.
+    let terminator = std::str::from_utf8(&data[start..start + len]).unwrap();
+    let trailing_data = &data[start + len..];
+    (terminator, trailing_data)
+}
+
+
+/// Maps one of the first two characters of a name to its lookup table value. Digits, `_`, and `-` share values with
+/// letters, and a `.` (or the end of the name) comes before all of them.
+fn lookup_value(c: Option<u8>) -> Option<usize> {
+    match c.map(|c| c.to_ascii_lowercase()) {
+        None | Some(b'.') => Some(0),
+        Some(c @ b'a'..=b'z') => Some((c - b'a') as usize + 1),
+        Some(c @ b'0'..=b'9') => Some((c - b'0') as usize + 1),
+        Some(b'_') => Some((b'k' - b'a') as usize + 1),
+        Some(b'-') => Some((b'l' - b'a') as usize + 1),
+        Some(_) => None,
+    }
+}
+
+
+/// Finds the lookup table entry for a name, if it has one.
+fn lookup_index(name: &str) -> Option<usize> {
+    let mut chars = name.bytes();
+    let first = lookup_value(chars.next())?.checked_sub(1)?;
+    let second = lookup_value(chars.next())?;
+    Some(first * LOOKUP_VALUE_MAX + second)
+}
+
+
+/// Builds a lookup table for a table of contents with the given names, in order. Each entry points at the first run
+/// of names that map to it; if the names aren't grouped together, the extras will be missed by the game.
+fn build_lookup_table<'n>(names: impl IntoIterator<Item = &'n str>) -> [LookupEntry; LOOKUP_TABLE_ENTRIES] {
+    let mut table = [LookupEntry::default(); LOOKUP_TABLE_ENTRIES];
+    let mut prev = None;
+
+    for (i, name) in names.into_iter().enumerate() {
+        let Some(index) = lookup_index(name) else {
+            prev = None;
+            continue;
+        };
+
+        let entry = &mut table[index];
+        if entry.toc_index == 0 {
+            *entry = LookupEntry { toc_index: (i + 1).min(u16::MAX as usize) as u16, count: 1 };
+        } else if prev == Some(index) && entry.toc_index as usize + entry.count as usize == i + 1 {
+            entry.count = entry.count.saturating_add(1);
+        }
+
+        prev = Some(index);
+    }
+
+    table
+}
+
+
+/// Writes a name, padded with null bytes to [`NAME_LEN`]. The name must already be known to fit.
+fn push_name(out: &mut Vec<u8>, name: &str) {
+    let start = out.len();
+    out.extend_from_slice(name.as_bytes());
+    out.resize(start + NAME_LEN, 0);
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::extract::{LGPReader, ManifestEntry};
+
+    /// Builds an archive, adding its files and conflicted files in the given order.
+    fn archive(files: &[(&str, &[u8])], conflicts: &[(&str, &str, &[u8])]) -> LGPFileOwned {
+        let mut archive = LGPFileOwned {
+            creator: "SQUARESOFT".to_owned(),
+            terminator: "FINAL FANTASY 7".to_owned(),
+            ..Default::default()
+        };
+        for &(name, data) in files {
+            archive.files.insert(name.to_owned(), data.to_vec());
+        }
+        for &(name, path, data) in conflicts {
+            archive.conflicts.insert((name.to_owned(), path.to_owned()), data.to_vec());
+        }
+        archive
+    }
+
+    const FILES: [(&str, &[u8]); 6] = [
+        ("aaaa.hrc", b"hierarchy"),
+        ("aaab.rsd", b"resource"),
+        ("zz.p", b"polygon"),
+        ("_under.tex", b"texture"),
+        ("same.a", b"duplicate"),
+        ("copy.a", b"duplicate"),
+    ];
+
+    const CONFLICTS: [(&str, &str, &[u8]); 3] = [
+        ("shared.tex", "char\\field", b"field"),
+        ("shared.tex", "char\\battle", b"battle"),
+        ("other.tex", "menu", b"menu"),
+    ];
+
+    #[test]
+    fn to_bytes_is_independent_of_insertion_order() {
+        let forwards = archive(&FILES, &CONFLICTS);
+
+        let mut files = FILES;
+        let mut conflicts = CONFLICTS;
+        files.reverse();
+        conflicts.reverse();
+        let backwards = archive(&files, &conflicts);
+
+        for deduplicate in [false, true] {
+            let options = WriteOptions { deduplicate, ..WriteOptions::default() };
+            let expected = forwards.as_borrowed().to_bytes(&options).unwrap();
+            assert_eq!(backwards.as_borrowed().to_bytes(&options).unwrap(), expected);
+
+            // Every map gets its own hash seed, so building the same archive again visits it in a different order.
+            for _ in 0..8 {
+                assert_eq!(archive(&FILES, &CONFLICTS).as_borrowed().to_bytes(&options).unwrap(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn get_resolves_case_collisions_consistently() {
+        let names = ["aaaa.hrc", "AAAA.HRC", "Aaaa.Hrc"];
+        for _ in 0..8 {
+            let files = names.map(|name| (name, name.as_bytes()));
+            let owned = archive(&files, &[]);
+            let archive = owned.as_borrowed();
+
+            // Exact matches always win; otherwise the smallest name does.
+            for name in names {
+                assert_eq!(archive.get(name), Some(name.as_bytes()));
+            }
+            assert_eq!(archive.get("aaaa.HRC"), Some(&b"AAAA.HRC"[..]));
+            assert_eq!(archive.get("missing.hrc"), None);
+
+            // The streaming reader has to agree, whatever order the names are in its table of contents.
+            let entries = names.map(|name| ManifestEntry { name: name.to_owned(), path: None, check: 0x0E, dupe: 0 });
+            let manifest = ArchiveManifest { creator: "SQUARESOFT".to_owned(), entries: entries.to_vec() };
+            let data = archive.to_bytes_with_manifest(&manifest, &WriteOptions::default()).unwrap();
+            let mut reader = LGPReader::new(Cursor::new(data)).unwrap();
+            for name in names {
+                assert_eq!(reader.get(name).unwrap().as_deref(), Some(name.as_bytes()));
+            }
+            assert_eq!(reader.get("aaaa.HRC").unwrap().as_deref(), Some(&b"AAAA.HRC"[..]));
+            assert_eq!(reader.get("missing.hrc").unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn entries_past_the_end_are_named() {
+        let owned = archive(&[("zz.p", b"polygon")], &[]);
+        let data = owned.as_borrowed().to_bytes(&WriteOptions::default()).unwrap();
+
+        // Cut off the last byte of the file's data, along with the terminator.
+        let truncated = &data[..data.len() - "FINAL FANTASY 7".len() - 1];
+        let (data_start, len) = (truncated.len() - 6, truncated.len());
+        assert!(matches!(
+            LGPFile::from_bytes(truncated),
+            Err(ParseError::EntryOutOfBoundsError("zz.p", start, 7, l)) if start == data_start && l == len
+        ));
+
+        // Point the file's TOC entry past the end of the archive.
+        let mut moved = data.clone();
+        let offset_pos = CREATOR_LEN + 4 + NAME_LEN;
+        moved[offset_pos..offset_pos + 4].copy_from_slice(&(data.len() as u32).to_le_bytes());
+        assert!(matches!(
+            LGPFile::from_bytes(&moved),
+            Err(ParseError::EntryOutOfBoundsError("zz.p", start, 24, _)) if start == data.len()
+        ));
+    }
+
+    /// A manifest for [`FILES`] and [`CONFLICTS`] that [`to_bytes`][LGPFile::to_bytes] would never follow: the table
+    /// of contents isn't sorted, some check bytes are `0x0B`, the conflict groups are numbered backwards, and the
+    /// creator is the one written by other tools.
+    fn unusual_manifest() -> ArchiveManifest {
+        let entries = [
+            ("zz.p", None, 0x0B, 0),
+            ("shared.tex", Some("char\\battle"), 0x0E, 2),
+            ("copy.a", None, 0x0E, 0),
+            ("aaab.rsd", None, 0x0B, 0),
+            ("other.tex", Some("menu"), 0x0B, 1),
+            ("aaaa.hrc", None, 0x0E, 0),
+            ("shared.tex", Some("char\\field"), 0x0E, 2),
+            ("_under.tex", None, 0x0E, 0),
+            ("same.a", None, 0x0B, 0),
+        ];
+        let entries = entries.map(|(name, path, check, dupe)| ManifestEntry {
+            name: name.to_owned(),
+            path: path.map(str::to_owned),
+            check,
+            dupe,
+        });
+        ArchiveManifest { creator: "FICEDULA-LGP".to_owned(), entries: entries.to_vec() }
+    }
+
+    #[test]
+    fn manifest_round_trips_through_both_readers() {
+        let manifest = unusual_manifest();
+        let options = WriteOptions::default();
+        let read_options = ReadOptions { validate_lookup_table: true, ..ReadOptions::default() };
+        let original = archive(&FILES, &CONFLICTS).as_borrowed().to_bytes_with_manifest(&manifest, &options).unwrap();
+        assert_ne!(original, archive(&FILES, &CONFLICTS).as_borrowed().to_bytes(&options).unwrap());
+
+        // The lookup table is built from the unsorted TOC, and the conflict table points into it, so both have to be
+        // read back correctly for every file to be found where it was put.
+        let file = LGPFile::from_bytes_with_options(&original, &read_options).unwrap();
+        assert_eq!(file.creator, "FICEDULA-LGP");
+        assert_eq!(file.lookup_mismatches, []);
+        assert_eq!(file.get("shared.tex"), None);
+        for (name, path, data) in CONFLICTS {
+            assert_eq!(file.get_conflicted(name, path), Some(data));
+        }
+        for (name, data) in FILES {
+            assert_eq!(file.get(name), Some(data));
+        }
+
+        // Repack the files with the manifest after it has been through its text form, as if it had been saved next to
+        // them when they were extracted.
+        assert_eq!(file.manifest(), manifest);
+        let parsed = file.manifest().to_string().parse::<ArchiveManifest>().unwrap();
+        assert_eq!(parsed, manifest);
+        assert_eq!(file.into_owned().as_borrowed().to_bytes_with_manifest(&parsed, &options).unwrap(), original);
+
+        let mut reader = LGPReader::with_options(Cursor::new(&original), &read_options).unwrap();
+        assert_eq!(reader.creator(), "FICEDULA-LGP");
+        assert_eq!(reader.lookup_mismatches(), []);
+        assert_eq!(reader.manifest(), manifest);
+
+        let mut extracted = LGPFileOwned { terminator: "FINAL FANTASY 7".to_owned(), ..Default::default() };
+        for entry in &manifest.entries {
+            let data = reader.read_file(&entry.name, entry.path.as_deref()).unwrap().unwrap();
+            match &entry.path {
+                None => extracted.files.insert(entry.name.clone(), data),
+                Some(path) => extracted.conflicts.insert((entry.name.clone(), path.clone()), data),
+            };
+        }
+        let parsed = reader.manifest().to_string().parse::<ArchiveManifest>().unwrap();
+        assert_eq!(extracted.as_borrowed().to_bytes_with_manifest(&parsed, &options).unwrap(), original);
+    }
+
+    #[test]
+    fn lookup_table_mismatches_are_reported() {
+        let manifest = unusual_manifest();
+        let owned = archive(&FILES, &CONFLICTS);
+        let mut data = owned.as_borrowed().to_bytes_with_manifest(&manifest, &WriteOptions::default()).unwrap();
+
+        // Point the lookup entry for "zz.p", the first file, at the second file instead.
+        let index = lookup_index("zz.p").unwrap();
+        let position = CREATOR_LEN + 4 + manifest.entries.len() * TOC_ENTRY_LEN + index * 4;
+        let expected = LookupEntry { toc_index: 1, count: 1 };
+        assert_eq!(data[position..position + 4], [1, 0, 1, 0]);
+        data[position] = 2;
+
+        let options = ReadOptions { validate_lookup_table: true, ..ReadOptions::default() };
+        let found = LookupEntry { toc_index: 2, count: 1 };
+        let mismatches = [LookupMismatch { index, expected, found }];
+        assert_eq!(LGPFile::from_bytes_with_options(&data, &options).unwrap().lookup_mismatches, mismatches);
+        assert_eq!(LGPReader::with_options(Cursor::new(&data), &options).unwrap().lookup_mismatches(), mismatches);
 
-        // Finally there is a string, terminated by end of file
-        let terminator = sz_to_str(&data[end_of_data..data.len()])?;
-        Ok(Self { creator, terminator, files })
+        // Nothing is checked unless it's asked for.
+        assert_eq!(LGPFile::from_bytes(&data).unwrap().lookup_mismatches, []);
     }
 }
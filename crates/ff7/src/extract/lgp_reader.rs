@@ -0,0 +1,195 @@
+//! Reads [LGP files](https://wiki.ffrtt.ru/index.php/FF7/LGP_format) from a stream, one file at a time.
+//!
+//! [`LGPFile`][super::LGPFile] needs the entire archive in memory, which is a lot to ask for archives like `flevel.lgp`
+//! (300+ MB). [`LGPReader`] only reads the header up front, then seeks to each file's data as it is asked for.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::lgp::{Header, CONFLICT_PATH_LEN, CREATOR_LEN, LOOKUP_TABLE_LEN, NAME_LEN, TOC_ENTRY_LEN};
+use super::{u16_from_le_bytes, u32_from_le_bytes, ArchiveManifest, LGPEntry, LGPEntryOwned, Limits, LookupMismatch};
+use super::{ReadError, ReadOptions};
+
+
+/// An LGP archive that reads its files on demand from an underlying stream.
+pub struct LGPReader<R: Read + Seek> {
+    source: R,
+    /// The length of the whole stream, used to check offsets before seeking to them.
+    archive_len: u64,
+    creator: String,
+    /// The table of contents. [`LGPEntry`] borrows its strings, so owned copies are kept here.
+    entries: Vec<LGPEntryOwned>,
+    lookup_mismatches: Vec<LookupMismatch>,
+    limits: Limits,
+}
+
+
+impl<R: Read + Seek> LGPReader<R> {
+    /// Reads the header of an archive, starting from the beginning of `source`. Every entry's offset is checked
+    /// against the length of the stream, so that a truncated archive fails here rather than partway through reading.
+    pub fn new(source: R) -> Result<Self, ReadError> {
+        Self::with_options(source, &ReadOptions::default())
+    }
+
+    /// Reads the header of an archive, like [`new`][Self::new], with the given options. The limits are kept and
+    /// applied to every file that is read later.
+    pub fn with_options(mut source: R, options: &ReadOptions) -> Result<Self, ReadError> {
+        let archive_len = source.seek(SeekFrom::End(0))?;
+        source.seek(SeekFrom::Start(0))?;
+
+        // The header's length isn't known ahead of time, so read it piece by piece: first up to the file count, then
+        // the TOC and lookup table, then the conflict table one conflict at a time.
+        let mut header = Vec::new();
+        read_more(&mut source, &mut header, CREATOR_LEN + 4)?;
+
+        let file_count = u32_from_le_bytes(&header[CREATOR_LEN..])? as usize;
+        read_more(&mut source, &mut header, file_count.saturating_mul(TOC_ENTRY_LEN) + LOOKUP_TABLE_LEN + 2)?;
+
+        let conflict_count = u16_from_le_bytes(&header[header.len() - 2..])?;
+        for _ in 0..conflict_count {
+            read_more(&mut source, &mut header, 2)?;
+            let entry_count = u16_from_le_bytes(&header[header.len() - 2..])? as usize;
+            read_more(&mut source, &mut header, entry_count * (CONFLICT_PATH_LEN + 2))?;
+        }
+
+        // Now that the whole header is in memory, it can be parsed the same way as a full archive.
+        let parsed = Header::from_bytes(&header)?;
+        let creator = parsed.creator.to_owned();
+        let lookup_mismatches = if options.validate_lookup_table {
+            parsed.lookup_mismatches()
+        } else {
+            Vec::new()
+        };
+        let header_len = header.len() as u64;
+        for entry in &parsed.entries {
+            let offset = entry.offset as u64;
+            if offset < header_len {
+                return Err(ReadError::EntryOverlapsHeaderError(entry.name.to_owned(), offset, header_len));
+            }
+
+            let block_header_len = (NAME_LEN + 4) as u64;
+            if offset + block_header_len > archive_len {
+                let name = entry.name.to_owned();
+                return Err(ReadError::EntryOutOfBoundsError(name, offset, block_header_len, archive_len));
+            }
+        }
+
+        let entries = parsed.entries.into_iter().map(LGPEntry::into_owned).collect();
+
+        let limits = options.limits;
+        Ok(Self { source, archive_len, creator, entries, lookup_mismatches, limits })
+    }
+
+    /// The "creator" marker string from the archive. See [`LGPFile::creator`][super::LGPFile::creator].
+    pub fn creator(&self) -> &str {
+        &self.creator
+    }
+
+    /// Entries in the lookup table that don't match the table of contents. Only filled in when reading with
+    /// [`ReadOptions::validate_lookup_table`].
+    pub fn lookup_mismatches(&self) -> &[LookupMismatch] {
+        &self.lookup_mismatches
+    }
+
+    /// Every entry in the archive's table of contents, in order.
+    pub fn entries(&self) -> impl Iterator<Item = LGPEntry<'_>> {
+        self.entries.iter().map(LGPEntryOwned::as_borrowed)
+    }
+
+    /// Records the metadata from the archive's header, so that its files can be repacked into the same archive. See
+    /// [`LGPFile::manifest`][super::LGPFile::manifest].
+    pub fn manifest(&self) -> ArchiveManifest {
+        ArchiveManifest::from_entries(&self.creator, self.entries())
+    }
+
+    /// The names of every file in the archive, in the order of the table of contents. Names that are shared by several
+    /// files appear once for each of them.
+    pub fn entry_names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.name.as_str())
+    }
+
+    /// The number of files in the archive.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether or not the archive has no files in it.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Reads a file by its name, matching case-insensitively like [`LGPFile::get`][super::LGPFile::get] does. Only
+    /// that file's data is read from the stream. Returns `Ok(None)` if there is no such file.
+    pub fn get(&mut self, name: &str) -> Result<Option<Vec<u8>>, ReadError> {
+        // Break ties between names that differ only by case the same way `LGPFile` does, by picking the smallest one,
+        // so that both backends give back the same file.
+        let entry = self.entry(name, None).or_else(|| {
+            self.entries()
+                .filter(|entry| entry.path.is_none() && entry.name.eq_ignore_ascii_case(name))
+                .min_by_key(|entry| entry.name)
+        });
+        match entry {
+            Some(entry) => {
+                let offset = entry.offset;
+                self.read_at(offset).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Finds a file's entry by its name, and its path if its name is shared with other files. Returns `None` if there
+    /// is no such file.
+    pub fn entry(&self, name: &str, path: Option<&str>) -> Option<LGPEntry<'_>> {
+        self.entries().find(|entry| entry.name == name && entry.path == path)
+    }
+
+    /// Reads the data of the file with the given name (and path, for files whose names are shared). Returns `Ok(None)`
+    /// if there is no such file.
+    pub fn read_file(&mut self, name: &str, path: Option<&str>) -> Result<Option<Vec<u8>>, ReadError> {
+        match self.entry(name, path) {
+            Some(entry) => {
+                let offset = entry.offset;
+                self.read_at(offset).map(Some)
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the data block at the given offset, as pointed to by an [`LGPEntry`].
+    pub fn read_at(&mut self, offset: u32) -> Result<Vec<u8>, ReadError> {
+        self.source.seek(SeekFrom::Start(offset as u64))?;
+
+        // Each block starts with a copy of the file's name, then its size.
+        let mut block_header = Vec::with_capacity(NAME_LEN + 4);
+        read_more(&mut self.source, &mut block_header, NAME_LEN + 4)?;
+        let size = u32_from_le_bytes(&block_header[NAME_LEN..])? as usize;
+        self.limits.check_entry_size(size)?;
+
+        // Check the size before reading anything, so that a bad one gives a useful error instead of a short read.
+        let data_start = offset as u64 + block_header.len() as u64;
+        if data_start + size as u64 > self.archive_len {
+            let name = String::from_utf8_lossy(&block_header[..NAME_LEN]).trim_end_matches('\0').to_owned();
+            return Err(ReadError::EntryOutOfBoundsError(name, data_start, size as u64, self.archive_len));
+        }
+
+        let mut data = Vec::new();
+        read_more(&mut self.source, &mut data, size)?;
+        Ok(data)
+    }
+
+    /// Gives back the underlying stream.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+}
+
+
+/// Reads exactly `len` more bytes onto the end of `buf`. Unlike [`Read::read_exact`], this doesn't allocate the whole
+/// length up front, so a garbage length can't cause a huge allocation.
+pub(super) fn read_more(source: &mut impl Read, buf: &mut Vec<u8>, len: usize) -> io::Result<()> {
+    let read = source.take(len as u64).read_to_end(buf)?;
+    if read < len {
+        Err(io::ErrorKind::UnexpectedEof.into())
+    } else {
+        Ok(())
+    }
+}
@@ -1,61 +1,138 @@
 //! Extracts [LZSS files](https://wiki.ffrtt.ru/index.php/FF7/LZSS_format).
+//!
+//! An LZSS file starts with a 4-byte length of the compressed data, followed by groups of up to eight blocks. Each
+//! group starts with a control byte whose bits (least significant first) say whether each block is a single literal
+//! byte (`1`) or a two-byte reference back into a 4096-byte circular buffer of everything decompressed so far (`0`).
+//! The buffer starts out filled with zeroes, and is first written to at offset `0xFEE`.
 
-use super::{read, u32_from_le_bytes, ParseError};
+use std::collections::HashMap;
+use std::io::{self, Read};
+
+use super::{read, u32_from_le_bytes, Limits, ParseError};
+
+
+/// The size of the circular buffer that references point into.
+const WINDOW_SIZE: usize = 4096;
+
+/// Where in the circular buffer the first decompressed byte is written.
+const WINDOW_START: usize = 0xFEE;
+
+/// The shortest run of bytes that a reference can copy. Anything shorter is cheaper to store as literals.
+const MIN_MATCH: usize = 3;
+
+/// The longest run of bytes that a reference can copy; the length is stored in 4 bits, offset by [`MIN_MATCH`].
+const MAX_MATCH: usize = 0x0F + MIN_MATCH;
+
+/// How many earlier positions to try when looking for a match. Higher values compress slightly better, but slower.
+const MAX_CANDIDATES: usize = 256;
 
 
 /// Decompresses an LZSS archive.
 ///
 /// See [module-level documentation](self) for more.
 pub fn decompress_lzss(data: &[u8]) -> Result<Vec<u8>, ParseError> {
+    decompress_lzss_with_limits(data, &Limits::default())
+}
+
+
+/// Decompresses an LZSS archive, failing if the output grows past the given limits.
+pub fn decompress_lzss_with_limits<'a>(data: &'a [u8], limits: &Limits) -> Result<Vec<u8>, ParseError<'a>> {
     let mut data_ptr = 0;
     let compressed_size = u32_from_le_bytes(read(data, &mut data_ptr, 4)?).unwrap() as usize;
 
-    let mut buff = vec![0u8; 4096];
-    let mut buff_ptr = 0xFEE;
+    let data_end = data_ptr + compressed_size;
+    if data_end > data.len() {
+        return Err(ParseError::EndOfBufferError);
+    }
+
+    decompress_blocks(data, data_ptr, data_end, limits)
+}
+
+
+/// Decompresses bare LZSS data, without the 4-byte length header that the game's files start with. Other tools often
+/// pass LZSS data around like this; the whole of `data` is taken to be compressed.
+pub fn decompress_lzss_raw(data: &[u8]) -> Result<Vec<u8>, ParseError<'_>> {
+    decompress_lzss_raw_with_limits(data, &Limits::default())
+}
+
+
+/// Decompresses bare LZSS data (see [`decompress_lzss_raw`]), failing if the output grows past the given limits.
+pub fn decompress_lzss_raw_with_limits<'a>(data: &'a [u8], limits: &Limits) -> Result<Vec<u8>, ParseError<'a>> {
+    decompress_blocks(data, 0, data.len(), limits)
+}
+
+
+/// Decompresses the groups of blocks in `data[data_ptr..data_end]`.
+fn decompress_blocks<'a>(
+    data: &'a [u8],
+    mut data_ptr: usize,
+    data_end: usize,
+    limits: &Limits,
+) -> Result<Vec<u8>, ParseError<'a>> {
+    // Nothing past the compressed span belongs to it, even if the buffer it's embedded in keeps going.
+    let data = &data[..data_end];
+
+    // The expansion ratio is measured against just the compressed span, not the whole buffer it's embedded in.
+    let compressed_size = data_end - data_ptr;
+
+    let mut buff = vec![0u8; WINDOW_SIZE];
+    let mut buff_ptr = WINDOW_START;
 
     // We will need to expand this buffer, but since there's no way to know the decompressed size, this is a good start.
-    let mut output = Vec::with_capacity(compressed_size);
+    let mut output = Vec::with_capacity(data_end - data_ptr);
 
-    while data_ptr < data.len() {
+    // The compressed data usually ends partway through a group, so stop as soon as it runs out.
+    while data_ptr < data_end {
         let ctrl_byte = read(data, &mut data_ptr, 1)?[0];
 
         for i in 0..8u8 {
+            if data_ptr >= data_end {
+                break;
+            }
+
             match (ctrl_byte >> i) & 1 {
                 // Literal block (AKA, one byte)
                 1 => {
                     let byte = read(data, &mut data_ptr, 1)?[0];
-                    push_circular(&[byte], &mut buff, &mut buff_ptr); // push to reference buffer
+                    buff[buff_ptr] = byte; // push to reference buffer
+                    buff_ptr = (buff_ptr + 1) % WINDOW_SIZE;
                     output.push(byte); // push to output
                 },
                 // Reference block
                 0 => {
                     // Read the two reference control bytes
                     // --------------------
-                    let &[ ref_h, ref_l ] = read(data, &mut data_ptr, 2)? else {
+                    let &[ref_h, ref_l] = read(data, &mut data_ptr, 2)? else {
                         // success of `read` with length 2 guarantees slice length
                         unreachable!();
                     };
 
-                    let off = (ref_l as u16 & 0xF0 << 4) | (ref_h as u16);
-                    let len = (ref_l as u16 & 0x0F) + 3;
+                    let off = ((ref_l as usize & 0xF0) << 4) | (ref_h as usize);
+                    let len = (ref_l as usize & 0x0F) + MIN_MATCH;
 
-                    // As `u16`, our control bytes look like:
+                    // As `usize`, our control bytes look like:
                     //
-                    // ref_h: ____ ____ OOOO OOOO
-                    // ref_l: ____ ____ OOOO LLLL
+                    // ref_h: .... OOOO OOOO
+                    // ref_l: .... OOOO LLLL
                     //
-                    // Hence the & and <<.
+                    // The offset's top four bits come from `ref_l`, hence the & and <<.
 
-                    // Look into our circular buffer of already-read bytes and read them back
+                    // Look into our circular buffer of already-read bytes and read them back. This has to go one byte
+                    // at a time, since a reference is allowed to overlap the bytes it is producing.
                     // --------------------
 
-                    let mut data = get_circular(&buff, off as usize, len as usize);
-                    push_circular(&data, &mut buff, &mut buff_ptr);
-                    output.append(&mut data);
+                    for k in 0..len {
+                        let byte = buff[(off + k) % WINDOW_SIZE];
+                        buff[buff_ptr] = byte;
+                        buff_ptr = (buff_ptr + 1) % WINDOW_SIZE;
+                        output.push(byte);
+                    }
                 },
                 // anything `& 1` will always be 0 or 1
                 _ => unreachable!(),
             }
+
+            limits.check_output(output.len(), compressed_size)?;
         }
     }
 
@@ -64,20 +141,352 @@ pub fn decompress_lzss(data: &[u8]) -> Result<Vec<u8>, ParseError> {
 }
 
 
-fn push_circular(data: &[u8], buff: &mut [u8], ptr: &mut usize) {
-    // go byte-by-byte so we can circle around when necessary
-    for &byte in data {
-        buff[*ptr % buff.len()] = byte;
-        *ptr = (*ptr + 1) % buff.len();
+/// Decompresses an LZSS archive incrementally, as it is read from an underlying stream.
+///
+/// Unlike [`decompress_lzss`], this never holds more than the 4096-byte window in memory, so large files can be parsed
+/// as they are decompressed. Going over its [limits][Limits] is an [`InvalidData`][io::ErrorKind::InvalidData] error.
+pub struct LzssReader<R: Read> {
+    source: R,
+    /// How many bytes of compressed data are left in the source.
+    remaining: usize,
+
+    limits: Limits,
+    /// How many bytes of compressed data the header says there are, for checking the expansion ratio.
+    compressed_size: usize,
+    /// How many bytes have been decompressed so far.
+    produced: usize,
+
+    window: Box<[u8; WINDOW_SIZE]>,
+    window_ptr: usize,
+
+    ctrl_byte: u8,
+    /// Which bit of `ctrl_byte` describes the next block. Once this reaches 8, a new control byte is needed.
+    ctrl_bit: u8,
+
+    /// A reference that is partway through being copied out, as its position in the window and how many bytes of it
+    /// are left.
+    pending: (usize, usize),
+}
+
+
+impl<R: Read> LzssReader<R> {
+    /// Reads the length header from `source`, ready to start decompressing.
+    pub fn new(source: R) -> io::Result<Self> {
+        Self::with_limits(source, Limits::default())
+    }
+
+    /// Reads the length header from `source`, ready to start decompressing, failing partway through if the output grows
+    /// past the given limits.
+    pub fn with_limits(mut source: R, limits: Limits) -> io::Result<Self> {
+        let mut header = [0u8; 4];
+        source.read_exact(&mut header)?;
+        let compressed_size = u32::from_le_bytes(header) as usize;
+
+        Ok(Self {
+            source,
+            remaining: compressed_size,
+            limits,
+            compressed_size,
+            produced: 0,
+            window: Box::new([0; WINDOW_SIZE]),
+            window_ptr: WINDOW_START,
+            ctrl_byte: 0,
+            ctrl_bit: 8,
+            pending: (0, 0),
+        })
+    }
+
+    /// Gives back the underlying stream.
+    pub fn into_inner(self) -> R {
+        self.source
+    }
+
+    /// Reads one byte of compressed data.
+    fn next_byte(&mut self) -> io::Result<u8> {
+        let mut byte = [0u8];
+        self.source.read_exact(&mut byte)?;
+        self.remaining = self.remaining.saturating_sub(1);
+        Ok(byte[0])
+    }
+
+    /// Adds a decompressed byte to the window, so that later references can see it. Fails if that takes the output past
+    /// the reader's limits.
+    fn push(&mut self, byte: u8) -> io::Result<u8> {
+        self.produced += 1;
+        self.limits
+            .check_output(self.produced, self.compressed_size)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+        self.window[self.window_ptr] = byte;
+        self.window_ptr = (self.window_ptr + 1) % WINDOW_SIZE;
+        Ok(byte)
     }
 }
 
 
-fn get_circular(buff: &[u8], off: usize, len: usize) -> Vec<u8> {
-    let mut v = Vec::with_capacity(len);
-    let a_end = (off + len).max(buff.len()); // read until end at most
-    let b_end = (off + len) % buff.len(); // read from start up to the remaining amount
-    v.extend_from_slice(&buff[off..a_end]);
-    v.extend_from_slice(&buff[0..b_end]);
-    v
+impl<R: Read> Read for LzssReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+
+        while written < buf.len() {
+            // Finish copying out any reference before moving on to the next block.
+            if self.pending.1 > 0 {
+                let (off, len) = self.pending;
+                buf[written] = self.push(self.window[off % WINDOW_SIZE])?;
+                written += 1;
+                self.pending = (off + 1, len - 1);
+                continue;
+            }
+
+            // The compressed data usually ends partway through a group.
+            if self.remaining == 0 {
+                break;
+            }
+
+            if self.ctrl_bit == 8 {
+                self.ctrl_byte = self.next_byte()?;
+                self.ctrl_bit = 0;
+                continue;
+            }
+
+            let literal = (self.ctrl_byte >> self.ctrl_bit) & 1 == 1;
+            self.ctrl_bit += 1;
+
+            if literal {
+                let byte = self.next_byte()?;
+                buf[written] = self.push(byte)?;
+                written += 1;
+            } else {
+                // See `decompress_lzss_with_limits` for the layout of a reference.
+                let ref_h = self.next_byte()?;
+                let ref_l = self.next_byte()?;
+                let off = ((ref_l as usize & 0xF0) << 4) | (ref_h as usize);
+                let len = (ref_l as usize & 0x0F) + MIN_MATCH;
+                self.pending = (off, len);
+            }
+        }
+
+        Ok(written)
+    }
+}
+
+
+/// Compresses data into an LZSS archive that the game can read, including the length header.
+///
+/// Matches are found greedily, so the output is usually a little larger than the game's own files, but it decompresses
+/// to exactly the same bytes.
+pub fn compress_lzss(data: &[u8]) -> Vec<u8> {
+    let mut output = vec![0u8; 4]; // space for the length, filled in at the end
+    compress_blocks(data, &mut output);
+
+    let compressed_size = (output.len() - 4) as u32;
+    output[0..4].copy_from_slice(&compressed_size.to_le_bytes());
+    output
+}
+
+
+/// Compresses data into bare LZSS data, without the length header. See [`decompress_lzss_raw`].
+pub fn compress_lzss_raw(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    compress_blocks(data, &mut output);
+    output
+}
+
+
+/// Compresses data into groups of blocks, appending them to `output`.
+fn compress_blocks(data: &[u8], output: &mut Vec<u8>) {
+    // Every position where each 3-byte sequence has been seen so far, oldest first.
+    let mut positions: HashMap<[u8; MIN_MATCH], Vec<usize>> = HashMap::new();
+
+    let mut ptr = 0;
+    let mut ctrl_ptr = 0;
+
+    for block in 0.. {
+        if ptr >= data.len() {
+            break;
+        }
+
+        // Every eighth block starts a new group with a fresh control byte.
+        if block % 8 == 0 {
+            ctrl_ptr = output.len();
+            output.push(0);
+        }
+
+        let (match_pos, match_len) = find_match(data, ptr, &positions);
+
+        let len = if match_len >= MIN_MATCH {
+            // The reference points at where the match's first byte sits in the circular buffer.
+            let off = (WINDOW_START + match_pos) % WINDOW_SIZE;
+            output.push((off & 0xFF) as u8);
+            output.push((((off >> 4) & 0xF0) | (match_len - MIN_MATCH)) as u8);
+            match_len
+        } else {
+            output[ctrl_ptr] |= 1 << (block % 8);
+            output.push(data[ptr]);
+            1
+        };
+
+        for pos in ptr..ptr + len {
+            if let Some(key) = data.get(pos..pos + MIN_MATCH) {
+                positions.entry(key.try_into().unwrap()).or_default().push(pos);
+            }
+        }
+
+        ptr += len;
+    }
+}
+
+
+/// Finds the longest earlier run of bytes (still within the window) that matches the bytes at `ptr`. Returns the
+/// run's position and length; the length is 0 if there is no match at all.
+fn find_match(data: &[u8], ptr: usize, positions: &HashMap<[u8; MIN_MATCH], Vec<usize>>) -> (usize, usize) {
+    let Some(key) = data.get(ptr..ptr + MIN_MATCH) else {
+        return (0, 0);
+    };
+
+    let Some(candidates) = positions.get(key) else {
+        return (0, 0);
+    };
+
+    let max_len = MAX_MATCH.min(data.len() - ptr);
+    let mut best = (0, 0);
+
+    // Candidates are oldest first, so go backwards to try the closest ones first. Anything a full window or more back
+    // has already been overwritten in the circular buffer.
+    for &pos in candidates.iter().rev().take(MAX_CANDIDATES).take_while(|&&pos| ptr - pos < WINDOW_SIZE) {
+        // Matches may run past `ptr`, since the decompressor copies one byte at a time.
+        let len = (0..max_len).take_while(|&k| data[pos + k] == data[ptr + k]).count();
+        if len > best.1 {
+            best = (pos, len);
+            if len == max_len {
+                break;
+            }
+        }
+    }
+
+    best
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Limits that only cap the expansion ratio.
+    const RATIO_LIMIT: Limits = Limits { max_expansion_ratio: 2, ..Limits::UNLIMITED };
+
+    /// A stream that expands by much more than [`RATIO_LIMIT`] allows, followed by a lot of unrelated data, like an
+    /// LZSS file embedded in something larger.
+    fn embedded_stream() -> Vec<u8> {
+        let mut data = compress_lzss(&[0; 200]);
+        data.extend_from_slice(&[0; 10_000]);
+        data
+    }
+
+    /// Bytes that don't repeat in any way LZSS can use, from a xorshift generator.
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_F491_u32;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    /// Checks that `data` survives being compressed and decompressed, both with and without the length header.
+    fn assert_round_trip(data: &[u8]) -> Vec<u8> {
+        let compressed = compress_lzss(data);
+        assert_eq!(decompress_lzss(&compressed).unwrap(), data);
+
+        let raw = compress_lzss_raw(data);
+        assert_eq!(raw, compressed[4..]);
+        assert_eq!(decompress_lzss_raw(&raw).unwrap(), data);
+
+        let mut streamed = Vec::new();
+        LzssReader::new(compressed.as_slice()).unwrap().read_to_end(&mut streamed).unwrap();
+        assert_eq!(streamed, data);
+
+        compressed
+    }
+
+    #[test]
+    fn round_trips_empty_input() {
+        assert_eq!(assert_round_trip(&[]), [0; 4]);
+    }
+
+    #[test]
+    fn round_trips_input_shorter_than_the_window_start() {
+        let data = b"Cloud Strife, ex-SOLDIER. Cloud Strife, ex-SOLDIER!";
+        assert!(data.len() < WINDOW_START);
+        let compressed = assert_round_trip(data);
+        assert!(compressed.len() < data.len());
+    }
+
+    #[test]
+    fn round_trips_runs_longer_than_a_reference() {
+        let mut data = vec![0xAB; MAX_MATCH * 10 + 5];
+        data.extend(b"xy".repeat(100));
+        let compressed = assert_round_trip(&data);
+        assert!(compressed.len() < data.len() / 4);
+    }
+
+    #[test]
+    fn round_trips_across_the_window_boundary() {
+        // Each copy of the block is matched against the one before it, so references reach back across the point where
+        // the circular buffer wraps around.
+        let block = noise(3000);
+        let data = block.iter().cycle().take(block.len() * 4).copied().collect::<Vec<_>>();
+        assert!(data.len() > WINDOW_SIZE * 2);
+        let compressed = assert_round_trip(&data);
+        assert!(compressed.len() < data.len() / 2);
+    }
+
+    #[test]
+    fn round_trips_incompressible_input() {
+        let data = noise(5000);
+        let compressed = assert_round_trip(&data);
+        // At worst, every byte is a literal, plus a control byte for every eight of them and the length header.
+        assert!(compressed.len() <= 4 + data.len() + data.len().div_ceil(8));
+    }
+
+    #[test]
+    fn expansion_ratio_is_measured_against_the_compressed_span() {
+        let data = embedded_stream();
+        assert_eq!(decompress_lzss(&data).unwrap(), [0; 200]);
+
+        let result = decompress_lzss_with_limits(&data, &RATIO_LIMIT);
+        assert!(matches!(result, Err(ParseError::LimitExceededError("expansion ratio", 2))), "{result:?}");
+    }
+
+    #[test]
+    fn references_cut_off_by_the_compressed_span_are_rejected() {
+        // A literal, then a reference whose second byte is past the end of the span. The byte after the span would make
+        // it a valid reference, if it were read.
+        let mut data = 3u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0b01, b'a', 0xEE, 0xF0]);
+
+        assert!(matches!(decompress_lzss(&data), Err(ParseError::EndOfBufferError)));
+        data.truncate(7);
+        assert!(matches!(decompress_lzss(&data), Err(ParseError::EndOfBufferError)));
+    }
+
+    #[test]
+    fn reader_applies_limits() {
+        let data = embedded_stream();
+
+        let mut output = Vec::new();
+        LzssReader::new(data.as_slice()).unwrap().read_to_end(&mut output).unwrap();
+        assert_eq!(output, [0; 200]);
+
+        let mut reader = LzssReader::with_limits(data.as_slice(), RATIO_LIMIT).unwrap();
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        let size_limit = Limits { max_decompressed_size: 100, ..Limits::UNLIMITED };
+        let mut reader = LzssReader::with_limits(data.as_slice(), size_limit).unwrap();
+        assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    }
 }
@@ -0,0 +1,122 @@
+//! A record of the parts of an LGP archive's header that aren't kept when its files are extracted, so that they can be
+//! put back when the files are repacked.
+//!
+//! [`LGPFile::to_bytes`][super::LGPFile::to_bytes] lays out a new archive from scratch: it sorts the table of
+//! contents, writes `0x0E` for every check byte, and numbers the conflict table's groups itself. Archives with other
+//! values for any of these (and some official ones have them) don't come back out the same. A manifest keeps them.
+//!
+//! Manifests are written as plain text, to sit next to the extracted files. The first line gives the creator, and each
+//! line after that is one entry in the table of contents, in order: its check byte in hex, its conflict group, its
+//! name, and its conflict path if it has one. Fields are separated by tabs, since paths may have spaces in them (the
+//! example below shows them as spaces):
+//!
+//! ```text
+//! creator  SQUARESOFT
+//! 0e  0  aaaa.hrc
+//! 0b  1  aaab.rsd  char\field
+//! ```
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+use super::LGPEntry;
+
+
+/// An error from reading a manifest.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ManifestError {
+    #[error("the manifest doesn't start with a creator line")]
+    MissingCreatorError,
+
+    #[error("line {0} of the manifest is not a valid entry")]
+    InvalidLineError(usize),
+}
+
+
+/// One entry of an archive's table of contents, as recorded in a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    pub name: String,
+    /// The file's directory path from the conflict table, if its name is shared with other files in the archive.
+    pub path: Option<String>,
+    /// See [`LGPEntry::check`].
+    pub check: u8,
+    /// See [`LGPEntry::dupe`].
+    pub dupe: u16,
+}
+
+
+/// The metadata from an archive's header that is needed to repack it as it was.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveManifest {
+    /// See [`LGPFile::creator`][super::LGPFile::creator].
+    pub creator: String,
+    /// Every entry in the table of contents, in order.
+    pub entries: Vec<ManifestEntry>,
+}
+
+
+impl ArchiveManifest {
+    /// Records a manifest from an archive's creator and the entries of its table of contents, in order.
+    pub fn from_entries<'e>(creator: &str, entries: impl IntoIterator<Item = LGPEntry<'e>>) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|entry| ManifestEntry {
+                name: entry.name.to_owned(),
+                path: entry.path.map(str::to_owned),
+                check: entry.check,
+                dupe: entry.dupe,
+            })
+            .collect();
+
+        Self { creator: creator.to_owned(), entries }
+    }
+}
+
+
+impl Display for ArchiveManifest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "creator\t{}", self.creator)?;
+        for entry in &self.entries {
+            write!(f, "{:02x}\t{}\t{}", entry.check, entry.dupe, entry.name)?;
+            if let Some(path) = &entry.path {
+                write!(f, "\t{path}")?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+
+impl FromStr for ArchiveManifest {
+    type Err = ManifestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Blank lines are skipped, in case the file has been edited by hand.
+        let mut lines = s.lines().enumerate().filter(|(_, line)| !line.trim().is_empty());
+
+        let creator = match lines.next().map(|(_, line)| line.split_once('\t')) {
+            Some(Some(("creator", creator))) => creator.to_owned(),
+            _ => return Err(ManifestError::MissingCreatorError),
+        };
+
+        let mut entries = Vec::new();
+        for (i, line) in lines {
+            let mut fields = line.split('\t');
+            let check = fields.next().and_then(|field| u8::from_str_radix(field, 16).ok());
+            let dupe = fields.next().and_then(|field| field.parse().ok());
+            let name = fields.next().filter(|name| !name.is_empty());
+            let path = fields.next().map(str::to_owned);
+
+            let (Some(check), Some(dupe), Some(name), None) = (check, dupe, name, fields.next()) else {
+                return Err(ManifestError::InvalidLineError(i + 1));
+            };
+            entries.push(ManifestEntry { name: name.to_owned(), path, check, dupe });
+        }
+
+        Ok(Self { creator, entries })
+    }
+}
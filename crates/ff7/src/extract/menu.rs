@@ -0,0 +1,88 @@
+//! Sorts out the contents of `menu.lgp`, which holds the graphics that menus and dialogue boxes are drawn with.
+//!
+//! Almost everything in the archive is an ordinary [TEX file][TextureFile], so decoding is the same as for any other
+//! texture. What this adds is knowing which file is which: the fonts (`usfont_*`, with a `_h` and `_l` version of each
+//! for high and low resolution), the window frames and battle menu graphics (`btl_win_*`), and one avatar per
+//! character, named after that character's internal name (e.g., `earith.tex` for Aerith, `ketcy.tex` for Cait Sith).
+//!
+//! Fonts and window graphics are paletted, and their palettes are the colors that the game can draw them in (e.g., gray
+//! text for disabled menu items), so they are decoded once per palette. Each font is a grid of glyphs in the order of
+//! the game's [text encoding][crate::text]; the width of each glyph is stored separately, in `window.bin`.
+
+use super::{LGPFile, ParseError};
+use crate::char::{RgbaImage, TextureFile};
+
+
+/// The names (without their extension) of the characters' avatars.
+const AVATAR_NAMES: [&str; 11] = [
+    "cloud", "earith", "barret", "tifa", "red13", "yuffie", "ketcy", "vincent", "cid", "sephiroth", "youngcloud",
+];
+
+
+/// What a file in `menu.lgp` is used for, based on its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MenuAsset {
+    /// A bitmap font.
+    Font,
+    /// Window frames, cursors, and the other pieces that windows and the battle menu are drawn out of.
+    Window,
+    /// A character's portrait, as shown in the main menu.
+    Avatar,
+    /// Anything else.
+    Other,
+}
+
+
+impl MenuAsset {
+    /// Works out what a file from `menu.lgp` is for, from its name, ignoring case. Files that aren't TEX files are
+    /// always [`Other`][MenuAsset::Other].
+    pub fn from_name(name: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        let Some(stem) = name.strip_suffix(".tex") else {
+            return MenuAsset::Other;
+        };
+
+        if stem.contains("font") {
+            MenuAsset::Font
+        } else if stem.starts_with("btl_win") {
+            MenuAsset::Window
+        } else if AVATAR_NAMES.contains(&stem) {
+            MenuAsset::Avatar
+        } else {
+            MenuAsset::Other
+        }
+    }
+}
+
+
+/// A decoded image from `menu.lgp`.
+#[derive(Debug, Clone)]
+pub struct MenuImage<'a> {
+    pub name: &'a str,
+    pub asset: MenuAsset,
+    /// The image decoded with each of its palettes, or a single image if it isn't paletted.
+    pub images: Vec<RgbaImage>,
+}
+
+
+impl<'a> LGPFile<'a> {
+    /// The names of every file in a `menu.lgp` archive that is used for the given kind of asset.
+    pub fn menu_assets(&self, asset: MenuAsset) -> impl Iterator<Item = &'a str> + '_ {
+        self.entry_names().filter(move |name| MenuAsset::from_name(name) == asset)
+    }
+
+    /// Decodes a file from a `menu.lgp` archive. Returns `None` if there is no file with that name.
+    pub fn menu_image(&self, name: &'a str) -> Option<Result<MenuImage<'a>, ParseError<'a>>> {
+        Some(TextureFile::from_bytes(self.get(name)?).map(|texture| MenuImage {
+            name,
+            asset: MenuAsset::from_name(name),
+            images: texture.decode_palettes(),
+        }))
+    }
+
+    /// Decodes every file in a `menu.lgp` archive that is used for the given kind of asset, in no particular order.
+    /// Files that can't be parsed are given with their error instead.
+    pub fn menu_images(&self, asset: MenuAsset) -> impl Iterator<Item = Result<MenuImage<'a>, ParseError<'a>>> + '_ {
+        self.menu_assets(asset).filter_map(|name| self.menu_image(name))
+    }
+}
@@ -5,11 +5,23 @@ use std::fmt::Debug;
 use thiserror::Error;
 
 
+mod disc;
+mod kind;
 mod lgp;
+mod lgp_reader;
 mod lzss;
+mod manifest;
+mod menu;
+mod save;
 
+pub use disc::*;
+pub use kind::*;
 pub use lgp::*;
+pub use lgp_reader::*;
 pub use lzss::*;
+pub use manifest::*;
+pub use menu::*;
+pub use save::*;
 
 
 #[derive(Error, Debug)]
@@ -28,6 +40,155 @@ pub enum ParseError<'a> {
 
     #[error("encountered a file with no or an unknown file-type.")]
     UnknownFileTypeError,
+
+    #[error("{0} exceeds the configured limit of {1}")]
+    LimitExceededError(&'static str, usize),
+
+    #[error("entry \"{0}\" claims {2} bytes at offset {1}, but the archive is only {3} bytes long")]
+    EntryOutOfBoundsError(&'a str, usize, usize, usize),
+}
+
+
+/// Caps on how much memory parsing is allowed to use, so that untrusted input can't exhaust it. Going over any of them
+/// raises a [`LimitExceededError`][ParseError::LimitExceededError].
+///
+/// The default has no limits at all; see [`Limits::UNTRUSTED`] for something more conservative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// The largest file an archive may contain, in bytes.
+    pub max_entry_size: usize,
+    /// The most bytes that a single decompression may output.
+    pub max_decompressed_size: usize,
+    /// The most that a single decompression may expand its input by, as a multiple of the compressed size.
+    pub max_expansion_ratio: usize,
+}
+
+
+impl Limits {
+    /// No limits.
+    pub const UNLIMITED: Self = Self {
+        max_entry_size: usize::MAX,
+        max_decompressed_size: usize::MAX,
+        max_expansion_ratio: usize::MAX,
+    };
+
+    /// Limits that comfortably fit every file from the original game. The largest files in the game are a few MB, and
+    /// LZSS can't expand anything by more than about 8.5×.
+    pub const UNTRUSTED: Self = Self {
+        max_entry_size: 64 * 1024 * 1024,
+        max_decompressed_size: 64 * 1024 * 1024,
+        max_expansion_ratio: 9,
+    };
+
+    /// Checks the size of a file from an archive against [`max_entry_size`][Self::max_entry_size].
+    pub(crate) fn check_entry_size<'a>(&self, size: usize) -> Result<(), ParseError<'a>> {
+        if size > self.max_entry_size {
+            Err(ParseError::LimitExceededError("entry size", self.max_entry_size))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks how much a decompression has output so far against both the decompressed size limit and the expansion
+    /// ratio limit. The ratio is relative to `compressed_size`, the size of just the data being decompressed.
+    pub(crate) fn check_output<'a>(&self, output_size: usize, compressed_size: usize) -> Result<(), ParseError<'a>> {
+        if output_size > self.max_decompressed_size {
+            Err(ParseError::LimitExceededError("decompressed size", self.max_decompressed_size))
+        } else if output_size > compressed_size.saturating_mul(self.max_expansion_ratio) {
+            Err(ParseError::LimitExceededError("expansion ratio", self.max_expansion_ratio))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::UNLIMITED
+    }
+}
+
+
+/// An error from reading a file out of a stream, rather than parsing it from a buffer that's already in memory.
+#[derive(Error, Debug)]
+pub enum ReadError {
+    #[error("could not read from the source: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// The data was read successfully but could not be parsed. Holds the message of the original [`ParseError`], since
+    /// that borrows from a buffer which no longer exists.
+    #[error("{0}")]
+    ParseError(String),
+
+    #[error("entry \"{0}\" claims {2} bytes at offset {1}, but the archive is only {3} bytes long")]
+    EntryOutOfBoundsError(String, u64, u64, u64),
+
+    #[error("entry \"{0}\" starts at offset {1}, which is inside the archive's {2}-byte header")]
+    EntryOverlapsHeaderError(String, u64, u64),
+}
+
+
+impl From<ParseError<'_>> for ReadError {
+    fn from(err: ParseError<'_>) -> Self {
+        ReadError::ParseError(err.to_string())
+    }
+}
+
+
+#[derive(Error, Debug)]
+pub enum WriteError {
+    #[error("the name \"{0}\" is too long to fit in the archive (max {1} bytes)")]
+    NameTooLongError(String, usize),
+
+    #[error("the archive is too large; offsets must fit in 32 bits")]
+    ArchiveTooLargeError,
+
+    #[error("could not write the file: {0}")]
+    IoError(#[from] std::io::Error),
+}
+
+
+/// A range of bytes that something was parsed from, used to find it again (e.g., to point at it in a hex view, or to
+/// write an edited value back without re-serializing everything around it).
+///
+/// Spans are relative to whatever buffer their parser was given; a tile's span is relative to the start of the
+/// background section, for example. Use [`offset`][Self::offset] to make them relative to something bigger.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: usize,
+    /// One past the last byte.
+    pub end: usize,
+}
+
+
+impl Span {
+    pub fn new(start: usize, len: usize) -> Self {
+        Self { start, end: start + len }
+    }
+
+    /// Finds where `part` is in `data`, or `None` if it isn't a slice of `data` at all.
+    pub fn of(data: &[u8], part: &[u8]) -> Option<Self> {
+        let start = (part.as_ptr() as usize).checked_sub(data.as_ptr() as usize)?;
+        (start + part.len() <= data.len()).then(|| Self::new(start, part.len()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Moves the span forwards by `by` bytes; e.g., to turn a span from a section into one from the whole file.
+    pub fn offset(&self, by: usize) -> Self {
+        Self { start: self.start + by, end: self.end + by }
+    }
+
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
 }
 
 
@@ -40,12 +201,20 @@ pub(crate) fn sz_to_str(data: &[u8]) -> Result<&str, ParseError> {
 }
 
 
+/// Creates an [`InvalidValueError`][ParseError::InvalidValueError] for a substring of a text file that was parsed from
+/// `data`. `substr` *must* be a slice of `data`, since its offset is computed from its address.
+pub(crate) fn invalid_substr<'a>(data: &'a [u8], substr: &'a str) -> ParseError<'a> {
+    let offset = substr.as_ptr() as usize - data.as_ptr() as usize;
+    ParseError::InvalidValueError(substr.as_bytes(), offset)
+}
+
+
 /// Reads `len` bytes from the given buffer starting at `ptr`, then advances `ptr`. [`ParseError::EndOfDataError`] is
 /// raised if `*ptr + len` exceeds the bounds of the buffer.
 #[inline]
 pub(crate) fn read<'a, 'b>(data: &'a [u8], ptr: &'b mut usize, len: usize) -> Result<&'a [u8], ParseError<'a>> {
     // Attempt to read and convert to the desired array size
-    let res = data.get(*ptr..*ptr + len).ok_or(ParseError::EndOfBufferError)?;
+    let res = data.get(*ptr..ptr.saturating_add(len)).ok_or(ParseError::EndOfBufferError)?;
     *ptr += len;
     Ok(res)
 }
@@ -86,3 +255,36 @@ num_from_bytes!(pub(crate), isize_from_le_bytes, isize, from_le_bytes, "an `isiz
 
 num_from_bytes!(pub(crate), f32_from_le_bytes, f32, from_le_bytes, "an `f32`");
 num_from_bytes!(pub(crate), f64_from_le_bytes, f64, from_le_bytes, "an `f64`");
+
+
+macro_rules! num_read {
+    ($vis:vis, $func_name:ident, $num:ty, $from_bytes:ident, $doc_name:literal) => {
+        #[doc="Reads"]
+        #[doc=$doc_name]
+        /// from the given buffer starting at `ptr`, then advances `ptr`. See [`read`].
+        #[allow(unused)]
+        $vis fn $func_name<'a>(data: &'a [u8], ptr: &mut usize) -> Result<$num, ParseError<'a>> {
+            $from_bytes(read(data, ptr, std::mem::size_of::<$num>())?)
+        }
+    };
+}
+
+num_read!(pub(crate), read_u16, u16, u16_from_le_bytes, "a little-endian `u16`");
+num_read!(pub(crate), read_u32, u32, u32_from_le_bytes, "a little-endian `u32`");
+num_read!(pub(crate), read_u64, u64, u64_from_le_bytes, "a little-endian `u64`");
+
+num_read!(pub(crate), read_i8, i8, i8_from_le_bytes, "an `i8`");
+num_read!(pub(crate), read_i16, i16, i16_from_le_bytes, "a little-endian `i16`");
+num_read!(pub(crate), read_i32, i32, i32_from_le_bytes, "a little-endian `i32`");
+num_read!(pub(crate), read_i64, i64, i64_from_le_bytes, "a little-endian `i64`");
+
+num_read!(pub(crate), read_f32, f32, f32_from_le_bytes, "a little-endian `f32`");
+num_read!(pub(crate), read_f64, f64, f64_from_le_bytes, "a little-endian `f64`");
+
+
+/// Reads a single byte from the given buffer starting at `ptr`, then advances `ptr`. See [`read`].
+#[inline]
+#[allow(unused)]
+pub(crate) fn read_u8<'a>(data: &'a [u8], ptr: &mut usize) -> Result<u8, ParseError<'a>> {
+    Ok(read(data, ptr, 1)?[0])
+}
@@ -0,0 +1,50 @@
+//! Writes files to disk without ever leaving them half-written.
+//!
+//! Repacking an archive like `flevel.lgp` takes long enough that it can easily be interrupted, and writing over the
+//! original as it goes would leave the game with a corrupt file. Instead, everything is written to a temporary file
+//! next to the original, flushed to disk, and only then renamed over it. Renaming within a directory is atomic, so
+//! the original is either untouched or completely replaced.
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+
+/// Writes `data` to `path`, replacing it atomically if it already exists. If `backup` is set, the original file is
+/// first copied to the same path with `.bak` added to the end (e.g., `char.lgp.bak`), replacing any older backup.
+///
+/// If anything fails, the temporary file is removed and the original is left as it was.
+pub fn save_atomic(path: impl AsRef<Path>, data: &[u8], backup: bool) -> io::Result<()> {
+    let path = path.as_ref();
+    let temp_path = with_suffix(path, ".tmp");
+
+    let result = write_synced(&temp_path, data).and_then(|_| {
+        if backup && path.exists() {
+            fs::copy(path, with_suffix(path, ".bak"))?;
+        }
+        fs::rename(&temp_path, path)
+    });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path); // the original error is the one that matters
+    }
+
+    result
+}
+
+
+/// Writes a file and waits until its contents are actually on disk, so that it can't be renamed into place empty.
+fn write_synced(path: &Path, data: &[u8]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+
+/// Adds a suffix to the end of a path's file name, keeping its extension (unlike [`Path::with_extension`]).
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(suffix);
+    path.with_file_name(name)
+}
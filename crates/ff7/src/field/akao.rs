@@ -0,0 +1,112 @@
+//! Splits the music and sound blocks out of the end of a field's script section.
+//!
+//! The script section's header lists the offsets of a set of blocks that come after the dialogue table. Most of them
+//! are AKAO frames: sequences for the game's own sequencer, which field scripts start with the `AKAO` and `AKAO2`
+//! opcodes. The rest are tutorials, which play back a list of controller presses in a menu. Neither kind stores its own
+//! length reliably, so each block runs until the next one starts.
+//!
+//! An AKAO frame starts with a 16-byte header (the `AKAO` magic, the frame's ID, the length of the sequence data, the
+//! reverb type, and a timestamp), followed by a bit mask of which of the sequencer's channels the frame uses. The
+//! sequence data itself is left as raw bytes.
+
+use super::ScriptSection;
+use crate::extract::{read, read_u16, read_u32, ParseError};
+
+
+/// The magic number that every AKAO frame starts with.
+pub const AKAO_MAGIC: &[u8] = b"AKAO";
+
+/// The length of an AKAO frame's header, not including its channel mask.
+const AKAO_HEADER_LEN: usize = 0x10;
+
+
+/// A sequence for the game's sequencer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AkaoFrame<'a> {
+    /// Where the frame starts, relative to the start of the script section.
+    pub offset: usize,
+    /// The frame's ID, which the `AKAO` opcodes refer to it by.
+    pub id: u16,
+    pub reverb: u16,
+    /// Which of the sequencer's channels the frame uses, one bit per channel.
+    pub channel_mask: u32,
+    /// The whole frame, including its header.
+    pub data: &'a [u8],
+}
+
+
+impl<'a> AkaoFrame<'a> {
+    /// Reads an AKAO frame from the start of `data`. The frame is assumed to run until the end of `data`, unless its
+    /// header says that it is shorter.
+    pub fn from_bytes(data: &'a [u8], offset: usize) -> Result<Self, ParseError<'a>> {
+        let mut ptr = 0;
+
+        let magic = read(data, &mut ptr, AKAO_MAGIC.len())?;
+        if magic != AKAO_MAGIC {
+            return Err(ParseError::InvalidValueError(magic, offset));
+        }
+
+        let id = read_u16(data, &mut ptr)?;
+        let len = read_u16(data, &mut ptr)? as usize;
+        let reverb = read_u16(data, &mut ptr)?;
+        ptr = AKAO_HEADER_LEN;
+        let channel_mask = read_u32(data, &mut ptr)?;
+
+        let data = &data[..(AKAO_HEADER_LEN + len).clamp(ptr, data.len())];
+        Ok(Self { offset, id, reverb, channel_mask, data })
+    }
+
+    /// The number of channels that the frame uses.
+    pub fn channel_count(&self) -> u32 {
+        self.channel_mask.count_ones()
+    }
+
+    /// The frame's sequence data, after its header and channel mask.
+    pub fn sequence(&self) -> &'a [u8] {
+        &self.data[AKAO_HEADER_LEN + 4..]
+    }
+}
+
+
+/// One of the blocks at the end of a script section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundBlock<'a> {
+    Akao(AkaoFrame<'a>),
+    /// A tutorial's list of controller presses, left as raw bytes, along with where it starts in the script section.
+    Tutorial(usize, &'a [u8]),
+}
+
+
+impl<'a> ScriptSection<'a> {
+    /// Splits out the blocks that the header lists, in order. `data` has to be the same script section that this was
+    /// parsed from.
+    pub fn sound_blocks(&self, data: &'a [u8]) -> Result<Vec<SoundBlock<'a>>, ParseError<'a>> {
+        let mut blocks = Vec::with_capacity(self.akao_offsets.len());
+
+        for (i, &offset) in self.akao_offsets.iter().enumerate() {
+            let offset = offset as usize;
+            let end = self.akao_offsets.get(i + 1).map_or(data.len(), |&next| next as usize);
+            let block = data.get(offset..end.max(offset)).ok_or(ParseError::EndOfBufferError)?;
+
+            if block.starts_with(AKAO_MAGIC) {
+                blocks.push(SoundBlock::Akao(AkaoFrame::from_bytes(block, offset)?));
+            } else {
+                blocks.push(SoundBlock::Tutorial(offset, block));
+            }
+        }
+
+        Ok(blocks)
+    }
+
+    /// Every AKAO frame in the section, skipping tutorials. See [`sound_blocks`][Self::sound_blocks].
+    pub fn akao_frames(&self, data: &'a [u8]) -> Result<Vec<AkaoFrame<'a>>, ParseError<'a>> {
+        let blocks = self.sound_blocks(data)?;
+        Ok(blocks
+            .into_iter()
+            .filter_map(|block| match block {
+                SoundBlock::Akao(frame) => Some(frame),
+                SoundBlock::Tutorial(..) => None,
+            })
+            .collect())
+    }
+}
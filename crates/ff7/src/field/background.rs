@@ -0,0 +1,435 @@
+//! Parses a field file's
+//! [palette and background sections](https://wiki.ffrtt.ru/index.php/FF7/Field_Module/DAT/Background), and decodes
+//! them into one image per background layer.
+//!
+//! A background is drawn out of tiles, which are copied out of a set of 256×256 texture pages and placed on screen.
+//! There are up to four layers of tiles: the first is the base of the scene, and the rest hold things that need to be
+//! drawn in front of or behind the field's models. Paletted textures get their colors from the separate palette
+//! section, which is stored in the PlayStation's 15-bit color format.
+
+use std::cmp::Reverse;
+
+use super::Camera;
+use crate::char::{Color, RgbaImage};
+use crate::extract::{read, read_i16, read_u16, read_u32, read_u8, ParseError, Span};
+
+
+/// The number of background layers.
+pub const LAYER_COUNT: usize = 4;
+
+/// The number of texture pages that the background section has room for.
+pub const TEXTURE_COUNT: usize = 42;
+
+/// The width and height of every texture page.
+pub const TEXTURE_SIZE: usize = 256;
+
+/// How far from the center of the screen a tile may be drawn when decoding layers, in pixels. The largest fields in
+/// the game are well inside this; anything further out is assumed to be junk, and would otherwise make layer images
+/// big enough to run out of memory.
+pub const MAX_TILE_DISTANCE: i32 = 2048;
+
+/// The size of a single tile in the background section.
+const TILE_LEN: usize = 52;
+
+/// How much deeper a point is in camera space than its place in the ordering table, which is what a tile's
+/// [`id`][Tile::id] gives. The PlayStation sorts everything it draws by a quarter of its depth.
+const DEPTH_PER_ID: f32 = 4.0;
+
+/// The number of unknown bytes in the header of each layer, which differs between layers.
+const LAYER_HEADER_EXTRA: [usize; LAYER_COUNT] = [2, 16, 10, 10];
+
+
+/// One of the ways that a tile can be blended with whatever is drawn behind it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The average of the tile and the background.
+    Average,
+    /// The tile is added to the background.
+    Add,
+    /// The tile is subtracted from the background.
+    Subtract,
+    /// A quarter of the tile is added to the background.
+    AddQuarter,
+}
+
+
+impl BlendMode {
+    fn from_u8(value: u8) -> Self {
+        match value & 0x03 {
+            0 => BlendMode::Average,
+            1 => BlendMode::Add,
+            2 => BlendMode::Subtract,
+            _ => BlendMode::AddQuarter,
+        }
+    }
+
+    /// Blends one 8-bit channel of a tile's color with the same channel of the background.
+    fn blend(self, under: u8, over: u8) -> u8 {
+        let (under, over) = (under as u16, over as u16);
+        let value = match self {
+            BlendMode::Average => (under + over) / 2,
+            BlendMode::Add => under + over,
+            BlendMode::Subtract => under.saturating_sub(over),
+            BlendMode::AddQuarter => under + over / 4,
+        };
+        value.min(255) as u8
+    }
+}
+
+
+/// The background's palettes, from the field file's palette section.
+#[derive(Debug, Clone)]
+pub struct PaletteSection {
+    /// How many colors each palette has (always 256 on PC).
+    pub colors_per_palette: u16,
+    /// Every palette's colors, one after the other.
+    pub colors: Vec<Color>,
+}
+
+
+impl PaletteSection {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = 0;
+
+        let _len = read_u32(data, &mut ptr)?;
+        let _x = read_u16(data, &mut ptr)?; // where the palettes were in the PlayStation's VRAM
+        let _y = read_u16(data, &mut ptr)?;
+        let colors_per_palette = read_u16(data, &mut ptr)?;
+        let palette_count = read_u16(data, &mut ptr)?;
+
+        let color_count = colors_per_palette as usize * palette_count as usize;
+        let mut colors = Vec::with_capacity(color_count.min(data.len() / 2));
+        for _ in 0..color_count {
+            colors.push(color_from_u16(read_u16(data, &mut ptr)?));
+        }
+
+        Ok(Self { colors_per_palette, colors })
+    }
+
+    /// Gets the colors of a single palette, or `None` if the index is out of range.
+    pub fn palette(&self, index: u8) -> Option<&[Color]> {
+        let start = index as usize * self.colors_per_palette as usize;
+        self.colors.get(start..start + self.colors_per_palette as usize)
+    }
+}
+
+
+/// A single tile of a background layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// Where the tile's data is, relative to the start of the background section.
+    pub span: Span,
+    /// Where the tile is drawn, relative to the center of the screen.
+    pub dst_x: i16,
+    pub dst_y: i16,
+    /// Where the tile is in its texture page.
+    pub src_x: u8,
+    pub src_y: u8,
+    /// Where the tile is in its texture page when it is blended. See [`texture_blend`][Self::texture_blend].
+    pub src_x_blend: u8,
+    pub src_y_blend: u8,
+    pub palette: u8,
+    /// The tile's depth; tiles with higher values are further back.
+    pub id: u16,
+    /// Which group of animated tiles this tile belongs to, or 0 if it is always shown.
+    pub param: u8,
+    /// Which states of its group this tile is shown in, as a bit field.
+    pub state: u8,
+    /// Whether or not the tile is blended with what is behind it, using [`blend_mode`][Self::blend_mode].
+    pub blending: bool,
+    pub blend_mode: BlendMode,
+    pub texture: u8,
+    /// Which texture page the tile is copied out of when it is blended.
+    pub texture_blend: u8,
+    /// The texture page's color depth: `2` for 16-bit direct colors, and anything else for paletted colors.
+    pub depth: u8,
+}
+
+
+/// One layer of a background.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    pub width: u16,
+    pub height: u16,
+    pub tiles: Vec<Tile>,
+}
+
+
+/// One of the background's texture pages.
+#[derive(Debug, Clone, Copy)]
+pub struct BackgroundTexture<'a> {
+    /// How many bytes each pixel takes up: `1` for palette indices, `2` for direct colors.
+    pub depth: u16,
+    /// `256 * 256 * depth` bytes of pixel data.
+    pub pixels: &'a [u8],
+}
+
+
+/// A background layer decoded into an image.
+#[derive(Debug, Clone)]
+pub struct LayerImage {
+    /// Which layer this is, from 0 to 3.
+    pub layer: usize,
+    /// Where the image's top-left corner goes, relative to the center of the screen.
+    pub x: i32,
+    pub y: i32,
+    pub image: RgbaImage,
+}
+
+
+/// A background tile placed in field space, at the depth that it is drawn at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlacedTile {
+    /// Which layer the tile is in, from 0 to 3.
+    pub layer: usize,
+    pub tile: Tile,
+    /// The tile's top-left, top-right, bottom-right, and bottom-left corners. Seen from the field's camera, they line
+    /// up exactly with where the tile is drawn on screen.
+    pub corners: [[f32; 3]; 4],
+}
+
+
+/// The parsed contents of a field file's background section.
+#[derive(Debug, Clone)]
+pub struct BackgroundSection<'a> {
+    /// Each layer, or `None` for layers that this field doesn't use. The first layer is always present.
+    pub layers: [Option<Layer>; LAYER_COUNT],
+    /// Each texture page, or `None` for unused pages.
+    pub textures: Vec<Option<BackgroundTexture<'a>>>,
+}
+
+
+impl<'a> BackgroundSection<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        let mut ptr = 0;
+
+        let _blank = read_u16(data, &mut ptr)?;
+        let _depth = read_u16(data, &mut ptr)?;
+        let _enabled = read_u8(data, &mut ptr)?;
+        expect_marker(data, &mut ptr, b"PALETTE")?;
+
+        // The palette marker is followed by a few flags that aren't needed here, so skip ahead to the first layer.
+        let back = data[ptr..].windows(4).position(|w| w == b"BACK").ok_or(ParseError::EndOfBufferError)?;
+        ptr += back + 4;
+
+        let mut layers = [None, None, None, None];
+        for (i, layer) in layers.iter_mut().enumerate() {
+            // Every layer but the first starts with a flag saying whether or not it is there at all.
+            if i > 0 && read_u8(data, &mut ptr)? == 0 {
+                continue;
+            }
+
+            let width = read_u16(data, &mut ptr)?;
+            let height = read_u16(data, &mut ptr)?;
+            let tile_count = read_u16(data, &mut ptr)? as usize;
+            read(data, &mut ptr, LAYER_HEADER_EXTRA[i] + 2)?; // unknown, then blank
+
+            let mut tiles = Vec::with_capacity(tile_count.min(data.len() / TILE_LEN));
+            for _ in 0..tile_count {
+                let span = Span::new(ptr, TILE_LEN);
+                tiles.push(read_tile(read(data, &mut ptr, TILE_LEN)?, span)?);
+            }
+            read(data, &mut ptr, 2)?; // blank
+
+            *layer = Some(Layer { width, height, tiles });
+        }
+
+        expect_marker(data, &mut ptr, b"TEXTURE")?;
+
+        let mut textures = Vec::with_capacity(TEXTURE_COUNT);
+        for _ in 0..TEXTURE_COUNT {
+            if read_u16(data, &mut ptr)? == 0 {
+                textures.push(None);
+                continue;
+            }
+
+            let _size = read_u16(data, &mut ptr)?;
+            let depth = read_u16(data, &mut ptr)?;
+            let pixels = read(data, &mut ptr, TEXTURE_SIZE * TEXTURE_SIZE * depth as usize)?;
+            textures.push(Some(BackgroundTexture { depth, pixels }));
+        }
+
+        expect_marker(data, &mut ptr, b"END")?;
+
+        Ok(Self { layers, textures })
+    }
+
+    /// Decodes every layer into an image, drawing only the tiles that are always shown. See
+    /// [`decode_layers_with`][Self::decode_layers_with].
+    pub fn decode_layers(&self, palettes: &PaletteSection) -> Vec<LayerImage> {
+        self.decode_layers_with(palettes, |tile| tile.param == 0)
+    }
+
+    /// Decodes every layer into an image, drawing the tiles that `show` returns `true` for. Each image is just big
+    /// enough to hold its layer's tiles. Tiles are drawn back to front, and blended tiles are blended with whatever has
+    /// been drawn behind them in the same layer. Tiles that aren't entirely within [`MAX_TILE_DISTANCE`] of the center
+    /// of the screen are left out.
+    pub fn decode_layers_with(&self, palettes: &PaletteSection, show: impl Fn(&Tile) -> bool) -> Vec<LayerImage> {
+        let mut images = Vec::new();
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            let Some(layer) = layer else { continue };
+
+            let size = tile_size(i) as i32;
+            let in_range = |position: i16| (-MAX_TILE_DISTANCE..=MAX_TILE_DISTANCE - size).contains(&(position as i32));
+            let mut tiles = layer
+                .tiles
+                .iter()
+                .filter(|tile| in_range(tile.dst_x) && in_range(tile.dst_y) && show(tile))
+                .collect::<Vec<_>>();
+            if tiles.is_empty() {
+                continue;
+            }
+
+            // Further tiles are drawn first. The sort is stable, so tiles at the same depth keep their order.
+            tiles.sort_by_key(|tile| Reverse(tile.id));
+
+            let min_x = tiles.iter().map(|t| t.dst_x as i32).min().unwrap_or(0);
+            let min_y = tiles.iter().map(|t| t.dst_y as i32).min().unwrap_or(0);
+            let max_x = tiles.iter().map(|t| t.dst_x as i32 + size).max().unwrap_or(0);
+            let max_y = tiles.iter().map(|t| t.dst_y as i32 + size).max().unwrap_or(0);
+
+            let (width, height) = ((max_x - min_x) as u32, (max_y - min_y) as u32);
+            let mut image = RgbaImage { width, height, pixels: vec![0; width as usize * height as usize * 4] };
+
+            for tile in tiles {
+                let (x, y) = ((tile.dst_x as i32 - min_x) as u32, (tile.dst_y as i32 - min_y) as u32);
+                self.draw_tile(&mut image, tile, size as u32, (x, y), palettes);
+            }
+
+            images.push(LayerImage { layer: i, x: min_x, y: min_y, image });
+        }
+
+        images
+    }
+
+    /// Places every tile of every layer in field space, using the field's camera to undo the projection. Each tile
+    /// becomes a square that faces the camera, pushed back to its depth; looked at from anywhere else, this shows how
+    /// the background's parts are layered around the walkmesh and models.
+    pub fn place_tiles(&self, camera: &Camera) -> Vec<PlacedTile> {
+        let mut placed = Vec::new();
+
+        for (i, layer) in self.layers.iter().enumerate() {
+            let Some(layer) = layer else { continue };
+
+            let size = tile_size(i) as f32;
+            placed.extend(layer.tiles.iter().map(|&tile| {
+                let (x, y) = (tile.dst_x as f32, tile.dst_y as f32);
+                let depth = tile.id as f32 * DEPTH_PER_ID;
+                let corners = [[x, y], [x + size, y], [x + size, y + size], [x, y + size]];
+                PlacedTile { layer: i, tile, corners: corners.map(|corner| camera.unproject(corner, depth)) }
+            }));
+        }
+
+        placed
+    }
+
+    /// Copies a tile's pixels into an image, with its top-left corner at `(x, y)`.
+    fn draw_tile(&self, image: &mut RgbaImage, tile: &Tile, size: u32, (x, y): (u32, u32), palettes: &PaletteSection) {
+        let (texture, src_x, src_y) = if tile.blending {
+            (tile.texture_blend, tile.src_x_blend, tile.src_y_blend)
+        } else {
+            (tile.texture, tile.src_x, tile.src_y)
+        };
+
+        // Missing textures and palettes shouldn't happen, but skip the tile rather than failing outright.
+        // log warning?
+        let Some(Some(texture)) = self.textures.get(texture as usize) else {
+            return;
+        };
+        let palette = palettes.palette(tile.palette);
+
+        for ty in 0..size {
+            for tx in 0..size {
+                let (sx, sy) = (src_x as usize + tx as usize, src_y as usize + ty as usize);
+                if sx >= TEXTURE_SIZE || sy >= TEXTURE_SIZE {
+                    continue;
+                }
+
+                let texel = sy * TEXTURE_SIZE + sx;
+                let color = if texture.depth == 2 {
+                    let bytes = &texture.pixels[texel * 2..texel * 2 + 2];
+                    color_from_u16(u16::from_le_bytes([bytes[0], bytes[1]]))
+                } else {
+                    let index = texture.pixels[texel] as usize;
+                    match palette.and_then(|p| p.get(index)) {
+                        Some(&color) => color,
+                        None => continue,
+                    }
+                };
+
+                // Fully transparent pixels leave whatever is behind them alone.
+                if color.a == 0 {
+                    continue;
+                }
+
+                let at = (((y + ty) * image.width + x + tx) * 4) as usize;
+                let under = &mut image.pixels[at..at + 4];
+                if tile.blending && under[3] != 0 {
+                    under[0] = tile.blend_mode.blend(under[0], color.r);
+                    under[1] = tile.blend_mode.blend(under[1], color.g);
+                    under[2] = tile.blend_mode.blend(under[2], color.b);
+                } else {
+                    under.copy_from_slice(&color.to_array());
+                }
+            }
+        }
+    }
+}
+
+
+/// The width and height of the tiles in a layer. The last two layers use larger tiles.
+pub fn tile_size(layer: usize) -> usize {
+    if layer >= 2 {
+        32
+    } else {
+        16
+    }
+}
+
+
+/// Converts a PlayStation color (5 bits per channel, red in the lowest bits) into an 8-bit color. Pure black is used
+/// as the transparent color.
+fn color_from_u16(value: u16) -> Color {
+    let channel = |shift: u16| (((value >> shift) & 0x1F) as u32 * 255 / 31) as u8;
+    let a = if value & 0x7FFF == 0 { 0 } else { 255 };
+    Color { r: channel(0), g: channel(5), b: channel(10), a }
+}
+
+
+/// Reads one of the ASCII markers between the parts of the background section.
+fn expect_marker<'a>(data: &'a [u8], ptr: &mut usize, marker: &[u8]) -> Result<(), ParseError<'a>> {
+    let start = *ptr;
+    let found = read(data, ptr, marker.len())?;
+    if found != marker {
+        return Err(ParseError::InvalidValueError(found, start));
+    }
+    Ok(())
+}
+
+
+fn read_tile(data: &[u8], span: Span) -> Result<Tile, ParseError<'_>> {
+    let mut ptr = 2; // blank
+    let dst_x = read_i16(data, &mut ptr)?;
+    let dst_y = read_i16(data, &mut ptr)?;
+
+    let field = |offset: usize| data[offset];
+    Ok(Tile {
+        span,
+        dst_x,
+        dst_y,
+        src_x: field(0x0A),
+        src_y: field(0x0C),
+        src_x_blend: field(0x0E),
+        src_y_blend: field(0x10),
+        palette: field(0x16),
+        id: u16::from_le_bytes([field(0x18), field(0x19)]),
+        param: field(0x1A),
+        state: field(0x1B),
+        blending: field(0x1C) != 0,
+        blend_mode: BlendMode::from_u8(field(0x1E)),
+        texture: field(0x20),
+        texture_blend: field(0x22),
+        depth: field(0x24),
+    })
+}
@@ -0,0 +1,82 @@
+//! Parses a field file's [camera section](https://wiki.ffrtt.ru/index.php/FF7/Field_Module/DAT/Camera_Matrix), which
+//! holds the fixed camera that the field's background was drawn from.
+//!
+//! The camera is stored the way the PlayStation's geometry coprocessor uses it: a rotation matrix in 4.12 fixed point,
+//! whose rows are the camera's axes in field space, and a translation that is applied after the rotation (so it is in
+//! camera space, not field space). A point is projected onto the screen by rotating and translating it into camera
+//! space, then scaling its x and y by `zoom / z`.
+
+use super::triggers::read_vertex;
+use crate::extract::{read, read_i16, read_i32, read_u16, ParseError};
+
+
+/// The value that stands for `1.0` in the camera's rotation matrix.
+const FIXED_ONE: f32 = 4096.0;
+
+
+/// A field's camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Camera {
+    /// The camera's x, y, and z axes, in field space. Each is in 4.12 fixed point.
+    pub axes: [[i16; 3]; 3],
+    /// The translation that is applied after rotating a point into camera space.
+    pub translation: [i32; 3],
+    /// The distance from the camera to the screen; larger values give a narrower field of view.
+    pub zoom: u16,
+}
+
+
+impl Camera {
+    /// Reads the field's first camera. A few fields have more than one camera in this section, but the first is the
+    /// one that the background is drawn from.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = 0;
+
+        let axes = [read_vertex(data, &mut ptr)?, read_vertex(data, &mut ptr)?, read_vertex(data, &mut ptr)?];
+        let _z_axis_z = read_i16(data, &mut ptr)?; // a copy of `axes[2][2]`
+
+        let translation = [read_i32(data, &mut ptr)?, read_i32(data, &mut ptr)?, read_i32(data, &mut ptr)?];
+        read(data, &mut ptr, 4)?; // blank
+        let zoom = read_u16(data, &mut ptr)?;
+
+        Ok(Self { axes, translation, zoom })
+    }
+
+    /// The camera's axes, as unit vectors.
+    pub fn axes_f32(&self) -> [[f32; 3]; 3] {
+        self.axes.map(|axis| axis.map(|v| v as f32 / FIXED_ONE))
+    }
+
+    /// Where the camera is, in field space.
+    pub fn position(&self) -> [f32; 3] {
+        self.camera_to_field([0.0; 3])
+    }
+
+    /// Projects a point in field space onto the screen, returning its position relative to the center of the screen
+    /// and its depth in camera space. Points at or behind the camera have a depth of zero or less, and their screen
+    /// position is meaningless.
+    pub fn project(&self, point: [f32; 3]) -> ([f32; 2], f32) {
+        let axes = self.axes_f32();
+        let dot = |axis: [f32; 3]| axis[0] * point[0] + axis[1] * point[1] + axis[2] * point[2];
+        let [x, y, z] = [0, 1, 2].map(|i| dot(axes[i]) + self.translation[i] as f32);
+
+        let scale = self.zoom as f32 / z;
+        ([x * scale, y * scale], z)
+    }
+
+    /// Finds the point in field space that is drawn at the given screen position (relative to the center of the
+    /// screen) and is `depth` away from the camera, along its z axis. This undoes [`project`][Self::project].
+    pub fn unproject(&self, screen: [f32; 2], depth: f32) -> [f32; 3] {
+        let scale = depth / self.zoom as f32;
+        let camera = [screen[0] * scale, screen[1] * scale, depth];
+        self.camera_to_field(camera)
+    }
+
+    /// Moves a point from camera space into field space, by undoing the translation and then the rotation. The
+    /// rotation is orthonormal, so undoing it is just multiplying by its transpose.
+    fn camera_to_field(&self, point: [f32; 3]) -> [f32; 3] {
+        let axes = self.axes_f32();
+        let p = [0, 1, 2].map(|i| point[i] - self.translation[i] as f32);
+        [0, 1, 2].map(|j| axes[0][j] * p[0] + axes[1][j] * p[1] + axes[2][j] * p[2])
+    }
+}
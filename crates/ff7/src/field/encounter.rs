@@ -0,0 +1,101 @@
+//! Parses a field file's [encounter section](https://wiki.ffrtt.ru/index.php/FF7/Field_Module/DAT/Encounter), which
+//! says which random battles can happen in the field.
+//!
+//! There are always two tables, and field scripts choose which of them is in use (e.g., to change the battles after a
+//! story event). Each battle is stored as a single `u16`: the lower 10 bits are the battle's ID in `scene.bin`, and the
+//! upper 6 are its chance of being picked, out of 64.
+
+use crate::extract::{read, read_u16, read_u8, ParseError};
+
+
+/// The number of encounter tables that every field has.
+pub const ENCOUNTER_TABLE_COUNT: usize = 2;
+
+
+/// A battle that can be picked when an encounter happens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Encounter {
+    /// The battle's ID in `scene.bin`.
+    pub battle_id: u16,
+    /// The battle's chance of being picked, out of 64.
+    pub probability: u8,
+}
+
+
+impl Encounter {
+    fn from_u16(value: u16) -> Self {
+        Self { battle_id: value & 0x03FF, probability: (value >> 10) as u8 }
+    }
+
+    /// Whether or not this slot in the table actually has a battle in it.
+    pub fn is_used(&self) -> bool {
+        self.probability != 0
+    }
+}
+
+
+/// One of the field's sets of random battles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncounterTable {
+    pub enabled: bool,
+    /// How often encounters happen; lower values mean more frequent battles.
+    pub rate: u8,
+    /// The regular battles. Their probabilities should add up to 64.
+    pub standard: [Encounter; 6],
+    /// The back attacks. Unlike the regular battles, their probabilities are each checked on their own, before a
+    /// regular battle is picked.
+    pub back_attacks: [Encounter; 2],
+    pub side_attack: Encounter,
+    pub pincer_attack: Encounter,
+}
+
+
+impl EncounterTable {
+    /// Every battle in this table that can actually happen.
+    pub fn encounters(&self) -> impl Iterator<Item = &Encounter> {
+        self.standard
+            .iter()
+            .chain(&self.back_attacks)
+            .chain([&self.side_attack, &self.pincer_attack])
+            .filter(|encounter| encounter.is_used())
+    }
+}
+
+
+/// The parsed contents of a field file's encounter section.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncounterSection {
+    pub tables: [EncounterTable; ENCOUNTER_TABLE_COUNT],
+}
+
+
+impl EncounterSection {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = 0;
+        let first = read_table(data, &mut ptr)?;
+        let second = read_table(data, &mut ptr)?;
+        Ok(Self { tables: [first, second] })
+    }
+}
+
+
+fn read_table<'a>(data: &'a [u8], ptr: &mut usize) -> Result<EncounterTable, ParseError<'a>> {
+    let enabled = read_u8(data, ptr)? != 0;
+    let rate = read_u8(data, ptr)?;
+
+    let mut read_encounter = || read_u16(data, ptr).map(Encounter::from_u16);
+    let standard = [
+        read_encounter()?,
+        read_encounter()?,
+        read_encounter()?,
+        read_encounter()?,
+        read_encounter()?,
+        read_encounter()?,
+    ];
+    let back_attacks = [read_encounter()?, read_encounter()?];
+    let side_attack = read_encounter()?;
+    let pincer_attack = read_encounter()?;
+    read(data, ptr, 2)?; // padding
+
+    Ok(EncounterTable { enabled, rate, standard, back_attacks, side_attack, pincer_attack })
+}
@@ -0,0 +1,48 @@
+//! Parses the [`maplist`](https://wiki.ffrtt.ru/index.php/FF7/Field_Module) file from `flevel.lgp`, which gives every
+//! field its ID.
+//!
+//! Fields refer to each other by ID (e.g., in [gateways][super::Gateway]), and the ID of a field is its position in
+//! this list. The file is a `u16` count followed by that many names, each null-padded to 32 bytes.
+
+use crate::extract::{read, read_u16, sz_to_str, ParseError};
+
+
+/// The name of the map list in `flevel.lgp`.
+pub const MAPLIST_NAME: &str = "maplist";
+
+/// The length of each name in the map list.
+const NAME_LEN: usize = 32;
+
+
+/// The parsed contents of the map list.
+#[derive(Debug, Clone)]
+pub struct MapList<'a> {
+    /// The name of every field, indexed by field ID. Some IDs have placeholder names for fields that don't exist.
+    pub names: Vec<&'a str>,
+}
+
+
+impl<'a> MapList<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        let mut ptr = 0;
+        let count = read_u16(data, &mut ptr)? as usize;
+
+        let mut names = Vec::with_capacity(count.min(data.len() / NAME_LEN));
+        for _ in 0..count {
+            let name_data = read(data, &mut ptr, NAME_LEN)?;
+            names.push(sz_to_str(&name_data[..name_data.iter().position(|&b| b == 0).unwrap_or(NAME_LEN)])?);
+        }
+
+        Ok(Self { names })
+    }
+
+    /// Gets the name of the field with the given ID.
+    pub fn name(&self, id: u16) -> Option<&'a str> {
+        self.names.get(id as usize).copied()
+    }
+
+    /// Finds the ID of the field with the given name, ignoring case.
+    pub fn id(&self, name: &str) -> Option<u16> {
+        self.names.iter().position(|n| n.eq_ignore_ascii_case(name)).map(|id| id as u16)
+    }
+}
@@ -3,3 +3,26 @@
 //! The field scripts are what contain all the information required to render the data in the [`char`](super::char)
 //! module. [`char`](super::char) holds the bone hierarchies and texture data, but the field scripts contain the camera,
 //! animation, and palette data required to render them.
+
+
+mod akao;
+mod background;
+mod camera;
+mod encounter;
+mod maplist;
+mod opcodes;
+mod script;
+mod sections;
+mod spawn;
+mod triggers;
+
+pub use akao::*;
+pub use background::*;
+pub use camera::*;
+pub use encounter::*;
+pub use maplist::*;
+pub use opcodes::*;
+pub use script::*;
+pub use sections::*;
+pub use spawn::*;
+pub use triggers::*;
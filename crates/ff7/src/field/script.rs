@@ -0,0 +1,230 @@
+//! Parses a field file's [script section](https://wiki.ffrtt.ru/index.php/FF7/Field_Module/DAT/Script), which holds
+//! every entity's scripts and the field's dialogue.
+//!
+//! The section starts with a 32-byte header, followed by each entity's name, the offsets of the field's music (AKAO)
+//! blocks, and then 32 script offsets for every entity. The script code comes next, followed by the dialogue table.
+//! All offsets are relative to the start of the section.
+
+use std::collections::BTreeSet;
+
+use super::opcodes::{opcode_info, special_info, KAWAI, SPECIAL};
+use crate::extract::{read, read_u16, read_u32, sz_to_str, ParseError, Span};
+use crate::text::{FfText, TEXT_END};
+
+
+/// The number of scripts that every entity has.
+pub const SCRIPTS_PER_ENTITY: usize = 32;
+
+/// The length of the names in the script header.
+const NAME_LEN: usize = 8;
+
+/// A single decoded instruction from a script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction<'a> {
+    /// Where the instruction starts, relative to the start of the script section.
+    pub offset: usize,
+    /// The instruction's length in bytes, including its opcode(s).
+    pub len: usize,
+    /// The opcode. For [`SPECIAL`] instructions, this is the sub-opcode instead.
+    pub opcode: u8,
+    /// The opcode's mnemonic (e.g., `"MESSAGE"`).
+    pub name: &'static str,
+    /// The instruction's operands, not including the opcode byte(s).
+    pub operands: &'a [u8],
+}
+
+
+/// One of an entity's scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Script<'a> {
+    /// Where the script starts, relative to the start of the script section.
+    pub offset: usize,
+    /// The script's raw code. Scripts don't store their own length, so this runs until the next script starts.
+    pub code: &'a [u8],
+}
+
+
+impl<'a> Instruction<'a> {
+    /// Where the instruction is, relative to the start of the script section.
+    pub fn span(&self) -> Span {
+        Span::new(self.offset, self.len)
+    }
+}
+
+
+impl<'a> Script<'a> {
+    /// Where the script's code is, relative to the start of the script section.
+    pub fn span(&self) -> Span {
+        Span::new(self.offset, self.code.len())
+    }
+
+    /// Decodes the script's instructions one at a time. Decoding stops at the first unknown opcode or truncated
+    /// instruction, which is returned as an [`InvalidValueError`][ParseError::InvalidValueError] or an
+    /// [`EndOfBufferError`][ParseError::EndOfBufferError].
+    pub fn instructions(&self) -> impl Iterator<Item = Result<Instruction<'a>, ParseError<'a>>> + 'a {
+        let Script { offset: base, code } = *self;
+        let mut ptr = 0;
+        let mut failed = false;
+
+        std::iter::from_fn(move || {
+            if failed || ptr >= code.len() {
+                return None;
+            }
+
+            match decode_instruction(code, ptr, base) {
+                Ok(instruction) => {
+                    ptr += instruction.len;
+                    Some(Ok(instruction))
+                },
+                Err(e) => {
+                    failed = true;
+                    Some(Err(e))
+                },
+            }
+        })
+    }
+}
+
+
+/// Something in a field that runs scripts, like a character, a door, or the field's own director.
+#[derive(Debug, Clone)]
+pub struct Entity<'a> {
+    pub name: &'a str,
+    /// The entity's scripts, in order. Script 0 runs when the field loads; the rest are started by other scripts.
+    /// Unused scripts share their offset (and code) with the next one that is used.
+    pub scripts: Vec<Script<'a>>,
+}
+
+
+/// The parsed contents of a field file's script section.
+#[derive(Debug, Clone)]
+pub struct ScriptSection<'a> {
+    /// The scale of the field's models and walkmesh.
+    pub scale: u16,
+    /// The number of models that the field uses.
+    pub model_count: u8,
+    /// The name of whoever made the field.
+    pub creator: &'a str,
+    /// The field's name.
+    pub name: &'a str,
+    pub entities: Vec<Entity<'a>>,
+    /// The offsets of the field's AKAO (music and sound) blocks. See [`sound_blocks`][Self::sound_blocks].
+    pub akao_offsets: Vec<u32>,
+    /// The field's dialogue, still in the game's text encoding and without the terminating `0xFF`. See
+    /// [`decode_text`].
+    pub strings: Vec<&'a [u8]>,
+}
+
+
+impl<'a> ScriptSection<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        let mut ptr = 0;
+
+        let _version = read_u16(data, &mut ptr)?; // always 0x0502
+        let entity_count = read(data, &mut ptr, 1)?[0] as usize;
+        let model_count = read(data, &mut ptr, 1)?[0];
+        let strings_offset = read_u16(data, &mut ptr)? as usize;
+        let akao_count = read_u16(data, &mut ptr)? as usize;
+        let scale = read_u16(data, &mut ptr)?;
+        read(data, &mut ptr, 6)?; // blank
+
+        let creator = name_from_bytes(read(data, &mut ptr, NAME_LEN)?)?;
+        let name = name_from_bytes(read(data, &mut ptr, NAME_LEN)?)?;
+
+        let mut entity_names = Vec::with_capacity(entity_count);
+        for _ in 0..entity_count {
+            entity_names.push(name_from_bytes(read(data, &mut ptr, NAME_LEN)?)?);
+        }
+
+        let mut akao_offsets = Vec::with_capacity(akao_count.min(data.len() / 4));
+        for _ in 0..akao_count {
+            akao_offsets.push(read_u32(data, &mut ptr)?);
+        }
+
+        let mut script_offsets = Vec::with_capacity(entity_count * SCRIPTS_PER_ENTITY);
+        for _ in 0..entity_count * SCRIPTS_PER_ENTITY {
+            script_offsets.push(read_u16(data, &mut ptr)? as usize);
+        }
+
+        // Scripts run until the next one starts, and the last one runs until the dialogue table.
+        let code_end = strings_offset.min(data.len());
+        let starts = script_offsets.iter().copied().collect::<BTreeSet<_>>();
+        let script = |offset: usize| {
+            let end = starts.range(offset + 1..).next().copied().unwrap_or(code_end).min(code_end);
+            let code = data.get(offset..end.max(offset)).ok_or(ParseError::EndOfBufferError)?;
+            Ok(Script { offset, code })
+        };
+
+        let mut entities = Vec::with_capacity(entity_count);
+        for (name, offsets) in entity_names.into_iter().zip(script_offsets.chunks_exact(SCRIPTS_PER_ENTITY)) {
+            let scripts = offsets.iter().map(|&offset| script(offset)).collect::<Result<_, _>>()?;
+            entities.push(Entity { name, scripts });
+        }
+
+        let strings = read_strings(data, strings_offset)?;
+
+        Ok(Self { scale, model_count, creator, name, entities, akao_offsets, strings })
+    }
+}
+
+
+/// Decodes a string from the dialogue table into plain text, with control codes written in braces. See
+/// [`FfText::decode`].
+pub fn decode_text(text: &[u8]) -> String {
+    FfText::from_bytes(text).decode()
+}
+
+
+/// Reads an 8-byte name from the header. Anything after the first null byte is padding, and is ignored.
+fn name_from_bytes(data: &[u8]) -> Result<&str, ParseError<'_>> {
+    let len = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    sz_to_str(&data[..len])
+}
+
+
+/// Reads the dialogue table: a count, then the offset of each string relative to the start of the table.
+fn read_strings(data: &[u8], table_offset: usize) -> Result<Vec<&[u8]>, ParseError<'_>> {
+    let mut ptr = table_offset;
+    let count = read_u16(data, &mut ptr)? as usize;
+
+    let mut strings = Vec::with_capacity(count.min(data.len() / 2));
+    for _ in 0..count {
+        let mut string_ptr = table_offset + read_u16(data, &mut ptr)? as usize;
+        let rest = data.get(string_ptr..).ok_or(ParseError::EndOfBufferError)?;
+        let len = rest.iter().position(|&b| b == TEXT_END).unwrap_or(rest.len());
+        strings.push(read(data, &mut string_ptr, len)?);
+    }
+
+    Ok(strings)
+}
+
+
+/// Decodes the instruction starting at `ptr` within a script's code. `base` is the offset of the script's code within
+/// the section.
+fn decode_instruction(code: &[u8], ptr: usize, base: usize) -> Result<Instruction<'_>, ParseError<'_>> {
+    let opcode = code[ptr];
+    let invalid = |at: usize| ParseError::InvalidValueError(&code[at..at + 1], base + at);
+
+    let (opcode, name, len, operands_start) = match opcode {
+        SPECIAL => {
+            let sub = *code.get(ptr + 1).ok_or(ParseError::EndOfBufferError)?;
+            let (name, len) = special_info(sub).ok_or_else(|| invalid(ptr + 1))?;
+            (sub, name, len, ptr + 2)
+        },
+        KAWAI => {
+            // The first operand is the length of the whole instruction.
+            let len = *code.get(ptr + 1).ok_or(ParseError::EndOfBufferError)? as usize;
+            if len < 2 {
+                return Err(invalid(ptr + 1));
+            }
+            (opcode, "KAWAI", len, ptr + 1)
+        },
+        _ => {
+            let (name, len) = opcode_info(opcode).ok_or_else(|| invalid(ptr))?;
+            (opcode, name, len, ptr + 1)
+        },
+    };
+
+    let operands = code.get(operands_start..ptr + len).ok_or(ParseError::EndOfBufferError)?;
+    Ok(Instruction { offset: base + ptr, len, opcode, name, operands })
+}
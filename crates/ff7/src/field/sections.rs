@@ -0,0 +1,133 @@
+//! Splits [field files](https://wiki.ffrtt.ru/index.php/FF7/Field_Module) into their sections.
+//!
+//! Once decompressed (see [`decompress_lzss`][crate::extract::decompress_lzss]), a field file from `flevel.lgp` starts
+//! with two blank bytes, the number of sections (always nine), and then the offset of each section from the start of
+//! the file. Each section starts with its own length.
+
+use crate::extract::{read, read_u16, read_u32, u32_from_le_bytes, ParseError, Span};
+
+
+/// The number of sections in every field file.
+pub const SECTION_COUNT: usize = 9;
+
+
+/// The sections of a field file, in the order that they appear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Section {
+    /// Field scripts and dialogue.
+    Script,
+    /// The camera matrix.
+    Camera,
+    /// Which models the field uses, and their animations.
+    ModelLoader,
+    /// The background's palettes.
+    Palette,
+    /// The walkmesh that characters move around on.
+    Walkmesh,
+    /// The tile map. Unused on PC.
+    TileMap,
+    /// Random encounter tables.
+    Encounter,
+    /// Triggers, gateways, and the field's camera range.
+    Triggers,
+    /// The background's tiles and textures.
+    Background,
+}
+
+
+impl Section {
+    /// Every section, in the order that they appear.
+    pub const ALL: [Section; SECTION_COUNT] = [
+        Section::Script,
+        Section::Camera,
+        Section::ModelLoader,
+        Section::Palette,
+        Section::Walkmesh,
+        Section::TileMap,
+        Section::Encounter,
+        Section::Triggers,
+        Section::Background,
+    ];
+}
+
+
+/// A decompressed field file, split into its sections. Each section is left as raw bytes.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldFile<'a> {
+    data: &'a [u8],
+    sections: [&'a [u8]; SECTION_COUNT],
+}
+
+
+impl<'a> FieldFile<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        let mut ptr = 0;
+
+        let _blank = read_u16(data, &mut ptr)?;
+        let count_data = read(data, &mut ptr, 4)?;
+        if u32_from_le_bytes(count_data)? as usize != SECTION_COUNT {
+            return Err(ParseError::InvalidValueError(count_data, 2));
+        }
+
+        let mut sections = [&data[0..0]; SECTION_COUNT];
+        for section in sections.iter_mut() {
+            let mut section_ptr = read_u32(data, &mut ptr)? as usize;
+            let len = read_u32(data, &mut section_ptr)? as usize;
+            *section = read(data, &mut section_ptr, len)?;
+        }
+
+        Ok(Self { data, sections })
+    }
+
+    /// Gets the raw bytes of one section, not including its length.
+    pub fn section(&self, section: Section) -> &'a [u8] {
+        self.sections[section as usize]
+    }
+
+    /// Where one section's bytes are in the field file. Add its start to a span from inside the section (see
+    /// [`Span::offset`]) to find that span in the whole file.
+    pub fn section_span(&self, section: Section) -> Span {
+        Span::of(self.data, self.section(section)).unwrap() // sections are always slices of `data`
+    }
+
+    /// Iterates over every section and its raw bytes, in order.
+    pub fn sections(&self) -> impl Iterator<Item = (Section, &'a [u8])> + '_ {
+        Section::ALL.into_iter().zip(self.sections.iter().copied())
+    }
+
+    pub fn script(&self) -> &'a [u8] {
+        self.section(Section::Script)
+    }
+
+    pub fn camera(&self) -> &'a [u8] {
+        self.section(Section::Camera)
+    }
+
+    pub fn model_loader(&self) -> &'a [u8] {
+        self.section(Section::ModelLoader)
+    }
+
+    pub fn palette(&self) -> &'a [u8] {
+        self.section(Section::Palette)
+    }
+
+    pub fn walkmesh(&self) -> &'a [u8] {
+        self.section(Section::Walkmesh)
+    }
+
+    pub fn tile_map(&self) -> &'a [u8] {
+        self.section(Section::TileMap)
+    }
+
+    pub fn encounter(&self) -> &'a [u8] {
+        self.section(Section::Encounter)
+    }
+
+    pub fn triggers(&self) -> &'a [u8] {
+        self.section(Section::Triggers)
+    }
+
+    pub fn background(&self) -> &'a [u8] {
+        self.section(Section::Background)
+    }
+}
@@ -0,0 +1,118 @@
+//! Works out where each entity starts out in a field, by running through its scripts far enough to see which model it
+//! uses, where it is placed, which way it faces, and whether it is shown.
+//!
+//! Entities are set up by their init script (script 0), followed by the start of their main script (script 1). This
+//! only steps through those scripts in a straight line: conditions aren't evaluated and jumps aren't taken, so an
+//! entity that is placed differently depending on story progress ends up wherever its last placement in the script
+//! puts it. Values that are read from variables (rather than given directly) are skipped, since they aren't known
+//! until the game is running.
+
+use super::Entity;
+
+
+/// An opcode that the spawn state depends on.
+mod op {
+    pub const RET: u8 = 0x00;
+    pub const JMPB: u8 = 0x12;
+    pub const JMPBL: u8 = 0x13;
+    pub const PC: u8 = 0xA0;
+    pub const CHAR: u8 = 0xA1;
+    pub const VISI: u8 = 0xA4;
+    pub const XYZI: u8 = 0xA5;
+    pub const XYI: u8 = 0xA6;
+    pub const XYZ: u8 = 0xA7;
+    pub const DIR: u8 = 0xB3;
+    pub const SOLID: u8 = 0xC7;
+}
+
+
+/// How an entity is set up when its field loads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpawnState {
+    /// The index of the entity's model in the field's model loader, if it has one.
+    pub model: Option<u8>,
+    /// Which party member the entity is, if it is one.
+    pub character: Option<u8>,
+    /// Where the entity is placed on the walkmesh, as (x, y).
+    pub position: Option<[i16; 2]>,
+    /// The entity's height. Usually only given when the entity isn't placed on a walkmesh triangle.
+    pub height: Option<i16>,
+    /// The walkmesh triangle that the entity is placed on.
+    pub triangle: Option<u16>,
+    /// Which way the entity faces, where 256 is a full turn.
+    pub direction: Option<u8>,
+    pub visible: bool,
+    /// Whether or not other entities collide with this one.
+    pub solid: bool,
+}
+
+
+impl Default for SpawnState {
+    fn default() -> Self {
+        Self {
+            model: None,
+            character: None,
+            position: None,
+            height: None,
+            triangle: None,
+            direction: None,
+            visible: true,
+            solid: true,
+        }
+    }
+}
+
+
+impl<'a> Entity<'a> {
+    /// Works out how this entity is set up when its field loads. See the [module-level documentation](self) for how
+    /// far its scripts are followed.
+    pub fn spawn_state(&self) -> SpawnState {
+        let mut state = SpawnState::default();
+
+        for script in self.scripts.iter().take(2) {
+            for instruction in script.instructions() {
+                // A script that can't be decoded any further has done all the setup that it can.
+                let Ok(instruction) = instruction else {
+                    break;
+                };
+
+                let operands = instruction.operands;
+                let i16_at = |at: usize| i16::from_le_bytes([operands[at], operands[at + 1]]);
+                match instruction.opcode {
+                    // Main scripts usually loop forever, so stop at the first backwards jump too.
+                    op::RET | op::JMPB | op::JMPBL => break,
+                    op::PC => state.character = Some(operands[0]),
+                    op::CHAR => state.model = Some(operands[0]),
+                    op::VISI => state.visible = operands[0] != 0,
+                    op::SOLID => state.solid = operands[0] != 0,
+                    op::DIR if operands[0] & 0x0F == 0 => state.direction = Some(operands[1]),
+                    op::XYZI | op::XYI | op::XYZ => {
+                        // Each nibble of the first two operands says whether the value after it is given directly (0)
+                        // or read from a variable bank.
+                        let banks = [operands[0] >> 4, operands[0] & 0x0F, operands[1] >> 4, operands[1] & 0x0F];
+                        let (x, y) = (i16_at(2), i16_at(4));
+                        if banks[0] == 0 && banks[1] == 0 {
+                            state.position = Some([x, y]);
+                        }
+
+                        // The value after (x, y) is the height for XYZ, the triangle for XYI, and both for XYZI.
+                        let (height, triangle) = match instruction.opcode {
+                            op::XYZI => (Some((banks[2], 6)), Some((banks[3], 8))),
+                            op::XYZ => (Some((banks[2], 6)), None),
+                            _ => (None, Some((banks[2], 6))),
+                        };
+                        if let Some((0, at)) = height {
+                            state.height = Some(i16_at(at));
+                        }
+                        if let Some((0, at)) = triangle {
+                            state.triangle = Some(i16_at(at) as u16);
+                        }
+                    },
+                    _ => (),
+                }
+            }
+        }
+
+        state
+    }
+}
@@ -0,0 +1,127 @@
+//! Parses a field file's [triggers section](https://wiki.ffrtt.ru/index.php/FF7/Field_Module/DAT/Triggers), which
+//! holds the field's exits (gateways) to other fields, and the lines that set off events when they are crossed.
+//!
+//! The section has a fixed size: a short header describing the field's camera, followed by room for exactly 12
+//! gateways and 12 triggers. Unused gateways lead to field [`UNUSED_FIELD`], and unused triggers are all zeroes.
+
+use crate::char::read_array;
+use crate::extract::{read, read_i16, read_u16, read_u8, sz_to_str, ParseError};
+
+
+/// The number of gateway and trigger slots that every field has.
+pub const GATEWAY_COUNT: usize = 12;
+
+/// The destination field ID of a gateway that isn't used.
+pub const UNUSED_FIELD: u16 = 0x7FFF;
+
+/// The length of the field's name at the start of the section.
+const NAME_LEN: usize = 9;
+
+
+/// A point in the field's coordinate space.
+pub type Vertex = [i16; 3];
+
+
+/// An exit from the field into another one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gateway {
+    /// The two ends of the line that the player has to cross to leave the field.
+    pub exit_line: [Vertex; 2],
+    /// Where the player ends up in the destination field, as an (x, y) position and the walkmesh triangle it is on.
+    pub destination: [i16; 2],
+    pub destination_triangle: u16,
+    /// The ID of the field that this gateway leads to.
+    pub field_id: u16,
+    /// Whether or not the game draws an arrow over this gateway.
+    pub show_arrow: bool,
+}
+
+
+/// A line in the field that changes part of the background when the player crosses it (e.g., opening a door).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trigger {
+    /// The two ends of the line.
+    pub corners: [Vertex; 2],
+    /// Which group of background tiles this trigger changes. See [`Tile::param`][super::Tile::param].
+    pub background_param: u8,
+    /// Which state the group of tiles is switched to. See [`Tile::state`][super::Tile::state].
+    pub background_state: u8,
+    /// How the trigger behaves when crossed (e.g., whether it turns back off when the player leaves).
+    pub behavior: u8,
+    /// Which sound plays when the trigger is crossed.
+    pub sound: u8,
+}
+
+
+/// The parsed contents of a field file's triggers section.
+#[derive(Debug, Clone)]
+pub struct TriggerSection<'a> {
+    /// The name of the field, which is usually the same as its file name.
+    pub name: &'a str,
+    /// Which direction pressing "up" moves the player in.
+    pub control_direction: u8,
+    /// How high above the player the camera looks.
+    pub focus_height: i16,
+    /// How far the camera may scroll, as left, bottom, right, and top.
+    pub camera_range: [i16; 4],
+    /// The field's gateways, not including unused ones.
+    pub gateways: Vec<Gateway>,
+    /// The field's triggers, not including unused ones.
+    pub triggers: Vec<Trigger>,
+}
+
+
+impl<'a> TriggerSection<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        let mut ptr = 0;
+
+        let name_data = read(data, &mut ptr, NAME_LEN)?;
+        let name = sz_to_str(&name_data[..name_data.iter().position(|&b| b == 0).unwrap_or(NAME_LEN)])?;
+        let control_direction = read_u8(data, &mut ptr)?;
+        let focus_height = read_i16(data, &mut ptr)?;
+        let mut camera_range = [0; 4];
+        for value in camera_range.iter_mut() {
+            *value = read_i16(data, &mut ptr)?;
+        }
+        read(data, &mut ptr, 4 + 8 + 24)?; // unknown, the sizes of the last two background layers, then unknown
+
+        let mut gateways = read_array(data, &mut ptr, GATEWAY_COUNT, read_gateway)?;
+        let triggers = read_array(data, &mut ptr, GATEWAY_COUNT, read_trigger)?;
+
+        let show_arrows = read(data, &mut ptr, GATEWAY_COUNT)?;
+        for (gateway, &show) in gateways.iter_mut().zip(show_arrows) {
+            gateway.show_arrow = show != 0;
+        }
+
+        gateways.retain(|gateway| gateway.field_id != UNUSED_FIELD);
+        let triggers = triggers.into_iter().filter(|trigger| trigger.corners != [[0; 3]; 2]).collect();
+
+        Ok(Self { name, control_direction, focus_height, camera_range, gateways, triggers })
+    }
+}
+
+
+pub(super) fn read_vertex<'a>(data: &'a [u8], ptr: &mut usize) -> Result<Vertex, ParseError<'a>> {
+    Ok([read_i16(data, ptr)?, read_i16(data, ptr)?, read_i16(data, ptr)?])
+}
+
+
+fn read_gateway<'a>(data: &'a [u8], ptr: &mut usize) -> Result<Gateway, ParseError<'a>> {
+    let exit_line = [read_vertex(data, ptr)?, read_vertex(data, ptr)?];
+    let destination = [read_i16(data, ptr)?, read_i16(data, ptr)?];
+    let destination_triangle = read_u16(data, ptr)?;
+    let field_id = read_u16(data, ptr)?;
+    read(data, ptr, 4)?; // unknown
+
+    Ok(Gateway { exit_line, destination, destination_triangle, field_id, show_arrow: false })
+}
+
+
+fn read_trigger<'a>(data: &'a [u8], ptr: &mut usize) -> Result<Trigger, ParseError<'a>> {
+    let corners = [read_vertex(data, ptr)?, read_vertex(data, ptr)?];
+    let &[background_param, background_state, behavior, sound] = read(data, ptr, 4)? else {
+        unreachable!(); // success of `read` with length 4 guarantees slice length
+    };
+
+    Ok(Trigger { corners, background_param, background_state, behavior, sound })
+}
@@ -1,3 +1,9 @@
+pub mod analysis;
+pub mod battle;
 pub mod char;
 pub mod extract;
 pub mod field;
+pub mod save;
+pub mod sound;
+pub mod text;
+pub mod world;
@@ -0,0 +1,125 @@
+//! Finds the savemaps in the different files that hold them.
+//!
+//! - A PC save file is a 9-byte header followed by fifteen savemaps, one per slot.
+//! - A PlayStation save is a single 8 KiB memory card block: a 512-byte header with the save's title and icon, which
+//!   starts with `SC`, then the savemap, then padding.
+//! - A raw memory card image (`.mcr`, `.mcd`, and most emulators' memory card files) is sixteen blocks. The first is
+//!   the card's directory, which has one 128-byte entry per block saying what is stored in it, and the rest are saves.
+
+use super::{Savemap, SAVEMAP_LEN};
+use crate::extract::{read, read_u32, ParseError};
+
+
+/// The number of save slots in a PC save file, and on a memory card.
+pub const SLOT_COUNT: usize = 15;
+
+/// The length of the header at the start of a PC save file.
+const PC_HEADER_LEN: usize = 9;
+
+/// The length of a single memory card block, which holds one PlayStation save.
+const BLOCK_LEN: usize = 0x2000;
+
+/// The length of the title and icon before the savemap in a memory card block.
+const BLOCK_HEADER_LEN: usize = 0x200;
+
+/// The magic number that every memory card block with a save in it starts with.
+const BLOCK_MAGIC: &[u8] = b"SC";
+
+/// The magic number that a raw memory card image starts with.
+const CARD_MAGIC: &[u8] = b"MC";
+
+/// The length of each entry in a memory card's directory.
+const DIRECTORY_ENTRY_LEN: usize = 0x80;
+
+/// The directory state of a block that holds the start of a save.
+const DIRECTORY_FIRST_BLOCK: u32 = 0x51;
+
+/// The part of the file name that every FF7 save on a memory card has, after the game's product code (e.g.,
+/// `BASCUS-94163FF7-S01`).
+const SAVE_NAME_MARKER: &[u8] = b"FF7-S";
+
+
+/// Which kind of file a save was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveFormat {
+    /// A PC save file, with fifteen slots.
+    Pc,
+    /// A single memory card block, as exported by most save managers.
+    PsxBlock,
+    /// A whole memory card image, with fifteen blocks.
+    MemoryCard,
+}
+
+
+/// A file with one or more saves in it.
+#[derive(Debug, Clone)]
+pub struct SaveFile {
+    pub format: SaveFormat,
+    /// Each slot's save, or `None` for empty slots. A [`PsxBlock`][SaveFormat::PsxBlock] only has one slot.
+    pub slots: Vec<Option<Savemap>>,
+}
+
+
+impl SaveFile {
+    /// Reads a save file, working out which kind it is from its length and magic number. Returns an
+    /// [`UnknownFileTypeError`][ParseError::UnknownFileTypeError] if it isn't any of them.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        if data.len() == PC_HEADER_LEN + SLOT_COUNT * SAVEMAP_LEN {
+            Self::from_pc(data)
+        } else if data.len() == BLOCK_LEN && data.starts_with(BLOCK_MAGIC) {
+            let savemap = Savemap::from_bytes(&data[BLOCK_HEADER_LEN..])?;
+            Ok(Self { format: SaveFormat::PsxBlock, slots: vec![Some(savemap)] })
+        } else if data.len() == (SLOT_COUNT + 1) * BLOCK_LEN && data.starts_with(CARD_MAGIC) {
+            Self::from_memory_card(data)
+        } else {
+            Err(ParseError::UnknownFileTypeError)
+        }
+    }
+
+    /// Every save in the file, along with the index of its slot.
+    pub fn saves(&self) -> impl Iterator<Item = (usize, &Savemap)> {
+        self.slots.iter().enumerate().filter_map(|(i, slot)| Some((i, slot.as_ref()?)))
+    }
+
+    fn from_pc(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = PC_HEADER_LEN;
+        let mut slots = Vec::with_capacity(SLOT_COUNT);
+        for _ in 0..SLOT_COUNT {
+            let savemap = read(data, &mut ptr, SAVEMAP_LEN)?;
+
+            // Unused slots are left zeroed out, checksum and all.
+            if savemap.iter().all(|&b| b == 0) {
+                slots.push(None);
+            } else {
+                slots.push(Some(Savemap::from_bytes(savemap)?));
+            }
+        }
+
+        Ok(Self { format: SaveFormat::Pc, slots })
+    }
+
+    fn from_memory_card(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut slots = Vec::with_capacity(SLOT_COUNT);
+        for i in 1..=SLOT_COUNT {
+            // The directory's first entry describes the card itself, so block `i` is described by entry `i`.
+            let mut ptr = i * DIRECTORY_ENTRY_LEN;
+            let state = read_u32(data, &mut ptr)?;
+            ptr = i * DIRECTORY_ENTRY_LEN + 0x0A;
+            let name = read(data, &mut ptr, 20)?;
+
+            let block = &data[i * BLOCK_LEN..(i + 1) * BLOCK_LEN];
+            let is_save = state == DIRECTORY_FIRST_BLOCK
+                && name.windows(SAVE_NAME_MARKER.len()).any(|w| w == SAVE_NAME_MARKER)
+                && block.starts_with(BLOCK_MAGIC);
+
+            // Blocks from other games, and deleted saves, are treated as empty.
+            if is_save {
+                slots.push(Some(Savemap::from_bytes(&block[BLOCK_HEADER_LEN..])?));
+            } else {
+                slots.push(None);
+            }
+        }
+
+        Ok(Self { format: SaveFormat::MemoryCard, slots })
+    }
+}
@@ -0,0 +1,11 @@
+//! Parsing of save games: the PC version's `save00.ff7` through `save09.ff7`, and PlayStation memory cards.
+//!
+//! Both versions store each save as the same block of memory (the "savemap"), which is copied straight out of the
+//! game's RAM. They only differ in what surrounds it: the PC version packs fifteen savemaps into one file, while on the
+//! PlayStation each save is a memory card file of its own, with a title and icon before the savemap and padding after.
+
+mod file;
+mod savemap;
+
+pub use file::*;
+pub use savemap::*;
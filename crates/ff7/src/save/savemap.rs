@@ -0,0 +1,314 @@
+//! Parses a single [savemap](https://wiki.ffrtt.ru/index.php/FF7/Savemap): the party, their equipment, the inventory,
+//! and the game's progress.
+//!
+//! Most of the game's progress isn't stored in named fields, but in the five 256-byte banks of variables that the
+//! field scripts read and write (see [`Savemap::bank`]). Only a handful of them have a well-known meaning, like the
+//! main story progress variable at the start of the first bank.
+
+use crate::char::read_array;
+use crate::extract::{read, read_i16, read_u16, read_u32, read_u8, u16_from_le_bytes, ParseError};
+use crate::text::FfText;
+
+
+/// The length of a savemap.
+pub const SAVEMAP_LEN: usize = 0x10F4;
+
+/// The number of characters that have a record in the savemap, including Young Cloud and Sephiroth.
+pub const CHARACTER_COUNT: usize = 9;
+
+/// The number of slots in the item inventory.
+pub const ITEM_SLOT_COUNT: usize = 320;
+
+/// The number of slots in the materia inventory.
+pub const MATERIA_SLOT_COUNT: usize = 200;
+
+/// The number of materia that Yuffie can steal.
+pub const STOLEN_MATERIA_COUNT: usize = 48;
+
+/// The number of banks of field script variables.
+pub const BANK_COUNT: usize = 5;
+
+/// The length of each bank of field script variables.
+pub const BANK_LEN: usize = 256;
+
+/// The length of each character record.
+const CHARACTER_LEN: usize = 0x84;
+
+/// The number of materia slots on each piece of equipment.
+const EQUIPMENT_MATERIA_SLOTS: usize = 8;
+
+/// Where each part of the savemap starts.
+mod offset {
+    pub const LOCATION: usize = 0x0028;
+    pub const CHARACTERS: usize = 0x0054;
+    pub const PARTY: usize = 0x04F8;
+    pub const ITEMS: usize = 0x04FC;
+    pub const MATERIA: usize = 0x077C;
+    pub const STOLEN_MATERIA: usize = 0x0A9C;
+    pub const GIL: usize = 0x0B7C;
+    pub const FIELD: usize = 0x0B96;
+    pub const POSITION: usize = 0x0B9A;
+    pub const BANKS: usize = 0x0BA4;
+}
+
+
+/// A stack of items in the inventory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemSlot {
+    /// The item's ID. IDs past 127 are weapons, armor, and accessories, in that order.
+    pub id: u16,
+    pub quantity: u8,
+}
+
+
+impl ItemSlot {
+    /// Reads an item slot, which is `None` if it is empty.
+    fn from_u16(value: u16) -> Option<Self> {
+        (value != 0xFFFF).then_some(Self { id: value & 0x01FF, quantity: (value >> 9) as u8 })
+    }
+}
+
+
+/// A single materia, in the inventory or on a piece of equipment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MateriaSlot {
+    pub id: u8,
+    /// How much AP the materia has earned.
+    pub ap: u32,
+}
+
+
+impl MateriaSlot {
+    /// Reads a materia slot, which is `None` if it is empty.
+    fn from_u32(value: u32) -> Option<Self> {
+        let id = value as u8;
+        (id != 0xFF).then_some(Self { id, ap: value >> 8 })
+    }
+}
+
+
+/// One character's stats and equipment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharacterRecord {
+    /// Which character this is. This usually matches the record's position, except that Vincent's and Cid's records
+    /// are used by Young Cloud and Sephiroth during the flashback.
+    pub id: u8,
+    pub level: u8,
+    /// Strength, vitality, magic, spirit, dexterity, and luck, not counting equipment and materia.
+    pub stats: [u8; 6],
+    /// How much each of [`stats`][Self::stats] has been raised by Sources, the items that permanently raise a stat.
+    pub bonus_stats: [u8; 6],
+    /// Which limit level is in use, from 1 to 4.
+    pub limit_level: u8,
+    /// How full the limit bar is, out of 255.
+    pub limit_bar: u8,
+    pub name: FfText,
+    pub weapon: u8,
+    pub armor: u8,
+    pub accessory: u8,
+    /// Which of the statuses that last outside of battle the character has, as a bit field (e.g., `0x10` for Fury).
+    pub status: u8,
+    /// Whether or not the character is in the back row.
+    pub back_row: bool,
+    /// Which limit breaks the character has learned, as a bit field.
+    pub learned_limits: u16,
+    pub kills: u16,
+    pub hp: u16,
+    pub max_hp: u16,
+    pub mp: u16,
+    pub max_mp: u16,
+    pub exp: u32,
+    /// The materia in each of the weapon's slots, including the slots that the weapon doesn't have.
+    pub weapon_materia: [Option<MateriaSlot>; EQUIPMENT_MATERIA_SLOTS],
+    /// The materia in each of the armor's slots, including the slots that the armor doesn't have.
+    pub armor_materia: [Option<MateriaSlot>; EQUIPMENT_MATERIA_SLOTS],
+    /// How much more experience the character needs to reach the next level.
+    pub exp_to_next: u32,
+}
+
+
+/// The contents of a single save.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Savemap {
+    /// The checksum of the rest of the savemap, which the game checks when loading.
+    pub checksum: u32,
+    /// The name of the location that the save menu shows.
+    pub location: FfText,
+    pub characters: Vec<CharacterRecord>,
+    /// The IDs of the characters in the party, or `None` for empty places.
+    pub party: [Option<u8>; 3],
+    /// Every slot of the item inventory, or `None` for empty slots.
+    pub items: Vec<Option<ItemSlot>>,
+    /// Every slot of the materia inventory, or `None` for empty slots.
+    pub materia: Vec<Option<MateriaSlot>>,
+    /// The materia that Yuffie stole, which she gives back at the end of Wutai.
+    pub stolen_materia: Vec<Option<MateriaSlot>>,
+    pub gil: u32,
+    pub seconds_played: u32,
+    /// The ID of the field that the save was made in.
+    pub field_id: u16,
+    /// Where the party leader is in the field, as (x, y), and the walkmesh triangle they are on.
+    pub position: [i16; 2],
+    pub triangle: u16,
+    /// Which way the party leader faces, where 256 is a full turn.
+    pub direction: u8,
+    /// The five banks of field script variables, one after the other.
+    pub variables: Vec<u8>,
+}
+
+
+impl Savemap {
+    /// Reads a savemap from the start of `data`. Anything after the savemap is ignored.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = 0;
+        let checksum = read_u32(data, &mut ptr)?;
+
+        ptr = offset::LOCATION;
+        let location = FfText::from_bytes(read(data, &mut ptr, 32)?);
+
+        ptr = offset::CHARACTERS;
+        let characters = read_array(data, &mut ptr, CHARACTER_COUNT, read_character)?;
+
+        ptr = offset::PARTY;
+        let party = read(data, &mut ptr, 3)?;
+        let party = [0, 1, 2].map(|i| (party[i] != 0xFF).then_some(party[i]));
+
+        ptr = offset::ITEMS;
+        let items = read_array(data, &mut ptr, ITEM_SLOT_COUNT, read_item)?;
+
+        ptr = offset::MATERIA;
+        let materia = read_array(data, &mut ptr, MATERIA_SLOT_COUNT, read_materia)?;
+
+        ptr = offset::STOLEN_MATERIA;
+        let stolen_materia = read_array(data, &mut ptr, STOLEN_MATERIA_COUNT, read_materia)?;
+
+        ptr = offset::GIL;
+        let gil = read_u32(data, &mut ptr)?;
+        let seconds_played = read_u32(data, &mut ptr)?;
+
+        ptr = offset::FIELD;
+        let field_id = read_u16(data, &mut ptr)?;
+        ptr = offset::POSITION;
+        let position = [read_i16(data, &mut ptr)?, read_i16(data, &mut ptr)?];
+        let triangle = read_u16(data, &mut ptr)?;
+        let direction = read_u8(data, &mut ptr)?;
+
+        ptr = offset::BANKS;
+        let variables = read(data, &mut ptr, BANK_COUNT * BANK_LEN)?.to_vec();
+
+        Ok(Self {
+            checksum,
+            location,
+            characters,
+            party,
+            items,
+            materia,
+            stolen_materia,
+            gil,
+            seconds_played,
+            field_id,
+            position,
+            triangle,
+            direction,
+            variables,
+        })
+    }
+
+    /// Gets one of the banks of field script variables, from 0 to 4.
+    pub fn bank(&self, bank: usize) -> Option<&[u8]> {
+        self.variables.get(bank * BANK_LEN..(bank + 1) * BANK_LEN)
+    }
+
+    /// The main story progress variable, which starts at 0 and counts up to 1999 by the end of the game.
+    pub fn story_progress(&self) -> u16 {
+        u16_from_le_bytes(&self.variables).unwrap_or(0)
+    }
+
+    /// The records of the characters in the party, in order.
+    pub fn party_members(&self) -> impl Iterator<Item = &CharacterRecord> {
+        self.party.iter().flatten().filter_map(|&id| self.characters.iter().find(|c| c.id == id))
+    }
+
+    /// The items in the inventory, skipping empty slots.
+    pub fn inventory(&self) -> impl Iterator<Item = &ItemSlot> {
+        self.items.iter().flatten()
+    }
+}
+
+
+fn read_item<'a>(data: &'a [u8], ptr: &mut usize) -> Result<Option<ItemSlot>, ParseError<'a>> {
+    Ok(ItemSlot::from_u16(read_u16(data, ptr)?))
+}
+
+
+fn read_materia<'a>(data: &'a [u8], ptr: &mut usize) -> Result<Option<MateriaSlot>, ParseError<'a>> {
+    Ok(MateriaSlot::from_u32(read_u32(data, ptr)?))
+}
+
+
+fn read_character<'a>(data: &'a [u8], ptr: &mut usize) -> Result<CharacterRecord, ParseError<'a>> {
+    let record = read(data, ptr, CHARACTER_LEN)?;
+    let mut ptr = 0;
+
+    let id = read_u8(record, &mut ptr)?;
+    let level = read_u8(record, &mut ptr)?;
+    let &[s0, s1, s2, s3, s4, s5, b0, b1, b2, b3, b4, b5] = read(record, &mut ptr, 12)? else {
+        unreachable!(); // success of `read` with length 12 guarantees slice length
+    };
+    let limit_level = read_u8(record, &mut ptr)?;
+    let limit_bar = read_u8(record, &mut ptr)?;
+    let name = FfText::from_bytes(read(record, &mut ptr, 12)?);
+    let weapon = read_u8(record, &mut ptr)?;
+    let armor = read_u8(record, &mut ptr)?;
+    let accessory = read_u8(record, &mut ptr)?;
+    let status = read_u8(record, &mut ptr)?;
+    let back_row = read_u8(record, &mut ptr)? == 0xFE;
+    let _level_progress = read_u8(record, &mut ptr)?;
+    let learned_limits = read_u16(record, &mut ptr)?;
+    let kills = read_u16(record, &mut ptr)?;
+    read(record, &mut ptr, 6)?; // how many times each limit level has been used
+    let hp = read_u16(record, &mut ptr)?;
+    let _base_hp = read_u16(record, &mut ptr)?;
+    let mp = read_u16(record, &mut ptr)?;
+    let _base_mp = read_u16(record, &mut ptr)?;
+    read(record, &mut ptr, 4)?; // unknown
+    let max_hp = read_u16(record, &mut ptr)?;
+    let max_mp = read_u16(record, &mut ptr)?;
+    let exp = read_u32(record, &mut ptr)?;
+
+    let read_slots = |ptr: &mut usize| {
+        let mut slots = [None; EQUIPMENT_MATERIA_SLOTS];
+        for slot in &mut slots {
+            *slot = read_materia(record, ptr)?;
+        }
+        Ok::<_, ParseError>(slots)
+    };
+    let weapon_materia = read_slots(&mut ptr)?;
+    let armor_materia = read_slots(&mut ptr)?;
+    let exp_to_next = read_u32(record, &mut ptr)?;
+
+    Ok(CharacterRecord {
+        id,
+        level,
+        stats: [s0, s1, s2, s3, s4, s5],
+        bonus_stats: [b0, b1, b2, b3, b4, b5],
+        limit_level,
+        limit_bar,
+        name,
+        weapon,
+        armor,
+        accessory,
+        status,
+        back_row,
+        learned_limits,
+        kills,
+        hp,
+        max_hp,
+        mp,
+        max_mp,
+        exp,
+        weapon_materia,
+        armor_materia,
+        exp_to_next,
+    })
+}
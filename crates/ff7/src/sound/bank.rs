@@ -0,0 +1,226 @@
+//! Parses the PC version's sound effect bank: `audio.fmt`, which describes every sound, and `audio.dat`, which holds
+//! their audio data one after the other.
+//!
+//! Each entry in `audio.fmt` is a 42-byte record: where the sound's data is in `audio.dat`, sixteen bytes of looping
+//! information, and a Windows `WAVEFORMATEX` structure. When the format has extra data (as Microsoft ADPCM does, for
+//! its coefficient table), that comes right after the record, so the records aren't all the same length. Slots without
+//! a sound have a length of zero.
+
+use super::Samples;
+use crate::extract::{read, read_i16, read_u16, read_u32, ParseError};
+
+
+/// The `WAVEFORMATEX` format tag for uncompressed PCM.
+pub const FORMAT_PCM: u16 = 1;
+
+/// The `WAVEFORMATEX` format tag for Microsoft ADPCM, which almost every sound in the bank uses.
+pub const FORMAT_MS_ADPCM: u16 = 2;
+
+/// How much each step size is scaled by after each ADPCM sample, indexed by the sample's 4-bit code.
+const ADPCM_ADAPTATION: [i32; 16] = [230, 230, 230, 230, 307, 409, 512, 614, 768, 614, 512, 409, 307, 230, 230, 230];
+
+/// The largest that an ADPCM step size is allowed to grow. Real sounds never get close, but a run of large codes in a
+/// broken file would otherwise keep tripling it until it overflowed.
+const MAX_ADPCM_DELTA: i32 = i32::MAX / 768;
+
+
+/// How a sound's data is encoded, from its `WAVEFORMATEX`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoundFormat {
+    /// Which encoding the data uses; see [`FORMAT_PCM`] and [`FORMAT_MS_ADPCM`].
+    pub format_tag: u16,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub avg_bytes_per_sec: u32,
+    /// The size of each block of data, across all channels.
+    pub block_align: u16,
+    pub bits_per_sample: u16,
+    /// The format's extra data, after the `WAVEFORMATEX` itself.
+    pub extra: Vec<u8>,
+}
+
+
+/// A single sound in the bank.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SoundEntry {
+    /// Where the sound's data starts in `audio.dat`.
+    pub offset: u32,
+    /// The length of the sound's data.
+    pub len: u32,
+    /// Looping information, which hasn't been worked out yet.
+    pub loop_info: [u8; 16],
+    pub format: SoundFormat,
+}
+
+
+/// The parsed contents of `audio.fmt`, along with the `audio.dat` that goes with it.
+#[derive(Debug, Clone)]
+pub struct SoundBank<'a> {
+    /// Every slot in the bank, or `None` for empty slots. Sounds are played by their index in this list.
+    pub entries: Vec<Option<SoundEntry>>,
+    data: &'a [u8],
+}
+
+
+impl<'a> SoundBank<'a> {
+    /// Reads the bank from the contents of `audio.fmt` and `audio.dat`.
+    pub fn from_bytes(fmt: &'a [u8], dat: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        let mut ptr = 0;
+        let mut entries = Vec::new();
+
+        while ptr < fmt.len() {
+            let len = read_u32(fmt, &mut ptr)?;
+            let offset = read_u32(fmt, &mut ptr)?;
+            let loop_info = read(fmt, &mut ptr, 16)?.try_into().unwrap(); // success of `read` guarantees length 16
+
+            let format_tag = read_u16(fmt, &mut ptr)?;
+            let channels = read_u16(fmt, &mut ptr)?;
+            let sample_rate = read_u32(fmt, &mut ptr)?;
+            let avg_bytes_per_sec = read_u32(fmt, &mut ptr)?;
+            let block_align = read_u16(fmt, &mut ptr)?;
+            let bits_per_sample = read_u16(fmt, &mut ptr)?;
+            let extra_len = read_u16(fmt, &mut ptr)? as usize;
+            let extra = read(fmt, &mut ptr, extra_len)?.to_vec();
+
+            if len == 0 {
+                entries.push(None);
+                continue;
+            }
+
+            let format = SoundFormat {
+                format_tag,
+                channels,
+                sample_rate,
+                avg_bytes_per_sec,
+                block_align,
+                bits_per_sample,
+                extra,
+            };
+            entries.push(Some(SoundEntry { offset, len, loop_info, format }));
+        }
+
+        Ok(Self { entries, data: dat })
+    }
+
+    /// Gets a sound's entry, or `None` if the slot is empty or out of range.
+    pub fn entry(&self, index: usize) -> Option<&SoundEntry> {
+        self.entries.get(index)?.as_ref()
+    }
+
+    /// Gets a sound's raw data from `audio.dat`. Returns `None` if the slot is empty or out of range.
+    pub fn raw(&self, index: usize) -> Option<Result<&'a [u8], ParseError<'a>>> {
+        let entry = self.entry(index)?;
+        let mut ptr = entry.offset as usize;
+        Some(read(self.data, &mut ptr, entry.len as usize))
+    }
+
+    /// Decodes a sound into 16-bit PCM samples. Returns `None` if the slot is empty or out of range.
+    ///
+    /// Only PCM and Microsoft ADPCM data can be decoded. Anything else gives an
+    /// [`UnknownFileTypeError`][ParseError::UnknownFileTypeError].
+    pub fn decode(&self, index: usize) -> Option<Result<Samples, ParseError<'a>>> {
+        let entry = self.entry(index)?;
+        Some(self.raw(index)?.and_then(|data| decode_sound(&entry.format, data)))
+    }
+}
+
+
+fn decode_sound<'a>(format: &SoundFormat, data: &'a [u8]) -> Result<Samples, ParseError<'a>> {
+    let samples = match (format.format_tag, format.bits_per_sample) {
+        (FORMAT_PCM, 16) => data.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect(),
+        (FORMAT_PCM, 8) => data.iter().map(|&b| (b as i16 - 128) << 8).collect(),
+        (FORMAT_MS_ADPCM, _) => decode_ms_adpcm(format, data)?,
+        _ => return Err(ParseError::UnknownFileTypeError),
+    };
+
+    Ok(Samples { channels: format.channels, sample_rate: format.sample_rate, samples })
+}
+
+
+/// Decodes Microsoft ADPCM data into interleaved 16-bit samples.
+///
+/// The data is split into blocks of [`block_align`][SoundFormat::block_align] bytes, each of which starts with a
+/// header giving every channel's predictor, step size, and first two samples. The rest of the block is 4-bit codes,
+/// high nibble first, alternating between channels.
+fn decode_ms_adpcm<'a>(format: &SoundFormat, data: &'a [u8]) -> Result<Vec<i16>, ParseError<'a>> {
+    let mut ptr = 0;
+    let samples_per_block = read_u16(&format.extra, &mut ptr).map_err(|_| ParseError::EndOfBufferError)? as usize;
+    let coefficient_count = read_u16(&format.extra, &mut ptr).map_err(|_| ParseError::EndOfBufferError)? as usize;
+    let mut coefficients = Vec::with_capacity(coefficient_count.min(format.extra.len() / 4));
+    for _ in 0..coefficient_count {
+        let pair = (read_i16(&format.extra, &mut ptr), read_i16(&format.extra, &mut ptr));
+        let (Ok(c1), Ok(c2)) = pair else {
+            return Err(ParseError::EndOfBufferError);
+        };
+        coefficients.push((c1 as i32, c2 as i32));
+    }
+
+    let channels = format.channels.max(1) as usize;
+    let block_align = format.block_align as usize;
+    if block_align < channels * 7 {
+        return Err(ParseError::EndOfBufferError);
+    }
+
+    // Every byte holds at most two samples, however many the format claims that each block has.
+    let claimed = (data.len() / block_align).saturating_mul(samples_per_block).saturating_mul(channels);
+    let mut out = Vec::with_capacity(claimed.min(data.len() * 2));
+    for (i, block) in data.chunks(block_align).enumerate() {
+        // The last block may be cut short; decode whatever is there.
+        let mut ptr = 0;
+        let Ok(predictors) = read(block, &mut ptr, channels) else {
+            break;
+        };
+        let read_all = |ptr: &mut usize| (0..channels).map(|_| read_i16(block, ptr)).collect::<Result<Vec<_>, _>>();
+        let header = (read_all(&mut ptr), read_all(&mut ptr), read_all(&mut ptr));
+        let (Ok(deltas), Ok(sample1s), Ok(sample2s)) = header else {
+            break;
+        };
+
+        let mut state = Vec::with_capacity(channels);
+        for (c, &predictor) in predictors.iter().enumerate() {
+            let Some(&(c1, c2)) = coefficients.get(predictor as usize) else {
+                return Err(ParseError::InvalidValueError(&predictors[c..c + 1], i * block_align + c));
+            };
+            let (delta, sample1, sample2) = (deltas[c] as i32, sample1s[c] as i32, sample2s[c] as i32);
+            state.push(AdpcmChannel { c1, c2, delta, sample1, sample2 });
+        }
+
+        // The header's samples are the block's first two, oldest first.
+        out.extend(state.iter().map(|channel| channel.sample2 as i16));
+        out.extend(state.iter().map(|channel| channel.sample1 as i16));
+
+        let codes = block[ptr..].iter().flat_map(|&byte| [byte >> 4, byte & 0x0F]);
+        let count = samples_per_block.saturating_sub(2) * channels;
+        for (n, code) in codes.take(count).enumerate() {
+            out.push(state[n % channels].next(code));
+        }
+    }
+
+    Ok(out)
+}
+
+
+/// The state of one channel while decoding Microsoft ADPCM.
+struct AdpcmChannel {
+    c1: i32,
+    c2: i32,
+    delta: i32,
+    sample1: i32,
+    sample2: i32,
+}
+
+
+impl AdpcmChannel {
+    /// Decodes the next sample from its 4-bit code.
+    fn next(&mut self, code: u8) -> i16 {
+        let signed = ((code as i8) << 4 >> 4) as i32;
+        // Done in 64 bits, since the coefficients and samples can both be as large as an `i16` allows.
+        let predicted = (self.sample1 as i64 * self.c1 as i64 + self.sample2 as i64 * self.c2 as i64) >> 8;
+        let sample = (predicted + signed as i64 * self.delta as i64).clamp(i16::MIN as i64, i16::MAX as i64) as i32;
+
+        self.sample2 = self.sample1;
+        self.sample1 = sample;
+        self.delta = ((ADPCM_ADAPTATION[code as usize] * self.delta) >> 8).clamp(16, MAX_ADPCM_DELTA);
+        sample as i16
+    }
+}
@@ -0,0 +1,10 @@
+//! Parsing of the PC version's sound effects, from `audio.fmt` and `audio.dat`, and conversion of them to WAV files.
+//!
+//! Music isn't stored here: the PC version plays its music from the MIDI files in `midi.lgp`, and the field files'
+//! [AKAO frames][crate::field::AkaoFrame] hold the sequences that the PlayStation version uses.
+
+mod bank;
+mod wav;
+
+pub use bank::*;
+pub use wav::*;
@@ -0,0 +1,46 @@
+//! Decoded audio, and writing it out as a WAV file.
+
+/// Decoded 16-bit PCM audio.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Samples {
+    pub channels: u16,
+    pub sample_rate: u32,
+    /// Every sample, with the channels interleaved.
+    pub samples: Vec<i16>,
+}
+
+
+impl Samples {
+    /// The length of the audio, in seconds.
+    pub fn duration(&self) -> f32 {
+        self.samples.len() as f32 / self.channels.max(1) as f32 / self.sample_rate.max(1) as f32
+    }
+
+    /// Writes the samples out as a 16-bit PCM WAV file.
+    pub fn to_wav(&self) -> Vec<u8> {
+        let data_len = self.samples.len() as u32 * 2;
+        let block_align = self.channels * 2;
+
+        let mut out = Vec::with_capacity(44 + data_len as usize);
+        out.extend_from_slice(b"RIFF");
+        out.extend_from_slice(&(36 + data_len).to_le_bytes());
+        out.extend_from_slice(b"WAVE");
+
+        out.extend_from_slice(b"fmt ");
+        out.extend_from_slice(&16u32.to_le_bytes());
+        out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        out.extend_from_slice(&self.channels.to_le_bytes());
+        out.extend_from_slice(&self.sample_rate.to_le_bytes());
+        out.extend_from_slice(&(self.sample_rate * block_align as u32).to_le_bytes());
+        out.extend_from_slice(&block_align.to_le_bytes());
+        out.extend_from_slice(&16u16.to_le_bytes());
+
+        out.extend_from_slice(b"data");
+        out.extend_from_slice(&data_len.to_le_bytes());
+        for sample in &self.samples {
+            out.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        out
+    }
+}
@@ -0,0 +1,235 @@
+//! Decodes and encodes FF7 text, the game's own character table.
+//!
+//! Bytes `0x00` through `0x5E` are the printable ASCII characters, shifted down by `0x20`. The bytes after that are
+//! accented letters and symbols, and everything from `0xE0` onwards is a control code: newlines, new pages, the names
+//! of the party, controller buttons, and so on. `0xFE` starts a two-byte code (like a color change), and `0xFF` ends
+//! the string.
+//!
+//! Decoded text writes every control code in braces, like `{CLOUD}` or `{RED}`, except for newlines and tabs, which
+//! are written as themselves. Bytes without a known meaning are written as their hex value, like `{D5}` or `{FE E2}`,
+//! so that every string can be decoded and then encoded back again. A literal `{` is written as `{{`. The codes that
+//! stand for plain text (like `0xE2` for `", "`) are decoded as that text, and so are encoded back as its characters.
+
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+
+/// The byte that ends every string.
+pub const TEXT_END: u8 = 0xFF;
+
+/// The byte that starts a two-byte control code.
+const TWO_BYTE_CODE: u8 = 0xFE;
+
+/// The characters for bytes `0x60` through `0xCF`.
+const EXTENDED: [char; 0x70] = [
+    'Ä', 'Å', 'Ç', 'É', 'Ñ', 'Ö', 'Ü', 'á', 'à', 'â', 'ä', 'ã', 'å', 'ç', 'é', 'è', // 0x60
+    'ê', 'ë', 'í', 'ì', 'î', 'ï', 'ñ', 'ó', 'ò', 'ô', 'ö', 'õ', 'ú', 'ù', 'û', 'ü', // 0x70
+    '⌘', '°', '¢', '£', 'Ù', 'Û', '¶', 'ß', '®', '©', '™', '´', '¨', '≠', 'Æ', 'Ø', // 0x80
+    '∞', '±', '≤', '≥', '¥', 'µ', '∂', 'Σ', 'Π', 'π', '⌡', 'ª', 'º', 'Ω', 'æ', 'ø', // 0x90
+    '¿', '¡', '¬', '√', 'ƒ', '≈', '∆', '«', '»', '…', '\u{A0}', 'À', 'Ã', 'Õ', 'Œ', 'œ', // 0xA0
+    '–', '—', '“', '”', '‘', '’', '÷', '◊', 'ÿ', 'Ÿ', '⁄', '¤', '‹', '›', 'ﬁ', 'ﬂ', // 0xB0
+    '■', '▪', '‚', '„', '‰', 'Â', 'Ê', 'Á', 'Ë', 'È', 'Í', 'Î', 'Ï', 'Ì', 'Ó', 'Ô', // 0xC0
+];
+
+/// Single-byte control codes that stand for plain text.
+const TEXT_CODES: [(u8, &str); 5] = [(0xE1, "\t"), (0xE2, ", "), (0xE3, ".\""), (0xE4, "…\""), (0xE7, "\n")];
+
+/// Single-byte control codes that are written by name.
+const NAMED_CODES: [(u8, &str); 18] = [
+    (0xE0, "CHOICE"),
+    (0xE8, "NEW"),
+    (0xEA, "CLOUD"),
+    (0xEB, "BARRET"),
+    (0xEC, "TIFA"),
+    (0xED, "AERITH"),
+    (0xEE, "RED XIII"),
+    (0xEF, "YUFFIE"),
+    (0xF0, "CAIT SITH"),
+    (0xF1, "VINCENT"),
+    (0xF2, "CID"),
+    (0xF3, "PARTY #1"),
+    (0xF4, "PARTY #2"),
+    (0xF5, "PARTY #3"),
+    (0xF6, "CIRCLE"),
+    (0xF7, "TRIANGLE"),
+    (0xF8, "SQUARE"),
+    (0xF9, "CROSS"),
+];
+
+/// Two-byte control codes (after [`TWO_BYTE_CODE`]) that are written by name.
+const NAMED_TWO_BYTE_CODES: [(u8, &str); 11] = [
+    (0xD2, "GRAY"),
+    (0xD3, "BLUE"),
+    (0xD4, "RED"),
+    (0xD5, "PURPLE"),
+    (0xD6, "GREEN"),
+    (0xD7, "CYAN"),
+    (0xD8, "YELLOW"),
+    (0xD9, "WHITE"),
+    (0xDA, "FLASH"),
+    (0xDB, "RAINBOW"),
+    (0xDC, "PAUSE"),
+];
+
+
+/// An error from encoding a string as FF7 text.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum TextError {
+    #[error("the character '{0}' can't be written in FF7 text")]
+    UnknownCharacterError(char),
+
+    #[error("unknown control code {{{0}}}")]
+    UnknownCodeError(String),
+
+    #[error("a control code was opened with '{{' but never closed")]
+    UnclosedCodeError,
+}
+
+
+/// A string in the game's text encoding, without its terminating `0xFF`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct FfText(pub Vec<u8>);
+
+
+impl FfText {
+    /// Takes a string from the start of `data`, up to (and not including) the first `0xFF`, or all of it if there
+    /// isn't one.
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let len = data.iter().position(|&b| b == TEXT_END).unwrap_or(data.len());
+        Self(data[..len].to_vec())
+    }
+
+    /// The string's bytes, followed by the `0xFF` that ends it.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.0.len() + 1);
+        out.extend_from_slice(&self.0);
+        out.push(TEXT_END);
+        out
+    }
+
+    /// Encodes a string, reading control codes written in braces. See the [module-level documentation](self).
+    pub fn encode(text: &str) -> Result<Self, TextError> {
+        let mut out = Vec::with_capacity(text.len());
+        let mut chars = text.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '{' {
+                out.push(encode_char(c)?);
+                continue;
+            }
+
+            let rest = chars.as_str();
+            if let Some(rest) = rest.strip_prefix('{') {
+                out.push(encode_char('{')?);
+                chars = rest.chars();
+                continue;
+            }
+
+            let (code, rest) = rest.split_once('}').ok_or(TextError::UnclosedCodeError)?;
+            out.extend(encode_code(code)?);
+            chars = rest.chars();
+        }
+
+        Ok(Self(out))
+    }
+
+    /// Decodes the string into plain text, with control codes written in braces. See the
+    /// [module-level documentation](self).
+    pub fn decode(&self) -> String {
+        let mut out = String::with_capacity(self.0.len());
+        let mut bytes = self.0.iter().copied();
+
+        while let Some(byte) = bytes.next() {
+            if byte == TWO_BYTE_CODE {
+                match bytes.next() {
+                    Some(second) => match lookup(&NAMED_TWO_BYTE_CODES, second) {
+                        Some(name) => out.push_str(&format!("{{{name}}}")),
+                        None => out.push_str(&format!("{{{TWO_BYTE_CODE:02X} {second:02X}}}")),
+                    },
+                    None => out.push_str(&format!("{{{TWO_BYTE_CODE:02X}}}")),
+                }
+            } else if let Some(c) = decode_char(byte) {
+                match c {
+                    '{' => out.push_str("{{"),
+                    c => out.push(c),
+                }
+            } else if let Some(text) = lookup(&TEXT_CODES, byte) {
+                out.push_str(text);
+            } else if let Some(name) = lookup(&NAMED_CODES, byte) {
+                out.push_str(&format!("{{{name}}}"));
+            } else {
+                out.push_str(&format!("{{{byte:02X}}}"));
+            }
+        }
+
+        out
+    }
+}
+
+
+impl Display for FfText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.decode())
+    }
+}
+
+
+impl FromStr for FfText {
+    type Err = TextError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::encode(s)
+    }
+}
+
+
+fn decode_char(byte: u8) -> Option<char> {
+    match byte {
+        0x00..=0x5E => Some((byte + 0x20) as char),
+        0x60..=0xCF => Some(EXTENDED[(byte - 0x60) as usize]),
+        _ => None,
+    }
+}
+
+
+fn encode_char(c: char) -> Result<u8, TextError> {
+    match c {
+        ' '..='~' => Ok(c as u8 - 0x20),
+        '\t' => Ok(0xE1),
+        '\n' => Ok(0xE7),
+        _ => match EXTENDED.iter().position(|&e| e == c) {
+            Some(i) => Ok(0x60 + i as u8),
+            None => Err(TextError::UnknownCharacterError(c)),
+        },
+    }
+}
+
+
+/// Encodes the contents of a pair of braces: either a code's name, or one or two hex bytes.
+fn encode_code(code: &str) -> Result<Vec<u8>, TextError> {
+    if let Some(byte) = reverse_lookup(&NAMED_CODES, code) {
+        return Ok(vec![byte]);
+    }
+    if let Some(byte) = reverse_lookup(&NAMED_TWO_BYTE_CODES, code) {
+        return Ok(vec![TWO_BYTE_CODE, byte]);
+    }
+
+    let bytes = code.split(' ').map(|hex| u8::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 2));
+    match bytes.collect::<Option<Vec<_>>>() {
+        Some(bytes) if bytes.contains(&TEXT_END) => Err(TextError::UnknownCodeError(code.to_owned())),
+        Some(bytes) if bytes.len() == 1 || (bytes.len() == 2 && bytes[0] == TWO_BYTE_CODE) => Ok(bytes),
+        _ => Err(TextError::UnknownCodeError(code.to_owned())),
+    }
+}
+
+
+fn lookup(table: &[(u8, &'static str)], byte: u8) -> Option<&'static str> {
+    table.iter().find(|&&(b, _)| b == byte).map(|&(_, name)| name)
+}
+
+
+fn reverse_lookup(table: &[(u8, &str)], name: &str) -> Option<u8> {
+    table.iter().find(|&&(_, n)| n.eq_ignore_ascii_case(name)).map(|&(b, _)| b)
+}
@@ -0,0 +1,5 @@
+//! The game's own text encoding, which dialogue, menus, and `kernel.bin` all use instead of ASCII.
+
+mod ff_text;
+
+pub use ff_text::*;
@@ -0,0 +1,90 @@
+//! Splits [world map files](https://wiki.ffrtt.ru/index.php/FF7/WorldMap_Module/Map) into their blocks and meshes.
+//!
+//! A map file is a list of fixed-size blocks, each of which covers a 4×4 square of meshes. A block starts with the
+//! offset of each of its 16 meshes from the start of the block, and each mesh is compressed on its own (see
+//! [`decompress_lzss`][crate::extract::decompress_lzss]) before being parsed as a [`MapMesh`][super::MapMesh].
+//!
+//! `wm0.map`, the main world map, is a grid of 9×7 blocks, stored row by row. The blocks after those replace parts of
+//! the grid as the story goes on (e.g., once the crater opens up).
+
+use crate::extract::{read, read_u32, ParseError};
+
+
+/// The length of every block in a map file.
+pub const BLOCK_LEN: usize = 0xB800;
+
+/// The number of meshes in each block, which are laid out in a 4×4 square.
+pub const MESHES_PER_BLOCK: usize = 16;
+
+/// The width (and depth) of a single mesh, in world units.
+pub const MESH_SIZE: i32 = 8192;
+
+/// The number of columns of blocks in `wm0.map`'s grid.
+pub const WORLD_MAP_COLUMNS: usize = 9;
+
+/// The number of rows of blocks in `wm0.map`'s grid.
+pub const WORLD_MAP_ROWS: usize = 7;
+
+
+/// A map file, split into its blocks. Each block's meshes are left compressed.
+#[derive(Debug, Clone)]
+pub struct MapFile<'a> {
+    pub blocks: Vec<MapBlock<'a>>,
+}
+
+
+/// A single block from a [map file][MapFile].
+#[derive(Debug, Clone, Copy)]
+pub struct MapBlock<'a> {
+    meshes: [&'a [u8]; MESHES_PER_BLOCK],
+}
+
+
+impl<'a> MapFile<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        // log warning if `data.len()` isn't a multiple of the block length?
+        let blocks = data.chunks_exact(BLOCK_LEN).map(MapBlock::from_bytes).collect::<Result<_, _>>()?;
+        Ok(Self { blocks })
+    }
+
+    /// The position of a mesh's corner in the world, given the index of its block, its index in that block, and how
+    /// many columns of blocks the map has (see [`WORLD_MAP_COLUMNS`]).
+    pub fn mesh_origin(block: usize, mesh: usize, columns: usize) -> [i32; 2] {
+        let x = (block % columns) * 4 + mesh % 4;
+        let z = (block / columns) * 4 + mesh / 4;
+        [x as i32 * MESH_SIZE, z as i32 * MESH_SIZE]
+    }
+}
+
+
+impl<'a> MapBlock<'a> {
+    pub fn from_bytes(data: &'a [u8]) -> Result<Self, ParseError<'a>> {
+        let mut ptr = 0;
+
+        let mut meshes = [&data[0..0]; MESHES_PER_BLOCK];
+        for mesh in meshes.iter_mut() {
+            let offset = read_u32(data, &mut ptr)? as usize;
+            if offset >= data.len() {
+                return Err(ParseError::InvalidValueError(&data[ptr - 4..ptr], ptr - 4));
+            }
+
+            // The compressed length comes first; keep it so that the slice can go straight to `decompress_lzss`.
+            let mut len_ptr = offset;
+            let len = read_u32(data, &mut len_ptr)? as usize;
+            let mut mesh_ptr = offset;
+            *mesh = read(data, &mut mesh_ptr, len + 4)?;
+        }
+
+        Ok(Self { meshes })
+    }
+
+    /// Gets the compressed bytes of one of the block's meshes, including their length.
+    pub fn mesh(&self, index: usize) -> &'a [u8] {
+        self.meshes[index]
+    }
+
+    /// Iterates over the compressed bytes of every mesh in the block, in order.
+    pub fn meshes(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        self.meshes.iter().copied()
+    }
+}
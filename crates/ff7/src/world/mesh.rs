@@ -0,0 +1,168 @@
+//! Parses a single decompressed [world map mesh](https://wiki.ffrtt.ru/index.php/FF7/WorldMap_Module/Map#Mesh).
+//!
+//! A mesh starts with its triangle and vertex counts (both `u16`), followed by the triangles (12 bytes each), the
+//! vertices, and then one normal for each vertex. Vertices and normals are both three `i16`s padded out to 8 bytes.
+//! Vertex positions are relative to the mesh's corner (see [`MapFile::mesh_origin`][super::MapFile::mesh_origin]).
+
+use crate::char::read_array;
+use crate::extract::{read, read_i16, read_u16, ParseError};
+
+
+/// A vertex position or normal from a [map mesh][MapMesh].
+pub type MapVertex = [i16; 3];
+
+
+/// What kind of ground a triangle is, which decides what can move over it and which battles happen on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Terrain {
+    Grass,
+    Forest,
+    Mountain,
+    Sea,
+    /// Shallow parts of rivers, which the buggy can cross.
+    RiverCrossing,
+    River,
+    Water,
+    Swamp,
+    Desert,
+    Wasteland,
+    Snow,
+    Riverside,
+    Cliff,
+    CorelBridge,
+    WutaiBridge,
+    HillSide,
+    Beach,
+    /// The dock at Junon's submarine pen.
+    SubPen,
+    Canyon,
+    MountainPass,
+    Waterfall,
+    GoldSaucerDesert,
+    Jungle,
+    /// Deep sea, which only the submarine can dive into.
+    DeepSea,
+    NorthernCave,
+    GoldSaucerDesertBorder,
+    Bridgehead,
+    /// The back entrance to a town.
+    BackEntrance,
+    /// A value that the game doesn't use.
+    Unused(u8),
+}
+
+
+impl Terrain {
+    /// Converts the 5-bit value stored in a triangle into a terrain type.
+    pub fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Terrain::Grass,
+            1 => Terrain::Forest,
+            2 => Terrain::Mountain,
+            3 => Terrain::Sea,
+            4 => Terrain::RiverCrossing,
+            5 => Terrain::River,
+            6 => Terrain::Water,
+            7 => Terrain::Swamp,
+            8 => Terrain::Desert,
+            9 => Terrain::Wasteland,
+            10 => Terrain::Snow,
+            11 => Terrain::Riverside,
+            12 => Terrain::Cliff,
+            13 => Terrain::CorelBridge,
+            14 => Terrain::WutaiBridge,
+            16 => Terrain::HillSide,
+            17 => Terrain::Beach,
+            18 => Terrain::SubPen,
+            19 => Terrain::Canyon,
+            20 => Terrain::MountainPass,
+            22 => Terrain::Waterfall,
+            24 => Terrain::GoldSaucerDesert,
+            25 => Terrain::Jungle,
+            26 => Terrain::DeepSea,
+            27 => Terrain::NorthernCave,
+            28 => Terrain::GoldSaucerDesertBorder,
+            29 => Terrain::Bridgehead,
+            30 => Terrain::BackEntrance,
+            other => Terrain::Unused(other),
+        }
+    }
+}
+
+
+/// A single triangle from a [map mesh][MapMesh].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapTriangle {
+    /// The indices of the triangle's corners in the mesh's [vertices][MapMesh::vertices].
+    pub vertices: [u8; 3],
+    pub terrain: Terrain,
+    /// The three bits stored alongside the terrain type, whose meaning isn't known.
+    pub flags: u8,
+    /// The texture coordinates of each corner, in pixels.
+    pub uvs: [[u8; 2]; 3],
+    /// Which of the world map's textures the triangle uses.
+    pub texture: u16,
+    /// Which location (e.g., a town's entrance) the triangle belongs to, if any.
+    pub location: u8,
+}
+
+
+/// The parsed contents of a decompressed map mesh.
+#[derive(Debug, Clone)]
+pub struct MapMesh {
+    pub triangles: Vec<MapTriangle>,
+    pub vertices: Vec<MapVertex>,
+    /// The normal of each vertex, in the same order as [`vertices`][Self::vertices].
+    pub normals: Vec<MapVertex>,
+}
+
+
+impl MapMesh {
+    pub fn from_bytes(data: &[u8]) -> Result<Self, ParseError<'_>> {
+        let mut ptr = 0;
+        let triangle_count = read_u16(data, &mut ptr)? as usize;
+        let vertex_count = read_u16(data, &mut ptr)? as usize;
+
+        let triangles = read_array(data, &mut ptr, triangle_count, read_triangle)?;
+        let vertices = read_array(data, &mut ptr, vertex_count, read_vertex)?;
+        let normals = read_array(data, &mut ptr, vertex_count, read_vertex)?;
+
+        Ok(Self { triangles, vertices, normals })
+    }
+
+    /// Looks up the positions of a triangle's corners, or `None` if it refers to a vertex that the mesh doesn't have.
+    pub fn positions(&self, triangle: &MapTriangle) -> Option<[MapVertex; 3]> {
+        let [a, b, c] = triangle.vertices.map(|i| self.vertices.get(i as usize).copied());
+        Some([a?, b?, c?])
+    }
+
+    /// Iterates over the corners of every triangle in the mesh, along with the triangle itself. Triangles that refer to
+    /// missing vertices are skipped.
+    pub fn triangle_list(&self) -> impl Iterator<Item = ([MapVertex; 3], &MapTriangle)> {
+        self.triangles.iter().filter_map(|triangle| Some((self.positions(triangle)?, triangle)))
+    }
+}
+
+
+fn read_triangle<'a>(data: &'a [u8], ptr: &mut usize) -> Result<MapTriangle, ParseError<'a>> {
+    let &[a, b, c, walkmap, u0, v0, u1, v1, u2, v2] = read(data, ptr, 10)? else {
+        unreachable!(); // success of `read` with length 10 guarantees slice length
+    };
+    let texture_info = read_u16(data, ptr)?;
+
+    Ok(MapTriangle {
+        vertices: [a, b, c],
+        terrain: Terrain::from_u8(walkmap & 0x1F),
+        flags: walkmap >> 5,
+        uvs: [[u0, v0], [u1, v1], [u2, v2]],
+        texture: texture_info & 0x01FF,
+        location: (texture_info >> 9) as u8,
+    })
+}
+
+
+fn read_vertex<'a>(data: &'a [u8], ptr: &mut usize) -> Result<MapVertex, ParseError<'a>> {
+    let vertex = [read_i16(data, ptr)?, read_i16(data, ptr)?, read_i16(data, ptr)?];
+    read(data, ptr, 2)?; // padding
+    Ok(vertex)
+}
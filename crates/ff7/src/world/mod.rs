@@ -0,0 +1,10 @@
+//! Parsing of world map files: the terrain meshes from the `.map` files in `data/wm`, and their terrain types.
+//!
+//! The models that move around on the world map (e.g., the Highwind and the Chocobos) come from `world_us.lgp`, and
+//! are stored in the same HRC, RSD, P, and A formats as field models; see the [`char`](crate::char) module for those.
+
+mod map;
+mod mesh;
+
+pub use map::*;
+pub use mesh::*;
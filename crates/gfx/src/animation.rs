@@ -0,0 +1,91 @@
+//! Playing a [bound animation][BoundAnimation] back in real time.
+//!
+//! A files don't store a frame rate. Field models are usually animated at 30 frames per second, so that's what is used
+//! unless it's changed.
+
+use ff7::char::{BoundAnimation, Pose, Skeleton};
+
+
+/// The frame rate that animations play at by default.
+pub const DEFAULT_FRAME_RATE: f32 = 30.0;
+
+
+/// Keeps track of how far through an animation playback is, and moves it along as time passes.
+#[derive(Debug, Clone)]
+pub struct AnimationPlayer {
+    pub animation: BoundAnimation,
+    /// How many of the animation's frames to play per second.
+    pub frame_rate: f32,
+    /// Whether or not playback moves forward when [`advance`][Self::advance] is called.
+    pub playing: bool,
+    /// Whether the animation starts over after its last frame, or stops on it.
+    pub looping: bool,
+    /// How far through the animation playback is, in frames. Always between 0 and the frame count (or the last frame,
+    /// when not looping).
+    position: f32,
+}
+
+
+impl AnimationPlayer {
+    /// Starts playing an animation from its first frame, looping.
+    pub fn new(animation: BoundAnimation) -> Self {
+        Self { animation, frame_rate: DEFAULT_FRAME_RATE, playing: true, looping: true, position: 0.0 }
+    }
+
+    /// How far through the animation playback is, in frames. Fractional positions are between two frames.
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+
+    /// Jumps to a position in the animation, in frames.
+    pub fn seek(&mut self, position: f32) {
+        self.position = position;
+        self.wrap();
+    }
+
+    /// Pauses playback and moves to the whole frame `frames` after the current one (or before it, for negative
+    /// numbers). Positions between two frames count as the earlier one when stepping forward, and the later one when
+    /// stepping back.
+    pub fn step(&mut self, frames: i32) {
+        self.playing = false;
+        let from = if frames > 0 { self.position.floor() } else { self.position.ceil() };
+        self.seek(from + frames as f32);
+    }
+
+    /// Whether or not playback has stopped on the last frame of an animation that doesn't loop.
+    pub fn finished(&self) -> bool {
+        !self.looping && self.position >= self.last_frame()
+    }
+
+    /// The number of the last frame, which is as far as animations that don't loop can go.
+    pub fn last_frame(&self) -> f32 {
+        self.animation.frame_count().saturating_sub(1) as f32
+    }
+
+    /// Moves playback forward by some amount of wall-clock time, in seconds. Animations that don't loop stop playing
+    /// once they reach their last frame.
+    pub fn advance(&mut self, seconds: f64) {
+        if self.playing {
+            self.position += seconds as f32 * self.frame_rate;
+            self.wrap();
+            if self.finished() {
+                self.playing = false;
+            }
+        }
+    }
+
+    /// The pose for the current position, blended between the frames on either side of it. Returns `None` if the
+    /// animation has no frames.
+    pub fn pose(&self, skeleton: &Skeleton) -> Option<Pose> {
+        skeleton.pose_at(&self.animation, self.position, self.looping)
+    }
+
+    /// Brings the position back within the animation.
+    fn wrap(&mut self) {
+        if self.looping {
+            self.position = self.position.rem_euclid(self.animation.frame_count().max(1) as f32);
+        } else {
+            self.position = self.position.clamp(0.0, self.last_frame());
+        }
+    }
+}
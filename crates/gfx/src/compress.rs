@@ -0,0 +1,324 @@
+//! Compressing textures to BC7 as they are loaded, to save video memory.
+//!
+//! An 8-bit RGBA texture takes four bytes per pixel, which adds up quickly with HD texture packs. BC7 gets that down to
+//! one byte per pixel with very little visible loss, and every desktop GPU that can run OpenGL 4.2 or newer can sample
+//! it directly. Compressing is slow, though, so compressed textures are kept in a [`TranscodeCache`] on disk and only
+//! compressed the first time they are seen.
+//!
+//! Only BC7's mode 6 (a single pair of RGBA endpoints per block, with 16 levels between them) is used. It is the
+//! simplest mode to encode and handles alpha well, which matters for the game's color-keyed textures.
+
+use std::fs;
+use std::path::PathBuf;
+
+use glfw::Glfw;
+
+use crate::GlVersion;
+
+
+/// The number of bytes in each compressed 4×4 block.
+pub const BC7_BLOCK_LEN: usize = 16;
+
+/// The weights used to interpolate between a block's endpoints, out of 64, for each of mode 6's 4-bit indices.
+const WEIGHTS: [u32; 16] = [0, 4, 9, 13, 17, 21, 26, 30, 34, 38, 43, 47, 51, 55, 60, 64];
+
+
+/// Whether or not the current context can sample BC7 textures. BC7 is core as of OpenGL 4.2, and is available as an
+/// extension on most older drivers.
+pub fn bc7_supported(glfw: &Glfw, version: GlVersion) -> bool {
+    version == GlVersion::Gl46 || glfw.extension_supported("GL_ARB_texture_compression_bptc")
+}
+
+
+/// The number of bytes that a `width` by `height` image takes up once compressed.
+pub fn bc7_len(width: u32, height: u32) -> usize {
+    width.div_ceil(4) as usize * height.div_ceil(4) as usize * BC7_BLOCK_LEN
+}
+
+
+/// Compresses an 8-bit RGBA image to BC7. `pixels` must be `width * height * 4` bytes long. Images whose sizes aren't
+/// multiples of 4 have their edge pixels repeated to fill out the last blocks.
+pub fn encode_bc7(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    assert_eq!(pixels.len(), width as usize * height as usize * 4, "Texture data is the wrong size.");
+
+    let mut out = Vec::with_capacity(bc7_len(width, height));
+    for block_y in 0..height.div_ceil(4) {
+        for block_x in 0..width.div_ceil(4) {
+            let mut texels = [[0u8; 4]; 16];
+            for (i, texel) in texels.iter_mut().enumerate() {
+                let x = (block_x * 4 + i as u32 % 4).min(width - 1) as usize;
+                let y = (block_y * 4 + i as u32 / 4).min(height - 1) as usize;
+                let at = (y * width as usize + x) * 4;
+                texel.copy_from_slice(&pixels[at..at + 4]);
+            }
+            out.extend_from_slice(&encode_block(&texels));
+        }
+    }
+
+    out
+}
+
+
+/// A directory of textures that have already been compressed, so that they only need to be compressed once.
+///
+/// Textures are looked up by a hash of their size and contents. Failing to read or write the cache is never fatal; the
+/// texture is just compressed again.
+#[derive(Debug, Clone)]
+pub struct TranscodeCache {
+    dir: PathBuf,
+}
+
+
+impl TranscodeCache {
+    /// Uses the cache in the given directory. The directory is only created once something is written to it.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Gets the BC7 version of an image from the cache, compressing it (and adding it to the cache) if it isn't there.
+    pub fn get_or_encode(&self, width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+        let path = self.dir.join(format!("{:016x}-{width}x{height}.bc7", fnv1a(pixels)));
+
+        match fs::read(&path) {
+            Ok(blocks) if blocks.len() == bc7_len(width, height) => return blocks,
+            Ok(_) => log::warn!("Ignoring cached texture with the wrong size: {}", path.display()),
+            Err(_) => (),
+        }
+
+        let blocks = encode_bc7(width, height, pixels);
+        if let Err(err) = fs::create_dir_all(&self.dir).and_then(|_| fs::write(&path, &blocks)) {
+            log::warn!("Could not write to the texture cache: {err}");
+        }
+
+        blocks
+    }
+
+    /// Deletes every cached texture.
+    pub fn clear(&self) -> std::io::Result<()> {
+        match fs::remove_dir_all(&self.dir) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+
+/// Encodes a single 4×4 block, given in row-major order, using mode 6.
+fn encode_block(texels: &[[u8; 4]; 16]) -> [u8; BC7_BLOCK_LEN] {
+    // Fit a line through the block's colors (along the direction that they vary the most), and use the ends of the
+    // part of it that the colors cover as the endpoints.
+    let mean = [0, 1, 2, 3].map(|c| texels.iter().map(|t| t[c] as f32).sum::<f32>() / 16.0);
+    let offsets = texels.map(|t| [0, 1, 2, 3].map(|c| t[c] as f32 - mean[c]));
+
+    let mut covariance = [[0.0f32; 4]; 4];
+    for offset in &offsets {
+        for (row, &a) in covariance.iter_mut().zip(offset) {
+            for (cell, &b) in row.iter_mut().zip(offset) {
+                *cell += a * b;
+            }
+        }
+    }
+
+    // A few rounds of power iteration are plenty to find the main axis of a 4×4 matrix.
+    let mut axis = [1.0f32; 4];
+    for _ in 0..8 {
+        let next = covariance.map(|row| row.iter().zip(&axis).map(|(a, b)| a * b).sum::<f32>());
+        let len = next.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if len < f32::EPSILON {
+            break; // every texel is the same color
+        }
+        axis = next.map(|v| v / len);
+    }
+
+    let project = |offset: &[f32; 4]| offset.iter().zip(&axis).map(|(a, b)| a * b).sum::<f32>();
+    let t_min = offsets.iter().map(project).fold(f32::MAX, f32::min);
+    let t_max = offsets.iter().map(project).fold(f32::MIN, f32::max);
+    let endpoint = |t: f32| [0, 1, 2, 3].map(|c| (mean[c] + axis[c] * t).round().clamp(0.0, 255.0) as u8);
+    let (lo, hi) = (endpoint(t_min), endpoint(t_max));
+
+    let (mut e0, mut p0) = quantize_endpoint(lo);
+    let (mut e1, mut p1) = quantize_endpoint(hi);
+
+    // Every color that the block can use, from which each texel picks the closest.
+    let (a, b) = (unquantize(e0, p0), unquantize(e1, p1));
+    let colors = WEIGHTS.map(|w| [0, 1, 2, 3].map(|c| (((64 - w) * a[c] as u32 + w * b[c] as u32 + 32) >> 6) as u8));
+
+    let mut indices = [0u8; 16];
+    for (index, texel) in indices.iter_mut().zip(texels) {
+        *index = (0..16).min_by_key(|&i| distance(&colors[i as usize], texel)).unwrap();
+    }
+
+    // The first index is stored with one less bit, so its top bit has to be zero. Swapping the endpoints flips every
+    // index around, which makes sure of that.
+    if indices[0] >= 8 {
+        std::mem::swap(&mut e0, &mut e1);
+        std::mem::swap(&mut p0, &mut p1);
+        indices.iter_mut().for_each(|i| *i = 15 - *i);
+    }
+
+    let mut bits = BitWriter::default();
+    bits.write(1 << 6, 7); // mode 6
+    for c in 0..4 {
+        bits.write(e0[c] as u128, 7);
+        bits.write(e1[c] as u128, 7);
+    }
+    bits.write(p0 as u128, 1);
+    bits.write(p1 as u128, 1);
+    bits.write(indices[0] as u128, 3);
+    for &index in &indices[1..] {
+        bits.write(index as u128, 4);
+    }
+
+    bits.0.to_le_bytes()
+}
+
+
+/// Quantizes an 8-bit endpoint to seven bits per channel, plus the shared lowest bit that mode 6 gives each endpoint.
+/// Both choices of the shared bit are tried, and whichever is closer is kept.
+fn quantize_endpoint(color: [u8; 4]) -> ([u8; 4], u8) {
+    [0u8, 1]
+        .map(|p| (color.map(|v| (v.saturating_sub(p) as u32).div_ceil(2).min(127) as u8), p))
+        .into_iter()
+        .min_by_key(|&(quantized, p)| distance(&unquantize(quantized, p), &color))
+        .unwrap()
+}
+
+
+/// Expands a quantized endpoint back to eight bits per channel.
+fn unquantize(quantized: [u8; 4], p: u8) -> [u8; 4] {
+    quantized.map(|v| (v << 1) | p)
+}
+
+
+/// The squared distance between two colors.
+fn distance(a: &[u8; 4], b: &[u8; 4]) -> u32 {
+    a.iter().zip(b).map(|(&a, &b)| (a as i32 - b as i32).pow(2) as u32).sum()
+}
+
+
+/// The 64-bit FNV-1a hash, which is stable across platforms and Rust versions (unlike `std`'s hashers), so that cache
+/// entries stay valid.
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01B3))
+}
+
+
+/// Packs values into a 128-bit block, least significant bit first.
+#[derive(Default)]
+struct BitWriter(u128, u32);
+
+
+impl BitWriter {
+    fn write(&mut self, value: u128, bits: u32) {
+        self.0 |= value << self.1;
+        self.1 += bits;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The fields of a mode 6 block, as a decoder sees them.
+    struct Mode6Block {
+        endpoints: [[u8; 4]; 2],
+        p_bits: [u8; 2],
+        indices: [u8; 16],
+    }
+
+    impl Mode6Block {
+        /// Reads a block, checking that it is mode 6.
+        fn decode(block: &[u8; BC7_BLOCK_LEN]) -> Self {
+            let mut bits = u128::from_le_bytes(*block);
+            let mut read = |count: u32| {
+                let value = (bits & ((1 << count) - 1)) as u8;
+                bits >>= count;
+                value
+            };
+
+            assert_eq!(read(7), 1 << 6, "the block should be mode 6");
+            // Each channel stores both endpoints before moving on to the next.
+            let (mut e0, mut e1) = ([0; 4], [0; 4]);
+            for (a, b) in e0.iter_mut().zip(&mut e1) {
+                *a = read(7);
+                *b = read(7);
+            }
+            let endpoints = [e0, e1];
+            let p_bits = [read(1), read(1)];
+            let mut indices = [0; 16];
+            for (i, index) in indices.iter_mut().enumerate() {
+                *index = read(if i == 0 { 3 } else { 4 });
+            }
+
+            Self { endpoints, p_bits, indices }
+        }
+
+        /// The colors of the block's texels.
+        fn texels(&self) -> [[u8; 4]; 16] {
+            let [a, b] = [0, 1].map(|e| unquantize(self.endpoints[e], self.p_bits[e]));
+            self.indices.map(|i| {
+                let w = WEIGHTS[i as usize];
+                [0, 1, 2, 3].map(|c| (((64 - w) * a[c] as u32 + w * b[c] as u32 + 32) >> 6) as u8)
+            })
+        }
+    }
+
+    /// Encodes and decodes a block, checking that every channel of every texel comes back within `tolerance`.
+    fn assert_round_trip(texels: &[[u8; 4]; 16], tolerance: u8) -> Mode6Block {
+        let block = Mode6Block::decode(&encode_block(texels));
+        assert!(block.indices[0] < 8, "the first index only has three bits");
+        for (decoded, original) in block.texels().iter().zip(texels) {
+            for (d, o) in decoded.iter().zip(original) {
+                assert!(d.abs_diff(*o) <= tolerance, "{decoded:?} should be close to {original:?}");
+            }
+        }
+        block
+    }
+
+    #[test]
+    fn solid_blocks_round_trip() {
+        for color in [[0, 0, 0, 255], [255; 4], [10, 200, 77, 255], [128, 64, 32, 16]] {
+            let block = assert_round_trip(&[color; 16], 1);
+            assert!(block.indices.iter().all(|&i| i == block.indices[0]));
+        }
+    }
+
+    #[test]
+    fn gradient_blocks_round_trip() {
+        let texels = std::array::from_fn(|i| {
+            let v = (i * 17) as u8;
+            [v, 255 - v, v / 2, 255]
+        });
+        assert_round_trip(&texels, 6);
+    }
+
+    #[test]
+    fn alpha_blocks_round_trip() {
+        // Color-keyed texels next to opaque ones. The transparent ones have to stay fully transparent; the opaque ones
+        // can lose their lowest bit of alpha to the shared p-bit, since the other channels are all even.
+        let texels = std::array::from_fn(|i| if i % 3 == 0 { [0, 0, 0, 0] } else { [200, 120, 40, 255] });
+        let block = assert_round_trip(&texels, 1);
+        for (decoded, original) in block.texels().iter().zip(&texels) {
+            match original[3] {
+                0 => assert_eq!(decoded[3], 0),
+                _ => assert!(decoded[3] >= 254),
+            }
+        }
+    }
+
+    #[test]
+    fn endpoints_are_stored_channel_by_channel_with_their_p_bits() {
+        // The first texel is white, so once the endpoints are swapped to keep its index under 8, white comes first.
+        let black = [0, 0, 0, 255];
+        let white = [255; 4];
+        let texels = std::array::from_fn(|i| if i % 2 == 0 { white } else { black });
+        let block = assert_round_trip(&texels, 1);
+
+        let (white_bits, white_p) = quantize_endpoint(white);
+        let (black_bits, black_p) = quantize_endpoint(black);
+        assert_eq!(block.endpoints, [white_bits, black_bits]);
+        assert_eq!(block.p_bits, [white_p, black_p]);
+        assert_eq!(block.indices.map(|i| i % 15), [0; 16]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Creation of the window's OpenGL context, and routing of the context's debug output.
+//!
+//! The viewer prefers an OpenGL 4.6 context, since that lets it use direct state access (DSA) for everything. Plenty
+//! of older laptops can't create one, though, so it falls back to 3.3 when it has to. Anything that creates or modifies
+//! GL objects should check [`has_dsa`] and bind objects the old-fashioned way when it returns `false`.
+
+use std::ffi::{c_void, CStr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+
+use gl::types::*;
+use glfw::{Context, Glfw, OpenGlProfileHint, Window, WindowEvent, WindowHint, WindowMode};
+
+
+/// Whether or not the current context supports direct state access. Set when the window is created.
+static DIRECT_STATE_ACCESS: AtomicBool = AtomicBool::new(false);
+
+/// Whether or not the current context supports anisotropic filtering. Set when the window is created.
+static ANISOTROPIC_FILTERING: AtomicBool = AtomicBool::new(false);
+
+
+/// The receiving end of a window's event queue.
+pub type WindowEvents = Receiver<(f64, WindowEvent)>;
+
+
+/// The versions of OpenGL that the viewer can run on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlVersion {
+    /// OpenGL 4.6, which has direct state access and debug output built in.
+    Gl46,
+    /// OpenGL 3.3, the fallback for older hardware.
+    Gl33,
+}
+
+
+impl GlVersion {
+    /// Every supported version, from most to least preferred.
+    pub const ALL: [GlVersion; 2] = [GlVersion::Gl46, GlVersion::Gl33];
+
+    /// The major and minor version numbers.
+    pub fn numbers(self) -> (u32, u32) {
+        match self {
+            GlVersion::Gl46 => (4, 6),
+            GlVersion::Gl33 => (3, 3),
+        }
+    }
+
+    /// The `#version` directive that shaders compiled for this version should start with.
+    pub fn glsl_header(self) -> &'static str {
+        match self {
+            GlVersion::Gl46 => "#version 460 core\n",
+            GlVersion::Gl33 => "#version 330 core\n",
+        }
+    }
+
+    /// Whether or not this version has direct state access, which was added in 4.5.
+    pub fn has_dsa(self) -> bool {
+        self == GlVersion::Gl46
+    }
+}
+
+
+/// Whether or not the current context supports direct state access. Only meaningful after calling [`create_window`].
+pub fn has_dsa() -> bool {
+    DIRECT_STATE_ACCESS.load(Ordering::Relaxed)
+}
+
+
+/// Whether or not the current context supports anisotropic filtering, which is core as of 4.6 but was a very widely
+/// supported extension long before that. Only meaningful after calling [`create_window`].
+pub fn has_anisotropic_filtering() -> bool {
+    ANISOTROPIC_FILTERING.load(Ordering::Relaxed)
+}
+
+
+/// Creates a window using the newest version of OpenGL that the system supports, makes its context current, and loads
+/// the GL function pointers. Returns `None` if not even the oldest supported version is available.
+///
+/// Every version that fails raises a GLFW error, so GLFW should be initialized with an error callback that doesn't
+/// panic (e.g., [`glfw::LOG_ERRORS`]).
+pub fn create_window(
+    glfw: &mut Glfw,
+    width: u32,
+    height: u32,
+    title: &str,
+) -> Option<(Window, WindowEvents, GlVersion)> {
+    for version in GlVersion::ALL {
+        let (major, minor) = version.numbers();
+        glfw.window_hint(WindowHint::ContextVersion(major, minor));
+        glfw.window_hint(WindowHint::OpenGlProfile(OpenGlProfileHint::Core));
+        glfw.window_hint(WindowHint::OpenGlForwardCompat(true)); // macOS won't make core contexts without this
+        glfw.window_hint(WindowHint::OpenGlDebugContext(cfg!(debug_assertions)));
+
+        if let Some((mut window, events)) = glfw.create_window(width, height, title, WindowMode::Windowed) {
+            window.make_current();
+            gl::load_with(|s| window.get_proc_address(s));
+            DIRECT_STATE_ACCESS.store(version.has_dsa(), Ordering::Relaxed);
+
+            let anisotropy = version == GlVersion::Gl46
+                || glfw.extension_supported("GL_ARB_texture_filter_anisotropic")
+                || glfw.extension_supported("GL_EXT_texture_filter_anisotropic");
+            ANISOTROPIC_FILTERING.store(anisotropy, Ordering::Relaxed);
+
+            log::info!("Created an OpenGL {major}.{minor} context.");
+            return Some((window, events, version));
+        }
+
+        log::warn!("Could not create an OpenGL {major}.{minor} context.");
+    }
+
+    None
+}
+
+
+/// Routes the current context's debug messages to the log, if it supports `KHR_debug` (which is core as of 4.3).
+/// Returns whether or not debug output was enabled.
+pub fn enable_debug_output(glfw: &Glfw, version: GlVersion) -> bool {
+    let supported = version == GlVersion::Gl46 || glfw.extension_supported("GL_KHR_debug");
+    if !supported || !gl::DebugMessageCallback::is_loaded() {
+        return false;
+    }
+
+    unsafe {
+        gl::Enable(gl::DEBUG_OUTPUT);
+        // Report messages from inside the call that caused them, so that a breakpoint in the callback is useful.
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(Some(debug_callback), std::ptr::null());
+    }
+
+    true
+}
+
+
+extern "system" fn debug_callback(
+    source: GLenum,
+    kind: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    // The length doesn't include the null terminator, and is allowed to be negative if the message is null-terminated.
+    let message = unsafe {
+        if length >= 0 {
+            std::slice::from_raw_parts(message.cast::<u8>(), length as usize)
+        } else {
+            CStr::from_ptr(message).to_bytes()
+        }
+    };
+
+    let level = match severity {
+        gl::DEBUG_SEVERITY_HIGH => log::Level::Error,
+        gl::DEBUG_SEVERITY_MEDIUM => log::Level::Warn,
+        gl::DEBUG_SEVERITY_LOW => log::Level::Info,
+        _ => log::Level::Debug, // DEBUG_SEVERITY_NOTIFICATION
+    };
+
+    let source = match source {
+        gl::DEBUG_SOURCE_API => "api",
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "window system",
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "shader compiler",
+        gl::DEBUG_SOURCE_THIRD_PARTY => "third party",
+        gl::DEBUG_SOURCE_APPLICATION => "application",
+        _ => "other",
+    };
+
+    let kind = match kind {
+        gl::DEBUG_TYPE_ERROR => "error",
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "deprecated behaviour",
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "undefined behaviour",
+        gl::DEBUG_TYPE_PORTABILITY => "portability",
+        gl::DEBUG_TYPE_PERFORMANCE => "performance",
+        gl::DEBUG_TYPE_MARKER => "marker",
+        _ => "other",
+    };
+
+    log::log!(target: "gl", level, "[{source}, {kind}, #{id}] {}", String::from_utf8_lossy(message));
+}
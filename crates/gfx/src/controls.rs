@@ -0,0 +1,86 @@
+//! Moving the [camera][Camera] with the mouse: dragging with the left button orbits around the model, dragging with the
+//! middle button pans, and scrolling zooms in and out.
+//!
+//! The keyboard has the [presets][ViewPreset]: 1, 2, and 3 look from the front, side, and top (as do 1, 3, and 7 on the
+//! keypad), and O switches between perspective and orthographic.
+
+use glfw::{Action, Key, MouseButton, MouseButtonLeft, MouseButtonMiddle, WindowEvent};
+
+use crate::{Camera, ViewPreset};
+
+
+/// How far the camera orbits for each pixel that the mouse is dragged, in radians.
+const ORBIT_SPEED: f32 = 0.01;
+
+/// How far the camera pans for each pixel that the mouse is dragged, as a fraction of its distance from its target, so
+/// that panning feels the same at any zoom.
+const PAN_SPEED: f32 = 0.002;
+
+/// How much each notch of the scroll wheel zooms by.
+const ZOOM_STEP: f32 = 0.9;
+
+
+/// Turns mouse and keyboard events into camera movements.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OrbitControls {
+    /// Where the cursor was at the last event, in screen coordinates.
+    cursor: Option<(f64, f64)>,
+    /// The button being held down to drag the camera, if any.
+    dragging: Option<MouseButton>,
+}
+
+
+impl OrbitControls {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves the camera in response to a window event. Returns whether or not the camera moved. The window needs key,
+    /// mouse button, cursor position, and scroll polling turned on.
+    pub fn handle_event(&mut self, camera: &mut Camera, event: &WindowEvent) -> bool {
+        match *event {
+            WindowEvent::MouseButton(button @ (MouseButtonLeft | MouseButtonMiddle), Action::Press, _) => {
+                self.dragging = Some(button);
+                false
+            },
+            WindowEvent::MouseButton(button, Action::Release, _) if self.dragging == Some(button) => {
+                self.dragging = None;
+                false
+            },
+            WindowEvent::CursorPos(x, y) => {
+                let last = self.cursor.replace((x, y));
+                let Some(((last_x, last_y), button)) = last.zip(self.dragging) else {
+                    return false;
+                };
+
+                let (dx, dy) = ((x - last_x) as f32, (y - last_y) as f32);
+                if button == MouseButtonLeft {
+                    camera.orbit(-dx * ORBIT_SPEED, dy * ORBIT_SPEED);
+                } else {
+                    let speed = PAN_SPEED * camera.distance;
+                    camera.pan(-dx * speed, dy * speed);
+                }
+                true
+            },
+            WindowEvent::Scroll(_, y) => {
+                camera.zoom(ZOOM_STEP.powf(y as f32));
+                true
+            },
+            WindowEvent::Key(key, _, Action::Press, _) => {
+                let preset = match key {
+                    Key::Num1 | Key::Kp1 => ViewPreset::Front,
+                    Key::Num2 | Key::Kp3 => ViewPreset::Side,
+                    Key::Num3 | Key::Kp7 => ViewPreset::Top,
+                    Key::O => {
+                        camera.orthographic = !camera.orthographic;
+                        return true;
+                    },
+                    _ => return false,
+                };
+                camera.look_from(preset);
+                true
+            },
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Frame pacing: vsync, frame-rate caps, redraw scheduling, and upload budgets.
+
+use std::time::Duration;
+
+use glfw::{Glfw, SwapInterval};
+
+
+/// When the viewer should render new frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedrawMode {
+    /// Render a new frame every time through the main loop, as fast as vsync and the FPS cap allow.
+    Continuous,
+    /// Only render when something has changed (input, window damage, resizing, etc.). While nothing is happening, the
+    /// main loop sleeps on the OS' event queue, which keeps the GPU idle while a static model is being shown.
+    OnDemand,
+}
+
+
+/// User-configurable frame pacing settings.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameSettings {
+    /// Whether or not buffer swaps should wait for the monitor's vertical refresh.
+    pub vsync: bool,
+    /// The maximum number of frames to render per second, if any.
+    pub fps_cap: Option<u32>,
+    /// When to render new frames.
+    pub redraw_mode: RedrawMode,
+    /// How much time each frame may spend on queued GPU uploads. See [`UploadQueue`][crate::UploadQueue].
+    pub upload_budget: Duration,
+}
+
+
+impl Default for FrameSettings {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            fps_cap: None,
+            redraw_mode: RedrawMode::OnDemand,
+            upload_budget: Duration::from_millis(4),
+        }
+    }
+}
+
+
+impl FrameSettings {
+    /// The swap interval corresponding to the current vsync setting.
+    pub fn swap_interval(&self) -> SwapInterval {
+        if self.vsync {
+            SwapInterval::Sync(1)
+        } else {
+            SwapInterval::None
+        }
+    }
+
+    /// Applies the vsync setting to the current context.
+    pub fn apply(&self, glfw: &mut Glfw) {
+        glfw.set_swap_interval(self.swap_interval());
+    }
+}
+
+
+/// Keeps track of frame timings so that the main loop can be held to [`FrameSettings::fps_cap`].
+#[derive(Debug, Clone, Copy)]
+pub struct FrameLimiter {
+    /// The time, in seconds, at which the last frame was finished.
+    last_frame: f64,
+}
+
+
+impl FrameLimiter {
+    pub fn new(glfw: &Glfw) -> Self {
+        Self { last_frame: glfw.get_time() }
+    }
+
+    /// Sleeps for however much of the current frame's time-slice is left over, then marks the start of a new frame.
+    pub fn wait(&mut self, glfw: &Glfw, settings: &FrameSettings) {
+        if let Some(cap) = settings.fps_cap.filter(|&cap| cap > 0) {
+            let target = self.last_frame + 1.0 / cap as f64;
+            let remaining = target - glfw.get_time();
+            if remaining > 0.0 {
+                std::thread::sleep(Duration::from_secs_f64(remaining));
+            }
+        }
+
+        self.last_frame = glfw.get_time();
+    }
+}
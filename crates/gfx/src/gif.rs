@@ -0,0 +1,200 @@
+//! Encoding animated GIFs, for [turntables][crate::TurntableRecorder].
+//!
+//! Like [PNGs][crate::encode_png], GIFs are written by hand. Every frame shares one fixed palette, with ordered
+//! dithering to hide the banding, so that frames can be encoded as soon as they're captured instead of all being held
+//! in memory until the palette can be worked out.
+
+use std::collections::HashMap;
+
+
+/// How many levels of red, green, and blue the palette has. Green gets the most, since eyes are most sensitive to it.
+const LEVELS: [u8; 3] = [6, 7, 6];
+
+/// The smallest LZW code size, in bits, for a 256-color palette.
+const MIN_CODE_SIZE: u8 = 8;
+
+/// The largest LZW code that a GIF can use.
+const MAX_CODE: u16 = 4095;
+
+/// The longest that a GIF data sub-block can be.
+const SUB_BLOCK_MAX: usize = 0xFF;
+
+/// A 4×4 Bayer matrix, for ordered dithering.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+
+/// An animated GIF, which loops forever, built up one frame at a time.
+#[derive(Debug, Clone)]
+pub struct GifEncoder {
+    width: u16,
+    height: u16,
+    /// How long each frame is shown for, in hundredths of a second.
+    delay: u16,
+    data: Vec<u8>,
+}
+
+
+impl GifEncoder {
+    /// Starts a GIF whose frames are `width` by `height` and shown at `frame_rate` frames per second. Most viewers
+    /// won't show more than 50 frames per second, and many treat anything over that as 10.
+    pub fn new(width: u16, height: u16, frame_rate: f32) -> Self {
+        let delay = (100.0 / frame_rate).round().clamp(1.0, u16::MAX as f32) as u16;
+
+        let mut data = b"GIF89a".to_vec();
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        // A global color table of 256 colors (2^(7 + 1)), with 8 bits per channel; background color 0; square pixels.
+        data.extend_from_slice(&[0xF7, 0, 0]);
+        data.extend_from_slice(&palette());
+
+        // The "NETSCAPE2.0" application extension makes the animation loop; a count of 0 means forever.
+        data.extend_from_slice(&[0x21, 0xFF, 11]);
+        data.extend_from_slice(b"NETSCAPE2.0");
+        data.extend_from_slice(&[3, 1, 0, 0, 0]);
+
+        Self { width, height, delay, data }
+    }
+
+    /// Adds a frame of 8-bit RGBA pixels, top row first, which must be the size the GIF was started with. Alpha is
+    /// ignored.
+    pub fn add_frame(&mut self, pixels: &[u8]) {
+        // A graphic control extension, for the frame's delay: no disposal, no transparency.
+        self.data.extend_from_slice(&[0x21, 0xF9, 4, 0]);
+        self.data.extend_from_slice(&self.delay.to_le_bytes());
+        self.data.extend_from_slice(&[0, 0]);
+
+        // An image descriptor covering the whole screen, with no local color table or interlacing.
+        self.data.push(0x2C);
+        for value in [0, 0, self.width, self.height] {
+            self.data.extend_from_slice(&value.to_le_bytes());
+        }
+        self.data.push(0);
+
+        let indices = quantize(self.width as usize, pixels);
+        self.data.push(MIN_CODE_SIZE);
+        for block in lzw_encode(&indices).chunks(SUB_BLOCK_MAX) {
+            self.data.push(block.len() as u8);
+            self.data.extend_from_slice(block);
+        }
+        self.data.push(0);
+    }
+
+    /// Ends the GIF, returning the whole file.
+    pub fn finish(mut self) -> Vec<u8> {
+        self.data.push(0x3B);
+        self.data
+    }
+}
+
+
+/// The fixed palette: every combination of [`LEVELS`], then black for the few entries left over.
+fn palette() -> [u8; 256 * 3] {
+    let mut palette = [0; 256 * 3];
+    let [r_levels, g_levels, b_levels] = LEVELS;
+    let mut colors = palette.chunks_exact_mut(3);
+    for r in 0..r_levels {
+        for g in 0..g_levels {
+            for b in 0..b_levels {
+                let color = colors.next().expect("the palette should fit in 256 colors");
+                color.copy_from_slice(&[level_value(r, r_levels), level_value(g, g_levels), level_value(b, b_levels)]);
+            }
+        }
+    }
+    palette
+}
+
+
+/// The 8-bit value of one of a channel's evenly spaced levels.
+fn level_value(level: u8, levels: u8) -> u8 {
+    (level as u32 * 255 / (levels as u32 - 1)) as u8
+}
+
+
+/// Turns RGBA pixels into indices into the [palette], dithering each channel between its two closest levels.
+fn quantize(width: usize, pixels: &[u8]) -> Vec<u8> {
+    let [_, g_levels, b_levels] = LEVELS;
+    pixels
+        .chunks_exact(4)
+        .enumerate()
+        .map(|(i, pixel)| {
+            let (x, y) = (i % width.max(1), i / width.max(1));
+            let threshold = (BAYER[y % 4][x % 4] as f32 + 0.5) / 16.0;
+            let [r, g, b] = [0, 1, 2].map(|c| {
+                let scaled = pixel[c] as f32 / 255.0 * (LEVELS[c] - 1) as f32;
+                (scaled + threshold).floor().min((LEVELS[c] - 1) as f32) as u8
+            });
+            (r * g_levels + g) * b_levels + b
+        })
+        .collect()
+}
+
+
+/// Compresses palette indices with GIF's flavour of LZW, returning the packed codes (not yet split into sub-blocks).
+fn lzw_encode(indices: &[u8]) -> Vec<u8> {
+    let clear_code = 1u16 << MIN_CODE_SIZE;
+    let end_code = clear_code + 1;
+
+    let mut writer = BitWriter::default();
+    let mut code_size = MIN_CODE_SIZE + 1;
+    let mut last_code = end_code;
+    let mut table = HashMap::<(u16, u8), u16>::new();
+
+    writer.write(clear_code, code_size);
+    let mut indices = indices.iter().copied();
+    if let Some(first) = indices.next() {
+        let mut prefix = first as u16;
+        for index in indices {
+            if let Some(&code) = table.get(&(prefix, index)) {
+                prefix = code;
+                continue;
+            }
+
+            writer.write(prefix, code_size);
+            last_code += 1;
+            table.insert((prefix, index), last_code);
+            if last_code >= 1 << code_size {
+                code_size += 1;
+            }
+            // Once the table is full, start it over.
+            if last_code == MAX_CODE {
+                writer.write(clear_code, code_size);
+                table.clear();
+                code_size = MIN_CODE_SIZE + 1;
+                last_code = end_code;
+            }
+            prefix = index as u16;
+        }
+        writer.write(prefix, code_size);
+    }
+    writer.write(end_code, code_size);
+    writer.finish()
+}
+
+
+/// Packs variable-length codes into bytes, least significant bit first.
+#[derive(Debug, Clone, Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u8,
+}
+
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
@@ -0,0 +1,25 @@
+//! The performance HUD: a small overlay in the corner of the window showing how long the last frame took to draw and
+//! how much it allocated, and the [breakdown][crate::LoadProfile] of where loading went after launching with
+//! `--profile`. It's toggled with F2.
+
+use crate::{is_counting_allocations, FrameProfile, LoadProfile};
+
+
+/// Shows the HUD in the window's top-right corner. It can't be clicked on, so it never gets in the way of the scene.
+pub fn show_performance_hud(ctx: &egui::Context, frame: &FrameProfile, load: &LoadProfile) {
+    let area = egui::Area::new("performance").anchor(egui::Align2::RIGHT_TOP, [-8.0, 8.0]).interactable(false);
+    area.show(ctx, |ui| {
+        egui::Frame::popup(ui.style()).show(ui, |ui| {
+            if let Some(stats) = &frame.last {
+                ui.monospace(format!("frame  {stats:8}"));
+            }
+            if !is_counting_allocations() {
+                ui.label("Allocations are only counted with the profile-alloc feature.");
+            }
+            if load.enabled {
+                ui.separator();
+                ui.monospace(format!("Loading:\n{}", load.to_string().trim_end()));
+            }
+        });
+    });
+}
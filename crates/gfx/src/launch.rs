@@ -0,0 +1,273 @@
+//! Starting the viewer in a particular state from the command line, e.g. to take screenshots from a script:
+//!
+//! ```text
+//! ff7-viewer view char.lgp --model AAAA.P --screenshot out.png --exit
+//! ```
+//!
+//! Or to record a [turntable][crate::TurntableRecorder] with `--turntable`.
+//!
+//! The binary also has an `info` [command][Command] for summarizing an archive without opening a window (see
+//! [`InfoOptions`]), and an `lzss` one for converting standalone files (see [`LzssOptions`]).
+//!
+//! Wrappers that want to show how loading is going can add `--json`; see [`ProgressEvent`][crate::ProgressEvent].
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::{Camera, TurntableOptions};
+
+
+/// An error from reading the viewer's command line.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum LaunchError {
+    #[error("{0} needs a value")]
+    MissingValueError(String),
+
+    #[error("unknown option {0}")]
+    UnknownOptionError(String),
+
+    #[error("{0} is not supported yet")]
+    UnsupportedOptionError(String),
+
+    #[error("unexpected argument \"{0}\"")]
+    ExtraArgumentError(String),
+
+    #[error("missing {0}")]
+    MissingArgumentError(String),
+
+    #[error("\"{1}\" is not a valid value for {0}")]
+    InvalidValueError(String, String),
+}
+
+
+/// What the binary has been asked to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// Open the viewer. This is the default, so `view` can be left out.
+    View(LaunchOptions),
+    /// Print a summary of an archive, and optionally a model from it.
+    Info(InfoOptions),
+    /// Compress or decompress a standalone LZSS file.
+    Lzss(LzssOptions),
+}
+
+
+impl Command {
+    /// Reads a command from command line arguments, not including the program's name. Arguments that don't start with
+    /// the name of a command are the [viewer's][LaunchOptions].
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, LaunchError> {
+        let mut args = args.into_iter().peekable();
+        match args.peek().map(String::as_str) {
+            Some("view") => {
+                args.next();
+                LaunchOptions::from_args(args).map(Self::View)
+            },
+            Some("info") => {
+                args.next();
+                InfoOptions::from_args(args).map(Self::Info)
+            },
+            Some("lzss") => {
+                args.next();
+                LzssOptions::from_args(args).map(Self::Lzss)
+            },
+            _ => LaunchOptions::from_args(args).map(Self::View),
+        }
+    }
+}
+
+
+/// How the viewer should start.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaunchOptions {
+    /// The archive to load a model from.
+    pub archive: Option<PathBuf>,
+    /// The name of the P or HRC file to show from the archive. Without one, the archive's first P file is shown.
+    pub model: Option<String>,
+    /// The name of an A file from the archive to play on the model, which must be an HRC file.
+    pub animation: Option<String>,
+    /// Where the camera starts, given as `<yaw>,<pitch>,<distance>` with the angles in degrees. Without one, it starts
+    /// in front of the model.
+    pub camera: Option<Camera>,
+    /// A file to keep [camera bookmarks][crate::CameraBookmarks] in. It's read when the viewer starts, and rewritten
+    /// whenever a bookmark is added or removed. Without one, bookmarks only last until the viewer closes.
+    pub bookmarks: Option<PathBuf>,
+    /// Where to save a PNG of the first complete frame.
+    pub screenshot: Option<PathBuf>,
+    /// Record the camera turning once around the model, starting from the first complete frame. Set with
+    /// `--turntable <path>`, and optionally `--turntable-frames <count>` and `--turntable-fps <rate>`.
+    pub turntable: Option<TurntableOptions>,
+    /// Close the viewer as soon as the first complete frame has been drawn (and saved, with
+    /// [`screenshot`][Self::screenshot]), or once the [`turntable`][Self::turntable] has been recorded.
+    pub exit: bool,
+    /// Print [progress events][crate::ProgressEvent] to stdout while loading, as JSON, one per line.
+    pub json: bool,
+    /// Measure how long each stage of loading takes and how much it allocates, log a breakdown afterwards, and start
+    /// with the [performance HUD][crate::show_performance_hud] showing. See [`LoadProfile`][crate::LoadProfile].
+    pub profile: bool,
+}
+
+
+impl LaunchOptions {
+    /// Reads the options from command line arguments, not including the program's name: an optional archive path,
+    /// followed by any of `--model <name>`, `--anim <name>`, `--camera <yaw,pitch,distance>`, `--bookmarks <path>`,
+    /// `--screenshot <path>`, `--turntable <path>`, `--turntable-frames <count>`, `--turntable-fps <rate>`, `--exit`,
+    /// `--json`, and `--profile`.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, LaunchError> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+
+        // The turntable's frame count and rate can come before its path, so they're only put together at the end.
+        let (mut turntable_frames, mut turntable_fps) = (None, None);
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| LaunchError::MissingValueError(arg.clone()));
+            match arg.as_str() {
+                "--model" => options.model = Some(value()?),
+                "--anim" => options.animation = Some(value()?),
+                "--bookmarks" => options.bookmarks = Some(value()?.into()),
+                "--screenshot" => options.screenshot = Some(value()?.into()),
+                "--turntable" => options.turntable = Some(TurntableOptions::new(value()?)),
+                "--exit" => options.exit = true,
+                "--json" => options.json = true,
+                "--profile" => options.profile = true,
+                "--camera" => {
+                    let value = value()?;
+                    let camera = parse_camera(&value).ok_or(LaunchError::InvalidValueError(arg, value))?;
+                    options.camera = Some(camera);
+                },
+                "--turntable-frames" => {
+                    let value = value()?;
+                    let frames = value.parse::<u32>().ok().filter(|&frames| frames > 0);
+                    turntable_frames = Some(frames.ok_or(LaunchError::InvalidValueError(arg, value))?);
+                },
+                "--turntable-fps" => {
+                    let value = value()?;
+                    let fps = value.parse::<f32>().ok().filter(|&fps| fps.is_finite() && fps > 0.0);
+                    turntable_fps = Some(fps.ok_or(LaunchError::InvalidValueError(arg, value))?);
+                },
+                _ if arg.starts_with("--") => return Err(LaunchError::UnknownOptionError(arg)),
+                _ if options.archive.is_none() => options.archive = Some(arg.into()),
+                _ => return Err(LaunchError::ExtraArgumentError(arg)),
+            }
+        }
+
+        if let Some(turntable) = &mut options.turntable {
+            turntable.frames = turntable_frames.unwrap_or(turntable.frames);
+            turntable.frame_rate = turntable_fps.unwrap_or(turntable.frame_rate);
+        }
+
+        Ok(options)
+    }
+}
+
+
+/// Options for the `info` command, which loads an archive (and a model from it) like the viewer would, but only prints
+/// what it found:
+///
+/// ```text
+/// ff7-viewer info char.lgp --model AAAA.HRC --profile
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfoOptions {
+    pub archive: PathBuf,
+    /// The name of a P or HRC file from the archive to load.
+    pub model: Option<String>,
+    /// The name of an A file from the archive to load with the model.
+    pub animation: Option<String>,
+    /// Print a [breakdown][crate::LoadProfile] of how long each stage of loading took and how much it allocated.
+    pub profile: bool,
+}
+
+
+impl InfoOptions {
+    /// Reads the options from the arguments after `info`: the archive's path, and any of `--model <name>`,
+    /// `--anim <name>`, and `--profile`.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, LaunchError> {
+        let (mut archive, mut model, mut animation, mut profile) = (None, None, None, false);
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or_else(|| LaunchError::MissingValueError(arg.clone()));
+            match arg.as_str() {
+                "--model" => model = Some(value()?),
+                "--anim" => animation = Some(value()?),
+                "--profile" => profile = true,
+                _ if arg.starts_with("--") => return Err(LaunchError::UnknownOptionError(arg)),
+                _ if archive.is_none() => archive = Some(PathBuf::from(arg)),
+                _ => return Err(LaunchError::ExtraArgumentError(arg)),
+            }
+        }
+
+        let archive = archive.ok_or_else(|| LaunchError::MissingArgumentError("archive path".to_owned()))?;
+        Ok(Self { archive, model, animation, profile })
+    }
+}
+
+
+/// Reads a camera position in the form `<yaw>,<pitch>,<distance>`, with the angles in degrees.
+fn parse_camera(value: &str) -> Option<Camera> {
+    let mut fields = value.split(',').map(|field| field.trim().parse::<f32>().ok());
+    let (Some(Some(yaw)), Some(Some(pitch)), Some(Some(distance)), None) =
+        (fields.next(), fields.next(), fields.next(), fields.next())
+    else {
+        return None;
+    };
+
+    let mut camera = Camera { distance: 1.0, ..Camera::default() };
+    camera.orbit(yaw.to_radians(), pitch.to_radians());
+    camera.zoom(distance);
+    Some(camera)
+}
+
+
+/// Which way the `lzss` command converts its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LzssMode {
+    Compress,
+    Decompress,
+}
+
+
+/// Options for the `lzss` command, which converts a standalone file rather than an entry in an archive:
+///
+/// ```text
+/// ff7-viewer lzss decompress md1stin.lzs md1stin.bin
+/// ff7-viewer lzss compress --raw md1stin.bin md1stin.raw
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LzssOptions {
+    pub mode: LzssMode,
+    pub input: PathBuf,
+    pub output: PathBuf,
+    /// Whether the compressed data has the 4-byte length header that the game's files start with (`--with-header`,
+    /// the default), or is bare LZSS data like many other tools pass around (`--raw`).
+    pub header: bool,
+}
+
+
+impl LzssOptions {
+    /// Reads the options from the arguments after `lzss`: `compress` or `decompress`, then the input and output paths.
+    /// `--raw` and `--with-header` can go anywhere; if both are given, the last one wins.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, LaunchError> {
+        let (mut mode, mut paths, mut header) = (None, Vec::new(), true);
+
+        for arg in args {
+            match arg.as_str() {
+                "--raw" => header = false,
+                "--with-header" => header = true,
+                _ if arg.starts_with("--") => return Err(LaunchError::UnknownOptionError(arg)),
+                "compress" if mode.is_none() => mode = Some(LzssMode::Compress),
+                "decompress" if mode.is_none() => mode = Some(LzssMode::Decompress),
+                _ if mode.is_none() => return Err(LaunchError::InvalidValueError("lzss".to_owned(), arg)),
+                _ if paths.len() < 2 => paths.push(PathBuf::from(arg)),
+                _ => return Err(LaunchError::ExtraArgumentError(arg)),
+            }
+        }
+
+        let mode = mode.ok_or_else(|| LaunchError::MissingArgumentError("compress or decompress".to_owned()))?;
+        let [input, output] = <[PathBuf; 2]>::try_from(paths)
+            .map_err(|_| LaunchError::MissingArgumentError("input and output paths".to_owned()))?;
+        Ok(Self { mode, input, output, header })
+    }
+}
@@ -1,201 +1,1021 @@
 #![allow(dead_code)] // Temporary
 
+use std::path::Path;
+
 use gl::types::*;
-use glfw::WindowMode::Windowed;
-use glfw::{Action, Context, Key, Window, WindowEvent};
+use ff7::char::{transform_point, Matrix, Model, Pose, RgbaImage, Skeleton, DEFAULT_SMOOTHING_ANGLE, IDENTITY};
+use ff7::extract::{FileKind, LGPFile};
+use glfw::{Action, Context, Key, Modifiers, Window, WindowEvent};
+
+
+mod animation;
+mod compress;
+mod context;
+mod controls;
+mod frame;
+mod gif;
+mod hud;
+mod launch;
+mod math;
+mod mesh;
+mod panel;
+mod picker;
+mod profile;
+mod progress;
+mod render;
+mod resources;
+mod retro;
+mod screenshot;
+mod sharing;
+mod skinning;
+mod textures;
+mod timeline;
+mod turntable;
+mod ui;
+mod upload;
+mod views;
+
+pub use animation::*;
+pub use compress::*;
+pub use context::*;
+pub use controls::*;
+pub use frame::*;
+pub use gif::*;
+pub use hud::*;
+pub use launch::*;
+pub use math::*;
+pub use mesh::*;
+pub use panel::*;
+pub use picker::*;
+pub use profile::*;
+pub use progress::*;
+pub use render::*;
+pub use resources::*;
+pub use retro::*;
+pub use screenshot::*;
+pub use sharing::*;
+pub use skinning::*;
+pub use textures::*;
+pub use timeline::*;
+pub use turntable::*;
+pub use ui::*;
+pub use upload::*;
+pub use views::*;
 
 
 pub trait ToBuffer {}
 
 
-#[allow(dead_code)]
-struct Vertex {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-    pub r: f32,
-    pub g: f32,
-    pub b: f32,
+/// Tracks the size and DPI scaling of the window's drawable surface.
+///
+/// GLFW reports window sizes in screen coordinates, which only match pixels on monitors with a content scale of 1.0.
+/// Everything that touches GL (viewport, render targets) should use [`framebuffer_size`][Self::framebuffer_size],
+/// and anything sized in "logical" units (e.g., UI elements) should be multiplied by [`ui_scale`][Self::ui_scale].
+#[derive(Debug, Clone, Copy)]
+pub struct Display {
+    /// The size of the framebuffer, in pixels.
+    pub framebuffer_size: (i32, i32),
+    /// The ratio between the current DPI and the platform's default DPI.
+    pub content_scale: (f32, f32),
+    /// How much larger the user wants the UI to be, on top of the content scale. Kept up to date with
+    /// [`UiSettings::scale`].
+    pub user_scale: f32,
+}
+
+
+impl Display {
+    pub fn from_window(window: &Window) -> Self {
+        Self {
+            framebuffer_size: window.get_framebuffer_size(),
+            content_scale: window.get_content_scale(),
+            user_scale: 1.0,
+        }
+    }
+
+    /// A single scale factor to use for UI elements: the user's scale times the content scale. Uses the larger of the
+    /// content scale's two axes, since they are only different on very unusual displays.
+    pub fn ui_scale(&self) -> f32 {
+        self.content_scale.0.max(self.content_scale.1) * self.user_scale
+    }
+
+    /// Updates the GL viewport to cover the entire framebuffer.
+    pub fn update_viewport(&self) {
+        let (width, height) = self.framebuffer_size;
+        unsafe { gl::Viewport(0, 0, width, height) };
+    }
 }
 
 
+/// Everything about how the viewer renders that can be changed at runtime.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Settings {
+    pub frame: FrameSettings,
+    pub retro: RetroSettings,
+    pub render: RenderSettings,
+    pub lighting: LightSettings,
+    pub ui: UiSettings,
+    /// Whether or not to show the number of live GL objects in the window's title bar, to help spot leaks.
+    pub show_resource_counts: bool,
+    /// Whether or not to show the [performance HUD][show_performance_hud].
+    pub show_performance: bool,
+    /// How textures are filtered. Changes only apply to textures loaded afterwards.
+    pub texture_filter: TextureFilter,
+    /// Whether or not to compress textures to BC7 as they are loaded, when the GPU supports it. See
+    /// [`TranscodeCache`].
+    pub compress_textures: bool,
+}
+
+
+const WINDOW_TITLE: &str = "Hello, GLFW!";
+
 const VERT_SHADER_SOURCE: &str = include_str!("./shaders/vert.glsl");
 const FRAG_SHADER_SOURCE: &str = include_str!("./shaders/frag.glsl");
 
-const VERTEX_COUNT: usize = 3;
-const VERTICES: [Vertex; VERTEX_COUNT] = [
-    Vertex {
-        x: -0.5,
-        y: -0.5,
-        z: 0.0,
-        r: 1.0,
-        g: 0.0,
-        b: 0.0,
-    },
-    Vertex {
-        x: 0.5,
-        y: -0.5,
-        z: 0.0,
-        r: 0.0,
-        g: 1.0,
-        b: 0.0,
-    },
-    Vertex {
-        x: 0.0,
-        y: 0.5,
-        z: 0.0,
-        r: 0.0,
-        g: 0.0,
-        b: 1.0,
-    },
+/// What to draw when no model has been loaded.
+const PLACEHOLDER_TRIANGLE: [MeshVertex; 3] = [
+    MeshVertex { position: [-0.5, -0.5, 0.0], color: [1.0, 0.0, 0.0], normal: [0.0, 0.0, 1.0], uv: [0.0; 2], bone: 0 },
+    MeshVertex { position: [0.5, -0.5, 0.0], color: [0.0, 1.0, 0.0], normal: [0.0, 0.0, 1.0], uv: [1.0, 0.0], bone: 0 },
+    MeshVertex { position: [0.0, 0.5, 0.0], color: [0.0, 0.0, 1.0], normal: [0.0, 0.0, 1.0], uv: [0.5, 1.0], bone: 0 },
 ];
 
 
-pub fn main() {
-    let mut glfw = glfw::init(glfw::FAIL_ON_ERRORS).unwrap();
+/// One of a loaded model's meshes.
+struct LoadedMesh {
+    data: MeshData,
+    /// The bone that the mesh is attached to, if it has one.
+    bone: Option<usize>,
+    /// The index in [`LoadedModel::textures`] of each of the mesh's textures, in the order that its groups refer to
+    /// them.
+    textures: Vec<Option<usize>>,
+}
 
-    // Request OpenGL version 4.6
-    glfw.window_hint(glfw::WindowHint::ContextVersion(4, 6));
-    glfw.window_hint(glfw::WindowHint::OpenGlProfile(glfw::OpenGlProfileHint::Core));
-    glfw.window_hint(glfw::WindowHint::DoubleBuffer(true));
-    glfw.window_hint(glfw::WindowHint::FocusOnShow(true));
-    glfw.window_hint(glfw::WindowHint::Focused(true));
 
-    let (mut window, events) = glfw
-        .create_window(512, 512, "Hello, GLFW!", Windowed)
-        .expect("Could not create an OpenGL 4.6 window.");
+/// A model that has been loaded and is ready to upload.
+struct LoadedModel<'a> {
+    meshes: Vec<LoadedMesh>,
+    /// The name of each of the model's textures, its image decoded with its first palette (if that worked), and whether
+    /// or not that palette is color-keyed.
+    textures: Vec<(String, Option<RgbaImage>, bool)>,
+    skeleton: Skeleton,
+    animation: Option<AnimationPlayer>,
+    /// The assembled HRC model, kept so that other animations can be bound to it. `None` for lone P files.
+    source: Option<Model<'a>>,
+    /// The names of the archive's animations that fit the model.
+    animations: Vec<&'a str>,
+    /// Everything that went wrong while loading the model that didn't stop it from being shown, for the control
+    /// panel to list.
+    problems: Vec<String>,
+}
 
-    // Pass OpenGL load calls to GLFW
-    gl::load_with(|s| window.get_proc_address(s));
 
-    glfw.set_swap_interval(glfw::SwapInterval::Sync(1));
+impl<'a> LoadedModel<'a> {
+    /// What to draw when no model has been loaded: a single triangle.
+    fn placeholder() -> Self {
+        let data = MeshData { vertices: PLACEHOLDER_TRIANGLE.to_vec(), indices: vec![0, 1, 2], groups: Vec::new() };
+        Self::unanimated(vec![LoadedMesh { data, bone: None, textures: Vec::new() }])
+    }
 
-    window.set_resizable(false);
-    window.set_key_polling(true);
-    window.make_current();
-
-    let (width, height) = window.get_framebuffer_size();
-    unsafe { gl::Viewport(0, 0, width, height) };
-
-    // Mutable because CreateBuffers will change these to the proper values
-    let mut vbo: GLuint = 0;
-
-    {
-        let size_of = std::mem::size_of::<[Vertex; VERTEX_COUNT]>()
-            .try_into()
-            .expect("Vertex data is too large.");
-        let pointer = VERTICES.as_ptr().cast();
-        unsafe {
-            // glCreateBuffers actually expects an array, but since an "array" is just a pointer, we just pass the
-            // single reference.
-            gl::CreateBuffers(1, &mut vbo);
-            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
-            gl::BufferData(gl::ARRAY_BUFFER, size_of, pointer, gl::STATIC_DRAW);
+    /// A model without a skeleton or textures, which can't be animated.
+    fn unanimated(meshes: Vec<LoadedMesh>) -> Self {
+        LoadedModel {
+            meshes,
+            textures: Vec::new(),
+            skeleton: Skeleton::default(),
+            animation: None,
+            source: None,
+            animations: Vec::new(),
+            problems: Vec::new(),
         }
     }
 
-    let vert_shader = unsafe { compile_shader(gl::VERTEX_SHADER, VERT_SHADER_SOURCE) }.unwrap();
-    let frag_shader = unsafe { compile_shader(gl::FRAGMENT_SHADER, FRAG_SHADER_SOURCE) }.unwrap();
+    /// The model's current pose: the animation's, if it has one, or else its rest pose.
+    fn pose(&self) -> Pose {
+        let animated = self.animation.as_ref().and_then(|player| player.pose(&self.skeleton));
+        animated.unwrap_or_else(|| self.skeleton.rest_pose())
+    }
 
-    let program = unsafe { gl::CreateProgram() };
-    unsafe {
-        gl::AttachShader(program, vert_shader);
-        gl::AttachShader(program, frag_shader);
-        gl::LinkProgram(program);
+    /// The bones that have meshes attached to them, by index and name, so that they can be framed on their own.
+    fn parts(&self) -> Vec<(usize, &'a str)> {
+        let Some(source) = &self.source else {
+            return Vec::new();
+        };
+        let has_meshes = |bone: usize| source.meshes_of(bone).next().is_some();
+        source.bones.iter().enumerate().filter(|&(i, _)| has_meshes(i)).map(|(i, bone)| (i, bone.name)).collect()
     }
 
-    // Error check program
-    unsafe {
-        let mut success = 0;
-        gl::GetProgramiv(program, gl::LINK_STATUS, &mut success);
-        if (success as GLboolean) == gl::FALSE {
-            let mut log_size = 0;
-            gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut log_size);
+    /// The box around the model in its current pose, once it has been moved by `transform`. With a `bone`, only the
+    /// meshes attached to that bone are counted.
+    fn bounds(&self, transform: &Matrix, bone: Option<usize>) -> Option<([f32; 3], [f32; 3])> {
+        let pose = self.pose();
+        self.meshes
+            .iter()
+            .filter(|mesh| bone.is_none() || mesh.bone == bone)
+            .filter_map(|mesh| mesh.data.bounds(&multiply_matrices(transform, &bone_transform(&pose, mesh.bone))))
+            .reduce(|a, b| ([0, 1, 2].map(|i| a.0[i].min(b.0[i])), [0, 1, 2].map(|i| a.1[i].max(b.1[i]))))
+    }
 
-            let mut buffer = vec![0; log_size as usize];
-            gl::GetProgramInfoLog(program, log_size, std::ptr::null_mut(), buffer.as_mut_ptr().cast());
+    /// Fits the model to the view in its current pose. The model is then left there as it moves.
+    fn fit_transform(&self) -> Matrix {
+        let pose = self.pose();
+        let transforms = self.meshes.iter().map(|mesh| bone_transform(&pose, mesh.bone)).collect::<Vec<_>>();
+        fit_transform(self.meshes.iter().map(|mesh| &mesh.data).zip(&transforms))
+    }
 
-            let log_output = String::from_utf8_lossy(&buffer);
-            panic!("{}", log_output.into_owned());
-        }
+    /// Queues up the model's meshes and textures to be uploaded. Textures are shared with any other models that use
+    /// the same images from the same archive.
+    fn upload(
+        &self,
+        archive_name: &str,
+        textures: &mut TextureManager,
+        uploads: &mut UploadQueue,
+        filter: TextureFilter,
+    ) -> Vec<UploadedMesh> {
+        let uploaded = self
+            .textures
+            .iter()
+            .map(|(name, image, color_key)| {
+                let key = TextureKey::new(archive_name, name, 0);
+                let texture = textures.load(uploads, key, image.as_ref()?, filter);
+                Some(MeshTexture { texture, color_key: *color_key })
+            })
+            .collect::<Vec<_>>();
+
+        // Every texture that a mesh's RSD file lists gets something, even if it's only the placeholder. Lone P files
+        // don't list any, so their groups are still drawn with just their vertex colors.
+        let mut mesh_textures = |mesh: &LoadedMesh| MeshTextures {
+            textures: mesh
+                .textures
+                .iter()
+                .map(|&i| {
+                    let loaded = i.and_then(|i| uploaded.get(i).cloned().flatten());
+                    let placeholder = || MeshTexture { texture: textures.placeholder(uploads), color_key: false };
+                    Some(loaded.unwrap_or_else(placeholder))
+                })
+                .collect(),
+        };
+        self.meshes.iter().map(|mesh| (GlMesh::new(&mesh.data), mesh.bone, mesh_textures(mesh))).collect()
     }
+}
+
 
-    unsafe {
-        gl::DeleteShader(vert_shader);
-        gl::DeleteShader(frag_shader);
+/// A mesh that has been uploaded, with the bone that it's attached to and the textures that it's drawn with.
+type UploadedMesh = (GlMesh, Option<usize>, MeshTextures);
+
+
+/// Where a mesh attached to the given bone goes in a pose. Meshes without a bone aren't moved.
+fn bone_transform(pose: &Pose, bone: Option<usize>) -> Matrix {
+    bone.and_then(|bone| pose.bones.get(bone)).copied().unwrap_or(IDENTITY)
+}
+
+
+/// Moves the camera to frame the model (as it has been moved by `transform`), or just the meshes attached to one of
+/// its bones. The camera keeps facing the same way.
+fn frame_camera(camera: &mut Camera, model: &LoadedModel, transform: &Matrix, part: Option<usize>, display: &Display) {
+    let (width, height) = display.framebuffer_size;
+    if let Some((min, max)) = model.bounds(transform, part) {
+        camera.frame(min, max, width as f32 / height.max(1) as f32);
+    }
+}
+
+
+/// Reads the archive that the viewer was launched with. Returns `None` (after logging and reporting why) if it can't
+/// be.
+fn read_archive(path: &Path, progress: &ProgressReporter, profile: &mut LoadProfile) -> Option<Vec<u8>> {
+    let item = path.display().to_string();
+    progress.start(&item);
+
+    let data = profile.measure("read", || std::fs::read(path));
+    data.map_err(|err| {
+        let message = format!("Could not read {item}: {err}");
+        log::error!("{message}");
+        progress.error(&item, &message);
+    })
+    .ok()
+}
+
+
+/// Parses the table of contents of an archive read by [`read_archive`]. Returns `None` (after logging and reporting
+/// why) if it can't be.
+fn parse_archive<'a>(
+    path: &Path,
+    data: &'a [u8],
+    progress: &ProgressReporter,
+    profile: &mut LoadProfile,
+) -> Option<LGPFile<'a>> {
+    let item = path.display().to_string();
+    match profile.measure("toc", || LGPFile::from_bytes(data)) {
+        Ok(archive) => {
+            progress.done(&item);
+            Some(archive)
+        },
+        Err(err) => {
+            let message = format!("Could not parse {item}: {err}");
+            log::error!("{message}");
+            progress.error(&item, &message);
+            None
+        },
     }
+}
+
 
-    let mut vao: GLuint = 0;
-    unsafe {
-        gl::CreateVertexArrays(1, &mut vao);
-        gl::BindVertexArray(vao);
+/// Loads a model from the archive. Without a model name, the archive's first P file is used. HRC files are assembled
+/// into a skeleton, which is posed by the named `animation` if there is one. Returns `None` (after logging and
+/// reporting why) if there's nothing to load.
+fn load_model<'a>(
+    archive: &LGPFile<'a>,
+    path: &str,
+    name: Option<&str>,
+    animation: Option<&str>,
+    progress: &ProgressReporter,
+    profile: &mut LoadProfile,
+) -> Option<LoadedModel<'a>> {
+    let name = match name {
+        Some(name) => name.to_owned(),
+        None => match archive.names_of_kind(FileKind::Polygon).min() {
+            Some(first) => first.to_owned(),
+            None => {
+                log::error!("{path} has no P files.");
+                return None;
+            },
+        },
+    };
+
+    progress.start(&name);
+    let fail = |message: String| {
+        log::error!("{message}");
+        progress.error(&name, &message);
+    };
+
+    if FileKind::from_name(&name) == FileKind::Hierarchy {
+        return match profile.measure("parse", || Model::assemble(archive, &name)) {
+            Some(Ok(model)) => {
+                log::info!("Showing {name} from {path}, with {} meshes.", model.meshes.len());
+                if model.bones.len() >= MAX_BONES {
+                    let (count, limit) = (model.bones.len(), MAX_BONES - 1);
+                    log::warn!("{name} has {count} bones, but only the first {limit} can move.");
+                }
+                let mut problems = Vec::new();
+                for missing in &model.missing {
+                    problems.push(format!("{missing} is not in the archive."));
+                    log::warn!("{name} refers to {missing}, which is not in {path}.");
+                }
+                for (file, err) in &model.failures {
+                    problems.push(format!("Could not parse {file}: {err}"));
+                    log::warn!("Could not parse {file}: {err}");
+                }
+
+                let total = model.meshes.len();
+                let mut meshes = Vec::with_capacity(total);
+                for (i, mesh) in model.meshes.iter().enumerate() {
+                    let mut data = profile.measure("mesh build", || MeshData::from_polygon(&mesh.polygon));
+                    data.attach_to_bone(Some(mesh.bone));
+                    meshes.push(LoadedMesh { data, bone: Some(mesh.bone), textures: mesh.textures.clone() });
+                    progress.progress(&name, i + 1, total);
+                }
+                progress.done(&name);
+
+                let animation = animation.and_then(|anim| {
+                    profile.measure("animation", || load_animation(archive, &model, anim, progress))
+                });
+                let textures = profile.measure("tex decode", || {
+                    model.textures
+                        .iter()
+                        .map(|texture| (texture.name.clone(), texture.texture.decode(0), texture.texture.is_keyed(0)))
+                        .collect::<Vec<_>>()
+                });
+                for (texture, _, _) in textures.iter().filter(|(_, image, _)| image.is_none()) {
+                    problems.push(format!("Could not decode {texture}."));
+                    log::warn!("Could not decode {texture}.");
+                }
+
+                let animations = model.compatible_animations(archive);
+                let skeleton = model.skeleton();
+                let source = Some(model);
+                Some(LoadedModel { meshes, textures, skeleton, animation, source, animations, problems })
+            },
+            Some(Err(err)) => {
+                fail(format!("Could not parse {name}: {err}"));
+                None
+            },
+            None => {
+                fail(format!("{path} has no file named {name}."));
+                None
+            },
+        };
     }
 
-    unsafe {
-        let v_size: i32 = std::mem::size_of::<Vertex>().try_into().unwrap();
-        let f_size: i32 = std::mem::size_of::<f32>().try_into().unwrap();
-        gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, v_size, (f_size * 0) as *const _);
-        gl::VertexAttribPointer(1, 3, gl::FLOAT, gl::FALSE, v_size, (f_size * 3) as *const _);
-        gl::EnableVertexAttribArray(0);
-        gl::EnableVertexAttribArray(1);
+    match profile.measure("parse", || archive.polygon(&name)) {
+        Some(Ok(mut polygon)) => {
+            log::info!("Showing {name} from {path}.");
+            if !polygon.has_usable_normals() && !polygon.generate_normals(DEFAULT_SMOOTHING_ANGLE) {
+                log::warn!("{name} has no usable normals, and too many polygons to generate them for.");
+            }
+            progress.done(&name);
+            if animation.is_some() {
+                log::warn!("Only HRC models can be animated, so the animation is ignored.");
+            }
+            // Without an RSD file, there's no way to know which textures a P file uses.
+            let data = profile.measure("mesh build", || MeshData::from_polygon(&polygon));
+            Some(LoadedModel::unanimated(vec![LoadedMesh { data, bone: None, textures: Vec::new() }]))
+        },
+        Some(Err(err)) => {
+            fail(format!("Could not parse {name}: {err}"));
+            None
+        },
+        None => {
+            fail(format!("{path} has no file named {name}."));
+            None
+        },
     }
+}
 
-    while !window.should_close() {
-        unsafe {
-            gl::ClearColor(0.17, 0.17, 0.17, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
 
-            gl::UseProgram(program);
-            gl::BindVertexArray(vao);
-            gl::DrawArrays(gl::TRIANGLES, 0, VERTEX_COUNT as i32);
+/// Loads an animation from the archive and binds it to a model, ready to play. Returns `None` (after logging and
+/// reporting why) if it can't be.
+fn load_animation(
+    archive: &LGPFile,
+    model: &Model,
+    name: &str,
+    progress: &ProgressReporter,
+) -> Option<AnimationPlayer> {
+    progress.start(name);
+    let fail = |message: String| {
+        log::error!("{message}");
+        progress.error(name, &message);
+    };
+
+    let animation = match archive.animation(name) {
+        Some(Ok(animation)) => animation,
+        Some(Err(err)) => {
+            fail(format!("Could not parse {name}: {err}"));
+            return None;
+        },
+        None => {
+            fail(format!("The archive has no file named {name}."));
+            return None;
+        },
+    };
+    let bound = model.bind(&animation).map_err(|err| fail(format!("Could not play {name}: {err}"))).ok()?;
+
+    log::info!("Playing {name}, which has {} frames.", bound.frame_count());
+    progress.done(name);
+    Some(AnimationPlayer::new(bound))
+}
+
+
+/// Prints a summary of an archive, and of a model from it if one is named, without opening a window. With `--profile`,
+/// loading is measured like it is in the viewer (up until the model would be uploaded, which needs a window), and the
+/// breakdown is printed too. Returns whether or not everything could be loaded; anything that couldn't has already
+/// been logged.
+pub fn info(options: &InfoOptions) -> bool {
+    simple_logger::SimpleLogger::new().with_level(log::LevelFilter::Warn).env().init().unwrap();
+
+    let progress = ProgressReporter::new(false);
+    let mut profile = LoadProfile::new(options.profile);
+    let path = options.archive.display().to_string();
+
+    let Some(data) = read_archive(&options.archive, &progress, &mut profile) else {
+        return false;
+    };
+    let Some(archive) = parse_archive(&options.archive, &data, &progress, &mut profile) else {
+        return false;
+    };
+    println!("{path}: {} files, created by {}", archive.len(), archive.creator);
+
+    let mut loaded = true;
+    if let Some(name) = &options.model {
+        match load_model(&archive, &path, Some(name), options.animation.as_deref(), &progress, &mut profile) {
+            Some(model) => {
+                let vertices = model.meshes.iter().map(|mesh| mesh.data.vertices.len()).sum::<usize>();
+                let triangles = model.meshes.iter().map(|mesh| mesh.data.indices.len() / 3).sum::<usize>();
+                print!("{name}: {} meshes, {vertices} vertices, {triangles} triangles", model.meshes.len());
+                println!(", {} textures, {} bones", model.textures.len(), model.skeleton.parents.len());
+            },
+            None => loaded = false,
         }
+    }
+
+    if profile.enabled {
+        println!("\nTime spent and memory allocated while loading:\n{profile}");
+    }
+    loaded
+}
+
+
+pub fn main(options: LaunchOptions) {
+    simple_logger::SimpleLogger::new().with_level(log::LevelFilter::Info).env().init().unwrap();
+
+    // Errors are only logged, since failing to create a context is expected on older hardware; see `create_window`.
+    let mut glfw = glfw::init(glfw::LOG_ERRORS).unwrap();
+
+    glfw.window_hint(glfw::WindowHint::DoubleBuffer(true));
+    glfw.window_hint(glfw::WindowHint::FocusOnShow(true));
+    glfw.window_hint(glfw::WindowHint::Focused(true));
+
+    // Have the window's size follow the monitor's content scale, so that a 512x512 window isn't tiny on a high-DPI
+    // monitor. On macOS, use a full-resolution framebuffer instead of letting the OS upscale a low-resolution one.
+    glfw.window_hint(glfw::WindowHint::ScaleToMonitor(true));
+    glfw.window_hint(glfw::WindowHint::CocoaRetinaFramebuffer(true));
+
+    // Shading is done in linear space, so the default framebuffer needs to convert back to sRGB on write.
+    glfw.window_hint(glfw::WindowHint::SRgbCapable(true));
+    // Models are drawn with depth testing, so ask for a depth buffer explicitly rather than relying on the default.
+    glfw.window_hint(glfw::WindowHint::DepthBits(Some(24)));
+
+    let (mut window, events, gl_version) =
+        create_window(&mut glfw, 512, 512, WINDOW_TITLE).expect("Could not create an OpenGL 3.3 or newer window.");
+
+    if !enable_debug_output(&glfw, gl_version) {
+        log::info!("OpenGL debug output is not available.");
+    }
+
+    let mut settings = Settings { show_performance: options.profile, ..Settings::default() };
+    settings.frame.apply(&mut glfw);
+
+    window.set_resizable(true);
+    window.set_key_polling(true);
+    window.set_refresh_polling(true);
+    window.set_framebuffer_size_polling(true);
+    window.set_content_scale_polling(true);
+    window.set_mouse_button_polling(true);
+    window.set_cursor_pos_polling(true);
+    window.set_scroll_polling(true);
+    window.set_char_polling(true);
+
+    let mut display = Display::from_window(&window);
+    display.update_viewport();
+
+    unsafe { gl::Enable(gl::FRAMEBUFFER_SRGB) };
+
+    let vert_source = format!("{}#define MAX_BONES {MAX_BONES}\n{VERT_SHADER_SOURCE}", gl_version.glsl_header());
+    let frag_source = format!("{}{FRAG_SHADER_SOURCE}", gl_version.glsl_header());
+    let textured_frag_source = format!("{}#define TEXTURED\n{FRAG_SHADER_SOURCE}", gl_version.glsl_header());
+    let program = GlProgram::new(&vert_source, &frag_source).unwrap();
+    let textured_program = GlProgram::new(&vert_source, &textured_frag_source).unwrap();
+    bind_bone_block(&program);
+    bind_bone_block(&textured_program);
+    let mut bones = BoneBuffer::new();
+
+    let progress = ProgressReporter::new(options.json);
+    let mut profile = LoadProfile::new(options.profile);
+
+    // The archive is kept around for as long as the viewer is open, so that other models can be loaded from it.
+    let archive_name = options.archive.as_ref().map(|path| path.display().to_string()).unwrap_or_default();
+    let archive_data = options.archive.as_ref().and_then(|path| read_archive(path, &progress, &mut profile));
+    let archive = options.archive.as_ref().zip(archive_data.as_ref()).and_then(|(path, data)| {
+        parse_archive(path, data, &progress, &mut profile)
+    });
+
+    let mut model = archive
+        .as_ref()
+        .and_then(|archive| {
+            let (name, animation) = (options.model.as_deref(), options.animation.as_deref());
+            load_model(archive, &archive_name, name, animation, &progress, &mut profile)
+        })
+        .unwrap_or_else(LoadedModel::placeholder);
+    let mut transform = model.fit_transform();
+
+    let mut uploads = UploadQueue::new();
+    let mut textures = TextureManager::new();
+
+    let mut meshes = profile.measure("upload", || {
+        model.upload(&archive_name, &mut textures, &mut uploads, settings.texture_filter)
+    });
+    if profile.enabled {
+        log::info!("Time spent and memory allocated while loading:\n{profile}");
+    }
+
+    // Bookmarks that can't be read are left alone, rather than being overwritten by the next one that's saved.
+    let bookmarks = options.bookmarks.as_ref().map_or_else(CameraBookmarks::new, |path| {
+        CameraBookmarks::load(path).unwrap_or_else(|err| {
+            log::error!("Could not read bookmarks from {}: {err}", path.display());
+            CameraBookmarks::new()
+        })
+    });
+
+    let mut ui = Ui::new(gl_version.glsl_header());
+    let picker = ModelPicker::new(archive.iter().flat_map(|archive| archive.names_of_kind(FileKind::Hierarchy)));
+    let mut panel = ControlPanel::new(picker, bookmarks);
+    panel.picker.current = options.model.clone();
+    panel.problems = model.problems.clone();
+    panel.camera.set_parts(model.parts());
+    panel.animations.set_animations(model.animations.iter().copied());
+    panel.animations.current = options.animation.clone().filter(|_| model.animation.is_some());
+
+    let mut camera = options.camera.unwrap_or_default();
+    let mut controls = OrbitControls::new();
 
-        window.swap_buffers();
-        glfw.poll_events();
+    // Animations play in wall-clock time, regardless of how often frames are drawn.
+    let mut last_time = glfw.get_time();
+
+    let mut limiter = FrameLimiter::new(&glfw);
+    let mut frame_profile = FrameProfile::new();
+    let mut needs_redraw = true;
+
+    let mut retro_target: Option<RenderTarget> = None;
+
+    // Whether the first complete frame has been drawn yet, for `--screenshot` and `--exit`.
+    let mut first_frame_done = false;
+    // The `--turntable` being recorded, which starts from the first complete frame.
+    let mut turntable: Option<TurntableRecorder> = None;
+
+    while !window.should_close() {
+        // A minimized window has a zero-sized framebuffer, which can't be drawn to (or have a projection computed for).
+        let minimized = display.framebuffer_size.0 <= 0 || display.framebuffer_size.1 <= 0;
+
+        if minimized {
+            glfw.wait_events();
+        } else if needs_redraw || settings.frame.redraw_mode == RedrawMode::Continuous {
+            frame_profile.begin();
+
+            // Keep drawing frames until everything has been uploaded or while an animation is playing, even when only
+            // redrawing on demand.
+            uploads.process(settings.frame.upload_budget);
+            let now = glfw.get_time();
+            if let Some(player) = &mut model.animation {
+                // While recording a turntable, animations move on by exactly one of its frames each time, no matter
+                // how long the frame took to draw and save.
+                player.advance(turntable.as_ref().map_or(now - last_time, TurntableRecorder::frame_time));
+            }
+            last_time = now;
+            let playing = model.animation.as_ref().is_some_and(|player| player.playing);
+            needs_redraw = !uploads.is_empty() || playing;
+
+            // In retro mode, draw to a low-resolution target first. It is recreated whenever the window changes size.
+            if settings.retro.enabled {
+                let size = settings.retro.target_size(display.framebuffer_size);
+                if retro_target.as_ref().map(RenderTarget::size) != Some(size) {
+                    retro_target = Some(RenderTarget::new(size));
+                }
+            } else {
+                retro_target = None;
+            }
+
+            if let Some(target) = &retro_target {
+                target.bind();
+            }
+
+            unsafe {
+                gl::ClearColor(0.17, 0.17, 0.17, 1.0);
+                gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+                gl::Enable(gl::DEPTH_TEST);
+                settings.render.apply();
+
+                let retro = &settings.retro;
+                let (width, height) = retro_target.as_ref().map_or(display.framebuffer_size, RenderTarget::size);
+                let projection = camera.projection_matrix(width as f32 / height.max(1) as f32);
+                let view = camera.view_matrix();
+
+                for program in [&program, &textured_program] {
+                    gl::UseProgram(program.id());
+                    set_uniform_bool(program.id(), "u_dither", retro.enabled && retro.dither);
+                    set_uniform_bool(program.id(), "u_affine", retro.enabled && retro.affine);
+                    set_uniform_bool(program.id(), "u_vertex_snap", retro.enabled && retro.vertex_snap);
+                    set_uniform_bool(program.id(), "u_flat", settings.render.flat_shading);
+
+                    let light = &settings.lighting;
+                    set_uniform_bool(program.id(), "u_lighting", light.enabled);
+                    set_uniform_vec3(program.id(), "u_light_direction", light.direction);
+                    set_uniform_vec3(program.id(), "u_light_color", light.color);
+                    set_uniform_vec3(program.id(), "u_ambient", light.ambient);
+                    set_uniform_vec2(program.id(), "u_resolution", [width as f32, height as f32]);
+                    set_uniform_mat4(program.id(), "u_transform", &transform);
+                    set_uniform_mat4(program.id(), "u_view", &view);
+                    set_uniform_mat4(program.id(), "u_projection", &projection);
+                }
+            }
+
+            // The pose is applied on the GPU, so each mesh is drawn as it was uploaded.
+            let pose = model.pose();
+            bones.update(&pose);
+
+            // Groups without textures (or with textures turned off) are drawn with just their vertex colors. Groups
+            // whose textures couldn't be loaded have the placeholder instead.
+            let prepare = |mesh_textures: &MeshTextures, texture: Option<u32>| {
+                let texture = mesh_textures.get(texture).filter(|_| settings.render.textures);
+                let program = if texture.is_some() { &textured_program } else { &program };
+                unsafe { gl::UseProgram(program.id()) };
+                if let Some(texture) = texture {
+                    bind_texture(&texture.texture);
+                    unsafe { set_uniform_bool(program.id(), "u_color_key", texture.color_key) };
+                }
+            };
+
+            for (mesh, _, mesh_textures) in &meshes {
+                mesh.draw_groups(|texture| prepare(mesh_textures, texture));
+            }
+
+            // Transparent groups go after everything opaque, from the back to the front, so that each one blends with
+            // everything behind it. The camera looks down -Z in view space, so the furthest group is the most negative.
+            let view_transform = multiply_matrices(&camera.view_matrix(), &transform);
+            let mut transparent = meshes
+                .iter()
+                .flat_map(|(mesh, bone, mesh_textures)| {
+                    let to_view = multiply_matrices(&view_transform, &bone_transform(&pose, *bone));
+                    mesh.groups()
+                        .iter()
+                        .filter(|group| group.blend.is_some() && group.index_count > 0)
+                        .map(move |group| {
+                            let depth = transform_point(&to_view, group.center)[2];
+                            (depth, mesh, mesh_textures, group)
+                        })
+                })
+                .collect::<Vec<_>>();
+            transparent.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            for (_, mesh, mesh_textures, group) in transparent {
+                prepare(mesh_textures, group.texture);
+                apply_blend_mode(group.blend);
+                mesh.draw_group(group);
+            }
+            apply_blend_mode(None);
+
+            if let Some(target) = &retro_target {
+                unsafe {
+                    gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+                    // The upscaled image may not cover the whole window, so letterbox it.
+                    gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+                    gl::Clear(gl::COLOR_BUFFER_BIT);
+                }
+
+                display.update_viewport();
+                target.blit_to_screen(settings.retro.screen_rect(display.framebuffer_size));
+            }
+
+            // The first frame that has nothing left to upload is the one the command line was asking for.
+            if !first_frame_done && uploads.is_empty() {
+                first_frame_done = true;
+                if let Some(path) = &options.screenshot {
+                    let item = path.display().to_string();
+                    progress.start(&item);
+                    let (width, height) = display.framebuffer_size;
+                    match save_screenshot(path, width as u32, height as u32) {
+                        Ok(()) => {
+                            log::info!("Saved a screenshot to {item}.");
+                            progress.done(&item);
+                        },
+                        Err(err) => {
+                            log::error!("Could not save a screenshot to {item}: {err}");
+                            progress.error(&item, err);
+                        },
+                    }
+                }
+                if let Some(turntable_options) = &options.turntable {
+                    let item = turntable_options.path.display().to_string();
+                    progress.start(&item);
+                    let (width, height) = display.framebuffer_size;
+                    match TurntableRecorder::new(turntable_options.clone(), width as u32, height as u32) {
+                        Ok(recorder) => turntable = Some(recorder),
+                        Err(err) => {
+                            log::error!("Could not start a turntable at {item}: {err}");
+                            progress.error(&item, err);
+                        },
+                    }
+                }
+                if options.exit && turntable.is_none() {
+                    window.set_should_close(true);
+                }
+            }
+
+            if let Some(recorder) = &mut turntable {
+                let item = recorder.path().display().to_string();
+                let mut result = recorder.capture(&mut camera);
+                let (captured, total) = recorder.progress();
+                progress.progress(&item, captured, total);
+                needs_redraw = true;
+
+                if result.is_err() || recorder.is_done() {
+                    let recorder = turntable.take().expect("the turntable should still be recording");
+                    result = result.and_then(|()| recorder.finish());
+                    match result {
+                        Ok(()) => {
+                            log::info!("Saved a turntable of {total} frames to {item}.");
+                            progress.done(&item);
+                        },
+                        Err(err) => {
+                            log::error!("Could not save a turntable to {item}: {err}");
+                            progress.error(&item, err);
+                        },
+                    }
+                    last_time = glfw.get_time();
+                    if options.exit {
+                        window.set_should_close(true);
+                    }
+                }
+            }
+
+            // The UI goes on top of everything, at the window's full resolution, but isn't part of screenshots.
+            let vsync = settings.frame.vsync;
+            let mut picked = ControlPanelOutput::default();
+            let previous_camera = camera;
+            display.user_scale = settings.ui.scale;
+            ui.apply_settings(&settings.ui);
+            let ui_animating = ui.run(&mut window, &display, glfw.get_time(), |ctx| {
+                picked = panel.show(ctx, &mut settings, model.animation.as_mut(), &mut camera);
+                if settings.show_performance {
+                    show_performance_hud(ctx, &frame_profile, &profile);
+                }
+            });
+            if picked.frame {
+                frame_camera(&mut camera, &model, &transform, panel.camera.part, &display);
+            }
+            needs_redraw |= ui_animating || camera != previous_camera;
+            if settings.frame.vsync != vsync {
+                settings.frame.apply(&mut glfw);
+            }
+
+            if let Some(((name, archive), source)) = picked.animation.zip(archive.as_ref()).zip(model.source.as_ref()) {
+                if let Some(mut player) = load_animation(archive, source, &name, &progress) {
+                    // Keep playing at the same speed, and looping (or not) the same way.
+                    if let Some(previous) = &model.animation {
+                        (player.frame_rate, player.looping) = (previous.frame_rate, previous.looping);
+                    }
+                    model.animation = Some(player);
+                    panel.animations.current = Some(name);
+                    last_time = glfw.get_time();
+                    needs_redraw = true;
+                }
+            }
+
+            if let Some((name, archive)) = picked.model.zip(archive.as_ref()) {
+                // Models picked from the UI aren't part of the launch, so they aren't profiled.
+                let mut profile = LoadProfile::new(false);
+                if let Some(loaded) = load_model(archive, &archive_name, Some(&name), None, &progress, &mut profile) {
+                    model = loaded;
+                    transform = model.fit_transform();
+                    meshes = model.upload(&archive_name, &mut textures, &mut uploads, settings.texture_filter);
+                    textures.prune();
+                    panel.picker.current = Some(name);
+                    panel.problems = model.problems.clone();
+                    panel.camera.set_parts(model.parts());
+                    panel.animations.set_animations(model.animations.iter().copied());
+                    panel.animations.current = None;
+                    last_time = glfw.get_time();
+                    needs_redraw = true;
+                }
+            }
+
+            if settings.show_resource_counts {
+                textures.prune();
+                window.set_title(&format!("{WINDOW_TITLE} [{}] [{}]", live_resources(), textures.stats()));
+            }
+
+            frame_profile.end();
+            window.swap_buffers();
+            limiter.wait(&glfw, &settings.frame);
+
+            glfw.poll_events();
+        } else {
+            // Nothing has changed since the last frame, so there's no point in drawing it again; sleep until the OS
+            // has something for us.
+            glfw.wait_events();
+        }
 
         for (_, event) in glfw::flush_messages(&events) {
-            handle_window_event(&mut window, event);
+            // Anything that comes through the event queue could change what's on screen.
+            needs_redraw = true;
+            ui.handle_event(&window, &display, &event);
+
+            // Whatever the UI is using doesn't go to the scene. Releasing a button always does, though, so that a
+            // drag that ends over the UI still ends.
+            let is_keyboard = matches!(event, WindowEvent::Key(..) | WindowEvent::Char(..));
+            let is_pointer =
+                matches!(event, WindowEvent::MouseButton(..) | WindowEvent::CursorPos(..) | WindowEvent::Scroll(..));
+            let is_release = matches!(event, WindowEvent::MouseButton(_, Action::Release, _));
+            if (is_keyboard && ui.wants_keyboard()) || (is_pointer && ui.wants_pointer() && !is_release) {
+                continue;
+            }
+
+            controls.handle_event(&mut camera, &event);
+            if let (WindowEvent::Key(Key::Space, _, Action::Press, _), Some(player)) = (&event, &mut model.animation) {
+                player.playing = !player.playing;
+                // Don't count the time spent paused.
+                last_time = glfw.get_time();
+            }
+            // Comma and period step through the animation a frame at a time, like the timeline's buttons.
+            if let (WindowEvent::Key(key, _, Action::Press | Action::Repeat, _), Some(player)) =
+                (&event, &mut model.animation)
+            {
+                match key {
+                    Key::Comma => player.step(-1),
+                    Key::Period => player.step(1),
+                    _ => (),
+                }
+            }
+            if let WindowEvent::Key(Key::F1, _, Action::Press, _) = event {
+                panel.open = !panel.open;
+            }
+            if let WindowEvent::Key(Key::Home, _, Action::Press, _) = event {
+                frame_camera(&mut camera, &model, &transform, panel.camera.part, &display);
+            }
+            handle_window_event(&mut window, &mut display, &mut settings, event);
         }
     }
 }
 
 
-unsafe fn compile_shader(shader_type: GLuint, source: &str) -> Result<GLuint, String> {
-    let shader = gl::CreateShader(shader_type);
-    let src = source.as_bytes().as_ptr().cast::<i8>();
-    let len: i32 = source.len().try_into().or(Err("Shader source is too long.".to_owned()))?;
+/// Sets a `bool` uniform on the given program, which must be in use. Uniforms that don't exist (or were optimized out)
+/// are silently ignored, just like they are by OpenGL.
+unsafe fn set_uniform_bool(program: GLuint, name: &str, value: bool) {
+    let name = std::ffi::CString::new(name).expect("Uniform names should not contain null bytes.");
+    let location = gl::GetUniformLocation(program, name.as_ptr());
+    gl::Uniform1i(location, value as GLint);
+}
 
-    // glShaderSource *actually* expects two arrays here, but since they expect C-style arrays and we've told them that
-    // there'll be only one, we can just pass the pointers directly.
-    gl::ShaderSource(shader, 1, &src, &len);
-    gl::CompileShader(shader);
 
-    let mut success = 0;
-    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
+/// Sets a `vec2` uniform on the given program. See [`set_uniform_bool`].
+unsafe fn set_uniform_vec2(program: GLuint, name: &str, value: [f32; 2]) {
+    let name = std::ffi::CString::new(name).expect("Uniform names should not contain null bytes.");
+    let location = gl::GetUniformLocation(program, name.as_ptr());
+    gl::Uniform2f(location, value[0], value[1]);
+}
 
-    if (success as GLboolean) == gl::FALSE {
-        let mut log_size = 0;
-        gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut log_size);
 
-        let mut buffer = vec![0; log_size as usize];
-        gl::GetShaderInfoLog(shader, log_size, std::ptr::null_mut(), buffer.as_mut_ptr().cast());
+/// Sets a `vec3` uniform on the given program. See [`set_uniform_bool`].
+unsafe fn set_uniform_vec3(program: GLuint, name: &str, value: [f32; 3]) {
+    let name = std::ffi::CString::new(name).expect("Uniform names should not contain null bytes.");
+    let location = gl::GetUniformLocation(program, name.as_ptr());
+    gl::Uniform3f(location, value[0], value[1], value[2]);
+}
 
-        let log_output = String::from_utf8_lossy(&buffer[..]);
-        println!("Could not compile shader. Info log:\n{}", log_output);
 
-        gl::DeleteShader(shader);
-        Err(log_output.into_owned())
-    } else {
-        Ok(shader)
-    }
+/// Sets a column-major `mat4` uniform on the given program. See [`set_uniform_bool`].
+unsafe fn set_uniform_mat4(program: GLuint, name: &str, value: &[f32; 16]) {
+    let name = std::ffi::CString::new(name).expect("Uniform names should not contain null bytes.");
+    let location = gl::GetUniformLocation(program, name.as_ptr());
+    gl::UniformMatrix4fv(location, 1, gl::FALSE, value.as_ptr());
 }
 
 
-fn handle_window_event(window: &mut Window, event: WindowEvent) {
+fn handle_window_event(window: &mut Window, display: &mut Display, settings: &mut Settings, event: WindowEvent) {
+    let Settings { frame, retro, render, lighting, ui, .. } = settings;
+    let ctrl = matches!(event, WindowEvent::Key(_, _, _, mods) if mods.contains(Modifiers::Control));
     match event {
         WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
             window.set_should_close(true);
         },
+        WindowEvent::Key(Key::V, _, Action::Press, _) => {
+            frame.vsync = !frame.vsync;
+            frame.apply(&mut window.glfw);
+        },
+        WindowEvent::Key(Key::R, _, Action::Press, _) => {
+            frame.redraw_mode = match frame.redraw_mode {
+                RedrawMode::Continuous => RedrawMode::OnDemand,
+                RedrawMode::OnDemand => RedrawMode::Continuous,
+            };
+        },
+        WindowEvent::Key(Key::P, _, Action::Press, _) => {
+            retro.enabled = !retro.enabled;
+        },
+        WindowEvent::Key(Key::W, _, Action::Press, _) => {
+            render.wireframe = !render.wireframe;
+        },
+        WindowEvent::Key(Key::F, _, Action::Press, _) => {
+            render.flat_shading = !render.flat_shading;
+        },
+        WindowEvent::Key(Key::T, _, Action::Press, _) => {
+            render.textures = !render.textures;
+        },
+        WindowEvent::Key(Key::L, _, Action::Press, _) => {
+            lighting.enabled = !lighting.enabled;
+        },
+        WindowEvent::Key(Key::F2, _, Action::Press, _) => {
+            settings.show_performance = !settings.show_performance;
+        },
+        WindowEvent::Key(Key::F3, _, Action::Press, _) => {
+            settings.show_resource_counts = !settings.show_resource_counts;
+            if !settings.show_resource_counts {
+                window.set_title(WINDOW_TITLE);
+            }
+        },
+        // Ctrl with =, -, and 0 scale the UI, like zooming in a browser. The keypad's + and - work too.
+        WindowEvent::Key(Key::Equal | Key::KpAdd, _, Action::Press | Action::Repeat, _) if ctrl => {
+            ui.zoom(1);
+        },
+        WindowEvent::Key(Key::Minus | Key::KpSubtract, _, Action::Press | Action::Repeat, _) if ctrl => {
+            ui.zoom(-1);
+        },
+        WindowEvent::Key(Key::Num0 | Key::Kp0, _, Action::Press, _) if ctrl => {
+            ui.scale = UiSettings::default().scale;
+        },
+        WindowEvent::Key(Key::F4, _, Action::Press, _) => {
+            ui.high_contrast = !ui.high_contrast;
+        },
+        WindowEvent::Key(Key::N, _, Action::Press, _) => {
+            retro.resolution = match retro.resolution {
+                RetroResolution::Scaled(_) => RetroResolution::Native,
+                RetroResolution::Native => RetroSettings::default().resolution,
+            };
+        },
+        // The framebuffer changes size when the window is resized or maximized, and also when it is dragged between
+        // monitors with different content scales. The projection's aspect ratio follows it on the next frame.
+        WindowEvent::FramebufferSize(width, height) => {
+            display.framebuffer_size = (width, height);
+            display.update_viewport();
+        },
+        WindowEvent::ContentScale(x_scale, y_scale) => {
+            display.content_scale = (x_scale, y_scale);
+        },
         _ => (),
     }
 }
@@ -0,0 +1,206 @@
+//! Matrices for placing the scene in front of the camera, and the camera itself.
+//!
+//! Matrices are column-major `[f32; 16]`s, the same as [`ff7::char::Matrix`], so they can be handed straight to GL.
+//! View space follows GL's conventions: the camera looks down -Z, with +Y up.
+
+use std::f32::consts::FRAC_PI_2;
+
+use ff7::char::Matrix;
+
+
+/// How close to straight up or down the camera can look, in radians. Looking exactly along the up axis would leave its
+/// sideways direction undefined.
+const PITCH_LIMIT: f32 = FRAC_PI_2 - 0.01;
+
+
+/// Multiplies two matrices, `a * b`.
+pub fn multiply_matrices(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [0.0; 16];
+    for col in 0..4 {
+        for row in 0..4 {
+            out[col * 4 + row] = (0..4).map(|k| a[k * 4 + row] * b[col * 4 + k]).sum();
+        }
+    }
+    out
+}
+
+
+/// A view matrix for a camera at `eye` looking towards `target`, with `up` pointing roughly upwards on screen.
+pub fn look_at(eye: [f32; 3], target: [f32; 3], up: [f32; 3]) -> Matrix {
+    let forward = normalize(sub(target, eye));
+    let right = normalize(cross(forward, up));
+    let up = cross(right, forward);
+
+    [
+        right[0], up[0], -forward[0], 0.0,
+        right[1], up[1], -forward[1], 0.0,
+        right[2], up[2], -forward[2], 0.0,
+        -dot(right, eye), -dot(up, eye), dot(forward, eye), 1.0,
+    ]
+}
+
+
+/// An orthographic projection showing `half_width` and `half_height` units either side of the view axis, between
+/// `near` and `far` units in front of the camera.
+pub fn orthographic(half_width: f32, half_height: f32, near: f32, far: f32) -> Matrix {
+    let depth = far - near;
+    [
+        1.0 / half_width, 0.0, 0.0, 0.0,
+        0.0, 1.0 / half_height, 0.0, 0.0,
+        0.0, 0.0, -2.0 / depth, 0.0,
+        0.0, 0.0, -(far + near) / depth, 1.0,
+    ]
+}
+
+
+/// A perspective projection with a vertical field of view of `fov_y` radians, for a screen `aspect` times wider than it
+/// is tall, showing everything between `near` and `far` units in front of the camera.
+pub fn perspective(fov_y: f32, aspect: f32, near: f32, far: f32) -> Matrix {
+    let f = 1.0 / (fov_y / 2.0).tan();
+    let depth = near - far;
+    [
+        f / aspect, 0.0, 0.0, 0.0,
+        0.0, f, 0.0, 0.0,
+        0.0, 0.0, (far + near) / depth, -1.0,
+        0.0, 0.0, 2.0 * far * near / depth, 0.0,
+    ]
+}
+
+
+/// One of the fixed directions that the camera can look at the model from. See [`Camera::look_from`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewPreset {
+    /// From the +Z side, where the camera starts.
+    Front,
+    /// From the +X side.
+    Side,
+    /// From straight above (or as close to it as the camera can get).
+    Top,
+}
+
+
+/// A camera that orbits around a target point, always looking at it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// The point that the camera looks at and orbits around.
+    pub target: [f32; 3],
+    /// The camera's angle around the target's vertical axis, in radians. At 0, the camera is on the +Z side.
+    pub yaw: f32,
+    /// The camera's angle above (positive) or below (negative) the target, in radians.
+    pub pitch: f32,
+    /// How far the camera is from the target.
+    pub distance: f32,
+    /// The camera's vertical field of view, in radians.
+    pub fov_y: f32,
+    /// Whether or not to draw without perspective, so that things are the same size however far away they are.
+    pub orthographic: bool,
+}
+
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self { target: [0.0; 3], yaw: 0.0, pitch: 0.0, distance: 2.0, fov_y: 60f32.to_radians(), orthographic: false }
+    }
+}
+
+
+impl Camera {
+    /// The closest that the camera can get to its target.
+    pub const MIN_DISTANCE: f32 = 0.05;
+    /// The furthest that the camera can get from its target.
+    pub const MAX_DISTANCE: f32 = 100.0;
+
+    /// Where the camera is.
+    pub fn eye(&self) -> [f32; 3] {
+        let (sin_yaw, cos_yaw) = self.yaw.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+        let offset = [cos_pitch * sin_yaw, sin_pitch, cos_pitch * cos_yaw];
+        [0, 1, 2].map(|i| self.target[i] + offset[i] * self.distance)
+    }
+
+    pub fn view_matrix(&self) -> Matrix {
+        look_at(self.eye(), self.target, [0.0, 1.0, 0.0])
+    }
+
+    /// The camera's projection, for a screen `aspect` times wider than it is tall. The near and far planes follow the
+    /// camera's distance from its target, to keep as much depth precision around the target as possible.
+    ///
+    /// An [orthographic][Self::orthographic] camera shows as much as a perspective one would at its target, so that
+    /// switching between them doesn't change the model's size. Zooming still works the same way.
+    pub fn projection_matrix(&self, aspect: f32) -> Matrix {
+        if self.orthographic {
+            let half_height = self.distance * (self.fov_y / 2.0).tan();
+            // Nothing gets smaller with distance, so whatever is behind the camera can be drawn as well.
+            orthographic(half_height * aspect, half_height, -self.distance * 100.0, self.distance * 100.0)
+        } else {
+            perspective(self.fov_y, aspect, self.distance / 100.0, self.distance * 100.0)
+        }
+    }
+
+    /// Swings the camera around to look at its target from one of the [presets][ViewPreset], and turns off
+    /// perspective so that the view lines up with the model's axes.
+    pub fn look_from(&mut self, preset: ViewPreset) {
+        (self.yaw, self.pitch) = match preset {
+            ViewPreset::Front => (0.0, 0.0),
+            ViewPreset::Side => (FRAC_PI_2, 0.0),
+            ViewPreset::Top => (0.0, PITCH_LIMIT),
+        };
+        self.orthographic = true;
+    }
+
+    /// Points the camera at the middle of a box, and moves it just far enough away to see all of it from any angle on a
+    /// screen `aspect` times wider than it is tall. The camera keeps facing the same way.
+    pub fn frame(&mut self, min: [f32; 3], max: [f32; 3], aspect: f32) {
+        self.target = [0, 1, 2].map(|i| (min[i] + max[i]) / 2.0);
+
+        // Fitting the sphere around the box, rather than the box itself, keeps it in view as the camera orbits.
+        let radius = dot(sub(max, min), sub(max, min)).sqrt() / 2.0;
+        let half_fov_y = self.fov_y / 2.0;
+        let half_fov = half_fov_y.min((half_fov_y.tan() * aspect).atan());
+        self.distance = (radius / half_fov.sin()).clamp(Self::MIN_DISTANCE, Self::MAX_DISTANCE);
+    }
+
+    /// Swings the camera around its target by the given angles, in radians. It can't go over the top or bottom.
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        self.yaw = (self.yaw + yaw).rem_euclid(std::f32::consts::TAU);
+        self.pitch = (self.pitch + pitch).clamp(-PITCH_LIMIT, PITCH_LIMIT);
+    }
+
+    /// Slides the camera and its target sideways and up or down, relative to the way the camera is facing.
+    pub fn pan(&mut self, right: f32, up: f32) {
+        let view = self.view_matrix();
+        // The first two rows of the view matrix are the camera's right and up directions in world space.
+        let (right_dir, up_dir) = ([view[0], view[4], view[8]], [view[1], view[5], view[9]]);
+        self.target = [0, 1, 2].map(|i| self.target[i] + right_dir[i] * right + up_dir[i] * up);
+    }
+
+    /// Moves the camera towards its target (for factors below 1) or away from it (above 1).
+    pub fn zoom(&mut self, factor: f32) {
+        self.distance = (self.distance * factor).clamp(Self::MIN_DISTANCE, Self::MAX_DISTANCE);
+    }
+}
+
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+
+fn normalize(v: [f32; 3]) -> [f32; 3] {
+    let len = dot(v, v).sqrt();
+    if len > 0.0 {
+        v.map(|c| c / len)
+    } else {
+        v
+    }
+}
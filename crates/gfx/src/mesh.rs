@@ -0,0 +1,296 @@
+//! Turning parsed [P files][PolygonFile] into vertex and index buffers, and drawing them.
+//!
+//! P files index their vertices per group: each polygon's indices are relative to its group's first vertex, and each
+//! group's texture coordinates line up with its vertices starting from the group's first texture coordinate. Everything
+//! is flattened here into one vertex buffer and one index buffer, so a whole model can be drawn with a single call.
+//! Each group's range of indices is kept, so that groups can also be drawn one at a time with their own textures.
+
+use std::collections::HashMap;
+
+use ff7::char::{transform_point, BlendMode, Color, Matrix, PolygonFile, RenderState, IDENTITY};
+use gl::types::*;
+
+use crate::{bone_slot, has_dsa, GlBuffer, GlVertexArray};
+
+
+/// One vertex, as laid out in a [`GlMesh`]'s vertex buffer.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MeshVertex {
+    pub position: [f32; 3],
+    /// The vertex's color, in sRGB.
+    pub color: [f32; 3],
+    pub normal: [f32; 3],
+    pub uv: [f32; 2],
+    /// Which of the pose's bone transforms moves the vertex, as a slot in the [`BoneBuffer`][crate::BoneBuffer].
+    pub bone: u32,
+}
+
+
+/// A range of a mesh's triangles that are drawn with the same texture.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MeshGroup {
+    /// The first of the group's indices, in [`MeshData::indices`].
+    pub index_start: usize,
+    pub index_count: usize,
+    /// Which of the P file's textures the group uses, if it's textured. These are numbered in the order that the
+    /// model's RSD file lists them.
+    pub texture: Option<u32>,
+    /// How the group is blended with what's behind it, or `None` if it's opaque.
+    pub blend: Option<BlendMode>,
+    /// The average position of the group's triangles' corners, for sorting transparent groups by their distance from
+    /// the camera.
+    pub center: [f32; 3],
+}
+
+
+/// A mesh's vertices and triangle indices, ready to be uploaded.
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    pub vertices: Vec<MeshVertex>,
+    /// Three indices into [`vertices`][Self::vertices] per triangle.
+    pub indices: Vec<u32>,
+    /// Every range of indices that shares a texture, in order. Meshes without any groups are drawn untextured.
+    pub groups: Vec<MeshGroup>,
+}
+
+
+impl MeshData {
+    /// Flattens a P file's groups into a single list of vertices and triangles. Polygons that point outside of their
+    /// group's vertices are skipped.
+    pub fn from_polygon(polygon: &PolygonFile) -> Self {
+        let to_rgb = |color: &Color| [color.r, color.g, color.b].map(|c| c as f32 / 255.0);
+
+        let mut vertices = polygon
+            .vertices
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| MeshVertex {
+                position,
+                color: polygon.vertex_colors.get(i).map_or([1.0; 3], to_rgb),
+                normal: polygon
+                    .normal_indices
+                    .get(i)
+                    .and_then(|&n| polygon.normals.get(n as usize))
+                    .copied()
+                    .unwrap_or_default(),
+                uv: [0.0; 2],
+                bone: 0,
+            })
+            .collect::<Vec<_>>();
+
+        // The normal index that each vertex was first given by a polygon's corner, and the copies made of vertices for
+        // the other normals they were given.
+        let mut corner_normals = vec![None; vertices.len()];
+        let mut copies = HashMap::new();
+
+        let mut indices = Vec::with_capacity(polygon.polygons.len() * 3);
+        let mut groups = Vec::with_capacity(polygon.groups.len());
+
+        for (group_index, group) in polygon.groups.iter().enumerate() {
+            let index_start = indices.len();
+            let vertex_start = group.vertex_start as usize;
+
+            if group.textured {
+                for i in 0..group.vertex_count as usize {
+                    let uv = polygon.tex_coords.get(group.tex_coord_start as usize + i);
+                    if let (Some(vertex), Some(&uv)) = (vertices.get_mut(vertex_start + i), uv) {
+                        vertex.uv = uv;
+                    }
+                }
+            }
+
+            let start = group.polygon_start as usize;
+            let end = start + group.polygon_count as usize;
+            for triangle in polygon.polygons.get(start..end).unwrap_or_default() {
+                let corners = triangle.vertices.map(|v| vertex_start + v as usize);
+                if corners.iter().any(|&v| v >= vertices.len()) {
+                    continue;
+                }
+
+                // Files without a per-vertex normal table give normals per corner instead. A vertex that is given
+                // different normals by different polygons (along a sharp edge) is copied, so that each keeps its own.
+                let corners = if polygon.normal_indices.is_empty() {
+                    let mut corners = corners;
+                    for (v, &n) in corners.iter_mut().zip(&triangle.normals) {
+                        let normal = polygon.normals.get(n as usize).copied().unwrap_or_default();
+                        match corner_normals[*v] {
+                            None => {
+                                corner_normals[*v] = Some(n);
+                                vertices[*v].normal = normal;
+                            },
+                            Some(first) if first == n => {},
+                            Some(_) => {
+                                *v = *copies.entry((*v, n)).or_insert_with(|| {
+                                    vertices.push(MeshVertex { normal, ..vertices[*v] });
+                                    vertices.len() - 1
+                                });
+                            },
+                        }
+                    }
+                    corners
+                } else {
+                    corners
+                };
+
+                indices.extend(corners.map(|v| v as u32));
+            }
+
+            let index_count = indices.len() - index_start;
+            let texture = group.textured.then_some(group.texture_index);
+            let blend = polygon.render_states.get(group_index).and_then(RenderState::blending);
+            let center = indices[index_start..]
+                .iter()
+                .fold([0.0; 3], |sum, &i| [0, 1, 2].map(|c| sum[c] + vertices[i as usize].position[c]))
+                .map(|c| c / index_count.max(1) as f32);
+            groups.push(MeshGroup { index_start, index_count, texture, blend, center });
+        }
+
+        Self { vertices, indices, groups }
+    }
+
+    /// Attaches every vertex to a bone, so that it moves with it. See [`bone_slot`].
+    pub fn attach_to_bone(&mut self, bone: Option<usize>) {
+        let slot = bone_slot(bone);
+        for vertex in &mut self.vertices {
+            vertex.bone = slot;
+        }
+    }
+
+    /// The smallest and largest coordinates of the vertices used by any triangle, after being moved by `transform`,
+    /// or `None` if there are none.
+    pub fn bounds(&self, transform: &Matrix) -> Option<([f32; 3], [f32; 3])> {
+        let mut positions = self
+            .indices
+            .iter()
+            .map(|&i| transform_point(transform, self.vertices[i as usize].position));
+        let first = positions.next()?;
+        Some(positions.fold((first, first), |(min, max), p| {
+            ([0, 1, 2].map(|i| min[i].min(p[i])), [0, 1, 2].map(|i| max[i].max(p[i])))
+        }))
+    }
+}
+
+
+/// A column-major transform that centers some meshes (each moved by its own transform) on the origin and scales them to
+/// fit in a box two units across, so that the [camera][crate::Camera] starts out with the whole model in view. Models
+/// are stored with Y pointing down, so Y is flipped to show them upright.
+pub fn fit_transform<'m>(meshes: impl IntoIterator<Item = (&'m MeshData, &'m Matrix)>) -> Matrix {
+    let bounds = meshes.into_iter().filter_map(|(mesh, transform)| mesh.bounds(transform)).reduce(|a, b| {
+        ([0, 1, 2].map(|i| a.0[i].min(b.0[i])), [0, 1, 2].map(|i| a.1[i].max(b.1[i])))
+    });
+    let Some((min, max)) = bounds else {
+        return IDENTITY;
+    };
+
+    let center = [0, 1, 2].map(|i| (min[i] + max[i]) / 2.0);
+    let extent = [0, 1, 2].map(|i| max[i] - min[i]).into_iter().fold(0.0, f32::max);
+    let scale = if extent > 0.0 { 1.8 / extent } else { 1.0 };
+
+    [
+        scale, 0.0, 0.0, 0.0,
+        0.0, -scale, 0.0, 0.0,
+        0.0, 0.0, -scale, 0.0,
+        -center[0] * scale, center[1] * scale, center[2] * scale, 1.0,
+    ]
+}
+
+
+/// A mesh that has been uploaded to the GPU, ready to draw with indexed rendering.
+#[derive(Debug)]
+pub struct GlMesh {
+    vao: GlVertexArray,
+    vbo: GlBuffer,
+    ibo: GlBuffer,
+    index_count: usize,
+    groups: Vec<MeshGroup>,
+}
+
+
+impl GlMesh {
+    /// Uploads a mesh. Its attributes are bound to locations 0 (position), 1 (color), 2 (normal), 3 (UV), and 4 (bone
+    /// slot, as an integer).
+    pub fn new(data: &MeshData) -> Self {
+        let vao = GlVertexArray::new();
+        let vbo = GlBuffer::with_data(&data.vertices, gl::STATIC_DRAW);
+        let ibo = GlBuffer::with_data(&data.indices, gl::STATIC_DRAW);
+
+        // (location, size, offset) of each attribute within a `MeshVertex`.
+        let f_size = std::mem::size_of::<f32>();
+        let attributes = [(0, 3, 0), (1, 3, f_size * 3), (2, 3, f_size * 6), (3, 2, f_size * 9)];
+        let bone_offset = f_size * 11;
+        let v_size: i32 = std::mem::size_of::<MeshVertex>().try_into().unwrap();
+
+        unsafe {
+            if has_dsa() {
+                gl::VertexArrayVertexBuffer(vao.id(), 0, vbo.id(), 0, v_size);
+                gl::VertexArrayElementBuffer(vao.id(), ibo.id());
+                for (location, size, offset) in attributes {
+                    gl::VertexArrayAttribFormat(vao.id(), location, size, gl::FLOAT, gl::FALSE, offset as GLuint);
+                    gl::VertexArrayAttribBinding(vao.id(), location, 0);
+                    gl::EnableVertexArrayAttrib(vao.id(), location);
+                }
+                gl::VertexArrayAttribIFormat(vao.id(), 4, 1, gl::UNSIGNED_INT, bone_offset as GLuint);
+                gl::VertexArrayAttribBinding(vao.id(), 4, 0);
+                gl::EnableVertexArrayAttrib(vao.id(), 4);
+            } else {
+                gl::BindVertexArray(vao.id());
+                gl::BindBuffer(gl::ARRAY_BUFFER, vbo.id());
+                gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo.id());
+                for (location, size, offset) in attributes {
+                    gl::VertexAttribPointer(location, size, gl::FLOAT, gl::FALSE, v_size, offset as *const _);
+                    gl::EnableVertexAttribArray(location);
+                }
+                gl::VertexAttribIPointer(4, 1, gl::UNSIGNED_INT, v_size, bone_offset as *const _);
+                gl::EnableVertexAttribArray(4);
+                gl::BindVertexArray(0);
+                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            }
+        }
+
+        Self { vao, vbo, ibo, index_count: data.indices.len(), groups: data.groups.clone() }
+    }
+
+    /// Draws the whole mesh with whichever program is in use.
+    pub fn draw(&self) {
+        unsafe {
+            gl::BindVertexArray(self.vao.id());
+            gl::DrawElements(gl::TRIANGLES, self.index_count as GLsizei, gl::UNSIGNED_INT, std::ptr::null());
+            gl::BindVertexArray(0);
+        }
+    }
+
+    /// Draws the mesh's opaque groups one at a time, calling `prepare` with each group's texture index first so that it
+    /// can switch programs and bind textures. Meshes without any groups are drawn in one go, as if untextured.
+    /// Transparent groups are left to be drawn afterwards with [`draw_group`][Self::draw_group], once they have been
+    /// sorted.
+    pub fn draw_groups(&self, mut prepare: impl FnMut(Option<u32>)) {
+        if self.groups.is_empty() {
+            prepare(None);
+            self.draw();
+            return;
+        }
+
+        unsafe { gl::BindVertexArray(self.vao.id()) };
+        for group in self.groups.iter().filter(|group| group.index_count > 0 && group.blend.is_none()) {
+            prepare(group.texture);
+            let offset = (group.index_start * std::mem::size_of::<u32>()) as *const _;
+            unsafe { gl::DrawElements(gl::TRIANGLES, group.index_count as GLsizei, gl::UNSIGNED_INT, offset) };
+        }
+        unsafe { gl::BindVertexArray(0) };
+    }
+
+    /// Draws a single one of the mesh's groups, with whichever program is in use.
+    pub fn draw_group(&self, group: &MeshGroup) {
+        let offset = (group.index_start * std::mem::size_of::<u32>()) as *const _;
+        unsafe {
+            gl::BindVertexArray(self.vao.id());
+            gl::DrawElements(gl::TRIANGLES, group.index_count as GLsizei, gl::UNSIGNED_INT, offset);
+            gl::BindVertexArray(0);
+        }
+    }
+
+    pub fn groups(&self) -> &[MeshGroup] {
+        &self.groups
+    }
+}
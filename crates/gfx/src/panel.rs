@@ -0,0 +1,166 @@
+//! The viewer's control panel: a side panel with sections for picking a model and an animation, for seeing what went
+//! wrong while loading the model, for moving the camera, for changing how the scene is lit and rendered, and for
+//! changing the UI itself.
+
+use crate::{
+    AnimationPanel, AnimationPlayer, Camera, CameraBookmarks, CameraPanel, ModelPicker, RedrawMode, RetroResolution,
+    RetroSettings, Settings, UI_SCALE_RANGE,
+};
+
+
+/// The largest that the retro mode's resolution can be divided by.
+const MAX_RETRO_SCALE: u32 = 8;
+
+
+/// What was chosen in the [`ControlPanel`] this frame, for the viewer to load.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControlPanelOutput {
+    /// The name of the model that was picked, if any.
+    pub model: Option<String>,
+    /// The name of the animation that was picked, if any.
+    pub animation: Option<String>,
+    /// Whether or not the camera should be moved to frame the [part of the model][CameraPanel::part] that was picked.
+    pub frame: bool,
+}
+
+
+/// A side panel holding all of the viewer's controls.
+#[derive(Debug, Clone)]
+pub struct ControlPanel {
+    /// Whether or not the panel is showing.
+    pub open: bool,
+    pub picker: ModelPicker,
+    pub animations: AnimationPanel,
+    pub camera: CameraPanel,
+    /// Everything that went wrong while loading the current model that didn't stop it from being shown.
+    pub problems: Vec<String>,
+}
+
+
+impl ControlPanel {
+    /// Creates an open panel, around a picker for the archive's models and the camera's bookmarks.
+    pub fn new(picker: ModelPicker, bookmarks: CameraBookmarks) -> Self {
+        let (animations, camera) = (AnimationPanel::new(), CameraPanel::new(bookmarks));
+        Self { open: true, picker, animations, camera, problems: Vec::new() }
+    }
+
+    /// Shows the panel, if it's open. Settings, the animation, and the camera are changed in place.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        settings: &mut Settings,
+        player: Option<&mut AnimationPlayer>,
+        camera: &mut Camera,
+    ) -> ControlPanelOutput {
+        let mut output = ControlPanelOutput::default();
+        if !self.open {
+            return output;
+        }
+
+        egui::SidePanel::left("controls").default_width(260.0).show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                egui::CollapsingHeader::new("Model").default_open(true).show(ui, |ui| {
+                    output.model = self.picker.ui(ui);
+                });
+                if !self.problems.is_empty() {
+                    let title = format!("Problems ({})", self.problems.len());
+                    egui::CollapsingHeader::new(title).id_source("problems").default_open(true).show(ui, |ui| {
+                        problems_ui(ui, &self.problems);
+                    });
+                }
+                egui::CollapsingHeader::new("Animation").default_open(true).show(ui, |ui| {
+                    output.animation = self.animations.ui(ui, player);
+                });
+                egui::CollapsingHeader::new("Camera").show(ui, |ui| {
+                    output.frame = self.camera.ui(ui, camera);
+                });
+                egui::CollapsingHeader::new("Lighting").show(ui, |ui| lighting_ui(ui, settings));
+                egui::CollapsingHeader::new("Rendering").show(ui, |ui| rendering_ui(ui, settings));
+                egui::CollapsingHeader::new("Interface").show(ui, |ui| interface_ui(ui, settings));
+            });
+        });
+
+        output
+    }
+}
+
+
+fn problems_ui(ui: &mut egui::Ui, problems: &[String]) {
+    ui.weak("The rest of the model is still shown. Missing meshes are left out, and missing textures are drawn as a \
+        magenta checkerboard.");
+    for problem in problems {
+        ui.label(problem);
+    }
+}
+
+
+fn lighting_ui(ui: &mut egui::Ui, settings: &mut Settings) {
+    let light = &mut settings.lighting;
+    ui.checkbox(&mut light.enabled, "Lighting (L)");
+    ui.add_enabled_ui(light.enabled, |ui| {
+        ui.horizontal(|ui| {
+            for (axis, value) in ["X", "Y", "Z"].into_iter().zip(&mut light.direction) {
+                ui.add(egui::DragValue::new(value).speed(0.01).clamp_range(-1.0..=1.0).prefix(format!("{axis}: ")));
+            }
+            ui.label("Direction");
+        });
+        ui.horizontal(|ui| {
+            ui.color_edit_button_rgb(&mut light.color);
+            ui.label("Light color");
+        });
+        ui.horizontal(|ui| {
+            ui.color_edit_button_rgb(&mut light.ambient);
+            ui.label("Ambient color");
+        });
+    });
+}
+
+
+fn rendering_ui(ui: &mut egui::Ui, settings: &mut Settings) {
+    let render = &mut settings.render;
+    ui.checkbox(&mut render.wireframe, "Wireframe (W)");
+    ui.checkbox(&mut render.flat_shading, "Flat shading (F)");
+    ui.checkbox(&mut render.textures, "Textures (T)");
+
+    ui.separator();
+    let retro = &mut settings.retro;
+    ui.checkbox(&mut retro.enabled, "PSX look (P)");
+    ui.add_enabled_ui(retro.enabled, |ui| {
+        ui.checkbox(&mut retro.dither, "Dithering");
+        ui.checkbox(&mut retro.affine, "Affine texturing");
+        ui.checkbox(&mut retro.vertex_snap, "Vertex snapping");
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut retro.resolution, RetroResolution::Native, "Native (N)");
+            let scaled = match retro.resolution {
+                RetroResolution::Scaled(_) => retro.resolution,
+                RetroResolution::Native => RetroSettings::default().resolution,
+            };
+            ui.radio_value(&mut retro.resolution, scaled, "Scaled");
+            if let RetroResolution::Scaled(scale) = &mut retro.resolution {
+                ui.add(egui::DragValue::new(scale).clamp_range(1..=MAX_RETRO_SCALE).prefix("1/"));
+            }
+        });
+    });
+
+    ui.separator();
+    let frame = &mut settings.frame;
+    ui.checkbox(&mut frame.vsync, "Vsync (V)");
+    let mut on_demand = frame.redraw_mode == RedrawMode::OnDemand;
+    if ui.checkbox(&mut on_demand, "Only redraw on changes (R)").changed() {
+        frame.redraw_mode = if on_demand { RedrawMode::OnDemand } else { RedrawMode::Continuous };
+    }
+    ui.checkbox(&mut settings.show_performance, "Performance HUD (F2)");
+}
+
+
+fn interface_ui(ui: &mut egui::Ui, settings: &mut Settings) {
+    let settings = &mut settings.ui;
+    // The scale only changes once the slider is let go of, so that it doesn't move out from under the cursor.
+    let mut scale = settings.scale;
+    let slider = ui.add(egui::Slider::new(&mut scale, UI_SCALE_RANGE).text("Scale (Ctrl with + and -)").suffix("×"));
+    if slider.drag_released() || (slider.changed() && !slider.dragged()) {
+        settings.scale = scale;
+    }
+    ui.checkbox(&mut settings.high_contrast, "High contrast (F4)");
+    ui.weak("Tab and Shift+Tab move between controls, Space or Enter uses them, and Escape goes back to the scene.");
+}
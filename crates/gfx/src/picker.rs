@@ -0,0 +1,54 @@
+//! Browsing the archive's models from inside the viewer, so that a different one can be shown without restarting.
+
+
+/// The tallest that the list of models gets before it scrolls, in points.
+const LIST_HEIGHT: f32 = 240.0;
+
+
+/// A list of every HRC model in the archive, which can be narrowed down by name.
+#[derive(Debug, Clone, Default)]
+pub struct ModelPicker {
+    /// The model being shown, which is highlighted in the list.
+    pub current: Option<String>,
+    /// Every model that can be picked, sorted by name.
+    models: Vec<String>,
+    /// Only models whose names contain this (ignoring case) are listed.
+    filter: String,
+}
+
+
+impl ModelPicker {
+    /// Creates a picker for the given models, which don't need to be in any order.
+    pub fn new<S: Into<String>>(models: impl IntoIterator<Item = S>) -> Self {
+        let mut models = models.into_iter().map(Into::into).collect::<Vec<_>>();
+        models.sort_unstable_by_key(|name| name.to_lowercase());
+        Self { models, ..Self::default() }
+    }
+
+    /// Adds the picker's filter box and list to a UI. Returns the name of the model that was clicked on, if any.
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        if self.models.is_empty() {
+            ui.label("The archive has no HRC models.");
+            return None;
+        }
+
+        ui.add(egui::TextEdit::singleline(&mut self.filter).hint_text("Filter by name"));
+
+        let filter = self.filter.to_lowercase();
+        let matches = self.models.iter().filter(|name| name.to_lowercase().contains(&filter)).collect::<Vec<_>>();
+        ui.label(format!("{} of {} models", matches.len(), self.models.len()));
+
+        let mut picked = None;
+        let row_height = ui.text_style_height(&egui::TextStyle::Button);
+        let list = egui::ScrollArea::vertical().max_height(LIST_HEIGHT).auto_shrink([false, true]);
+        list.show_rows(ui, row_height, matches.len(), |ui, rows| {
+            for &name in &matches[rows] {
+                let selected = self.current.as_ref() == Some(name);
+                if ui.selectable_label(selected, name).clicked() && !selected {
+                    picked = Some(name.clone());
+                }
+            }
+        });
+        picked
+    }
+}
@@ -0,0 +1,268 @@
+//! Measuring how long each stage of loading a model takes (and each frame, for the [performance
+//! HUD][crate::show_performance_hud]), and how much memory it allocates, so that optimization effort goes where it
+//! matters.
+//!
+//! Allocations are only counted when the program uses [`CountingAllocator`] as its global allocator, which the viewer
+//! only does when it's built with the `profile-alloc` feature. The count is shared by every thread, so it's only
+//! accurate for stages that run while nothing else is allocating.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::{self, Display};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+
+static BYTES_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+
+/// A global allocator that counts how many bytes are allocated, and otherwise just uses the [system
+/// allocator][System]. Install it in the final binary with `#[global_allocator]`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CountingAllocator;
+
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        BYTES_ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        BYTES_ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        // Only growth counts; shrinking doesn't give anything back, since this is a running total.
+        BYTES_ALLOCATED.fetch_add(new_size.saturating_sub(layout.size()), Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+
+/// The total number of bytes allocated so far, by every thread. Always 0 without a [`CountingAllocator`].
+pub fn bytes_allocated() -> usize {
+    BYTES_ALLOCATED.load(Ordering::Relaxed)
+}
+
+
+/// Whether or not a [`CountingAllocator`] is counting allocations. Every program allocates something before `main`, so
+/// this is only `false` when there's no allocator to count them.
+pub fn is_counting_allocations() -> bool {
+    bytes_allocated() > 0
+}
+
+
+/// How long one stage of loading took, and how much it allocated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StageStats {
+    pub name: &'static str,
+    pub time: Duration,
+    /// The number of bytes allocated during the stage, whether or not they were freed again afterwards.
+    pub bytes_allocated: usize,
+}
+
+
+impl StageStats {
+    /// Measures a single run of a stage.
+    fn measure<T>(name: &'static str, stage: impl FnOnce() -> T) -> (T, Self) {
+        let (start, bytes_before) = (Instant::now(), bytes_allocated());
+        let output = stage();
+        let (time, bytes_allocated) = (start.elapsed(), self::bytes_allocated().saturating_sub(bytes_before));
+        (output, Self { name, time, bytes_allocated })
+    }
+}
+
+
+impl Display for StageStats {
+    /// Writes the time in milliseconds, then the bytes allocated in KiB if they're being
+    /// [counted][is_counting_allocations]. The name is left out, and the width is passed on to the time.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let millis = self.time.as_secs_f64() * 1000.0;
+        let width = f.width().unwrap_or(0);
+        write!(f, "{millis:>width$.3} ms")?;
+        if is_counting_allocations() {
+            let kib = self.bytes_allocated as f64 / 1024.0;
+            write!(f, "  {kib:>width$.1} KiB", width = width + 2)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// A breakdown of where the time and memory went while loading.
+#[derive(Debug, Clone, Default)]
+pub struct LoadProfile {
+    /// Whether or not anything is measured. Disabled profiles just run each stage.
+    pub enabled: bool,
+    /// Every stage that has been measured, in the order they first ran.
+    pub stages: Vec<StageStats>,
+}
+
+
+impl LoadProfile {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, stages: Vec::new() }
+    }
+
+    /// Runs one stage of loading, and measures it if the profile is enabled. Stages that run more than once (e.g.,
+    /// once per mesh) are added up under the same name.
+    pub fn measure<T>(&mut self, name: &'static str, stage: impl FnOnce() -> T) -> T {
+        if !self.enabled {
+            return stage();
+        }
+
+        let (output, measured) = StageStats::measure(name, stage);
+        match self.stages.iter_mut().find(|stats| stats.name == name) {
+            Some(stats) => {
+                stats.time += measured.time;
+                stats.bytes_allocated += measured.bytes_allocated;
+            },
+            None => self.stages.push(measured),
+        }
+
+        output
+    }
+
+    /// The time spent in every stage put together.
+    pub fn total_time(&self) -> Duration {
+        self.stages.iter().map(|stats| stats.time).sum()
+    }
+
+    /// The number of bytes allocated by every stage put together.
+    pub fn total_bytes_allocated(&self) -> usize {
+        self.stages.iter().map(|stats| stats.bytes_allocated).sum()
+    }
+}
+
+
+impl Display for LoadProfile {
+    /// Writes the profile as a table, one stage per line, with a total at the end. Allocations are left out if they
+    /// aren't being [counted][is_counting_allocations].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.stages.iter().map(|stats| stats.name.len()).chain(["total".len()]).max().unwrap_or(0);
+        let total = StageStats {
+            name: "total",
+            time: self.total_time(),
+            bytes_allocated: self.total_bytes_allocated(),
+        };
+
+        for stats in self.stages.iter().chain([&total]) {
+            writeln!(f, "{:width$}  {stats:10}", stats.name)?;
+        }
+        Ok(())
+    }
+}
+
+
+/// How long the last frame took to draw, and how much it allocated, for the [performance
+/// HUD][crate::show_performance_hud].
+#[derive(Debug, Clone, Default)]
+pub struct FrameProfile {
+    /// The frame being drawn, if any: when it started, and how much had been allocated by then.
+    current: Option<(Instant, usize)>,
+    /// The last frame that was [finished][Self::end].
+    pub last: Option<StageStats>,
+}
+
+
+impl FrameProfile {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts measuring a frame.
+    pub fn begin(&mut self) {
+        self.current = Some((Instant::now(), bytes_allocated()));
+    }
+
+    /// Finishes measuring the frame that was [begun][Self::begin], if there is one. This should be called before
+    /// swapping buffers, so that time spent waiting for vsync (or the FPS cap) isn't counted.
+    pub fn end(&mut self) {
+        if let Some((start, bytes_before)) = self.current.take() {
+            let (time, bytes_allocated) = (start.elapsed(), self::bytes_allocated().saturating_sub(bytes_before));
+            self.last = Some(StageStats { name: "frame", time, bytes_allocated });
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::hint::black_box;
+
+    use super::*;
+
+    // The counters are only ever added to by a `CountingAllocator`, so the tests need one too.
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// How many bytes a stage allocates, measured on its own.
+    fn bytes_allocated_by(stage: impl FnOnce()) -> usize {
+        StageStats::measure("stage", stage).1.bytes_allocated
+    }
+
+    // Other tests run on other threads, and their allocations are counted too, so only lower bounds can be checked.
+
+    #[test]
+    fn allocations_are_counted() {
+        assert!(is_counting_allocations());
+        assert!(bytes_allocated_by(|| drop(black_box(vec![0u8; 100_000]))) >= 100_000);
+        assert!(bytes_allocated_by(|| drop(black_box(Box::new([0u64; 1000])))) >= 8000);
+    }
+
+    #[test]
+    fn only_growth_is_counted_when_reallocating() {
+        let mut data = Vec::<u8>::with_capacity(1000);
+        assert!(bytes_allocated_by(|| data.reserve_exact(50_000)) >= 50_000 - 1000);
+
+        // Shrinking never takes anything off of the running total.
+        let before = bytes_allocated();
+        data.shrink_to(10);
+        assert!(bytes_allocated() >= before);
+    }
+
+    #[test]
+    fn repeated_stages_are_added_up() {
+        let mut profile = LoadProfile::new(true);
+        for len in [10_000, 20_000] {
+            assert_eq!(profile.measure("build", || vec![1u8; len]).len(), len);
+        }
+        profile.measure("read", || ());
+
+        let names = profile.stages.iter().map(|stats| stats.name).collect::<Vec<_>>();
+        assert_eq!(names, ["build", "read"]);
+        assert!(profile.stages[0].bytes_allocated >= 30_000);
+        assert!(profile.total_bytes_allocated() >= profile.stages[0].bytes_allocated);
+        assert_eq!(profile.total_time(), profile.stages[0].time + profile.stages[1].time);
+    }
+
+    #[test]
+    fn disabled_profiles_measure_nothing() {
+        let mut profile = LoadProfile::new(false);
+        assert_eq!(profile.measure("build", || vec![1u8; 10_000]).len(), 10_000);
+        assert!(profile.stages.is_empty());
+    }
+
+    #[test]
+    fn frames_are_measured_between_begin_and_end() {
+        let mut frames = FrameProfile::new();
+        frames.end();
+        assert_eq!(frames.last, None);
+
+        frames.begin();
+        drop(black_box(vec![0u8; 40_000]));
+        frames.end();
+        let last = frames.last.clone().expect("the frame should have been measured");
+        assert_eq!(last.name, "frame");
+        assert!(last.bytes_allocated >= 40_000);
+
+        // Ending again without beginning a new frame keeps the last one.
+        frames.end();
+        assert_eq!(frames.last, Some(last));
+    }
+}
@@ -0,0 +1,101 @@
+//! Newline-delimited JSON progress events, for GUIs and scripts that wrap the viewer.
+//!
+//! With `--json`, each step of loading prints one JSON object per line to stdout as it happens. Logs go to stderr, so
+//! stdout only ever has events on it. Every event names the item it's about, and has one of these forms:
+//!
+//! ```text
+//! {"event":"start","item":"char.lgp"}
+//! {"event":"progress","item":"AAAA.HRC","done":3,"total":12}
+//! {"event":"done","item":"char.lgp"}
+//! {"event":"error","item":"AAAA.HRC","message":"..."}
+//! ```
+//!
+//! Each item gets a `start`, then any number of `progress` events, then either a `done` or an `error`.
+
+use std::fmt::{self, Display, Write};
+
+
+/// One step in loading something.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressEvent<'a> {
+    Start { item: &'a str },
+    Progress { item: &'a str, done: usize, total: usize },
+    Done { item: &'a str },
+    Error { item: &'a str, message: &'a str },
+}
+
+
+impl Display for ProgressEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Start { item } => write!(f, r#"{{"event":"start","item":{}}}"#, JsonStr(item)),
+            Self::Progress { item, done, total } => {
+                write!(f, r#"{{"event":"progress","item":{},"done":{done},"total":{total}}}"#, JsonStr(item))
+            },
+            Self::Done { item } => write!(f, r#"{{"event":"done","item":{}}}"#, JsonStr(item)),
+            Self::Error { item, message } => {
+                write!(f, r#"{{"event":"error","item":{},"message":{}}}"#, JsonStr(item), JsonStr(message))
+            },
+        }
+    }
+}
+
+
+/// Prints progress events to stdout, if they were asked for.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProgressReporter {
+    pub enabled: bool,
+}
+
+
+impl ProgressReporter {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    pub fn report(&self, event: ProgressEvent) {
+        if self.enabled {
+            // Stdout is line-buffered, so each event is flushed as soon as it's printed.
+            println!("{event}");
+        }
+    }
+
+    pub fn start(&self, item: &str) {
+        self.report(ProgressEvent::Start { item });
+    }
+
+    pub fn progress(&self, item: &str, done: usize, total: usize) {
+        self.report(ProgressEvent::Progress { item, done, total });
+    }
+
+    pub fn done(&self, item: &str) {
+        self.report(ProgressEvent::Done { item });
+    }
+
+    pub fn error(&self, item: &str, message: impl Display) {
+        self.report(ProgressEvent::Error { item, message: &message.to_string() });
+    }
+}
+
+
+/// Writes a string as a quoted JSON string.
+struct JsonStr<'a>(&'a str);
+
+
+impl Display for JsonStr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            match c {
+                '"' => f.write_str("\\\"")?,
+                '\\' => f.write_str("\\\\")?,
+                '\n' => f.write_str("\\n")?,
+                '\r' => f.write_str("\\r")?,
+                '\t' => f.write_str("\\t")?,
+                c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+                c => f.write_char(c)?,
+            }
+        }
+        f.write_char('"')
+    }
+}
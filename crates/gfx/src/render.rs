@@ -0,0 +1,96 @@
+//! Alternative ways of drawing models, for inspecting their geometry, and how they are lit and blended.
+
+use ff7::char::BlendMode;
+
+
+/// Settings for how models are drawn, which can be toggled at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderSettings {
+    /// Whether or not to draw only the edges of polygons, with `glPolygonMode`.
+    pub wireframe: bool,
+    /// Whether or not to give each polygon a single color (its last vertex's), rather than blending between the colors
+    /// of its vertices.
+    pub flat_shading: bool,
+    /// Whether or not to draw textured groups with their textures. Without them, every group shows only its vertex
+    /// colors.
+    pub textures: bool,
+}
+
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self { wireframe: false, flat_shading: false, textures: true }
+    }
+}
+
+
+impl RenderSettings {
+    /// Applies the settings that are GL state rather than shader uniforms.
+    pub fn apply(&self) {
+        let mode = if self.wireframe { gl::LINE } else { gl::FILL };
+        unsafe { gl::PolygonMode(gl::FRONT_AND_BACK, mode) };
+    }
+}
+
+
+/// A directional light and an ambient term, applied to each vertex like the game's own lighting: a vertex's color is
+/// multiplied by the ambient color plus the light's color, scaled by how directly the vertex faces the light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightSettings {
+    /// Whether or not models are lit at all. Without lighting, models show their vertex colors as they are.
+    pub enabled: bool,
+    /// The direction that the light travels in, in the same space as the model (after it has been fitted to the view,
+    /// with +Y up). It doesn't need to be normalized.
+    pub direction: [f32; 3],
+    /// The directional light's color, in linear RGB.
+    pub color: [f32; 3],
+    /// The light that reaches every surface no matter which way it faces, in linear RGB.
+    pub ambient: [f32; 3],
+}
+
+
+impl Default for LightSettings {
+    /// A white light shining down from above and in front of the model, which is where the camera starts.
+    fn default() -> Self {
+        Self { enabled: true, direction: [-0.3, -0.6, -0.75], color: [0.75; 3], ambient: [0.35; 3] }
+    }
+}
+
+
+/// Sets up GL's blending for drawing polygons with one of the game's blend modes, or turns it off for opaque polygons.
+///
+/// Blended polygons don't write to the depth buffer, so that other transparent polygons behind them (which should be
+/// drawn first) aren't hidden by them. They still test against it, so that opaque polygons in front of them hide them.
+pub fn apply_blend_mode(blend: Option<BlendMode>) {
+    unsafe {
+        let Some(blend) = blend else {
+            gl::Disable(gl::BLEND);
+            gl::DepthMask(gl::TRUE);
+            return;
+        };
+
+        gl::Enable(gl::BLEND);
+        gl::DepthMask(gl::FALSE);
+        match blend {
+            BlendMode::Average => {
+                gl::BlendEquation(gl::FUNC_ADD);
+                gl::BlendColor(0.0, 0.0, 0.0, 0.5);
+                gl::BlendFunc(gl::CONSTANT_ALPHA, gl::ONE_MINUS_CONSTANT_ALPHA);
+            },
+            BlendMode::Additive => {
+                gl::BlendEquation(gl::FUNC_ADD);
+                gl::BlendFunc(gl::ONE, gl::ONE);
+            },
+            BlendMode::Subtractive => {
+                // The polygon's color is taken away from what's behind it, not the other way around.
+                gl::BlendEquation(gl::FUNC_REVERSE_SUBTRACT);
+                gl::BlendFunc(gl::ONE, gl::ONE);
+            },
+            BlendMode::QuarterAdditive => {
+                gl::BlendEquation(gl::FUNC_ADD);
+                gl::BlendColor(0.0, 0.0, 0.0, 0.25);
+                gl::BlendFunc(gl::CONSTANT_ALPHA, gl::ONE);
+            },
+        }
+    }
+}
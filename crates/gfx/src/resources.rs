@@ -0,0 +1,329 @@
+//! RAII wrappers around OpenGL objects.
+//!
+//! Every wrapper deletes its object when dropped, and keeps a count of how many objects of its kind are alive. The
+//! counts can be read with [`live_resources`], which makes it easy to spot leaks: switching between models should never
+//! make them grow.
+//!
+//! Objects are created with direct state access when the context supports it. Otherwise, they are only given names,
+//! and don't actually exist until they are first bound; see [`has_dsa`].
+
+use std::fmt::{self, Display};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use gl::types::*;
+
+use crate::{has_anisotropic_filtering, has_dsa};
+
+
+static BUFFERS: AtomicUsize = AtomicUsize::new(0);
+static VERTEX_ARRAYS: AtomicUsize = AtomicUsize::new(0);
+static TEXTURES: AtomicUsize = AtomicUsize::new(0);
+static FRAMEBUFFERS: AtomicUsize = AtomicUsize::new(0);
+static RENDERBUFFERS: AtomicUsize = AtomicUsize::new(0);
+static PROGRAMS: AtomicUsize = AtomicUsize::new(0);
+
+
+/// `GL_TEXTURE_MAX_ANISOTROPY`, which the `gl` crate's 4.5 bindings don't include. The extensions use the same value.
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+
+
+/// How many of each kind of GL object are currently alive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceCounts {
+    pub buffers: usize,
+    pub vertex_arrays: usize,
+    pub textures: usize,
+    pub framebuffers: usize,
+    pub renderbuffers: usize,
+    pub programs: usize,
+}
+
+
+impl Display for ResourceCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "buf: {}, vao: {}, tex: {}, fbo: {}, rbo: {}, prog: {}",
+            self.buffers, self.vertex_arrays, self.textures, self.framebuffers, self.renderbuffers, self.programs,
+        )
+    }
+}
+
+
+/// Counts the GL objects that are currently alive.
+pub fn live_resources() -> ResourceCounts {
+    ResourceCounts {
+        buffers: BUFFERS.load(Ordering::Relaxed),
+        vertex_arrays: VERTEX_ARRAYS.load(Ordering::Relaxed),
+        textures: TEXTURES.load(Ordering::Relaxed),
+        framebuffers: FRAMEBUFFERS.load(Ordering::Relaxed),
+        renderbuffers: RENDERBUFFERS.load(Ordering::Relaxed),
+        programs: PROGRAMS.load(Ordering::Relaxed),
+    }
+}
+
+
+macro_rules! gl_object {
+    ($name:ident, $counter:ident, $create:ident, $gen:ident, $delete:ident, $doc:literal) => {
+        #[doc=$doc]
+        #[derive(Debug)]
+        pub struct $name(GLuint);
+
+        impl $name {
+            pub fn id(&self) -> GLuint {
+                self.0
+            }
+        }
+
+        impl Drop for $name {
+            fn drop(&mut self) {
+                unsafe { gl::$delete(1, &self.0) };
+                $counter.fetch_sub(1, Ordering::Relaxed);
+            }
+        }
+    };
+    ($name:ident, $counter:ident, $create:ident, $gen:ident, $delete:ident, $doc:literal, no_target) => {
+        gl_object!($name, $counter, $create, $gen, $delete, $doc);
+
+        impl $name {
+            pub fn new() -> Self {
+                let mut id = 0;
+                if has_dsa() {
+                    unsafe { gl::$create(1, &mut id) };
+                } else {
+                    unsafe { gl::$gen(1, &mut id) };
+                }
+                $counter.fetch_add(1, Ordering::Relaxed);
+                Self(id)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+gl_object!(GlBuffer, BUFFERS, CreateBuffers, GenBuffers, DeleteBuffers, "An owned buffer object.", no_target);
+gl_object!(
+    GlVertexArray, VERTEX_ARRAYS,
+    CreateVertexArrays, GenVertexArrays, DeleteVertexArrays,
+    "An owned VAO.", no_target
+);
+gl_object!(
+    GlFramebuffer, FRAMEBUFFERS,
+    CreateFramebuffers, GenFramebuffers, DeleteFramebuffers,
+    "An owned FBO.", no_target
+);
+gl_object!(
+    GlRenderbuffer, RENDERBUFFERS,
+    CreateRenderbuffers, GenRenderbuffers, DeleteRenderbuffers,
+    "An owned RBO.", no_target
+);
+gl_object!(GlTexture, TEXTURES, CreateTextures, GenTextures, DeleteTextures, "An owned texture object.");
+
+
+impl GlTexture {
+    /// Creates a new texture object for the given target (e.g., `gl::TEXTURE_2D`). Without DSA, the target is only set
+    /// once the texture is first bound.
+    pub fn new(target: GLenum) -> Self {
+        let mut id = 0;
+        if has_dsa() {
+            unsafe { gl::CreateTextures(target, 1, &mut id) };
+        } else {
+            unsafe { gl::GenTextures(1, &mut id) };
+        }
+        TEXTURES.fetch_add(1, Ordering::Relaxed);
+        Self(id)
+    }
+}
+
+
+/// How a texture is sampled when it is drawn larger or smaller than its actual size.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TextureFilter {
+    /// Blocky, unfiltered pixels, like the original game.
+    #[default]
+    Nearest,
+    /// Smooth, but without mipmaps, so textures shimmer when they are far away.
+    Bilinear,
+    /// Smooth, blending between mipmap levels.
+    Trilinear,
+    /// Trilinear filtering that stays sharp at steep angles, taking up to the given number of samples. Falls back to
+    /// trilinear filtering when the context doesn't support it.
+    Anisotropic(f32),
+}
+
+
+impl TextureFilter {
+    /// Whether or not this filter reads from a texture's mipmaps. Textures without mipmaps should only be drawn with
+    /// filters that don't.
+    pub fn uses_mipmaps(self) -> bool {
+        matches!(self, TextureFilter::Trilinear | TextureFilter::Anisotropic(_))
+    }
+}
+
+
+impl GlTexture {
+    /// Sets how a 2D texture is sampled.
+    pub fn set_filter(&self, filter: TextureFilter) {
+        let (min, mag) = match filter {
+            TextureFilter::Nearest => (gl::NEAREST, gl::NEAREST),
+            TextureFilter::Bilinear => (gl::LINEAR, gl::LINEAR),
+            TextureFilter::Trilinear | TextureFilter::Anisotropic(_) => (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR),
+        };
+        let anisotropy = match filter {
+            TextureFilter::Anisotropic(samples) => samples.max(1.0),
+            _ => 1.0,
+        };
+
+        unsafe {
+            if has_dsa() {
+                gl::TextureParameteri(self.0, gl::TEXTURE_MIN_FILTER, min as GLint);
+                gl::TextureParameteri(self.0, gl::TEXTURE_MAG_FILTER, mag as GLint);
+                if has_anisotropic_filtering() {
+                    gl::TextureParameterf(self.0, TEXTURE_MAX_ANISOTROPY, anisotropy);
+                }
+            } else {
+                gl::BindTexture(gl::TEXTURE_2D, self.0);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min as GLint);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag as GLint);
+                if has_anisotropic_filtering() {
+                    gl::TexParameterf(gl::TEXTURE_2D, TEXTURE_MAX_ANISOTROPY, anisotropy);
+                }
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+            }
+        }
+    }
+
+    /// Fills in every mipmap level of a 2D texture from its first level.
+    pub fn generate_mipmaps(&self) {
+        unsafe {
+            if has_dsa() {
+                gl::GenerateTextureMipmap(self.0);
+            } else {
+                gl::BindTexture(gl::TEXTURE_2D, self.0);
+                gl::GenerateMipmap(gl::TEXTURE_2D);
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+            }
+        }
+    }
+}
+
+
+/// The number of mipmap levels that a full chain needs for a texture of the given size.
+pub fn mip_levels(width: u32, height: u32) -> u32 {
+    32 - width.max(height).max(1).leading_zeros()
+}
+
+
+impl GlBuffer {
+    /// Creates a new buffer and fills it with the given data.
+    pub fn with_data<T>(data: &[T], usage: GLenum) -> Self {
+        let buffer = Self::new();
+        let size = std::mem::size_of_val(data).try_into().expect("Buffer data is too large.");
+        if has_dsa() {
+            unsafe { gl::NamedBufferData(buffer.0, size, data.as_ptr().cast(), usage) };
+        } else {
+            unsafe {
+                gl::BindBuffer(gl::ARRAY_BUFFER, buffer.0);
+                gl::BufferData(gl::ARRAY_BUFFER, size, data.as_ptr().cast(), usage);
+                gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            }
+        }
+        buffer
+    }
+}
+
+
+/// An owned, linked shader program.
+#[derive(Debug)]
+pub struct GlProgram(GLuint);
+
+
+impl GlProgram {
+    /// Compiles and links a program from vertex and fragment shader sources. On failure, returns the info log from
+    /// whichever step failed.
+    pub fn new(vert_source: &str, frag_source: &str) -> Result<Self, String> {
+        let vert_shader = unsafe { compile_shader(gl::VERTEX_SHADER, vert_source) }?;
+        let frag_shader = unsafe { compile_shader(gl::FRAGMENT_SHADER, frag_source) }.inspect_err(|_| unsafe {
+            gl::DeleteShader(vert_shader);
+        })?;
+
+        let program = unsafe { gl::CreateProgram() };
+        PROGRAMS.fetch_add(1, Ordering::Relaxed);
+        let program = Self(program);
+
+        unsafe {
+            gl::AttachShader(program.0, vert_shader);
+            gl::AttachShader(program.0, frag_shader);
+            gl::LinkProgram(program.0);
+
+            // Shaders are only flagged for deletion while attached; they go away along with the program.
+            gl::DeleteShader(vert_shader);
+            gl::DeleteShader(frag_shader);
+        }
+
+        // Error check program
+        unsafe {
+            let mut success = 0;
+            gl::GetProgramiv(program.0, gl::LINK_STATUS, &mut success);
+            if (success as GLboolean) == gl::FALSE {
+                let mut log_size = 0;
+                gl::GetProgramiv(program.0, gl::INFO_LOG_LENGTH, &mut log_size);
+
+                let mut buffer = vec![0; log_size as usize];
+                gl::GetProgramInfoLog(program.0, log_size, std::ptr::null_mut(), buffer.as_mut_ptr().cast());
+
+                return Err(String::from_utf8_lossy(&buffer).into_owned());
+            }
+        }
+
+        Ok(program)
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.0
+    }
+}
+
+
+impl Drop for GlProgram {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteProgram(self.0) };
+        PROGRAMS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+
+unsafe fn compile_shader(shader_type: GLuint, source: &str) -> Result<GLuint, String> {
+    let shader = gl::CreateShader(shader_type);
+    let src = source.as_bytes().as_ptr().cast::<i8>();
+    let len: i32 = source.len().try_into().or(Err("Shader source is too long.".to_owned()))?;
+
+    // glShaderSource *actually* expects two arrays here, but since they expect C-style arrays and we've told them that
+    // there'll be only one, we can just pass the pointers directly.
+    gl::ShaderSource(shader, 1, &src, &len);
+    gl::CompileShader(shader);
+
+    let mut success = 0;
+    gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut success);
+
+    if (success as GLboolean) == gl::FALSE {
+        let mut log_size = 0;
+        gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut log_size);
+
+        let mut buffer = vec![0; log_size as usize];
+        gl::GetShaderInfoLog(shader, log_size, std::ptr::null_mut(), buffer.as_mut_ptr().cast());
+
+        let log_output = String::from_utf8_lossy(&buffer[..]);
+        println!("Could not compile shader. Info log:\n{}", log_output);
+
+        gl::DeleteShader(shader);
+        Err(log_output.into_owned())
+    } else {
+        Ok(shader)
+    }
+}
@@ -0,0 +1,174 @@
+//! An optional render mode that emulates the way the original PlayStation release presented its graphics.
+
+use gl::types::*;
+
+use crate::{has_dsa, GlFramebuffer, GlRenderbuffer, GlTexture};
+
+
+/// The resolution used by the PSX-style render mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetroResolution {
+    /// Render at a fraction of the window's framebuffer size; e.g., `Scaled(3)` renders at one third the width and
+    /// height of the window. The result is stretched to fill the window.
+    Scaled(u32),
+    /// Render at the game's original 320×240 resolution. The result is upscaled by the largest integer factor that
+    /// fits in the window and centered, so that every original pixel is the same size on screen.
+    Native,
+}
+
+
+impl RetroResolution {
+    /// The resolution that field scenes were originally rendered at.
+    pub const NATIVE_SIZE: (i32, i32) = (320, 240);
+}
+
+
+/// Settings for the PSX-style render mode.
+#[derive(Debug, Clone, Copy)]
+pub struct RetroSettings {
+    /// Whether or not the scene should be rendered with the PSX look at all.
+    pub enabled: bool,
+    /// The resolution of the low-resolution render target. The result is upscaled with nearest-neighbour filtering.
+    pub resolution: RetroResolution,
+    /// Whether or not to quantize output to 15-bit color using the PSX's ordered dithering pattern.
+    pub dither: bool,
+    /// Whether or not to interpolate vertex attributes without perspective correction, which gives the PSX's
+    /// characteristic texture "wobble".
+    pub affine: bool,
+    /// Whether or not to snap vertices to whole pixels of the low-resolution target, emulating the PSX's lack of
+    /// sub-pixel precision for screen-space vertex positions. This is what makes animated models "jitter".
+    pub vertex_snap: bool,
+}
+
+
+impl Default for RetroSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            resolution: RetroResolution::Scaled(3),
+            dither: true,
+            affine: true,
+            vertex_snap: true,
+        }
+    }
+}
+
+
+impl RetroSettings {
+    /// Computes the size of the low-resolution render target for a framebuffer of the given size.
+    pub fn target_size(&self, framebuffer_size: (i32, i32)) -> (i32, i32) {
+        match self.resolution {
+            RetroResolution::Scaled(scale) => {
+                let scale = scale.max(1) as i32;
+                let (width, height) = framebuffer_size;
+                ((width / scale).max(1), (height / scale).max(1))
+            },
+            RetroResolution::Native => RetroResolution::NATIVE_SIZE,
+        }
+    }
+
+    /// Computes the rectangle (`x`, `y`, `width`, `height`) of a framebuffer of the given size that the low-resolution
+    /// render target should be upscaled onto.
+    pub fn screen_rect(&self, framebuffer_size: (i32, i32)) -> (i32, i32, i32, i32) {
+        let (fb_w, fb_h) = framebuffer_size;
+        match self.resolution {
+            RetroResolution::Scaled(_) => (0, 0, fb_w, fb_h),
+            RetroResolution::Native => {
+                let (src_w, src_h) = RetroResolution::NATIVE_SIZE;
+                // If the window is smaller than 320x240, there is no integer scale that fits, so just shrink it.
+                let scale = (fb_w / src_w).min(fb_h / src_h);
+                if scale < 1 {
+                    (0, 0, fb_w, fb_h)
+                } else {
+                    let (w, h) = (src_w * scale, src_h * scale);
+                    ((fb_w - w) / 2, (fb_h - h) / 2, w, h)
+                }
+            },
+        }
+    }
+}
+
+
+/// An off-screen framebuffer with an sRGB color attachment and a depth attachment.
+///
+/// The color attachment is sRGB so that quantized 15-bit colors are stored exactly, instead of being smeared by the
+/// precision loss of storing linear values in 8 bits.
+pub struct RenderTarget {
+    fbo: GlFramebuffer,
+    color: GlTexture,
+    depth: GlRenderbuffer,
+    size: (i32, i32),
+}
+
+
+impl RenderTarget {
+    pub fn new(size: (i32, i32)) -> Self {
+        let (width, height) = size;
+        let color = GlTexture::new(gl::TEXTURE_2D);
+        let depth = GlRenderbuffer::new();
+        let fbo = GlFramebuffer::new();
+
+        if has_dsa() {
+            unsafe {
+                gl::TextureStorage2D(color.id(), 1, gl::SRGB8_ALPHA8, width, height);
+                gl::TextureParameteri(color.id(), gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+                gl::TextureParameteri(color.id(), gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+
+                gl::NamedRenderbufferStorage(depth.id(), gl::DEPTH_COMPONENT24, width, height);
+
+                gl::NamedFramebufferTexture(fbo.id(), gl::COLOR_ATTACHMENT0, color.id(), 0);
+                gl::NamedFramebufferRenderbuffer(fbo.id(), gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, depth.id());
+            }
+        } else {
+            unsafe {
+                let (format, kind, pixels) = (gl::RGBA, gl::UNSIGNED_BYTE, std::ptr::null());
+                gl::BindTexture(gl::TEXTURE_2D, color.id());
+                gl::TexImage2D(gl::TEXTURE_2D, 0, gl::SRGB8_ALPHA8 as GLint, width, height, 0, format, kind, pixels);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as GLint);
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as GLint);
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+
+                gl::BindRenderbuffer(gl::RENDERBUFFER, depth.id());
+                gl::RenderbufferStorage(gl::RENDERBUFFER, gl::DEPTH_COMPONENT24, width, height);
+                gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+
+                gl::BindFramebuffer(gl::FRAMEBUFFER, fbo.id());
+                gl::FramebufferTexture2D(gl::FRAMEBUFFER, gl::COLOR_ATTACHMENT0, gl::TEXTURE_2D, color.id(), 0);
+                gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, gl::RENDERBUFFER, depth.id());
+                gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            }
+        }
+
+        Self { fbo, color, depth, size }
+    }
+
+    pub fn size(&self) -> (i32, i32) {
+        self.size
+    }
+
+    /// Binds this target for drawing and sets the viewport to cover it.
+    pub fn bind(&self) {
+        let (width, height) = self.size;
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo.id());
+            gl::Viewport(0, 0, width, height);
+        }
+    }
+
+    /// Copies this target's color attachment onto the given rectangle (`x`, `y`, `width`, `height`) of the default
+    /// framebuffer, scaling with nearest-neighbour filtering.
+    pub fn blit_to_screen(&self, dest: (i32, i32, i32, i32)) {
+        let (src_w, src_h) = self.size;
+        let (x, y, w, h) = dest;
+        let (fbo, mask) = (self.fbo.id(), gl::COLOR_BUFFER_BIT);
+        if has_dsa() {
+            unsafe { gl::BlitNamedFramebuffer(fbo, 0, 0, 0, src_w, src_h, x, y, x + w, y + h, mask, gl::NEAREST) };
+        } else {
+            unsafe {
+                gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+                gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, 0);
+                gl::BlitFramebuffer(0, 0, src_w, src_h, x, y, x + w, y + h, mask, gl::NEAREST);
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Saving what's on screen as a PNG.
+//!
+//! Images are written without compression, using stored deflate blocks, so no image or compression library is needed.
+//! The files are large, but any PNG reader can open them.
+
+use std::path::Path;
+
+use gl::types::*;
+
+
+/// The largest amount of data that one stored deflate block can hold.
+const STORED_BLOCK_MAX: usize = 0xFFFF;
+
+
+/// Reads the currently bound framebuffer's pixels as 8-bit RGBA, top row first.
+pub fn read_framebuffer(width: u32, height: u32) -> Vec<u8> {
+    let row_len = width as usize * 4;
+    let mut pixels = vec![0u8; row_len * height as usize];
+    unsafe {
+        gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+        let (w, h) = (width as GLsizei, height as GLsizei);
+        gl::ReadPixels(0, 0, w, h, gl::RGBA, gl::UNSIGNED_BYTE, pixels.as_mut_ptr().cast());
+    }
+
+    // GL's rows start from the bottom.
+    pixels.chunks_exact(row_len.max(1)).rev().flatten().copied().collect()
+}
+
+
+/// Reads the currently bound framebuffer and saves it as a PNG.
+pub fn save_screenshot(path: impl AsRef<Path>, width: u32, height: u32) -> std::io::Result<()> {
+    let pixels = read_framebuffer(width, height);
+    std::fs::write(path, encode_png(width, height, &pixels))
+}
+
+
+/// Encodes 8-bit RGBA pixels, top row first, as a PNG.
+pub fn encode_png(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+    let row_len = width as usize * 4;
+
+    // Every row starts with its filter type, which is always 0 (none).
+    let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+    for row in pixels.chunks_exact(row_len.max(1)).take(height as usize) {
+        raw.push(0);
+        raw.extend_from_slice(row);
+    }
+
+    // A zlib stream of stored (uncompressed) deflate blocks.
+    let mut zlib = vec![0x78, 0x01];
+    let mut blocks = raw.chunks(STORED_BLOCK_MAX).peekable();
+    if blocks.peek().is_none() {
+        zlib.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(block) = blocks.next() {
+        let is_final = blocks.peek().is_none();
+        let len = block.len() as u16;
+        zlib.push(is_final as u8);
+        zlib.extend_from_slice(&len.to_le_bytes());
+        zlib.extend_from_slice(&(!len).to_le_bytes());
+        zlib.extend_from_slice(block);
+    }
+    zlib.extend_from_slice(&adler32(&raw).to_be_bytes());
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    header.extend_from_slice(&[8, 6, 0, 0, 0]); // 8 bits per channel, RGBA, no interlacing
+
+    let mut png = b"\x89PNG\r\n\x1A\n".to_vec();
+    push_chunk(&mut png, b"IHDR", &header);
+    push_chunk(&mut png, b"IDAT", &zlib);
+    push_chunk(&mut png, b"IEND", &[]);
+    png
+}
+
+
+/// Writes one PNG chunk: its length, type, data, and the CRC of its type and data.
+fn push_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+
+/// The CRC-32 used by PNG (and zlib, and most everything else).
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+
+/// The Adler-32 checksum that ends a zlib stream.
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
@@ -1,8 +1,81 @@
-#version 460 core
+// The `#version` directive is added when the shader is compiled, since it depends on the context's version. The
+// textured variant is compiled with `#define TEXTURED` after it.
 
 in vec3 vertex_color;
+noperspective in vec3 vertex_color_affine;
+flat in vec3 vertex_color_flat;
+in vec2 vertex_uv;
+noperspective in vec2 vertex_uv_affine;
 out vec4 frag_color;
 
+#ifdef TEXTURED
+// Textures are stored in sRGB, so sampling them gives linear colors.
+uniform sampler2D u_texture;
+// Whether or not the texture is color-keyed. Other textures are drawn as they are, whatever their alpha.
+uniform bool u_color_key;
+#endif
+
+// PSX-style rendering options
+uniform bool u_dither;
+uniform bool u_affine;
+
+// Gives each polygon a single color, for seeing its shape.
+uniform bool u_flat;
+
+// The PSX's ordered dithering matrix, applied to 8-bit color values before they are truncated to 5 bits.
+const float DITHER[16] = float[](
+    -4.0,  0.0, -3.0,  1.0,
+     2.0, -2.0,  3.0, -1.0,
+    -3.0,  1.0, -4.0,  0.0,
+     3.0, -1.0,  2.0, -2.0
+);
+
+vec3 srgb_to_linear(vec3 color) {
+    bvec3 cutoff = lessThanEqual(color, vec3(0.04045));
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, cutoff);
+}
+
+vec3 linear_to_srgb(vec3 color) {
+    bvec3 cutoff = lessThanEqual(color, vec3(0.0031308));
+    vec3 low = color * 12.92;
+    vec3 high = 1.055 * pow(color, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, cutoff);
+}
+
+// Quantizes a linear color to 15 bits, the same way the PSX's GPU did: in display (sRGB) space, after dithering.
+vec3 dither_15bit(vec3 color) {
+    ivec2 pixel = ivec2(gl_FragCoord.xy) & 3;
+    float offset = DITHER[pixel.y * 4 + pixel.x];
+
+    vec3 display = clamp(linear_to_srgb(color) * 255.0 + offset, 0.0, 255.0);
+    vec3 quantized = floor(display / 8.0) / 31.0;
+    return srgb_to_linear(quantized);
+}
+
 void main() {
-    frag_color = vec4(vertex_color, 1.0);
+    vec3 color = u_flat ? vertex_color_flat : u_affine ? vertex_color_affine : vertex_color;
+
+#ifdef TEXTURED
+    // The PSX drew textures without perspective correction, which is what makes them swim.
+    vec4 texel = texture(u_texture, u_affine ? vertex_uv_affine : vertex_uv);
+    if (u_color_key) {
+        // Color-keyed pixels are fully transparent, and are cut out rather than blended.
+        if (texel.a < 0.5) {
+            discard;
+        }
+        // Keyed pixels are decoded as transparent black, so filtering blends black into the edges of cutouts. Dividing
+        // by the filtered alpha (which is at least 0.5 by now) takes that back out, leaving only the colors of the
+        // pixels that are actually shown.
+        texel.rgb /= texel.a;
+    }
+    color *= texel.rgb;
+#endif
+
+    if (u_dither) {
+        color = dither_15bit(color);
+    }
+
+    frag_color = vec4(color, 1.0);
 }
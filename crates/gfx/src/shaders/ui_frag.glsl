@@ -0,0 +1,13 @@
+// The `#version` directive is added when the shader is compiled, since it depends on the context's version.
+
+in vec2 vertex_uv;
+in vec4 vertex_color;
+out vec4 frag_color;
+
+// egui's textures hold sRGB colors with premultiplied alpha, just like its vertices, and are blended without being
+// converted to linear first.
+uniform sampler2D u_texture;
+
+void main() {
+    frag_color = vertex_color * texture(u_texture, vertex_uv);
+}
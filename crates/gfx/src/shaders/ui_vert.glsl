@@ -0,0 +1,20 @@
+// The `#version` directive is added when the shader is compiled, since it depends on the context's version.
+
+// egui's vertices: positions in points, texture coordinates, and sRGB colors with premultiplied alpha.
+layout (location = 0) in vec2 a_position;
+layout (location = 1) in vec2 a_uv;
+layout (location = 2) in vec4 a_color;
+
+out vec2 vertex_uv;
+out vec4 vertex_color;
+
+// The size of the screen, in points.
+uniform vec2 u_screen_size;
+
+void main() {
+    // Points start from the top left, with +Y down.
+    vec2 ndc = a_position / u_screen_size * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+    vertex_uv = a_uv;
+    vertex_color = a_color;
+}
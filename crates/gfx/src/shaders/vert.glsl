@@ -1,11 +1,73 @@
-#version 460 core
+// The `#version` directive is added when the shader is compiled, since it depends on the context's version, along with
+// `MAX_BONES`.
 
 layout (location = 0) in vec3 a_position;
 layout (location = 1) in vec3 a_color;
+layout (location = 2) in vec3 a_normal;
+layout (location = 3) in vec2 a_uv;
+layout (location = 4) in uint a_bone;
 
 out vec3 vertex_color;
+noperspective out vec3 vertex_color_affine;
+flat out vec3 vertex_color_flat;
+out vec2 vertex_uv;
+noperspective out vec2 vertex_uv_affine;
+
+// Fits the model into a box two units across, centered on the origin, with +Y up.
+uniform mat4 u_transform;
+// Every bone's transform in the current pose, which places each vertex within the model. Slot 0 is the identity, for
+// vertices without a bone.
+layout (std140) uniform Bones {
+    mat4 u_bones[MAX_BONES];
+};
+// The camera, and how what it sees is projected onto the screen.
+uniform mat4 u_view;
+uniform mat4 u_projection;
+
+// A directional light and ambient term. Vertices without normals are left unlit.
+uniform bool u_lighting;
+uniform vec3 u_light_direction;
+uniform vec3 u_light_color;
+uniform vec3 u_ambient;
+
+// PSX-style rendering options
+uniform bool u_vertex_snap;
+uniform vec2 u_resolution;
+
+// Vertex colors are stored in sRGB, but all shading happens in linear space. The framebuffer converts back to sRGB.
+vec3 srgb_to_linear(vec3 color) {
+    bvec3 cutoff = lessThanEqual(color, vec3(0.04045));
+    vec3 low = color / 12.92;
+    vec3 high = pow((color + 0.055) / 1.055, vec3(2.4));
+    return mix(high, low, cutoff);
+}
+
+// Rounds a clip-space position to the nearest whole pixel, like the PSX's integer screen-space vertex coordinates.
+vec4 snap_to_pixel(vec4 position) {
+    vec2 pixel = round((position.xy / position.w * 0.5 + 0.5) * u_resolution);
+    position.xy = (pixel / u_resolution * 2.0 - 1.0) * position.w;
+    return position;
+}
 
 void main() {
-    gl_Position = vec4(a_position, 1.0);
-    vertex_color = a_color;
+    mat4 model = u_bones[a_bone];
+    gl_Position = u_projection * u_view * u_transform * model * vec4(a_position, 1.0);
+    if (u_vertex_snap) {
+        gl_Position = snap_to_pixel(gl_Position);
+    }
+
+    vertex_color = srgb_to_linear(a_color);
+
+    // Lighting is done per vertex, like the game did. The bone and fit transforms are only ever rotations,
+    // translations, and uniform scales, so they can move normals as they are.
+    if (u_lighting && a_normal != vec3(0.0)) {
+        vec3 normal = normalize(mat3(u_transform * model) * a_normal);
+        float facing = max(dot(normal, -normalize(u_light_direction)), 0.0);
+        vertex_color *= u_ambient + u_light_color * facing;
+    }
+
+    vertex_color_affine = vertex_color;
+    vertex_color_flat = vertex_color;
+    vertex_uv = a_uv;
+    vertex_uv_affine = a_uv;
 }
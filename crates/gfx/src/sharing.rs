@@ -0,0 +1,88 @@
+//! Sharing textures between models that use the same image.
+//!
+//! Many models in an archive use the same TEX files (e.g., every model of a character shares its face textures). Each
+//! texture is uploaded once, and every model that uses it gets another reference to the same GL object. The cache only
+//! holds weak references, so a texture is still deleted as soon as the last model using it is dropped.
+
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::rc::{Rc, Weak};
+
+use crate::GlTexture;
+
+
+/// Identifies one decoded image: which archive and file it came from, and which of the file's palettes it was decoded
+/// with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TextureKey {
+    pub archive: String,
+    /// The file's name, which is compared case-insensitively, like names in archives are.
+    pub name: String,
+    pub palette: u32,
+}
+
+
+impl TextureKey {
+    pub fn new(archive: impl Into<String>, name: &str, palette: u32) -> Self {
+        Self { archive: archive.into(), name: name.to_ascii_lowercase(), palette }
+    }
+}
+
+
+/// How much a [`SharedTextures`] cache is being shared.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SharingStats {
+    /// How many distinct textures are alive.
+    pub textures: usize,
+    /// How many references to those textures are held, across every model.
+    pub references: usize,
+    /// How many times a texture was asked for and already existed, so didn't need to be uploaded again.
+    pub hits: usize,
+}
+
+
+impl Display for SharingStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "shared tex: {}, refs: {}, hits: {}", self.textures, self.references, self.hits)
+    }
+}
+
+
+/// A cache of the textures that are currently loaded, so that models using the same image share one GL texture.
+#[derive(Debug, Default)]
+pub struct SharedTextures {
+    textures: HashMap<TextureKey, Weak<GlTexture>>,
+    hits: usize,
+}
+
+
+impl SharedTextures {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the texture for the given key, if it's still alive, or creates it with `create` (e.g., with
+    /// [`UploadQueue::texture_rgba`][crate::UploadQueue::texture_rgba]) if it isn't.
+    pub fn get_or_create(&mut self, key: TextureKey, create: impl FnOnce() -> Rc<GlTexture>) -> Rc<GlTexture> {
+        if let Some(texture) = self.textures.get(&key).and_then(Weak::upgrade) {
+            self.hits += 1;
+            return texture;
+        }
+
+        let texture = create();
+        self.textures.insert(key, Rc::downgrade(&texture));
+        texture
+    }
+
+    /// Forgets about textures that have been dropped. Entries for dropped textures are harmless, but they would
+    /// otherwise pile up as models are loaded and unloaded.
+    pub fn prune(&mut self) {
+        self.textures.retain(|_, texture| texture.strong_count() > 0);
+    }
+
+    pub fn stats(&self) -> SharingStats {
+        let alive = self.textures.values().map(Weak::strong_count).filter(|&count| count > 0);
+        let (textures, references) = alive.fold((0, 0), |(textures, refs), count| (textures + 1, refs + count));
+        SharingStats { textures, references, hits: self.hits }
+    }
+}
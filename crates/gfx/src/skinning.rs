@@ -0,0 +1,93 @@
+//! Posing models on the GPU.
+//!
+//! Every vertex names the bone that moves it (see [`MeshVertex::bone`][crate::MeshVertex::bone]), and the pose's bone
+//! transforms are uploaded once per frame to a uniform buffer that every program reads from. Nothing about a model has
+//! to change on the CPU while it's animated, and its meshes can be drawn without switching uniforms between them.
+//!
+//! Slot 0 of the buffer always holds the identity, for vertices that aren't attached to a bone. Bone `i` of the pose
+//! goes in slot `i + 1`.
+
+use ff7::char::{Matrix, Pose, IDENTITY};
+use gl::types::*;
+
+use crate::{has_dsa, GlBuffer, GlProgram};
+
+
+/// How many bone transforms fit in the buffer, including the identity in slot 0. 256 of them take up 16 KiB, which is
+/// the most that every GL implementation has to allow in one uniform block.
+pub const MAX_BONES: usize = 256;
+
+/// The uniform buffer binding point that the bone transforms are bound to.
+pub const BONE_BLOCK_BINDING: GLuint = 0;
+
+/// The name of the vertex shader's uniform block that holds the bone transforms.
+const BONE_BLOCK_NAME: &[u8] = b"Bones\0";
+
+
+/// The slot in a [`BoneBuffer`] that a vertex attached to the given bone should use. Vertices on bones that don't fit
+/// in the buffer aren't moved, just like those without a bone.
+pub fn bone_slot(bone: Option<usize>) -> u32 {
+    bone.map(|bone| bone + 1).filter(|&slot| slot < MAX_BONES).unwrap_or(0) as u32
+}
+
+
+/// A uniform buffer holding every bone's transform in the current pose.
+#[derive(Debug)]
+pub struct BoneBuffer {
+    buffer: GlBuffer,
+    /// The transforms that were last uploaded, kept to avoid allocating every frame.
+    matrices: Vec<Matrix>,
+}
+
+
+impl BoneBuffer {
+    /// Creates a buffer with every slot holding the identity, and binds it to [`BONE_BLOCK_BINDING`].
+    pub fn new() -> Self {
+        let matrices = vec![IDENTITY; MAX_BONES];
+        let buffer = GlBuffer::with_data(&matrices, gl::DYNAMIC_DRAW);
+        unsafe { gl::BindBufferBase(gl::UNIFORM_BUFFER, BONE_BLOCK_BINDING, buffer.id()) };
+        Self { buffer, matrices: Vec::with_capacity(MAX_BONES) }
+    }
+
+    /// Uploads a pose's bone transforms. Only as many slots as the pose uses are written.
+    pub fn update(&mut self, pose: &Pose) {
+        self.matrices.clear();
+        self.matrices.push(IDENTITY);
+        self.matrices.extend(pose.bones.iter().take(MAX_BONES - 1));
+
+        // Matrices are column-major, so they're already laid out the way std140 lays out a `mat4`.
+        let size = std::mem::size_of_val(self.matrices.as_slice()) as GLsizeiptr;
+        let data = self.matrices.as_ptr().cast();
+        unsafe {
+            if has_dsa() {
+                gl::NamedBufferSubData(self.buffer.id(), 0, size, data);
+            } else {
+                gl::BindBuffer(gl::UNIFORM_BUFFER, self.buffer.id());
+                gl::BufferSubData(gl::UNIFORM_BUFFER, 0, size, data);
+                gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+            }
+            // Something else may have taken over the binding point since the last frame.
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, BONE_BLOCK_BINDING, self.buffer.id());
+        }
+    }
+}
+
+
+impl Default for BoneBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+
+/// Points a program's bone transform block at [`BONE_BLOCK_BINDING`]. GLSL 3.30 can't choose the binding in the
+/// shader itself. Programs without the block are left alone.
+pub fn bind_bone_block(program: &GlProgram) {
+    unsafe {
+        let index = gl::GetUniformBlockIndex(program.id(), BONE_BLOCK_NAME.as_ptr().cast());
+        if index != gl::INVALID_INDEX {
+            gl::UniformBlockBinding(program.id(), index, BONE_BLOCK_BINDING);
+        }
+    }
+}
+
@@ -0,0 +1,116 @@
+//! Uploading models' decoded textures and binding them for drawing.
+//!
+//! Textures are uploaded through an [`UploadQueue`] like everything else, and shared between models through a
+//! [`SharedTextures`] cache. A mesh's groups refer to textures by their position in the mesh's RSD file, so each mesh
+//! keeps its own list of which uploaded texture each of those positions ended up as. Positions whose TEX file is
+//! missing or couldn't be decoded get a [placeholder][TextureManager::placeholder] instead, so that they stand out.
+
+use std::rc::Rc;
+
+use ff7::char::RgbaImage;
+
+use crate::{has_dsa, GlTexture, SharedTextures, SharingStats, TextureFilter, TextureKey, UploadQueue};
+
+
+/// The size of the placeholder texture, and how many pixels wide each of its squares is.
+const PLACEHOLDER_SIZE: u32 = 8;
+const PLACEHOLDER_SQUARE: u32 = 2;
+
+
+/// Keeps track of every texture that has been loaded, so that models using the same image share it.
+#[derive(Debug, Default)]
+pub struct TextureManager {
+    shared: SharedTextures,
+}
+
+
+impl TextureManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Gets the texture for a decoded image, queueing it up to be uploaded if the same image isn't loaded already.
+    pub fn load(
+        &mut self,
+        uploads: &mut UploadQueue,
+        key: TextureKey,
+        image: &RgbaImage,
+        filter: TextureFilter,
+    ) -> Rc<GlTexture> {
+        self.shared.get_or_create(key, || {
+            uploads.texture_rgba(image.width, image.height, image.pixels.clone(), filter)
+        })
+    }
+
+    /// Gets the texture drawn in place of ones that couldn't be loaded: a magenta and black checkerboard, which doesn't
+    /// look like anything in the game. It's shared like any other texture, and is always unfiltered so that its squares
+    /// stay sharp.
+    pub fn placeholder(&mut self, uploads: &mut UploadQueue) -> Rc<GlTexture> {
+        // Textures from an archive always have its path in their key, so this can't be mistaken for one of them.
+        let key = TextureKey::new("", "placeholder", 0);
+        self.shared.get_or_create(key, || {
+            let image = placeholder_image();
+            uploads.texture_rgba(image.width, image.height, image.pixels, TextureFilter::Nearest)
+        })
+    }
+
+    /// See [`SharedTextures::prune`].
+    pub fn prune(&mut self) {
+        self.shared.prune();
+    }
+
+    pub fn stats(&self) -> SharingStats {
+        self.shared.stats()
+    }
+}
+
+
+/// One of the textures that a mesh is drawn with.
+#[derive(Debug, Clone)]
+pub struct MeshTexture {
+    pub texture: Rc<GlTexture>,
+    /// Whether or not the texture is color-keyed, so that its transparent pixels are cut out. See
+    /// [`TextureFile::is_keyed`][ff7::char::TextureFile::is_keyed].
+    pub color_key: bool,
+}
+
+
+/// The textures that one mesh's groups are drawn with.
+#[derive(Debug, Clone, Default)]
+pub struct MeshTextures {
+    /// The texture for each of the mesh's texture indices, or `None` where it couldn't be loaded.
+    pub textures: Vec<Option<MeshTexture>>,
+}
+
+
+impl MeshTextures {
+    /// The texture for one of the mesh's texture indices, if it was loaded.
+    pub fn get(&self, index: Option<u32>) -> Option<&MeshTexture> {
+        self.textures.get(index? as usize)?.as_ref()
+    }
+}
+
+
+/// The image for [`TextureManager::placeholder`].
+fn placeholder_image() -> RgbaImage {
+    let pixels = (0..PLACEHOLDER_SIZE * PLACEHOLDER_SIZE)
+        .flat_map(|i| {
+            let (x, y) = (i % PLACEHOLDER_SIZE / PLACEHOLDER_SQUARE, i / PLACEHOLDER_SIZE / PLACEHOLDER_SQUARE);
+            if (x + y) % 2 == 0 { [255, 0, 255, 255] } else { [0, 0, 0, 255] }
+        })
+        .collect();
+    RgbaImage { width: PLACEHOLDER_SIZE, height: PLACEHOLDER_SIZE, pixels }
+}
+
+
+/// Binds a 2D texture to texture unit 0.
+pub fn bind_texture(texture: &GlTexture) {
+    unsafe {
+        if has_dsa() {
+            gl::BindTextureUnit(0, texture.id());
+        } else {
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindTexture(gl::TEXTURE_2D, texture.id());
+        }
+    }
+}
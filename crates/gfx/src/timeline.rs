@@ -0,0 +1,91 @@
+//! Choosing which animation a model plays, and stepping through it frame by frame.
+
+use crate::{AnimationPlayer, DEFAULT_FRAME_RATE};
+
+
+/// The slowest and fastest that animations can be played, relative to [`DEFAULT_FRAME_RATE`].
+const SPEED_RANGE: std::ops::RangeInclusive<f32> = 0.1..=4.0;
+
+
+/// A list of the animations that fit the current model, and controls for playing the chosen one.
+#[derive(Debug, Clone, Default)]
+pub struct AnimationPanel {
+    /// The animation being played, which is selected in the list.
+    pub current: Option<String>,
+    /// Every animation that can be chosen, sorted by name.
+    animations: Vec<String>,
+}
+
+
+impl AnimationPanel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the list of animations, e.g. after a different model has been loaded. They don't need to be in any
+    /// order.
+    pub fn set_animations<S: Into<String>>(&mut self, animations: impl IntoIterator<Item = S>) {
+        self.animations = animations.into_iter().map(Into::into).collect();
+        self.animations.sort_unstable_by_key(|name| name.to_lowercase());
+    }
+
+    /// Adds the panel's animation list and playback controls to a UI. Returns the name of the animation that was
+    /// chosen, if a different one was.
+    pub fn ui(&mut self, ui: &mut egui::Ui, player: Option<&mut AnimationPlayer>) -> Option<String> {
+        if self.animations.is_empty() {
+            ui.label("No animations in the archive fit this model.");
+            return None;
+        }
+
+        let mut picked = None;
+        egui::ComboBox::from_label("Animation")
+            .selected_text(self.current.as_deref().unwrap_or("None"))
+            .show_ui(ui, |ui| {
+                for name in &self.animations {
+                    let selected = self.current.as_ref() == Some(name);
+                    if ui.selectable_label(selected, name).clicked() && !selected {
+                        picked = Some(name.clone());
+                    }
+                }
+            });
+
+        let Some(player) = player else {
+            return picked;
+        };
+
+        ui.horizontal(|ui| {
+            if ui.button("<").on_hover_text("Previous frame (,)").clicked() {
+                player.step(-1);
+            }
+            let label = if player.playing { "Pause" } else { "Play" };
+            if ui.button(label).on_hover_text("Play or pause (Space)").clicked() {
+                // Playing an animation that has stopped at its end starts it over.
+                if !player.playing && player.finished() {
+                    player.seek(0.0);
+                }
+                player.playing = !player.playing;
+            }
+            if ui.button(">").on_hover_text("Next frame (.)").clicked() {
+                player.step(1);
+            }
+            ui.checkbox(&mut player.looping, "Loop");
+        });
+
+        // Dragging the slider pauses playback, so that the frame it's left on stays on screen.
+        let mut position = player.position();
+        let slider = egui::Slider::new(&mut position, 0.0..=player.last_frame()).text("Frame").fixed_decimals(1);
+        if ui.add(slider).changed() {
+            player.playing = false;
+            player.seek(position);
+        }
+        ui.label(format!("{} frames", player.animation.frame_count()));
+
+        let mut speed = player.frame_rate / DEFAULT_FRAME_RATE;
+        let slider = egui::Slider::new(&mut speed, SPEED_RANGE).text("Speed").logarithmic(true).suffix("×");
+        if ui.add(slider).changed() {
+            player.frame_rate = speed * DEFAULT_FRAME_RATE;
+        }
+
+        picked
+    }
+}
@@ -0,0 +1,124 @@
+//! Recording the model as the camera swings once around it, as an animated GIF or a sequence of PNGs:
+//!
+//! ```text
+//! ff7-viewer view char.lgp --model AAAA.HRC --turntable spin.gif --turntable-frames 72 --turntable-fps 24 --exit
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::{encode_png, read_framebuffer, Camera, GifEncoder};
+
+
+/// How many frames a turntable has if none is chosen: one every 10°.
+pub const DEFAULT_TURNTABLE_FRAMES: u32 = 36;
+
+/// How many frames per second a turntable plays at if no rate is chosen.
+pub const DEFAULT_TURNTABLE_FRAME_RATE: f32 = 12.0;
+
+
+/// How a turntable should be recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurntableOptions {
+    /// Where to save the turntable. Paths ending in `.gif` are saved as an animated GIF; anything else is a directory,
+    /// which is filled with numbered PNGs.
+    pub path: PathBuf,
+    /// How many frames to take over one full turn.
+    pub frames: u32,
+    /// How many frames are shown per second. Animations also move on by this much between frames.
+    pub frame_rate: f32,
+}
+
+
+impl TurntableOptions {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), frames: DEFAULT_TURNTABLE_FRAMES, frame_rate: DEFAULT_TURNTABLE_FRAME_RATE }
+    }
+
+    /// Whether or not the turntable is saved as a GIF instead of a directory of PNGs.
+    pub fn is_gif(&self) -> bool {
+        self.path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gif"))
+    }
+}
+
+
+/// Where a turntable's frames go as they're captured.
+#[derive(Debug)]
+enum TurntableOutput {
+    Gif(GifEncoder),
+    Pngs(PathBuf),
+}
+
+
+/// A turntable being recorded. Each call to [`capture`][Self::capture] saves whatever has been drawn and swings the
+/// camera on to where the next frame should be taken from.
+#[derive(Debug)]
+pub struct TurntableRecorder {
+    options: TurntableOptions,
+    /// The size of every frame, which is the size of the framebuffer when recording started.
+    size: (u32, u32),
+    captured: u32,
+    output: TurntableOutput,
+}
+
+
+impl TurntableRecorder {
+    /// Starts recording frames of the given size. For PNGs, the directory they go in is created if it needs to be.
+    pub fn new(options: TurntableOptions, width: u32, height: u32) -> std::io::Result<Self> {
+        let output = if options.is_gif() {
+            let too_big = || std::io::Error::new(std::io::ErrorKind::InvalidInput, "the window is too large for a GIF");
+            let width = u16::try_from(width).map_err(|_| too_big())?;
+            let height = u16::try_from(height).map_err(|_| too_big())?;
+            TurntableOutput::Gif(GifEncoder::new(width, height, options.frame_rate))
+        } else {
+            std::fs::create_dir_all(&options.path)?;
+            TurntableOutput::Pngs(options.path.clone())
+        };
+
+        Ok(Self { options, size: (width, height), captured: 0, output })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.options.path
+    }
+
+    /// How far animations should move on between frames, in seconds.
+    pub fn frame_time(&self) -> f64 {
+        1.0 / self.options.frame_rate as f64
+    }
+
+    /// How many frames have been captured so far, and how many there will be in total.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.captured as usize, self.options.frames as usize)
+    }
+
+    /// Whether or not every frame has been captured, and the turntable can be [finished][Self::finish].
+    pub fn is_done(&self) -> bool {
+        self.captured >= self.options.frames
+    }
+
+    /// Reads the currently bound framebuffer as the next frame, then turns the camera by one step. After the last
+    /// frame, the camera is back where it started.
+    pub fn capture(&mut self, camera: &mut Camera) -> std::io::Result<()> {
+        let (width, height) = self.size;
+        let pixels = read_framebuffer(width, height);
+        match &mut self.output {
+            TurntableOutput::Gif(encoder) => encoder.add_frame(&pixels),
+            TurntableOutput::Pngs(dir) => {
+                let path = dir.join(format!("{:04}.png", self.captured));
+                std::fs::write(path, encode_png(width, height, &pixels))?;
+            },
+        }
+
+        self.captured += 1;
+        camera.orbit(std::f32::consts::TAU / self.options.frames as f32, 0.0);
+        Ok(())
+    }
+
+    /// Saves the GIF, if that's what's being recorded. PNGs are already saved as they're captured.
+    pub fn finish(self) -> std::io::Result<()> {
+        match self.output {
+            TurntableOutput::Gif(encoder) => std::fs::write(&self.options.path, encoder.finish()),
+            TurntableOutput::Pngs(_) => Ok(()),
+        }
+    }
+}
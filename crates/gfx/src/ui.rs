@@ -0,0 +1,453 @@
+//! An [egui] layer drawn over the scene: handing GLFW's events to it, and painting what it produces with GL.
+//!
+//! egui works in "points", each of which is [`Display::ui_scale`] pixels across. It produces sRGB colors with
+//! premultiplied alpha, which are blended as they are, so the framebuffer's sRGB conversion is turned off while the UI
+//! is painted.
+//!
+//! Everything in the UI can be used from the keyboard: Tab and Shift+Tab move between widgets (in the order they're
+//! laid out), Space or Enter uses the one with focus, and Escape gives focus back to the scene.
+
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+
+use egui::epaint::{ImageData, Primitive, Vertex};
+use egui::{Pos2, RawInput, Rect, TextureId, TexturesDelta};
+use gl::types::*;
+use glfw::{Action, Window, WindowEvent};
+
+use crate::{has_dsa, set_uniform_vec2, Display, GlBuffer, GlProgram, GlTexture, GlVertexArray};
+
+
+const UI_VERT_SHADER_SOURCE: &str = include_str!("./shaders/ui_vert.glsl");
+const UI_FRAG_SHADER_SOURCE: &str = include_str!("./shaders/ui_frag.glsl");
+
+/// (location, size, type, normalized, offset) of each attribute within one of egui's [`Vertex`]es.
+const VERTEX_ATTRIBUTES: [(GLuint, GLint, GLenum, GLboolean, usize); 3] = [
+    (0, 2, gl::FLOAT, gl::FALSE, std::mem::offset_of!(Vertex, pos)),
+    (1, 2, gl::FLOAT, gl::FALSE, std::mem::offset_of!(Vertex, uv)),
+    (2, 4, gl::UNSIGNED_BYTE, gl::TRUE, std::mem::offset_of!(Vertex, color)),
+];
+
+/// How many points the UI scrolls for each notch of the scroll wheel.
+const SCROLL_STEP: f32 = 50.0;
+
+/// How much smaller or larger than the monitor's content scale the user can make the UI.
+pub const UI_SCALE_RANGE: RangeInclusive<f32> = 0.5..=3.0;
+/// How much [`UiSettings::zoom`] changes the UI's scale by with each step.
+const UI_SCALE_STEP: f32 = 0.25;
+
+
+/// User-configurable UI settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiSettings {
+    /// How much larger than the monitor's content scale to draw the UI, within [`UI_SCALE_RANGE`]. See
+    /// [`Display::user_scale`].
+    pub scale: f32,
+    /// Whether or not to draw the UI in white and yellow on black, rather than egui's usual grays.
+    pub high_contrast: bool,
+}
+
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self { scale: 1.0, high_contrast: false }
+    }
+}
+
+
+impl UiSettings {
+    /// Makes the UI larger (for positive `steps`) or smaller (for negative ones), staying within [`UI_SCALE_RANGE`].
+    pub fn zoom(&mut self, steps: i32) {
+        let scale = self.scale + steps as f32 * UI_SCALE_STEP;
+        self.scale = scale.clamp(*UI_SCALE_RANGE.start(), *UI_SCALE_RANGE.end());
+    }
+
+    /// The colors to draw the UI with.
+    pub fn visuals(&self) -> egui::Visuals {
+        if self.high_contrast {
+            high_contrast_visuals()
+        } else {
+            egui::Visuals::dark()
+        }
+    }
+}
+
+
+/// The UI's state between frames, and everything needed to draw it.
+pub struct Ui {
+    pub ctx: egui::Context,
+    /// Events received since the last frame.
+    input: RawInput,
+    /// Where the cursor was at the last event, in points.
+    cursor: Pos2,
+    /// Whether or not the UI is currently using the high-contrast theme.
+    high_contrast: bool,
+    painter: UiPainter,
+}
+
+
+impl Ui {
+    /// Sets up the UI, compiling its shaders with the given `#version` header.
+    pub fn new(glsl_header: &str) -> Self {
+        Self {
+            ctx: egui::Context::default(),
+            input: RawInput::default(),
+            cursor: Pos2::ZERO,
+            high_contrast: false,
+            painter: UiPainter::new(glsl_header),
+        }
+    }
+
+    /// Switches the UI's theme, if it has changed since the last frame. The scale is applied through [`Display`]
+    /// instead, since events need it too.
+    pub fn apply_settings(&mut self, settings: &UiSettings) {
+        if settings.high_contrast != self.high_contrast {
+            self.high_contrast = settings.high_contrast;
+            self.ctx.set_visuals(settings.visuals());
+        }
+    }
+
+    /// Whether the UI is using the mouse, because it is over one of the UI's windows or dragging something in one, so
+    /// the scene shouldn't react to it.
+    pub fn wants_pointer(&self) -> bool {
+        self.ctx.wants_pointer_input()
+    }
+
+    /// Whether the UI is using the keyboard, because something like a text box has focus, so the viewer's shortcuts
+    /// shouldn't react to it.
+    pub fn wants_keyboard(&self) -> bool {
+        self.ctx.wants_keyboard_input()
+    }
+
+    /// Passes a window event along to the UI, for the next frame. The window needs character polling turned on, on
+    /// top of the polling that [`OrbitControls`][crate::OrbitControls] needs.
+    pub fn handle_event(&mut self, window: &Window, display: &Display, event: &WindowEvent) {
+        match *event {
+            WindowEvent::CursorPos(x, y) => {
+                // Cursor positions are in screen coordinates, which aren't always the same as pixels.
+                let (width, height) = window.get_size();
+                let (fb_width, fb_height) = display.framebuffer_size;
+                let scale_x = fb_width as f32 / width.max(1) as f32 / display.ui_scale();
+                let scale_y = fb_height as f32 / height.max(1) as f32 / display.ui_scale();
+                self.cursor = Pos2::new(x as f32 * scale_x, y as f32 * scale_y);
+                self.input.events.push(egui::Event::PointerMoved(self.cursor));
+            },
+            WindowEvent::MouseButton(button, action, modifiers) => {
+                let button = match button {
+                    glfw::MouseButtonLeft => egui::PointerButton::Primary,
+                    glfw::MouseButtonRight => egui::PointerButton::Secondary,
+                    glfw::MouseButtonMiddle => egui::PointerButton::Middle,
+                    _ => return,
+                };
+                self.input.events.push(egui::Event::PointerButton {
+                    pos: self.cursor,
+                    button,
+                    pressed: action != Action::Release,
+                    modifiers: to_egui_modifiers(modifiers),
+                });
+            },
+            WindowEvent::Scroll(x, y) => {
+                let delta = egui::vec2(x as f32, y as f32) * SCROLL_STEP;
+                self.input.events.push(egui::Event::Scroll(delta));
+            },
+            WindowEvent::Char(c) if !c.is_control() => {
+                self.input.events.push(egui::Event::Text(c.to_string()));
+            },
+            WindowEvent::Key(key, _, action, modifiers) => {
+                let modifiers = to_egui_modifiers(modifiers);
+                self.input.modifiers = modifiers;
+
+                let shortcut = modifiers.command && action == Action::Press;
+                if shortcut && key == glfw::Key::C {
+                    self.input.events.push(egui::Event::Copy);
+                } else if shortcut && key == glfw::Key::X {
+                    self.input.events.push(egui::Event::Cut);
+                } else if shortcut && key == glfw::Key::V {
+                    if let Some(text) = window.get_clipboard_string() {
+                        self.input.events.push(egui::Event::Paste(text));
+                    }
+                } else if let Some(key) = to_egui_key(key) {
+                    let (pressed, repeat) = (action != Action::Release, action == Action::Repeat);
+                    self.input.events.push(egui::Event::Key { key, pressed, repeat, modifiers });
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Runs the UI for one frame and paints it over whatever has been drawn to the default framebuffer. Returns
+    /// whether the UI wants to be drawn again right away, e.g. because something in it is animating.
+    pub fn run(
+        &mut self,
+        window: &mut Window,
+        display: &Display,
+        time: f64,
+        run_ui: impl FnOnce(&egui::Context),
+    ) -> bool {
+        let pixels_per_point = display.ui_scale();
+        let (width, height) = display.framebuffer_size;
+        let screen_size = egui::vec2(width as f32, height as f32) / pixels_per_point;
+
+        let mut input = std::mem::take(&mut self.input);
+        input.screen_rect = Some(Rect::from_min_size(Pos2::ZERO, screen_size));
+        input.pixels_per_point = Some(pixels_per_point);
+        input.time = Some(time);
+        // Keep holding down the same modifiers next frame.
+        self.input.modifiers = input.modifiers;
+
+        let output = self.ctx.run(input, run_ui);
+        if !output.platform_output.copied_text.is_empty() {
+            window.set_clipboard_string(&output.platform_output.copied_text);
+        }
+
+        let primitives = self.ctx.tessellate(output.shapes);
+        self.painter.set_textures(&output.textures_delta);
+        self.painter.paint(&primitives, display);
+        self.painter.free_textures(&output.textures_delta);
+
+        output.repaint_after.is_zero()
+    }
+}
+
+
+/// Draws egui's meshes with GL.
+struct UiPainter {
+    program: GlProgram,
+    vao: GlVertexArray,
+    textures: HashMap<TextureId, GlTexture>,
+}
+
+
+impl UiPainter {
+    fn new(glsl_header: &str) -> Self {
+        let vert_source = format!("{glsl_header}{UI_VERT_SHADER_SOURCE}");
+        let frag_source = format!("{glsl_header}{UI_FRAG_SHADER_SOURCE}");
+        let program = GlProgram::new(&vert_source, &frag_source).unwrap();
+
+        let vao = GlVertexArray::new();
+        unsafe {
+            if has_dsa() {
+                for (location, size, kind, normalized, offset) in VERTEX_ATTRIBUTES {
+                    gl::VertexArrayAttribFormat(vao.id(), location, size, kind, normalized, offset as GLuint);
+                    gl::VertexArrayAttribBinding(vao.id(), location, 0);
+                    gl::EnableVertexArrayAttrib(vao.id(), location);
+                }
+            } else {
+                // Without DSA, the attributes' formats are given along with their buffers; see `paint`.
+                gl::BindVertexArray(vao.id());
+                for (location, ..) in VERTEX_ATTRIBUTES {
+                    gl::EnableVertexAttribArray(location);
+                }
+                gl::BindVertexArray(0);
+            }
+        }
+
+        Self { program, vao, textures: HashMap::new() }
+    }
+
+    /// Creates or updates the textures that egui has asked for.
+    fn set_textures(&mut self, delta: &TexturesDelta) {
+        for (id, image_delta) in &delta.set {
+            let pixels: Vec<u8> = match &image_delta.image {
+                ImageData::Color(image) => image.pixels.iter().flat_map(|color| color.to_array()).collect(),
+                ImageData::Font(image) => image.srgba_pixels(None).flat_map(|color| color.to_array()).collect(),
+            };
+            let [w, h] = image_delta.image.size().map(|side| side as GLsizei);
+            let data = pixels.as_ptr().cast();
+
+            // Patches go into the texture that's already there; whole images replace it.
+            let (texture, [x, y]) = match image_delta.pos {
+                Some(pos) if self.textures.contains_key(id) => (&self.textures[id], pos.map(|p| p as GLint)),
+                _ => {
+                    let texture = GlTexture::new(gl::TEXTURE_2D);
+                    unsafe {
+                        if has_dsa() {
+                            gl::TextureStorage2D(texture.id(), 1, gl::RGBA8, w, h);
+                        } else {
+                            gl::BindTexture(gl::TEXTURE_2D, texture.id());
+                            let format = gl::RGBA8 as GLint;
+                            gl::TexImage2D(gl::TEXTURE_2D, 0, format, w, h, 0, gl::RGBA, gl::UNSIGNED_BYTE, data);
+                            gl::BindTexture(gl::TEXTURE_2D, 0);
+                        }
+                    }
+                    let filter = match image_delta.options.magnification {
+                        egui::TextureFilter::Nearest => crate::TextureFilter::Nearest,
+                        egui::TextureFilter::Linear => crate::TextureFilter::Bilinear,
+                    };
+                    texture.set_filter(filter);
+                    self.textures.insert(*id, texture);
+                    (&self.textures[id], [0, 0])
+                },
+            };
+
+            unsafe {
+                if has_dsa() {
+                    gl::TextureSubImage2D(texture.id(), 0, x, y, w, h, gl::RGBA, gl::UNSIGNED_BYTE, data);
+                } else if image_delta.pos.is_some() {
+                    gl::BindTexture(gl::TEXTURE_2D, texture.id());
+                    gl::TexSubImage2D(gl::TEXTURE_2D, 0, x, y, w, h, gl::RGBA, gl::UNSIGNED_BYTE, data);
+                    gl::BindTexture(gl::TEXTURE_2D, 0);
+                }
+            }
+        }
+    }
+
+    /// Deletes the textures that egui no longer needs. Done after painting, since they may still be used by the
+    /// frame that frees them.
+    fn free_textures(&mut self, delta: &TexturesDelta) {
+        for id in &delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    fn paint(&self, primitives: &[egui::ClippedPrimitive], display: &Display) {
+        let pixels_per_point = display.ui_scale();
+        let (width, height) = display.framebuffer_size;
+        let v_size = std::mem::size_of::<Vertex>() as GLsizei;
+
+        unsafe {
+            display.update_viewport();
+            gl::Disable(gl::FRAMEBUFFER_SRGB);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Disable(gl::CULL_FACE);
+            gl::Enable(gl::SCISSOR_TEST);
+            gl::Enable(gl::BLEND);
+            gl::BlendEquation(gl::FUNC_ADD);
+            gl::BlendFunc(gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+
+            gl::UseProgram(self.program.id());
+            let screen_size = [width as f32 / pixels_per_point, height as f32 / pixels_per_point];
+            set_uniform_vec2(self.program.id(), "u_screen_size", screen_size);
+            gl::BindVertexArray(self.vao.id());
+
+            for primitive in primitives {
+                let Primitive::Mesh(mesh) = &primitive.primitive else {
+                    continue;
+                };
+                let Some(texture) = self.textures.get(&mesh.texture_id) else {
+                    continue;
+                };
+
+                // The clip rectangle is in points from the top left; GL's scissor box is in pixels from the bottom
+                // left.
+                let clip = primitive.clip_rect;
+                let min_x = (clip.min.x * pixels_per_point).round().clamp(0.0, width as f32) as GLint;
+                let max_x = (clip.max.x * pixels_per_point).round().clamp(0.0, width as f32) as GLint;
+                let min_y = (clip.min.y * pixels_per_point).round().clamp(0.0, height as f32) as GLint;
+                let max_y = (clip.max.y * pixels_per_point).round().clamp(0.0, height as f32) as GLint;
+                if max_x <= min_x || max_y <= min_y {
+                    continue;
+                }
+                gl::Scissor(min_x, height - max_y, max_x - min_x, max_y - min_y);
+
+                // UI meshes change every frame, so they are uploaded fresh each time.
+                let vbo = GlBuffer::with_data(&mesh.vertices, gl::STREAM_DRAW);
+                let ibo = GlBuffer::with_data(&mesh.indices, gl::STREAM_DRAW);
+                if has_dsa() {
+                    gl::VertexArrayVertexBuffer(self.vao.id(), 0, vbo.id(), 0, v_size);
+                    gl::VertexArrayElementBuffer(self.vao.id(), ibo.id());
+                    gl::BindTextureUnit(0, texture.id());
+                } else {
+                    gl::BindBuffer(gl::ARRAY_BUFFER, vbo.id());
+                    gl::BindBuffer(gl::ELEMENT_ARRAY_BUFFER, ibo.id());
+                    for (location, size, kind, normalized, offset) in VERTEX_ATTRIBUTES {
+                        gl::VertexAttribPointer(location, size, kind, normalized, v_size, offset as *const _);
+                    }
+                    gl::ActiveTexture(gl::TEXTURE0);
+                    gl::BindTexture(gl::TEXTURE_2D, texture.id());
+                }
+
+                let count = mesh.indices.len() as GLsizei;
+                gl::DrawElements(gl::TRIANGLES, count, gl::UNSIGNED_INT, std::ptr::null());
+            }
+
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+            gl::Disable(gl::SCISSOR_TEST);
+            gl::Disable(gl::BLEND);
+            gl::Enable(gl::FRAMEBUFFER_SRGB);
+        }
+    }
+}
+
+
+/// egui's dark theme, with everything pushed to black, white, and yellow. The widget with keyboard focus is drawn like
+/// one that is being clicked: in solid yellow, so that it's easy to find.
+fn high_contrast_visuals() -> egui::Visuals {
+    use egui::{Color32, Stroke};
+
+    let mut visuals = egui::Visuals::dark();
+    visuals.window_fill = Color32::BLACK;
+    visuals.panel_fill = Color32::BLACK;
+    visuals.extreme_bg_color = Color32::BLACK;
+    visuals.faint_bg_color = Color32::from_gray(32);
+    visuals.window_stroke = Stroke::new(1.0, Color32::WHITE);
+    visuals.hyperlink_color = Color32::YELLOW;
+    visuals.selection.bg_fill = Color32::from_rgb(0, 0, 192);
+    visuals.selection.stroke = Stroke::new(2.0, Color32::WHITE);
+
+    let widgets = &mut visuals.widgets;
+    for (widget, fill, stroke) in [
+        (&mut widgets.noninteractive, Color32::BLACK, Stroke::new(1.0, Color32::WHITE)),
+        (&mut widgets.inactive, Color32::from_gray(32), Stroke::new(1.0, Color32::WHITE)),
+        (&mut widgets.hovered, Color32::from_gray(64), Stroke::new(2.0, Color32::YELLOW)),
+        (&mut widgets.open, Color32::from_gray(64), Stroke::new(2.0, Color32::YELLOW)),
+    ] {
+        (widget.bg_fill, widget.weak_bg_fill, widget.bg_stroke) = (fill, fill, stroke);
+        widget.fg_stroke = Stroke::new(widget.fg_stroke.width.max(1.5), Color32::WHITE);
+    }
+    let active = &mut widgets.active;
+    (active.bg_fill, active.weak_bg_fill) = (Color32::YELLOW, Color32::YELLOW);
+    active.bg_stroke = Stroke::new(2.0, Color32::WHITE);
+    active.fg_stroke = Stroke::new(2.0, Color32::BLACK);
+
+    visuals
+}
+
+
+fn to_egui_modifiers(modifiers: glfw::Modifiers) -> egui::Modifiers {
+    let ctrl = modifiers.contains(glfw::Modifiers::Control);
+    let mac_cmd = cfg!(target_os = "macos") && modifiers.contains(glfw::Modifiers::Super);
+    egui::Modifiers {
+        alt: modifiers.contains(glfw::Modifiers::Alt),
+        ctrl,
+        shift: modifiers.contains(glfw::Modifiers::Shift),
+        mac_cmd,
+        command: if cfg!(target_os = "macos") { mac_cmd } else { ctrl },
+    }
+}
+
+
+/// The keys that egui's widgets react to: the ones used for editing text and moving between widgets, and letters for
+/// shortcuts like select-all and undo. Copying, cutting, and pasting are sent as their own events instead.
+fn to_egui_key(key: glfw::Key) -> Option<egui::Key> {
+    use egui::Key as E;
+    use glfw::Key as G;
+
+    let key = match key {
+        G::Left => E::ArrowLeft,
+        G::Right => E::ArrowRight,
+        G::Up => E::ArrowUp,
+        G::Down => E::ArrowDown,
+        G::Escape => E::Escape,
+        G::Tab => E::Tab,
+        G::Backspace => E::Backspace,
+        G::Enter | G::KpEnter => E::Enter,
+        G::Space => E::Space,
+        G::Insert => E::Insert,
+        G::Delete => E::Delete,
+        G::Home => E::Home,
+        G::End => E::End,
+        G::PageUp => E::PageUp,
+        G::PageDown => E::PageDown,
+        G::A => E::A,
+        G::K => E::K,
+        G::U => E::U,
+        G::W => E::W,
+        G::Y => E::Y,
+        G::Z => E::Z,
+        _ => return None,
+    };
+    Some(key)
+}
@@ -0,0 +1,211 @@
+//! Spreading GPU uploads out over several frames.
+//!
+//! Uploading a whole field's worth of buffers and textures at once can stall the main loop for seconds. Instead,
+//! uploads are queued up and the main loop works through them a little at a time, spending at most
+//! [`FrameSettings::upload_budget`][crate::FrameSettings::upload_budget] on them each frame. Large textures are split
+//! into strips of rows so that no single upload blows through the budget by itself.
+
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use gl::types::*;
+
+use crate::{bc7_len, has_dsa, mip_levels, GlBuffer, GlTexture, TextureFilter};
+
+
+/// Roughly how many bytes each queued piece of a texture should be.
+const TEXTURE_CHUNK_SIZE: usize = 256 * 1024;
+
+
+/// A single queued upload.
+struct PendingUpload {
+    bytes: usize,
+    upload: Box<dyn FnOnce()>,
+}
+
+
+/// A queue of uploads waiting to be sent to the GPU.
+///
+/// Objects are created as soon as their uploads are queued, so they can be handed out straight away; they are kept
+/// alive by the queue until their data has been uploaded. Drawing with an object before its uploads are finished is
+/// safe, but it will show up incomplete.
+#[derive(Default)]
+pub struct UploadQueue {
+    pending: VecDeque<PendingUpload>,
+    pending_bytes: usize,
+}
+
+
+impl UploadQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of uploads that haven't been run yet.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether or not every queued upload has been run.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// The total size of the uploads that haven't been run yet, in bytes.
+    pub fn pending_bytes(&self) -> usize {
+        self.pending_bytes
+    }
+
+    /// Queues an arbitrary upload. `bytes` is only used for [`pending_bytes`][Self::pending_bytes].
+    pub fn push(&mut self, bytes: usize, upload: impl FnOnce() + 'static) {
+        self.pending_bytes += bytes;
+        self.pending.push_back(PendingUpload { bytes, upload: Box::new(upload) });
+    }
+
+    /// Creates a new buffer, and queues up filling it with the given data.
+    pub fn buffer<T: 'static>(&mut self, data: Vec<T>, usage: GLenum) -> Rc<GlBuffer> {
+        let buffer = Rc::new(GlBuffer::new());
+        let target = Rc::clone(&buffer);
+        self.push(std::mem::size_of_val(data.as_slice()), move || {
+            let size = std::mem::size_of_val(data.as_slice()).try_into().expect("Buffer data is too large.");
+            if has_dsa() {
+                unsafe { gl::NamedBufferData(target.id(), size, data.as_ptr().cast(), usage) };
+            } else {
+                unsafe {
+                    gl::BindBuffer(gl::ARRAY_BUFFER, target.id());
+                    gl::BufferData(gl::ARRAY_BUFFER, size, data.as_ptr().cast(), usage);
+                    gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+                }
+            }
+        });
+        buffer
+    }
+
+    /// Creates a new 2D texture with room for an 8-bit RGBA image, and queues up copying the image into it a few rows
+    /// at a time. `pixels` must be `width * height * 4` bytes long. If the filter [uses
+    /// mipmaps][TextureFilter::uses_mipmaps], they are generated once the whole image has been copied.
+    pub fn texture_rgba(&mut self, width: u32, height: u32, pixels: Vec<u8>, filter: TextureFilter) -> Rc<GlTexture> {
+        assert_eq!(pixels.len(), width as usize * height as usize * 4, "Texture data is the wrong size.");
+
+        // Allocating the storage is cheap, so it is done right away; only the copies are queued.
+        let texture = Rc::new(GlTexture::new(gl::TEXTURE_2D));
+        let levels = if filter.uses_mipmaps() { mip_levels(width, height) } else { 1 };
+        let (w, h) = (width as GLsizei, height as GLsizei);
+        unsafe {
+            if has_dsa() {
+                gl::TextureStorage2D(texture.id(), levels as GLsizei, gl::SRGB8_ALPHA8, w, h);
+            } else {
+                gl::BindTexture(gl::TEXTURE_2D, texture.id());
+                let format = gl::SRGB8_ALPHA8 as GLint;
+                gl::TexImage2D(gl::TEXTURE_2D, 0, format, w, h, 0, gl::RGBA, gl::UNSIGNED_BYTE, std::ptr::null());
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+            }
+        }
+        texture.set_filter(filter);
+
+        let row_len = width as usize * 4;
+        let rows_per_chunk = (TEXTURE_CHUNK_SIZE / row_len.max(1)).max(1);
+        let pixels = Rc::new(pixels);
+
+        for first_row in (0..height as usize).step_by(rows_per_chunk) {
+            let rows = rows_per_chunk.min(height as usize - first_row);
+            let (target, pixels) = (Rc::clone(&texture), Rc::clone(&pixels));
+            self.push(rows * row_len, move || {
+                let data = pixels[first_row * row_len..].as_ptr().cast();
+                let (y, rows) = (first_row as GLint, rows as GLsizei);
+                unsafe {
+                    if has_dsa() {
+                        gl::TextureSubImage2D(target.id(), 0, 0, y, w, rows, gl::RGBA, gl::UNSIGNED_BYTE, data);
+                    } else {
+                        gl::BindTexture(gl::TEXTURE_2D, target.id());
+                        gl::TexSubImage2D(gl::TEXTURE_2D, 0, 0, y, w, rows, gl::RGBA, gl::UNSIGNED_BYTE, data);
+                        gl::BindTexture(gl::TEXTURE_2D, 0);
+                    }
+                }
+            });
+        }
+
+        if levels > 1 {
+            let target = Rc::clone(&texture);
+            self.push(0, move || target.generate_mipmaps());
+        }
+
+        texture
+    }
+
+    /// Creates a new 2D texture from an image that has already been compressed to BC7 (see
+    /// [`encode_bc7`][crate::encode_bc7]), and queues up copying it in a few rows of blocks at a time. Check
+    /// [`bc7_supported`][crate::bc7_supported] first. Compressed textures don't get mipmaps, so any filter that uses
+    /// them samples the full-size image instead.
+    pub fn texture_bc7(&mut self, width: u32, height: u32, blocks: Vec<u8>, filter: TextureFilter) -> Rc<GlTexture> {
+        assert_eq!(blocks.len(), bc7_len(width, height), "Texture data is the wrong size.");
+
+        let texture = Rc::new(GlTexture::new(gl::TEXTURE_2D));
+        let format = gl::COMPRESSED_SRGB_ALPHA_BPTC_UNORM;
+        let (w, h) = (width as GLsizei, height as GLsizei);
+        unsafe {
+            if has_dsa() {
+                gl::TextureStorage2D(texture.id(), 1, format, w, h);
+            } else {
+                // Compressed formats can't be allocated without data, so the whole image goes up in one go.
+                gl::BindTexture(gl::TEXTURE_2D, texture.id());
+                let len = blocks.len() as GLsizei;
+                gl::CompressedTexImage2D(gl::TEXTURE_2D, 0, format, w, h, 0, len, blocks.as_ptr().cast());
+                // Without this, mipmap filters would leave the texture incomplete, since only the first level exists.
+                gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAX_LEVEL, 0);
+                gl::BindTexture(gl::TEXTURE_2D, 0);
+            }
+        }
+        texture.set_filter(filter);
+        if !has_dsa() {
+            return texture;
+        }
+
+        // Each row of blocks covers four rows of pixels. Only the last chunk may end partway through a block.
+        let row_len = bc7_len(width, 4);
+        let block_rows = height.div_ceil(4) as usize;
+        let rows_per_chunk = (TEXTURE_CHUNK_SIZE / row_len.max(1)).max(1);
+        let blocks = Rc::new(blocks);
+
+        for first_row in (0..block_rows).step_by(rows_per_chunk) {
+            let rows = rows_per_chunk.min(block_rows - first_row);
+            let (target, blocks) = (Rc::clone(&texture), Rc::clone(&blocks));
+            self.push(rows * row_len, move || {
+                let data = &blocks[first_row * row_len..(first_row + rows) * row_len];
+                let y = first_row as GLint * 4;
+                let rows = (rows as GLsizei * 4).min(h - y);
+                let (len, data) = (data.len() as GLsizei, data.as_ptr().cast());
+                unsafe { gl::CompressedTextureSubImage2D(target.id(), 0, 0, y, w, rows, format, len, data) };
+            });
+        }
+
+        texture
+    }
+
+    /// Runs queued uploads, oldest first, until either the queue is empty or `budget` has been spent. At least one
+    /// upload is always run, so that the queue keeps moving even with a budget of zero. Returns how many were run.
+    pub fn process(&mut self, budget: Duration) -> usize {
+        let start = Instant::now();
+        let mut count = 0;
+
+        while let Some(PendingUpload { bytes, upload }) = self.pending.pop_front() {
+            upload();
+            self.pending_bytes -= bytes;
+            count += 1;
+
+            if start.elapsed() >= budget {
+                break;
+            }
+        }
+
+        count
+    }
+
+    /// Runs every queued upload right away, no matter how long it takes.
+    pub fn flush(&mut self) {
+        while !self.is_empty() {
+            self.process(Duration::MAX);
+        }
+    }
+}
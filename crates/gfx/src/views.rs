@@ -0,0 +1,270 @@
+//! Getting back to a particular view of the model: framing all or part of it, looking at it from the front, side, or
+//! top, and named bookmarks for any other view.
+//!
+//! Bookmarks can be kept in a file given with `--bookmarks`, so that they last between sessions (e.g., for taking
+//! screenshots of several models from the same angles). Each line of the file is a bookmark's name, a tab, and then
+//! `<yaw>,<pitch>,<distance>,<x>,<y>,<z>,<projection>`: the camera's angles in degrees, its distance from its target,
+//! where its target is, and either `perspective` or `orthographic`.
+
+use std::fmt::Write;
+use std::io;
+use std::path::PathBuf;
+
+use ff7::extract::save_atomic;
+
+use crate::{Camera, ViewPreset};
+
+
+/// A list of named camera views, optionally kept in a file. See the [module-level documentation](self) for the
+/// file's format.
+#[derive(Debug, Clone, Default)]
+pub struct CameraBookmarks {
+    /// Each bookmark's name and view, in the order they were added.
+    pub bookmarks: Vec<(String, Camera)>,
+    /// The file that the bookmarks are saved to whenever they change, if any.
+    path: Option<PathBuf>,
+}
+
+
+impl CameraBookmarks {
+    /// An empty list of bookmarks that only lasts until the viewer closes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads bookmarks from a file, which they'll be saved back to whenever they change. A file that doesn't exist yet
+    /// is treated as empty. Lines that can't be read are logged and skipped.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err),
+        };
+
+        let mut bookmarks = Self::from_text(&text);
+        if bookmarks.bookmarks.len() < text.lines().filter(|line| !line.trim().is_empty()).count() {
+            log::warn!("Some of the bookmarks in {} could not be read, and were skipped.", path.display());
+        }
+        bookmarks.path = Some(path);
+        Ok(bookmarks)
+    }
+
+    /// Reads bookmarks in the file's format, skipping any lines that aren't bookmarks.
+    pub fn from_text(text: &str) -> Self {
+        let bookmarks = text
+            .lines()
+            .filter_map(|line| {
+                let (name, camera) = line.split_once('\t')?;
+                Some((name.to_owned(), parse_bookmark(camera)?))
+            })
+            .collect();
+        Self { bookmarks, path: None }
+    }
+
+    /// Writes the bookmarks in the file's format.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (name, camera) in &self.bookmarks {
+            let (yaw, pitch) = (camera.yaw.to_degrees(), camera.pitch.to_degrees());
+            let [x, y, z] = camera.target;
+            let projection = if camera.orthographic { "orthographic" } else { "perspective" };
+            writeln!(text, "{name}\t{yaw},{pitch},{},{x},{y},{z},{projection}", camera.distance).unwrap();
+        }
+        text
+    }
+
+    /// Bookmarks a view, replacing any other bookmark with the same name. Names can't have line breaks or tabs in
+    /// them, so any control characters are turned into spaces.
+    pub fn set(&mut self, name: &str, camera: Camera) -> io::Result<()> {
+        let name = name.trim().replace(char::is_control, " ");
+        match self.bookmarks.iter_mut().find(|(existing, _)| *existing == name) {
+            Some((_, existing)) => *existing = camera,
+            None => self.bookmarks.push((name, camera)),
+        }
+        self.save()
+    }
+
+    /// Removes the bookmark with the given name, if there is one.
+    pub fn remove(&mut self, name: &str) -> io::Result<()> {
+        self.bookmarks.retain(|(existing, _)| existing != name);
+        self.save()
+    }
+
+    /// Saves the bookmarks to their file, if they have one.
+    fn save(&self) -> io::Result<()> {
+        match &self.path {
+            Some(path) => save_atomic(path, self.to_text().as_bytes(), false),
+            None => Ok(()),
+        }
+    }
+}
+
+
+/// Reads a bookmarked view in the form `<yaw>,<pitch>,<distance>,<x>,<y>,<z>,<projection>`.
+fn parse_bookmark(value: &str) -> Option<Camera> {
+    let mut fields = value.split(',').map(str::trim);
+    let mut numbers = [0.0; 6];
+    for number in &mut numbers {
+        *number = fields.next()?.parse::<f32>().ok().filter(|number| number.is_finite())?;
+    }
+    let orthographic = match fields.next()? {
+        "perspective" => false,
+        "orthographic" => true,
+        _ => return None,
+    };
+    if fields.next().is_some() {
+        return None;
+    }
+
+    let [yaw, pitch, distance, x, y, z] = numbers;
+    let mut camera = Camera { target: [x, y, z], distance: 1.0, orthographic, ..Camera::default() };
+    camera.orbit(yaw.to_radians(), pitch.to_radians());
+    camera.zoom(distance);
+    Some(camera)
+}
+
+
+/// The control panel's section for moving the camera to a particular view.
+#[derive(Debug, Clone, Default)]
+pub struct CameraPanel {
+    /// The parts of the model that can be framed on their own: the index and name of each bone that has meshes.
+    parts: Vec<(usize, String)>,
+    /// The bone whose meshes should be framed, or `None` for the whole model.
+    pub part: Option<usize>,
+    pub bookmarks: CameraBookmarks,
+    /// The name being typed in for a new bookmark.
+    name: String,
+}
+
+
+impl CameraPanel {
+    pub fn new(bookmarks: CameraBookmarks) -> Self {
+        Self { bookmarks, ..Self::default() }
+    }
+
+    /// Changes which parts of the model can be framed, going back to framing the whole model.
+    pub fn set_parts<S: Into<String>>(&mut self, parts: impl IntoIterator<Item = (usize, S)>) {
+        self.parts = parts.into_iter().map(|(bone, name)| (bone, name.into())).collect();
+        self.part = None;
+    }
+
+    /// Shows the camera's controls, moving it right away for anything but framing. Returns whether or not the camera
+    /// should be moved to frame [`part`][Self::part], which needs the model.
+    pub fn ui(&mut self, ui: &mut egui::Ui, camera: &mut Camera) -> bool {
+        ui.horizontal(|ui| {
+            let presets = [ViewPreset::Front, ViewPreset::Side, ViewPreset::Top];
+            for (preset, label) in presets.into_iter().zip(["Front (1)", "Side (2)", "Top (3)"]) {
+                if ui.button(label).clicked() {
+                    camera.look_from(preset);
+                }
+            }
+        });
+        ui.checkbox(&mut camera.orthographic, "Orthographic (O)");
+
+        let mut frame = false;
+        ui.horizontal(|ui| {
+            let part_name = |part: Option<usize>| {
+                let name = part.and_then(|part| self.parts.iter().find(|(bone, _)| *bone == part));
+                name.map_or("Whole model", |(_, name)| name.as_str())
+            };
+            egui::ComboBox::from_id_source("frame part").selected_text(part_name(self.part)).show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.part, None, part_name(None));
+                for (bone, name) in &self.parts {
+                    ui.selectable_value(&mut self.part, Some(*bone), name);
+                }
+            });
+            frame = ui.button("Frame (Home)").clicked();
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let edit = egui::TextEdit::singleline(&mut self.name).hint_text("Bookmark name").desired_width(140.0);
+            let edit = ui.add(edit);
+            let entered = edit.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            let has_name = !self.name.trim().is_empty();
+            if (ui.add_enabled(has_name, egui::Button::new("Save")).clicked() || entered) && has_name {
+                if let Err(err) = self.bookmarks.set(&self.name, *camera) {
+                    log::error!("Could not save the bookmarks: {err}");
+                }
+                self.name.clear();
+            }
+        });
+
+        let mut removed = None;
+        for (name, saved) in &self.bookmarks.bookmarks {
+            ui.horizontal(|ui| {
+                if ui.button(name).on_hover_text("Go to this view").clicked() {
+                    *camera = *saved;
+                }
+                if ui.small_button("×").on_hover_text("Delete this bookmark").clicked() {
+                    removed = Some(name.clone());
+                }
+            });
+        }
+        if let Some(name) = removed {
+            if let Err(err) = self.bookmarks.remove(&name) {
+                log::error!("Could not save the bookmarks: {err}");
+            }
+        }
+        if self.bookmarks.bookmarks.is_empty() {
+            ui.weak("No bookmarks yet. Start the viewer with --bookmarks <path> to keep them after it closes.");
+        }
+
+        frame
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bookmarks_survive_a_round_trip() {
+        let mut bookmarks = CameraBookmarks::new();
+        let mut side = Camera { target: [0.5, -0.25, 1.0], ..Camera::default() };
+        side.look_from(ViewPreset::Side);
+        bookmarks.set("Side", side).unwrap();
+        bookmarks.set(" Close\tup ", Camera { distance: 0.5, ..Camera::default() }).unwrap();
+
+        let read = CameraBookmarks::from_text(&bookmarks.to_text());
+        assert_eq!(read.bookmarks.len(), 2);
+        assert_eq!(read.bookmarks[0].0, "Side");
+        assert_eq!(read.bookmarks[1].0, "Close up");
+
+        let camera = read.bookmarks[0].1;
+        assert!(camera.orthographic);
+        assert!((camera.yaw - side.yaw).abs() < 1e-5);
+        assert!((camera.distance - side.distance).abs() < 1e-5);
+        assert_eq!(camera.target, side.target);
+        assert!(!read.bookmarks[1].1.orthographic);
+    }
+
+    #[test]
+    fn setting_a_bookmark_again_replaces_it() {
+        let mut bookmarks = CameraBookmarks::new();
+        bookmarks.set("View", Camera::default()).unwrap();
+        bookmarks.set("View", Camera { distance: 4.0, ..Camera::default() }).unwrap();
+        assert_eq!(bookmarks.bookmarks.len(), 1);
+        assert_eq!(bookmarks.bookmarks[0].1.distance, 4.0);
+
+        bookmarks.remove("View").unwrap();
+        assert!(bookmarks.bookmarks.is_empty());
+    }
+
+    #[test]
+    fn unreadable_lines_are_skipped() {
+        let lines = [
+            "Good\t0,0,2,0,0,0,perspective",
+            "No tab 0,0,2,0,0,0,perspective",
+            "Fisheye\t0,0,2,0,0,0,fisheye",
+            "Short\t0,0,2",
+            "Long\t0,0,2,0,0,0,perspective,1",
+            "NaN\tNaN,0,2,0,0,0,perspective",
+        ];
+        let bookmarks = CameraBookmarks::from_text(&lines.join("\n"));
+        assert_eq!(bookmarks.bookmarks.len(), 1);
+        assert_eq!(bookmarks.bookmarks[0].0, "Good");
+    }
+}
@@ -1 +1,57 @@
-pub fn main() {}
+use ff7::extract::{compress_lzss, compress_lzss_raw, decompress_lzss, decompress_lzss_raw, save_atomic};
+use gfx::LzssMode;
+
+
+// Counting allocations costs an atomic add for each one, so it's only done in builds made for profiling.
+#[cfg(feature = "profile-alloc")]
+#[global_allocator]
+static ALLOCATOR: gfx::CountingAllocator = gfx::CountingAllocator;
+
+
+pub fn main() {
+    match gfx::Command::from_args(std::env::args().skip(1)) {
+        Ok(gfx::Command::View(options)) => gfx::main(options),
+        Ok(gfx::Command::Info(options)) => {
+            if !gfx::info(&options) {
+                std::process::exit(1);
+            }
+        },
+        Ok(gfx::Command::Lzss(options)) => {
+            if let Err(message) = lzss(&options) {
+                eprintln!("ff7-viewer: {message}");
+                std::process::exit(1);
+            }
+        },
+        Err(err) => {
+            eprintln!("ff7-viewer: {err}");
+            eprintln!("usage: ff7-viewer [view] [<archive>] [--model <name>] [--anim <name>]");
+            eprintln!("                  [--camera <yaw,pitch,distance>] [--bookmarks <path>]");
+            eprintln!("                  [--screenshot <path>] [--turntable <path.gif or dir>]");
+            eprintln!("                  [--turntable-frames <count>] [--turntable-fps <rate>]");
+            eprintln!("                  [--exit] [--json] [--profile]");
+            eprintln!("       ff7-viewer info <archive> [--model <name>] [--anim <name>] [--profile]");
+            eprintln!("       ff7-viewer lzss compress|decompress <in> <out> [--raw | --with-header]");
+            std::process::exit(2);
+        },
+    }
+}
+
+
+/// Runs the `lzss` command: reads the input file, compresses or decompresses it, and writes the output file.
+fn lzss(options: &gfx::LzssOptions) -> Result<(), String> {
+    let (input, output) = (options.input.display(), options.output.display());
+    let data = std::fs::read(&options.input).map_err(|err| format!("could not read {input}: {err}"))?;
+
+    let converted = match (options.mode, options.header) {
+        (LzssMode::Compress, true) => compress_lzss(&data),
+        (LzssMode::Compress, false) => compress_lzss_raw(&data),
+        (LzssMode::Decompress, true) => {
+            decompress_lzss(&data).map_err(|err| format!("could not decompress {input}: {err}"))?
+        },
+        (LzssMode::Decompress, false) => {
+            decompress_lzss_raw(&data).map_err(|err| format!("could not decompress {input}: {err}"))?
+        },
+    };
+
+    save_atomic(&options.output, &converted, false).map_err(|err| format!("could not write {output}: {err}"))
+}